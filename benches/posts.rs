@@ -0,0 +1,74 @@
+//! Benchmarks of merging downloaded posts into the history of a feed.
+//!
+//! Run with `cargo bench`. There's no benchmarking framework, so each case
+//! is timed a few times and the fastest run is printed.
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::DateTime;
+use nia::{Post, PostId, Posts};
+
+/// How many times each case is run.
+const RUNS: usize = 5;
+
+/// Make `count` posts published `step` minutes apart from `start` minutes.
+fn posts(start: i64, count: i64, step: i64) -> Posts {
+    (0..count)
+        .map(|idx| {
+            let minute = start + idx * step;
+            let published = DateTime::from_timestamp(minute * 60, 0).unwrap();
+            Post {
+                id: PostId(Arc::from(minute.to_string())),
+                title: Arc::from(format!("Post {}", minute)),
+                urls: Vec::new(),
+                published,
+                read: false,
+                arrived: published,
+                starred: false,
+                comments: None,
+                comment_feed: None,
+                authors: Vec::new(),
+                categories: Vec::new(),
+                summary: None,
+                updated: None,
+                hash: None,
+                edited: false,
+            }
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Time merging `new` posts into the `history`, the fastest of the runs.
+fn time(history: &Posts, new: &Posts) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let mut history = history.clone();
+            let new = new.clone();
+            let start = Instant::now();
+            history.append(new);
+            let elapsed = start.elapsed();
+            black_box(history);
+            elapsed
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let cases = [
+        ("100 new posts into 10 000", posts(0, 10_000, 2),
+            posts(20_001, 100, 1)),
+        ("10 000 new posts into 10 000", posts(0, 10_000, 2),
+            posts(1, 10_000, 2)),
+        ("100 000 interleaved into 100 000", posts(0, 100_000, 2),
+            posts(1, 100_000, 2)),
+        ("10 000 already merged posts", posts(0, 10_000, 1),
+            posts(0, 10_000, 1)),
+    ];
+
+    for (name, history, new) in cases {
+        println!("{:<36} {:>10.2?}", name, time(&history, &new));
+    }
+}
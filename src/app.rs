@@ -1,13 +1,41 @@
-use std::io::Write;
-use std::process::{Command, Stdio};
+use std::fmt;
+use std::sync::Arc;
+use std::io::{self, Write};
+use std::process::{self, Stdio};
+use std::path::PathBuf;
 use std::time::{Instant, Duration};
-use std::collections::HashMap;
-use crossterm::event::{self, Event, KeyCode};
-use ratatui::prelude::*;
-use crate::tui::{main, Page, PageAction, Spinner};
-use crate::config::{Section, Feed, FeedId, FeedConfig, Post, Posts};
+use std::collections::{HashMap, HashSet, VecDeque};
+use crossterm::cursor::{Hide, Show};
+use crossterm::event::{Event, KeyCode, KeyModifiers};
+use crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
+    enable_raw_mode,
+};
+use ratatui::{prelude::*, widgets::Paragraph};
+use crate::tui::{actions, main, feed, post, Page, PageAction, Spinner};
+use crate::tui::image::{self, Protocol};
+use crate::tui::timeline::Timeline;
+use crate::tui::suggestions::SuggestionsPage;
+use crate::tui::catch_up::CatchUpPage;
+use crate::tui::health::HealthPage;
+use crate::tui::stats::StatsPage;
+use crate::tui::history::HistoryPage;
+use crate::tui::downloads::DownloadsPage;
+use crate::tui::command::{Command, CommandLine};
+use crate::tui::theme::{self, Theme, ThemeKind};
+use crate::settings::{MarkRead, Screensaver, Settings, ThemeMode};
+use url::Url;
+use chrono::{DateTime, TimeDelta, Utc};
+use crate::config::{
+    Section, Feed, FeedHealth, FeedId, FeedConfig, FeedMeta,
+    MergedPost, Post, PostId, Posts,
+};
 use crate::download::*;
+use crate::events::{AppEvent, EventChannel, InputReader};
+use crate::hooks::HookRunner;
 use crate::database::*;
+use crate::cache::BodyCache;
+use crate::error::Result;
 
 /// The download state of this feed.
 enum DownloadState {
@@ -18,47 +46,307 @@ enum DownloadState {
     Downloading,
 }
 
+/// How the download of a feed has gone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadStatus {
+    /// It is queued to be downloaded but is not being downloaded yet.
+    Queued,
+
+    /// Is being downloaded.
+    Downloading,
+
+    /// Is waiting for the network to come back to be downloaded.
+    Waiting,
+
+    /// Has been downloaded.
+    Done,
+
+    /// Couldn't be downloaded for the given reason.
+    Failed(DownloadError),
+}
+
+/// A download of a feed, kept to show how the downloads are going.
+#[derive(Debug, Clone)]
+pub struct Download {
+    /// The feed being downloaded.
+    pub feed_id: FeedId,
+
+    /// How the download has gone so far.
+    pub status: DownloadStatus,
+
+    /// When the downloader has started downloading the feed.
+    pub started: Option<Instant>,
+
+    /// When the download has finished or failed.
+    pub ended: Option<Instant>,
+
+    /// The size of the downloaded feed, once it has been received.
+    pub bytes: Option<usize>,
+}
+
+impl Download {
+    /// Get how long the feed has been downloading as of `now`, or how long it
+    /// took once it's over.
+    pub fn elapsed(&self, now: Instant) -> Option<Duration> {
+        let started = self.started?;
+        Some(self.ended.unwrap_or(now).saturating_duration_since(started))
+    }
+
+    /// Whether the download is over, whether it has succeeded or not.
+    pub fn is_over(&self) -> bool {
+        matches!(self.status, DownloadStatus::Done | DownloadStatus::Failed(_))
+    }
+}
+
+/// State of a subscription started from the TUI.
+pub enum Subscription {
+    /// The feed is being looked for.
+    Discovering,
+
+    /// The feed has been added under the given title.
+    Subscribed(Arc<str>),
+
+    /// The feed couldn't be added for the given reason.
+    Failed(String),
+}
+
 /// State of the feeds.
 pub struct FeedState {
     /// A global spinner that can be used to draw a spin animation.
     pub spinner: Spinner,
 
+    /// The application settings.
+    pub settings: Settings,
+
+    /// The current color theme.
+    pub theme: Theme,
+
     /// State of the feeds.
     feed_config: FeedConfig,
 
     /// A map of feeds that are currently queued to be downloaded.
     downloading: HashMap<FeedId, DownloadState>,
+
+    /// The downloads queued since the downloader was last idle, in the order
+    /// they were queued.
+    downloads: Vec<Download>,
+
+    /// Reachability of the URLs that have been checked.
+    link_health: HashMap<Url, LinkHealth>,
+
+    /// Subscriptions started from the TUI, by the URL they were started with.
+    subscriptions: HashMap<Url, Subscription>,
+
+    /// Whether the posts matching the killfile are hidden.
+    pub killfile: bool,
+
+    /// The images that have been downloaded to preview them.
+    images: HashMap<Url, ImagePreview>,
+
+    /// The posts that have arrived with the downloads since the feed was
+    /// last opened, by the URL of the feed.
+    fresh: HashMap<Url, HashSet<PostId>>,
+
+    /// The URLs of the feeds whose posts are still being loaded from the
+    /// database.
+    loading: HashSet<Arc<str>>,
+
+    /// The URLs of the feeds whose posts couldn't be loaded from the
+    /// database. They aren't downloaded, which would overwrite the stored
+    /// posts, until they're loaded.
+    load_failed: HashSet<Arc<str>>,
 }
 
 impl FeedState {
     /// Create a new feed state.
-    pub fn new(feed_config: FeedConfig) -> Self {
+    pub fn new(feed_config: FeedConfig, settings: Settings) -> Self {
+        // Pick the initial theme. The terminal is asked once it's set up.
+        let kind = match settings.theme.mode {
+            ThemeMode::Dark | ThemeMode::Terminal => ThemeKind::Dark,
+            ThemeMode::Light => ThemeKind::Light,
+            ThemeMode::Time => ThemeKind::for_time_of_day(&settings.theme),
+        };
+
         Self {
+            theme: Theme {
+                focus: settings.theme.focus,
+                ..Theme::new(kind)
+            },
+            settings,
             feed_config,
             downloading: HashMap::new(),
+            downloads: Vec::new(),
+            link_health: HashMap::new(),
+            subscriptions: HashMap::new(),
             spinner: Spinner::new(),
+            killfile: true,
+            images: HashMap::new(),
+            fresh: HashMap::new(),
+            loading: HashSet::new(),
+            load_failed: HashSet::new(),
         }
     }
 
+    /// Whether the `post` is hidden by the killfile.
+    pub fn is_killed(&self, post: &Post) -> bool {
+        self.killfile && self.settings.killfile.kills(post)
+    }
+
+    /// Get the number of posts that have been read in all feeds.
+    pub fn read_posts(&self) -> usize {
+        self.feed_config.sections.iter()
+            .flat_map(|section| section.feeds.iter())
+            .map(|feed| feed.posts.len() - feed.posts.unread())
+            .sum()
+    }
+
+    /// Get when a feed was last downloaded successfully, if any has been.
+    pub fn last_refresh(&self) -> Option<DateTime<Utc>> {
+        self.feed_config.sections.iter()
+            .flat_map(|section| section.feeds.iter())
+            .filter_map(|feed| feed.health.as_ref()?.succeeded)
+            .max()
+    }
+
+    /// Get when the feeds were last refreshed, if none has been since for
+    /// long enough to offer catching up.
+    fn long_break(&self) -> Option<DateTime<Utc>> {
+        let days = self.settings.catch_up.days;
+        self.last_refresh().filter(|&refreshed| {
+            days > 0 && Utc::now() - refreshed >= TimeDelta::days(days.into())
+        })
+    }
+
+    /// Merge the posts of all feeds, newest first.
+    ///
+    /// The posts of different feeds that link to the same article are
+    /// collapsed into one. Posts without a URL are never collapsed.
+    pub fn merged_posts(&self) -> Vec<MergedPost> {
+        self.feed_config.merged_posts()
+    }
+
+    /// Check whether there's any background work we're waiting for.
+    pub fn is_busy(&self) -> bool {
+        !self.downloading.is_empty()
+            || !self.loading.is_empty()
+            || self.link_health.values().any(|h| *h == LinkHealth::Checking)
+            || self.subscriptions.values()
+                .any(|s| matches!(s, Subscription::Discovering))
+            || self.images.values()
+                .any(|image| matches!(image, ImagePreview::Loading))
+    }
+
+    /// Get the download of the image at the `url` to preview, if it has been
+    /// started.
+    pub fn image(&self, url: &Url) -> Option<&ImagePreview> {
+        self.images.get(url)
+    }
+
+    /// Get the state of the subscription started with the `url`, if any.
+    pub fn subscription(&self, url: &Url) -> Option<&Subscription> {
+        self.subscriptions.get(url)
+    }
+
+    /// Get the reachability of the `url` if it has been checked.
+    pub fn link_health(&self, url: &Url) -> Option<&LinkHealth> {
+        self.link_health.get(url)
+    }
+
     /// Check whether the `feed_id` is being currently downloaded.
     pub fn is_downloading(&self, feed_id: &FeedId) -> bool {
-        self.downloading.get(&feed_id)
+        self.downloading.get(feed_id)
             .map(|state| matches!(state, DownloadState::Downloading))
             .unwrap_or(false)
     }
 
+    /// Check whether the posts of a feed are still being loaded from the
+    /// database.
+    pub fn is_loading(&self, feed_id: &FeedId) -> bool {
+        self.get_feed(feed_id)
+            .is_some_and(|feed| self.loading.contains(feed.url.as_str()))
+    }
+
+    /// Check whether the posts of a feed couldn't be loaded from the
+    /// database.
+    pub fn is_load_failed(&self, feed_id: &FeedId) -> bool {
+        self.get_feed(feed_id)
+            .is_some_and(|feed| self.load_failed.contains(feed.url.as_str()))
+    }
+
+    /// Check whether the posts of all feeds have been loaded from the
+    /// database.
+    pub fn is_loaded(&self) -> bool {
+        self.loading.is_empty()
+    }
+
+    /// Get the downloads queued since the downloader was last idle, in the
+    /// order they were queued.
+    pub fn downloads(&self) -> &[Download] {
+        &self.downloads
+    }
+
+    /// Mark the `feed` as queued up for download, unless it's being
+    /// downloaded already.
+    fn queue_download(&mut self, feed: &FeedId) {
+        // The downloads queued while the downloader is idle start over.
+        if self.downloading.is_empty() {
+            self.downloads.clear();
+        }
+        if self.downloading.contains_key(feed) {
+            return;
+        }
+
+        self.downloading.insert(feed.clone(), DownloadState::Queued);
+        self.downloads.retain(|download| download.feed_id != *feed);
+        self.downloads.push(Download {
+            feed_id: feed.clone(),
+            status: DownloadStatus::Queued,
+            started: None,
+            ended: None,
+            bytes: None,
+        });
+    }
+
+    /// Get the latest download of the `feed`.
+    fn download_mut(&mut self, feed: &FeedId) -> Option<&mut Download> {
+        self.downloads.iter_mut().rev()
+            .find(|download| download.feed_id == *feed)
+    }
+
+    /// Mark the `feed` as being downloaded.
+    fn start_download(&mut self, feed: &FeedId) {
+        self.downloading.insert(feed.clone(), DownloadState::Downloading);
+        if let Some(download) = self.download_mut(feed) {
+            download.status = DownloadStatus::Downloading;
+            download.started = Some(Instant::now());
+        }
+    }
+
+    /// Mark the `feed` as waiting for the network to be downloaded.
+    fn wait_download(&mut self, feed: &FeedId) {
+        self.downloading.insert(feed.clone(), DownloadState::Queued);
+        if let Some(download) = self.download_mut(feed) {
+            download.status = DownloadStatus::Waiting;
+        }
+    }
+
+    /// Mark the download of the `feed` as over with the `status`.
+    fn end_download(&mut self, feed: &FeedId, status: DownloadStatus) {
+        self.downloading.remove(feed);
+        if let Some(download) = self.download_mut(feed) {
+            download.status = status;
+            download.ended = Some(Instant::now());
+        }
+    }
+
     /// Get a reference to a feed.
     pub fn get_feed(&self, feed_id: &FeedId) -> Option<&Feed> {
-        self.feed_config.sections.get(feed_id.section_idx)
-            .map(|section| section.feeds.get(feed_id.feed_idx))
-            .flatten()
+        self.feed_config.get_feed(feed_id)
     }
 
     /// Get a mutable reference to a feed.
     pub fn get_feed_mut(&mut self, feed_id: &FeedId) -> Option<&mut Feed> {
-        self.feed_config.sections.get_mut(feed_id.section_idx)
-            .map(|section| section.feeds.get_mut(feed_id.feed_idx))
-            .flatten()
+        self.feed_config.get_feed_mut(feed_id)
     }
 
     /// Get a reference to a section.
@@ -66,17 +354,211 @@ impl FeedState {
         self.feed_config.sections.get(section_idx)
     }
 
+    /// Iterate over the IDs of all feeds.
+    pub fn feed_ids(&self) -> impl Iterator<Item = FeedId> + '_ {
+        self.feed_config.feed_ids()
+    }
+
+    /// Iterate over the titles of all feeds.
+    pub fn feed_titles(&self) -> impl Iterator<Item = String> + '_ {
+        self.feed_config.sections.iter()
+            .flat_map(|section| section.feeds.iter())
+            .map(|feed| feed.title.to_string())
+    }
+
+    /// Find a feed by its title, ignoring case.
+    pub fn find_feed(&self, title: &str) -> Option<FeedId> {
+        self.feed_ids().find(|feed_id| {
+            self.get_feed(feed_id)
+                .is_some_and(|feed| feed.title.eq_ignore_ascii_case(title))
+        })
+    }
+
+    /// Get the number of posts that have arrived in the feed since it was
+    /// last opened.
+    pub fn fresh_posts(&self, feed_id: &FeedId) -> usize {
+        self.get_feed(feed_id)
+            .and_then(|feed| self.fresh.get(&feed.url))
+            .map_or(0, |fresh| fresh.len())
+    }
+
+    /// Take the posts that have arrived in the feed since it was last
+    /// opened, so that they're no longer new the next time.
+    pub fn take_fresh_posts(&mut self, feed_id: &FeedId) -> HashSet<PostId> {
+        let Some(feed) = self.feed_config.get_feed(feed_id) else {
+            return HashSet::new();
+        };
+        self.fresh.remove(&feed.url).unwrap_or_default()
+    }
+
     /// Check if the `feed` contains the following `post`.
     pub fn contains_post(&self, feed: &FeedId, post: &Post) -> bool {
         self.get_feed(feed)
-            .map(|feed| feed.posts.contains(post))
-            .unwrap()
+            .is_some_and(|feed| feed.posts.contains(post))
+    }
+
+    /// Remember how the last download of the `feed` went, returning the
+    /// request that saves it in the database.
+    pub fn record_fetch(&mut self, feed: &FeedId, health: FeedHealth)
+        -> Option<DatabaseRequest>
+    {
+        let feed = self.get_feed_mut(feed)?;
+        feed.record_fetch(health);
+
+        let feed_url = feed.url.as_str().into();
+        let health = feed.health.clone()?;
+        Some(DatabaseRequest::SaveHealth { feed_url, health })
+    }
+
+    /// Remember what the `feed` says about itself, returning the request
+    /// that saves it in the database.
+    pub fn record_meta(&mut self, feed: &FeedId, meta: Arc<FeedMeta>)
+        -> Option<DatabaseRequest>
+    {
+        let feed = self.get_feed_mut(feed)?;
+        feed.meta = Some(meta.clone());
+
+        let feed_url = feed.url.as_str().into();
+        Some(DatabaseRequest::SaveMeta { feed_url, meta })
+    }
+}
+
+/// A summary of a reading session, printed on quit.
+pub struct SessionSummary {
+    /// The number of posts that have been read during the session.
+    pub posts_read: usize,
+
+    /// The number of feeds that have been downloaded successfully.
+    pub feeds_refreshed: usize,
+
+    /// How long the session took.
+    pub duration: Duration,
+}
+
+impl fmt::Display for SessionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = self.duration.as_secs();
+        let (hours, mins, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+        write!(f, "Read {} posts, refreshed {} feeds in ",
+            self.posts_read, self.feeds_refreshed)?;
+        if hours > 0 {
+            write!(f, "{}h {}m", hours, mins)
+        } else if mins > 0 {
+            write!(f, "{}m {}s", mins, secs)
+        } else {
+            write!(f, "{}s", secs)
+        }
     }
+}
+
+/// The message shown when something can't be done in read-only mode.
+const READ_ONLY: &str = "Read-only: nothing is downloaded or marked as read";
+
+/// The message shown when a feed is gone by the time it's acted on, e.g.
+/// removed from the feed file meanwhile.
+const FEED_GONE: &str = "The feed isn't there anymore";
+
+/// The message shown when the feed read from stdin is to be downloaded.
+const NOT_DOWNLOADABLE: &str = "The feed read from stdin can't be downloaded";
+
+/// The message shown when feeds are to be downloaded before their posts have
+/// been loaded, which the downloaded posts would be merged with.
+const LOADING: &str = "The posts are still being loaded";
+
+/// The message shown when a feed whose posts couldn't be loaded is to be
+/// downloaded, and they're loaded again instead.
+const LOAD_RETRY: &str = "The posts couldn't be loaded, loading them again";
+
+/// How long the downloads in progress may take to finish when quitting.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How often the clipboard is read while it's watched.
+const CLIPBOARD_POLL: Duration = Duration::from_secs(1);
+
+/// How often the feeds are checked for being due to be downloaded again.
+const REFRESH_POLL: Duration = Duration::from_secs(30);
+
+/// The state of watching the feed file for changes.
+struct FeedFileWatch {
+    /// The path to the feed file.
+    path: PathBuf,
+
+    /// Whether the feed file has been saved since it was last read.
+    changed: bool,
+}
+
+/// A shell command run in the terminal while the TUI is suspended.
+struct ExternalCommand {
+    /// The command line, run with `sh -c`.
+    command: String,
+
+    /// The text piped into the command, if any.
+    stdin: Option<String>,
+}
+
+/// The state of watching the clipboard for feed URLs.
+struct ClipboardWatch {
+    /// The text in the clipboard when it was last read.
+    contents: Option<String>,
+
+    /// When the clipboard was last read.
+    read_at: Instant,
+}
+
+/// A question shown at the bottom of the screen, which is answered by the
+/// next key: usually `y` for yes, anything else for no.
+#[derive(PartialEq, Eq)]
+enum Prompt {
+    /// Subscribe to the feed URL found in the clipboard.
+    Subscribe(Url),
 
-    /// Insert new `posts` into `feed`.
-    pub fn insert_posts(&mut self, feed: &FeedId, posts: Posts) {
-        let feed = self.get_feed_mut(feed).unwrap();
-        feed.posts.append(posts);
+    /// Mark the given number of posts of a feed downloaded for the first time
+    /// as read.
+    MarkBacklogRead(FeedId, usize),
+
+    /// Mark the posts of the feed older than the age to be answered with as
+    /// read.
+    MarkReadOlder(FeedId),
+
+    /// Remove the feed from the feed file.
+    RemoveFeed(FeedId),
+
+    /// Point the feed at where it has permanently moved to in the feed file.
+    UpdateMovedFeed(FeedId),
+}
+
+impl Prompt {
+    /// Get the question asked by this prompt.
+    fn question(&self, state: &FeedState) -> String {
+        match self {
+            Self::Subscribe(url) => format!("Subscribe to {}? [y/N]", url),
+            Self::MarkBacklogRead(feed_id, posts) => {
+                let title = state.get_feed(feed_id)
+                    .map_or("The new feed", |feed| &feed.title);
+                format!("{} has {} posts. Mark them all as read? [y/N]",
+                    title, posts)
+            },
+            Self::MarkReadOlder(feed_id) => {
+                let title = state.get_feed(feed_id)
+                    .map_or("the feed", |feed| &feed.title);
+                format!("Mark the posts of {} older than a day, week or month \
+                    as read? [d/w/m/N]", title)
+            },
+            Self::RemoveFeed(feed_id) => {
+                let title = state.get_feed(feed_id)
+                    .map_or("the feed", |feed| &feed.title);
+                format!("Remove {} from the feed file? [y/N]", title)
+            },
+            Self::UpdateMovedFeed(feed_id) => {
+                let feed = state.get_feed(feed_id);
+                let title = feed.map_or("The feed", |feed| &feed.title);
+                let url = feed.and_then(|feed| feed.moved.as_ref())
+                    .map_or_else(String::new, |url| url.to_string());
+                format!("{} has moved to {}. Update the feed file? [y/N]",
+                    title, url)
+            },
+        }
     }
 }
 
@@ -91,287 +573,2507 @@ pub struct App {
     /// Application state.
     feed_state: FeedState,
 
-    /// State of the background feed downloader.
-    download: DownloadChannel,
+    /// Requests to the background feed downloader.
+    download: std::sync::mpsc::Sender<DownloadRequest>,
+
+    /// The channel of the events from the terminal, the downloader and the
+    /// database.
+    events: EventChannel,
 
     /// State of the background feed storage.
     database: DatabaseChannel,
-}
 
-impl App {
-    /// Create a new application state given the `config`.
-    pub fn new(mut feeds: FeedConfig) -> Self {
-        let download = DownloadChannel::spawn_downloader_thread();
-        let database = DatabaseChannel::spawn_database_thread(&mut feeds);
-        let pages = vec![Box::new(main::MainPage::new(&feeds)) as Box<dyn Page>];
-        let feed_state = FeedState::new(feeds);
+    /// The `:` command line, if it's open.
+    command_line: Option<CommandLine>,
 
-        Self { download, database, pages, feed_state }
-    }
+    /// A message shown at the bottom of the screen until the next key press.
+    message: Option<String>,
 
-    /// Run the application.
-    pub fn run<B: Backend>(mut self, terminal: &mut Terminal<B>) {
-        // Set the tick rate for animations.
-        let fps = 60;
-        let tick_rate = Duration::from_millis(1000 / fps);
-        let mut last_tick = Instant::now();
+    /// Whether the previous key was the first `g` of `gg`.
+    pending_g: bool,
 
-        loop {
-            // Draw the page.
-            terminal.draw(|f| self.draw(f)).unwrap();
-
-            // If there's an active download, we have to do ticks because of
-            // animations and polls and stuff.
-            if !self.feed_state.downloading.is_empty() {
-                // Handle events from the background downloader.
-                self.handle_download_events();
-
-                // Our input handler _blocks_, so we will poll for events on a
-                // timeout and only call the handler when we get an event.
-                let timeout = tick_rate
-                    .checked_sub(last_tick.elapsed())
-                    .unwrap_or(Duration::ZERO);
-
-                if event::poll(timeout).unwrap() {
-                    if self.handle_input() {
-                        break;
-                    }
-                }
+    /// The count typed in before a command, e.g. the `15` in `15j`.
+    count: Option<usize>,
 
-                // Animate the global spinner.
-                if last_tick.elapsed() >= tick_rate {
-                    let now = Instant::now();
-                    self.feed_state.spinner.tick(now);
-                    last_tick = now;
-                }
-            } else {
-                // No active download. We can block on input
-                if self.handle_input() {
-                    break;
-                }
-            }
-        }
-    }
+    /// Whether the theme follows the time of day.
+    auto_theme: bool,
 
-    /// Handle the input for the app in a blocking manner.
-    fn handle_input(&mut self) -> bool {
-        // Get the key.
-        let Event::Key(key) = event::read().unwrap() else {
-            return false;
-        };
+    /// When the application has been started.
+    started: Instant,
 
-        // Global escape: pop page if possible. If we're on the first page, we
-        // allow this event to reach it, otherwise we use it to pop the current
-        // page.
-        if self.pages.len() > 1 {
-            if matches!(key.code, KeyCode::Esc | KeyCode::Char('h')) {
-                self.go_back();
-                return false;
-            }
-        }
+    /// The number of read posts when the application has been started.
+    read_at_start: usize,
 
-        // Shared list navigation hook for all pages. If we handle the input
-        // here, it won't be passed to the page specific handler.
-        let page = self.pages.last_mut().unwrap();
-        let mut input_handled = true;
-        match key.code {
-            KeyCode::Up | KeyCode::Char('k') => page.list().up(1),
-            KeyCode::Down | KeyCode::Char('j') => page.list().down(1),
-            KeyCode::PageUp | KeyCode::Char('K') => page.list().up(10),
-            KeyCode::PageDown | KeyCode::Char('J') => page.list().down(10),
-            KeyCode::Char('g') => page.list().up(usize::MAX),
-            KeyCode::Char('G') => page.list().down(usize::MAX),
-            KeyCode::Char('q') => return true,
-            _ => input_handled = false,
-        }
+    /// The number of feeds that have been downloaded successfully.
+    feeds_refreshed: usize,
 
-        // If we have handled the input above, there's nothing else to do.
-        if input_handled {
-            return false;
-        }
+    /// When the TUI becomes idle, if idling is enabled.
+    idle_deadline: Option<Instant>,
 
-        // We haven't handled the input above. The page might wanna handle it
-        // instead.
-        match page.on_key(key.code, &mut self.feed_state) {
-            PageAction::None                  => {},
-            PageAction::NewPage(p)            => self.new_page(p),
-            PageAction::DownloadFeed(feed_id) => self.start_download(feed_id),
-            PageAction::DownloadAllFeeds      => self.download_all(),
-            PageAction::CopyToClipboard(url)  => Self::to_clipboard(&url),
+    /// Whether the screensaver is shown.
+    screensaver: bool,
 
-            PageAction::MarkFeedRead(feed_id) => {
-                // Crate the vector that will be saved in the database.
-                let mut posts = Posts::new();
+    /// Requests waiting for space in the database queue, oldest first, so
+    /// that the UI never waits for the database.
+    pending_saves: VecDeque<DatabaseRequest>,
 
-                // Go through each post in the feed and mark it as read.
-                let feed = self.feed_state.get_feed_mut(&feed_id).unwrap();
+    /// Whether the downloaded posts are among the `pending_saves`, in which
+    /// case no more feeds are let through from the downloader until they're
+    /// sent.
+    downloads_held: bool,
 
-                // Build a vector of unread posts.
-                let posts_to_mark: Vec<Post> = feed.posts.as_ref().iter()
-                    .filter(|post| !post.read)
-                    .cloned()
-                    .collect();
+    /// When the feeds were last refreshed before a long break, if the
+    /// catch-up page is yet to be shown after the first refresh.
+    catch_up_since: Option<DateTime<Utc>>,
 
-                // Mark the unread posts as read.
-                for mut post in posts_to_mark.into_iter() {
-                    feed.posts.mark_read(&post.id, true);
-                    post.read = true;
-                    posts.insert(post);
-                }
+    /// The clipboard watch, if the clipboard is watched for feed URLs.
+    clipboard_watch: Option<ClipboardWatch>,
 
-                // Save the unread posts in our database.
-                let feed_url = feed.url.as_str().into();
-                self.database.request_tx.send(DatabaseRequest::SavePosts {
-                    feed_url, posts
-                }).expect("Database channel closed abruptly");
-            },
+    /// The questions waiting to be answered, asked one at a time.
+    prompts: VecDeque<Prompt>,
 
-            PageAction::TogglePostRead(feed_id, post_id) => {
-                // Get the post and toggle its read state.
-                let feed = self.feed_state.get_feed_mut(&feed_id).unwrap();
-                feed.posts.toggle_read(&post_id);
+    /// Runs the hooks of the newly downloaded posts.
+    hooks: HookRunner,
 
-                // Save the post in our database.
-                let post = feed.posts.get_by_id(&post_id).unwrap();
-                let posts = Posts::from(post.clone());
-                let feed_url = feed.url.as_str().into();
-                self.database.request_tx.send(DatabaseRequest::SavePosts {
-                    feed_url, posts
-                }).expect("Database channel closed abruptly");
-            },
-        }
+    /// The shell command of an external action or the pager, run with the
+    /// terminal suspended before the next redraw.
+    pending_action: Option<ExternalCommand>,
 
-        false
+    /// The feed file, whose changes are merged in as they're saved.
+    feed_file: Option<FeedFileWatch>,
+
+    /// When the refresh intervals of the feeds were last checked.
+    refresh_checked_at: Instant,
+
+    /// The protocol the images are previewed with, if the terminal supports
+    /// one.
+    image_protocol: Option<Protocol>,
+
+    /// The image drawn over the page, and where.
+    shown_image: Option<(Url, Rect)>,
+
+    /// The session the application last quit with, opened once the posts
+    /// of all feeds have been loaded unless a key has been pressed meanwhile.
+    session: Option<Session>,
+
+    /// Whether the posts of the feeds the application started with have
+    /// been loaded.
+    loaded: bool,
+
+    /// The URLs of the feeds added meanwhile, downloaded once their stored
+    /// posts have been loaded.
+    download_on_load: HashSet<Arc<str>>,
+}
+
+impl App {
+    /// Create a new application state given the `config`.
+    pub fn new(feeds: FeedConfig, settings: Settings) -> Result<Self> {
+        let (database, session) =
+            DatabaseChannel::spawn_loading_thread(&settings)?;
+        let cache = BodyCache::open(&settings)?;
+        let mut app = Self::with_database(feeds, settings, database,
+            Some(cache));
+        app.load_feeds(session);
+        Ok(app)
     }
 
-    /// Copy the string `s` into the system clipboard using wl-copy.
-    fn to_clipboard(s: &str) {
-        let mut child = Command::new("wl-copy")
-            .stdin(Stdio::piped())
-            .spawn()
-            .expect("Failed to spawn wl-copy");
+    /// Create a new application state whose posts are never stored in the
+    /// database.
+    pub fn new_ephemeral(feeds: FeedConfig, settings: Settings) -> Self {
+        let database = DatabaseChannel::spawn_ephemeral_thread();
+        Self::with_database(feeds, settings, database, None)
+    }
 
-        {
-            let stdin = child
-                .stdin
-                .as_mut()
-                .expect("Failed to open stdin for wl-copy");
+    /// Create a new application state backed by `database`, keeping the
+    /// downloaded bodies of the feeds in the `cache`.
+    fn with_database(
+        feeds: FeedConfig,
+        settings: Settings,
+        database: DatabaseChannel,
+        cache: Option<BodyCache>,
+    ) -> Self {
+        let DownloadChannel { request_tx: download, response_rx } =
+            DownloadChannel::spawn_downloader_thread(
+                settings.queues.downloads, settings.titles.clone(),
+                settings.politeness, settings.timeouts, cache,
+                settings.network.clone());
+        let mut events = EventChannel::new();
+        events.forward_downloads(response_rx);
+        database.notify(events.sender());
+        let hooks = HookRunner::new(settings.hooks.clone());
+        let pages = vec![Box::new(main::MainPage::new(&feeds)) as Box<dyn Page>];
+        let auto_theme = settings.theme.mode == ThemeMode::Time;
+        let feed_state = FeedState::new(feeds, settings);
+        let read_at_start = feed_state.read_posts();
+        let idle_deadline = feed_state.settings.idle.period()
+            .map(|period| Instant::now() + period);
 
-            stdin
-                .write_all(s.as_bytes())
-                .expect("Failed to write to wl-copy stdin");
-        }
+        // Nothing has been refreshed for a while.
+        let catch_up_since = feed_state.long_break();
 
-        let status = child.wait().expect("Failed to wait on wl-copy");
+        // Say why nothing is downloaded or marked as read.
+        let message = feed_state.settings.storage.read_only
+            .then(|| READ_ONLY.to_string());
 
-        if !status.success() {
-            panic!("wl-copy exited with non-zero status");
+        Self {
+            download,
+            events,
+            database,
+            pages,
+            feed_state,
+            command_line: None,
+            message,
+            pending_g: false,
+            count: None,
+            auto_theme,
+            started: Instant::now(),
+            read_at_start,
+            feeds_refreshed: 0,
+            idle_deadline,
+            screensaver: false,
+            pending_saves: VecDeque::new(),
+            downloads_held: false,
+            catch_up_since,
+            clipboard_watch: None,
+            prompts: VecDeque::new(),
+            hooks,
+            pending_action: None,
+            feed_file: None,
+            refresh_checked_at: Instant::now(),
+            image_protocol: None,
+            shown_image: None,
+            session: None,
+            loaded: false,
+            download_on_load: HashSet::new(),
         }
     }
 
-    /// Go back from the currently shown page to the one before.
-    fn go_back(&mut self) {
-        if self.pages.len() > 1 {
-            self.pages.pop();
+    /// Load the posts of all feeds from the database in the background,
+    /// opening the pages of the `session` once they're loaded.
+    fn load_feeds(&mut self, session: Session) {
+        let urls: HashSet<Arc<str>> = self.feed_state.feed_config.sections
+            .iter()
+            .flat_map(|section| section.feeds.iter())
+            .map(|feed| feed.url.as_str().into())
+            .collect();
+        self.session = Some(session);
+        if urls.is_empty() {
+            self.on_loaded();
+            return;
         }
-    }
 
-    /// Go from the current page to a new page.
-    fn new_page(&mut self, mut page: Box<dyn Page>) {
-        page.on_new(&mut self.feed_state, &self.database);
-        self.pages.push(page);
+        let request = DatabaseRequest::LoadFeeds(urls.iter().cloned()
+            .collect());
+        self.feed_state.loading = urls;
+        self.database.send(request);
     }
 
-    /// Draw the page.
-    fn draw(&mut self, f: &mut Frame) {
-        self.pages.last_mut().unwrap().draw(f, &self.feed_state);
+    /// Open the pages of the last session and check for a long break, once
+    /// the posts of all feeds have been loaded.
+    fn on_loaded(&mut self) {
+        self.loaded = true;
+        if let Some(session) = self.session.take() {
+            self.restore(session);
+        }
+        self.catch_up_since = self.feed_state.long_break();
     }
 
-    /// Start downloading a single feed.
-    fn start_download(&mut self, feed: FeedId) {
-        // Mark the feed as queued up for download.
-        self.feed_state.downloading.insert(feed.clone(), DownloadState::Queued);
+    /// Quit the application gracefully on SIGTERM and SIGHUP, instead of
+    /// being killed in the middle of saving.
+    pub fn quit_on_signals(&self) -> io::Result<()> {
+        use signal_hook::consts::{SIGHUP, SIGTERM};
 
-        // Send the request to the downloader.
-        let url = self.feed_state.get_feed(&feed).unwrap().url.clone();
-        self.download
-            .request_tx
-            .send(DownloadRequest::Feed { feed, url })
-            .expect("The downloader has closed abruptly.");
+        self.events.forward_signals(&[SIGTERM, SIGHUP])
     }
 
-    /// Download all feeds.
+    /// Merge in the changes of the feed file as they're saved, for feeds
+    /// that have been read from it.
+    pub fn watch_feed_file(&mut self) -> io::Result<()> {
+        let Some(path) = FeedConfig::get_feed_file()? else {
+            return Ok(());
+        };
+        self.events.forward_feed_file_changes(&path)?;
+        self.feed_file = Some(FeedFileWatch { path, changed: false });
+        Ok(())
+    }
+
+    /// Run the application and return the summary of the session.
     ///
-    /// One downloader is spawned for each section.
-    fn download_all(&mut self) {
-        // Build the URL map for the request.
-        let url_map = UrlMap::from(&self.feed_state.feed_config);
-
-        // Queue up all feeds.
-        for (section_idx, section) in url_map.0.iter().enumerate() {
-            for (feed_idx, _) in section.iter().enumerate() {
-                let feed = FeedId { section_idx, feed_idx };
-
-                // If we're already downloading something, do not change the
-                // queue state.
-                //
-                // NOTE We intentionally do not remove feeds from the map while
-                // downloading. `FeedId` is index-based (section_idx, feed_idx),
-                // so removing a feed would shift indices and invalidate
-                // existing IDs.
-                //
-                // Re-queuing a feed that is already downloading is not ideal,
-                // but in practice this is unlikely for my use case
-                // (downloading all feeds at app startup), so we accept the
-                // trade-off.
-                if !self.feed_state.downloading.contains_key(&feed) {
-                    self.feed_state.downloading
-                        .insert(feed, DownloadState::Queued);
+    /// Fails only when the terminal can't be drawn to or read from. Even
+    /// then, everything is saved before returning.
+    pub fn run<B>(mut self, terminal: &mut Terminal<B>)
+        -> io::Result<SessionSummary>
+    where
+        B: Backend,
+        io::Error: From<B::Error>,
+    {
+        let result = self.event_loop(terminal);
+        let summary = self.shutdown();
+        result.map(|()| summary)
+    }
+
+    /// Draw the pages and handle the input until the application quits.
+    fn event_loop<B>(&mut self, terminal: &mut Terminal<B>) -> io::Result<()>
+    where
+        B: Backend,
+        io::Error: From<B::Error>,
+    {
+        // Set the tick rate for animations.
+        let animations = self.feed_state.settings.ui.is_animated();
+        let low_bandwidth = self.feed_state.settings.ui.is_low_bandwidth();
+        let tick_rate = self.feed_state.settings.ui.tick_rate();
+        let mut last_tick = Instant::now();
+
+        // Match the theme to the terminal background.
+        if self.feed_state.settings.theme.mode == ThemeMode::Terminal
+            && let Some(kind) = theme::query_terminal_background()
+        {
+            self.feed_state.theme.set_kind(kind);
+        }
+
+        // Find out how the images can be previewed.
+        self.image_protocol =
+            image::detect(self.feed_state.settings.ui.image_protocol());
+
+        // The terminal is only read by the input reader from now on.
+        let input = InputReader::spawn(self.events.sender());
+
+        loop {
+            // Switch the theme when the day or night starts.
+            if self.auto_theme {
+                let kind = ThemeKind::for_time_of_day(
+                    &self.feed_state.settings.theme);
+                if kind != self.feed_state.theme.kind {
+                    self.feed_state.theme.set_kind(kind);
                 }
             }
-        }
 
-        // Send the request to the downloader.
-        self.download
-            .request_tx
-            .send(DownloadRequest::All(url_map))
-            .expect("The downloader has closed abruptly.");
-    }
-
-    /// Handle events from the background downloader _in a non-blocking manner_.
-    fn handle_download_events(&mut self) {
-        for response in self.download.response_rx.try_iter() {
-            match response {
-                DownloadResponse::Started(feed) => {
-                    self.feed_state.downloading.insert(
-                        feed, DownloadState::Downloading);
-                },
-                DownloadResponse::Failed(feed) => {
-                    self.feed_state.downloading.remove(&feed);
-                },
-                DownloadResponse::Finished { feed, mut posts } => {
-                    // Retain only new posts.
-                    posts.retain(|p| !self.feed_state.contains_post(&feed, p));
-
-                    // Save them in the feed.
-                    self.feed_state.insert_posts(&feed, posts.clone());
-
-                    // Save them in the database.
-                    let feed_url = self.feed_state.get_feed(&feed)
-                        .unwrap()
-                        .url
-                        .as_str()
-                        .into();
-
-                    self.database.request_tx.send(DatabaseRequest::SavePosts {
-                        feed_url, posts
-                    }).expect("The database channel closed abruptly.");
-
-                    // Remove the feed's downloading status.
-                    self.feed_state.downloading.remove(&feed);
-                },
+            // Nobody has pressed a key for a while.
+            if let Some(deadline) = self.idle_deadline
+                && Instant::now() >= deadline
+            {
+                self.on_idle();
             }
-        }
+
+            // Offer to subscribe to the feeds copied meanwhile.
+            self.poll_clipboard();
+
+            // Merge in the changes saved to the feed file meanwhile.
+            self.reload_feed_file();
+
+            // Download the feeds whose refresh intervals have passed.
+            self.poll_refresh();
+
+            // Tell the user about the requests the database couldn't handle.
+            if let Some(err) = self.database.errors().last() {
+                self.message = Some(format!("Database error: {}", err));
+            }
+
+            // Hand the terminal over to the chosen external action.
+            if let Some(command) = self.pending_action.take() {
+                self.run_action(terminal, &input, &command)?;
+            }
+
+            // Draw the page, and the image previewed on it.
+            terminal.draw(|f| self.draw(f))?;
+            self.draw_image(terminal)?;
+            let drawn = Instant::now();
+
+            // While something is going on in the background, tick for the
+            // animations. Otherwise wait until something has to be done.
+            let busy = self.feed_state.is_busy() || self.is_saving();
+            let tick = busy
+                .then(|| tick_rate.saturating_sub(last_tick.elapsed()));
+            let timeout = [tick, self.input_timeout()].into_iter()
+                .flatten()
+                .min();
+
+            // Handle the event, and whatever else has arrived meanwhile
+            // before drawing again.
+            let mut event = Some(self.events.next(timeout));
+            let mut pressed = false;
+            while let Some(next) = event {
+                pressed |= matches!(next, AppEvent::Input(_));
+                if self.handle_event(next)? {
+                    return Ok(());
+                }
+                event = self.events.try_next();
+
+                // Over a slow connection, only the key presses are answered
+                // right away. The rest waits for the next tick.
+                let wait = tick_rate.saturating_sub(drawn.elapsed());
+                if event.is_none() && low_bandwidth && !pressed
+                    && !wait.is_zero()
+                {
+                    event = Some(self.events.next(Some(wait)));
+                }
+            }
+
+            // Animate the global spinner.
+            if last_tick.elapsed() >= tick_rate {
+                let now = Instant::now();
+                if animations {
+                    self.feed_state.spinner.tick(now);
+                }
+                last_tick = now;
+            }
+        }
+    }
+
+    /// Handle the `event`, returning whether the application should quit.
+    fn handle_event(&mut self, event: AppEvent) -> io::Result<bool> {
+        match event {
+            AppEvent::Input(event) => return Ok(self.handle_input(event)),
+            AppEvent::InputFailed(err) => return Err(err),
+            AppEvent::Download(response) => self.handle_download(response),
+            AppEvent::Saved => self.flush_pending_saves(),
+            AppEvent::Database(response) => self.handle_database(response),
+            // Quit as if `q` was pressed.
+            AppEvent::Quit => return Ok(true),
+            AppEvent::FeedFileChanged => {
+                if let Some(watch) = &mut self.feed_file {
+                    watch.changed = true;
+                }
+            },
+            AppEvent::Tick => {},
+        }
+        Ok(false)
+    }
+
+    /// Save everything that is still pending and stop the background
+    /// threads, returning the summary of the session.
+    ///
+    /// The downloads in progress are given a while to finish, so that their
+    /// posts are saved too. The read states and views are saved as soon as
+    /// they change, so they're only waited for.
+    fn shutdown(mut self) -> SessionSummary {
+        self.save_session();
+
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        loop {
+            // Nothing is drawn anymore, so wait for room in the queue of the
+            // database instead of for the event.
+            for request in self.pending_saves.drain(..) {
+                self.database.send(request);
+            }
+            if std::mem::take(&mut self.downloads_held) {
+                self.events.resume_downloads();
+            }
+
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if self.feed_state.downloading.is_empty() || timeout.is_zero() {
+                break;
+            }
+            if let AppEvent::Download(response) =
+                self.events.next(Some(timeout))
+            {
+                self.handle_download(response);
+            }
+        }
+
+        // Wait until everything has been saved. The errors have been logged
+        // by the database thread already.
+        self.database.close();
+        self.hooks.finish(Some(deadline));
+
+        // Posts marked as unread again are subtracted.
+        let read = self.feed_state.read_posts();
+        SessionSummary {
+            posts_read: read.saturating_sub(self.read_at_start),
+            feeds_refreshed: self.feeds_refreshed,
+            duration: self.started.elapsed(),
+        }
+    }
+
+    /// Get how long we can wait for input before something has to be done,
+    /// or `None` if we can wait forever.
+    fn input_timeout(&self) -> Option<Duration> {
+        // Check whether the theme should be switched every minute.
+        let theme = self.auto_theme.then_some(Duration::from_secs(60));
+
+        let idle = self.idle_deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+        // Keep the clock ticking.
+        let clock = (self.screensaver
+            && self.feed_state.settings.idle.screensaver == Screensaver::Clock)
+            .then_some(Duration::from_secs(1));
+
+        let clipboard = self.clipboard_watch.as_ref().map(|watch| {
+            CLIPBOARD_POLL.saturating_sub(watch.read_at.elapsed())
+        });
+
+        let refresh = self.feed_state.feed_ids()
+            .filter_map(|feed_id| self.feed_state.get_feed(&feed_id))
+            .any(|feed| feed.options.refresh.is_some())
+            .then(|| REFRESH_POLL.saturating_sub(
+                self.refresh_checked_at.elapsed()));
+
+        [theme, idle, clock, clipboard, refresh].into_iter()
+            .flatten()
+            .min()
+    }
+
+    /// Refresh the feeds and show the screensaver, as configured.
+    ///
+    /// Called every idle period until a key is pressed.
+    fn on_idle(&mut self) {
+        self.reset_idle_deadline();
+
+        let idle = &self.feed_state.settings.idle;
+        self.screensaver = idle.screensaver != Screensaver::None;
+
+        if idle.refresh && !self.feed_state.is_busy() && !self.is_read_only() {
+            self.download_all();
+        }
+    }
+
+    /// Whether nothing may be downloaded or marked as read, so that the
+    /// database stays as it is.
+    fn is_read_only(&self) -> bool {
+        self.feed_state.settings.storage.read_only
+    }
+
+    /// Fail if the feeds are the subscriptions of a remote rather than the
+    /// feed file.
+    fn check_feed_file(&self) -> Result<(), String> {
+        crate::remote::check_feed_file(&self.feed_state.settings)
+    }
+
+    /// Start counting the idle period from now.
+    fn reset_idle_deadline(&mut self) {
+        self.idle_deadline = self.feed_state.settings.idle.period()
+            .map(|period| Instant::now() + period);
+    }
+
+    /// Handle the input `event` read from the terminal.
+    fn handle_input(&mut self, event: Event) -> bool {
+        // Only keys are handled.
+        let Event::Key(key) = event else {
+            return false;
+        };
+
+        // We're not idle anymore. The key that hides the screensaver doesn't
+        // do anything else.
+        self.reset_idle_deadline();
+        self.session = None;
+        if std::mem::take(&mut self.screensaver) {
+            return false;
+        }
+
+        // The prompt takes the key, which either confirms or dismisses it.
+        // Escape dismisses all of the prompts at once.
+        if self.command_line.is_none()
+            && let Some(prompt) = self.prompts.pop_front()
+        {
+            match key.code {
+                KeyCode::Char(c) => self.answer(prompt, c),
+                KeyCode::Esc => self.prompts.clear(),
+                _ => {},
+            }
+            return false;
+        }
+
+        // Any key press dismisses the message.
+        self.message = None;
+
+        // If the command line is open, it takes all of the input.
+        if self.command_line.is_some() {
+            return self.handle_command_line_input(key.code);
+        }
+
+        // A page that is typed into takes all of the keys, Escape leaves it.
+        let page = self.pages.last_mut().unwrap();
+        if page.is_editing() {
+            if key.code == KeyCode::Esc {
+                self.go_back();
+            } else {
+                let action = page.on_key(key.code, &self.feed_state);
+                self.handle_action(action);
+            }
+            return false;
+        }
+
+        // Buffer the count prefix. A leading zero is not a count.
+        if let KeyCode::Char(c @ '0'..='9') = key.code
+            && (c != '0' || self.count.is_some())
+        {
+            let digit = c.to_digit(10).unwrap() as usize;
+            let count = self.count.unwrap_or(0);
+            self.count = Some(count.saturating_mul(10).saturating_add(digit));
+            return false;
+        }
+
+        // The count applies to this key only.
+        let count = self.count.take();
+        let n = count.unwrap_or(1);
+
+        // Global escape: pop page if possible. If we're on the first page, we
+        // allow this event to reach it, otherwise we use it to pop the current
+        // page.
+        if self.pages.len() > 1
+            && matches!(key.code, KeyCode::Esc | KeyCode::Char('h'))
+        {
+            for _ in 0..n {
+                self.go_back();
+            }
+            return false;
+        }
+
+        // Shared list navigation hook for all pages. If we handle the input
+        // here, it won't be passed to the page specific handler.
+        let page = self.pages.last_mut().unwrap();
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let pending_g = std::mem::take(&mut self.pending_g);
+        let half_page = (page.list().page_height() / 2).max(1);
+        let mut input_handled = true;
+        match key.code {
+            KeyCode::Char('d') if ctrl => {
+                page.list().down(half_page.saturating_mul(n))
+            },
+            KeyCode::Char('u') if ctrl => {
+                page.list().up(half_page.saturating_mul(n))
+            },
+            KeyCode::Up | KeyCode::Char('k') => page.list().up(n),
+            KeyCode::Down | KeyCode::Char('j') => page.list().down(n),
+            KeyCode::PageUp | KeyCode::Char('K') => {
+                page.list().up(10usize.saturating_mul(n))
+            },
+            KeyCode::PageDown | KeyCode::Char('J') => {
+                page.list().down(10usize.saturating_mul(n))
+            },
+
+            // With a count, `gg` and `G` go to the entry with that index.
+            KeyCode::Char('g') if pending_g => {
+                page.list().select(count.unwrap_or(0))
+            },
+            KeyCode::Char('g') => {
+                self.pending_g = true;
+                self.count = count;
+            },
+            KeyCode::Char('G') if count.is_some() => page.list().select(n),
+            KeyCode::Home => page.list().first(),
+            KeyCode::End | KeyCode::Char('G') => page.list().last(),
+            KeyCode::Char('q') => return true,
+            KeyCode::Char(':') => self.command_line = Some(CommandLine::new()),
+
+            // Toggling the theme by hand stops it from following the time.
+            KeyCode::Char('T') => {
+                let kind = self.feed_state.theme.kind.toggled();
+                self.feed_state.theme.set_kind(kind);
+                self.auto_theme = false;
+            },
+
+            // Toggle the focus mode.
+            KeyCode::Char('F') => {
+                self.feed_state.theme.focus = !self.feed_state.theme.focus;
+            },
+            _ => input_handled = false,
+        }
+
+        // If we have handled the input above, there's nothing else to do.
+        if input_handled {
+            return false;
+        }
+
+        // We haven't handled the input above. The page might wanna handle it
+        // instead. The count only moves around, so it's ignored here.
+        let page = self.pages.last_mut().unwrap();
+        let action = page.on_key(key.code, &self.feed_state);
+        self.handle_action(action);
+
+        false
+    }
+
+    /// Handle the input while the command line is open.
+    fn handle_command_line_input(&mut self, key: KeyCode) -> bool {
+        let command_line = self.command_line.as_mut().unwrap();
+
+        match key {
+            KeyCode::Esc => self.command_line = None,
+            KeyCode::Tab => command_line.complete(&self.feed_state),
+            KeyCode::Char(c) => command_line.push(c),
+
+            // Deleting past the start of the input closes the command line.
+            KeyCode::Backspace if !command_line.pop() => {
+                self.command_line = None;
+            },
+
+            KeyCode::Enter => {
+                let command_line = self.command_line.take().unwrap();
+                match Command::parse(command_line.input()) {
+                    Ok(command) => return self.run_command(command),
+                    Err(err) => self.message = Some(err),
+                }
+            },
+
+            _ => {},
+        }
+
+        false
+    }
+
+    /// Run a command from the command line. Returns `true` if the application
+    /// should quit.
+    fn run_command(&mut self, command: Command) -> bool {
+        match command {
+            Command::Quit => return true,
+            Command::RefreshAll => self.download_all(),
+
+            Command::Refresh(title) => match self.feed_state.find_feed(&title) {
+                Some(feed_id) => self.start_download(feed_id),
+                None => self.message = Some(format!("No such feed: {}", title)),
+            },
+
+            Command::MarkReadOlder(age, None) => {
+                self.mark_read_older(age, None);
+            },
+            Command::MarkReadOlder(age, Some(title)) => {
+                match self.feed_state.find_feed(&title) {
+                    Some(feed_id) => self.mark_read_older(age, Some(&feed_id)),
+                    None => {
+                        self.message = Some(format!("No such feed: {}", title));
+                    },
+                }
+            },
+
+            // Select the entry and act as if it was entered.
+            Command::Open(idx) => {
+                let page = self.pages.last_mut().unwrap();
+                page.list().select(idx);
+                let action = page.on_key(KeyCode::Enter, &self.feed_state);
+                self.handle_action(action);
+            },
+
+            Command::Goto(title) => {
+                let Some(feed_id) = self.feed_state.find_feed(&title) else {
+                    self.message = Some(format!("No such feed: {}", title));
+                    return false;
+                };
+
+                // Go back to the main page and open the feed from there.
+                self.pages.truncate(1);
+                let Some(feed) = self.feed_state.get_feed(&feed_id) else {
+                    return false;
+                };
+                if feed.posts.is_empty() {
+                    self.message = Some(format!("Feed is empty: {}", title));
+                } else {
+                    self.new_page(Box::new(feed::FeedPage::new(feed_id)));
+                }
+            },
+
+            Command::AsOf(day, title) => {
+                let Some(feed_id) = self.feed_state.find_feed(&title) else {
+                    self.message = Some(format!("No such feed: {}", title));
+                    return false;
+                };
+
+                self.pages.truncate(1);
+                let page = feed::FeedPage::as_of(feed_id, day);
+                self.new_page(Box::new(page));
+            },
+
+            Command::Suggest => {
+                let page = SuggestionsPage::new(&self.feed_state.feed_config);
+                self.new_page(Box::new(page));
+            },
+
+            Command::WatchClipboard => self.toggle_clipboard_watch(),
+
+            Command::Killfile => {
+                self.feed_state.killfile = !self.feed_state.killfile;
+                let message = if self.feed_state.killfile {
+                    "Hiding the posts matching the killfile"
+                } else {
+                    "Showing the posts matching the killfile"
+                };
+                self.message = Some(message.into());
+            },
+
+            Command::Health => {
+                let page = HealthPage::new(&self.feed_state);
+                self.new_page(Box::new(page));
+            },
+
+            Command::Stats => self.new_page(Box::new(StatsPage::default())),
+
+            Command::History => {
+                self.new_page(Box::new(HistoryPage::default()));
+            },
+
+            Command::Downloads => {
+                self.new_page(Box::new(DownloadsPage::default()));
+            },
+        }
+
+        false
+    }
+
+    /// Handle an action returned by a page.
+    fn handle_action(&mut self, action: PageAction) {
+        match action {
+            PageAction::None                  => {},
+            PageAction::NewPage(p)            => self.new_page(p),
+            PageAction::DownloadFeed(feed_id) => self.start_download(feed_id),
+            PageAction::DownloadAllFeeds      => self.download_all(),
+            PageAction::DownloadSection(idx)  => self.download_section(idx),
+            PageAction::StopWaiting           => {
+                self.request_download(DownloadRequest::StopWaiting);
+            },
+            PageAction::OpenLink(feed_id, post_id, url, action) => {
+                if self.feed_state.settings.mark_read != MarkRead::Never
+                    && !self.is_read_only()
+                {
+                    self.mark_posts_read(&feed_id, |post| post.id == post_id);
+                }
+                self.mark_opened(&feed_id, post_id, url);
+                self.handle_action(*action);
+            },
+            PageAction::CopyToClipboard(url)  => {
+                if let Err(err) = Self::to_clipboard(&url) {
+                    self.message = Some(format!("Couldn't copy: {}", err));
+                }
+            },
+            PageAction::CheckLinks(urls)      => self.check_links(urls),
+            PageAction::Subscribe(url)        => self.subscribe(url),
+            PageAction::ReadLater(url)        => self.read_later(url),
+            PageAction::PreviewImage(url)     => self.preview_image(url),
+
+            PageAction::RunAction(template, url) => {
+                // Back on the post page once the action is done.
+                let command = actions::command_line(&template, &url);
+                self.pending_action = Some(ExternalCommand {
+                    command, stdin: None
+                });
+                self.pages.pop();
+            },
+
+            PageAction::ShowInPager(text)     => self.show_in_pager(text),
+            PageAction::ReadArticle(feed_id, url) => {
+                self.read_article(&feed_id, url);
+            },
+
+            PageAction::WriteNote(feed_id, post_id) => {
+                self.write_note(&feed_id, &post_id);
+            },
+            PageAction::ArchiveArticle(feed_id, post_id) => {
+                self.archive_article(feed_id, post_id);
+            },
+            PageAction::PlayMedia(feed_id, url) => {
+                self.play_media(&feed_id, url);
+            },
+
+            PageAction::OpenInBrowser(url) => {
+                let browser = self.feed_state.settings.browser();
+                let command = actions::command_line(browser, &url);
+                self.pending_action = Some(ExternalCommand {
+                    command, stdin: None
+                });
+            },
+
+            PageAction::MarkFeedRead(feed_id) => {
+                self.mark_posts_read(&feed_id, |_| true);
+            },
+
+            PageAction::MarkFeedReadOlder(feed_id) => {
+                self.ask(Prompt::MarkReadOlder(feed_id));
+            },
+
+            PageAction::SetFeedView(feed_id, view) => {
+                let Some(feed) = self.feed_state.get_feed_mut(&feed_id) else {
+                    return;
+                };
+                feed.view = view;
+
+                let feed_url = feed.url.as_str().into();
+                let request = DatabaseRequest::SaveView { feed_url, view };
+                self.save(request);
+            },
+
+            PageAction::KeepLatestUnread(feed_id, keep) => {
+                let Some(feed) = self.feed_state.get_feed(&feed_id) else {
+                    return;
+                };
+                let mut unread: Vec<&Post> = feed.posts.as_ref().iter()
+                    .filter(|post| !post.read)
+                    .collect();
+                unread.sort_by_key(|post| std::cmp::Reverse(post.published));
+
+                let kept: HashSet<PostId> = unread.into_iter()
+                    .take(keep)
+                    .map(|post| post.id.clone())
+                    .collect();
+                self.mark_posts_read(&feed_id, |post| !kept.contains(&post.id));
+            },
+
+            PageAction::TogglePostRead(..)
+            | PageAction::TogglePostStarred(..)
+            | PageAction::MarkPostsRead(..)
+            | PageAction::StarPosts(..)
+            | PageAction::SaveFeed(..)
+            | PageAction::RemoveFeed(..)
+            | PageAction::SwapFeeds(..)
+            | PageAction::MoveFeed(..)
+                if self.is_read_only() =>
+            {
+                self.message = Some(READ_ONLY.into());
+            },
+
+            PageAction::SaveFeed(feed_id, section, title, url) => {
+                let result = match feed_id {
+                    Some(feed_id) => self.edit_feed(feed_id, &section, title,
+                        url),
+                    None => self.add_feed(Some(&section), title, url),
+                };
+                match result {
+                    Ok(()) => self.go_back(),
+                    Err(err) => self.message = Some(err),
+                }
+            },
+
+            PageAction::SwapFeeds(a, b) => {
+                if let Err(err) = self.swap_feeds(&a, &b) {
+                    self.message = Some(err);
+                }
+            },
+
+            PageAction::MoveFeed(feed_id, section_idx) => {
+                let (Some(feed), Some(section)) = (
+                    self.feed_state.get_feed(&feed_id),
+                    self.feed_state.get_section(section_idx),
+                ) else {
+                    return;
+                };
+                let (title, url) = (feed.title.clone(), feed.url.clone());
+                let section = section.title.clone();
+                let result = self.edit_feed(feed_id, &section, title, url);
+                if let Err(err) = result {
+                    self.message = Some(err);
+                }
+            },
+
+            PageAction::RemoveFeed(feed_id) => match self.check_feed_file() {
+                Ok(()) => self.ask(Prompt::RemoveFeed(feed_id)),
+                Err(err) => self.message = Some(err),
+            },
+
+            PageAction::TogglePostStarred(feed_id, post_id) => {
+                let Some(feed) = self.feed_state.get_feed_mut(&feed_id) else {
+                    return;
+                };
+                feed.posts.toggle_starred(&post_id);
+
+                let Some(post) = feed.posts.get_by_id(&post_id) else {
+                    return;
+                };
+                let posts = Posts::from(post.clone());
+                let feed_url = feed.url.as_str().into();
+                self.save(DatabaseRequest::SavePosts {
+                    feed_url, posts
+                });
+            },
+
+            PageAction::TogglePostRead(feed_id, post_id) => {
+                // Get the post and toggle its read state.
+                let Some(feed) = self.feed_state.get_feed_mut(&feed_id) else {
+                    return;
+                };
+                feed.posts.toggle_read(&post_id);
+
+                // Save the read state in our database right away.
+                let Some(post) = feed.posts.get_by_id(&post_id) else {
+                    return;
+                };
+                let read = post.read;
+                if read {
+                    feed.log_reads(std::slice::from_ref(&post_id), Utc::now());
+                }
+                let feed_url = feed.url.as_str().into();
+                self.save(DatabaseRequest::MarkRead {
+                    feed_url, post_ids: vec![post_id], read
+                });
+            },
+
+            PageAction::MarkPostsRead(feed_id, post_ids, read) => {
+                let Some(feed) = self.feed_state.get_feed_mut(&feed_id) else {
+                    return;
+                };
+                for post_id in &post_ids {
+                    feed.posts.mark_read(post_id, read);
+                }
+                if read {
+                    feed.log_reads(&post_ids, Utc::now());
+                }
+
+                let feed_url = feed.url.as_str().into();
+                self.save(DatabaseRequest::MarkRead {
+                    feed_url, post_ids, read
+                });
+            },
+
+            PageAction::StarPosts(feed_id, post_ids, starred) => {
+                let Some(feed) = self.feed_state.get_feed_mut(&feed_id) else {
+                    return;
+                };
+                for post_id in &post_ids {
+                    feed.posts.mark_starred(post_id, starred);
+                }
+
+                let posts: Vec<Post> = post_ids.iter()
+                    .filter_map(|post_id| feed.posts.get_by_id(post_id))
+                    .cloned()
+                    .collect();
+                let feed_url = feed.url.as_str().into();
+                self.save(DatabaseRequest::SavePosts {
+                    feed_url, posts: posts.into()
+                });
+            },
+        }
+    }
+
+    /// Mark all unread posts in the feed that match the predicate as read.
+    fn mark_posts_read<F>(&mut self, feed_id: &FeedId, f: F)
+    where
+        F: Fn(&Post) -> bool
+    {
+        if self.is_read_only() {
+            self.message = Some(READ_ONLY.into());
+            return;
+        }
+
+        // Go through each post in the feed and mark it as read.
+        let Some(feed) = self.feed_state.get_feed_mut(feed_id) else {
+            return;
+        };
+
+        // Build a vector of the IDs of the unread posts.
+        let post_ids: Vec<PostId> = feed.posts.as_ref().iter()
+            .filter(|post| !post.read && f(post))
+            .map(|post| post.id.clone())
+            .collect();
+
+        // Nothing to mark, nothing to save.
+        if post_ids.is_empty() {
+            return;
+        }
+
+        // Mark the unread posts as read.
+        for post_id in post_ids.iter() {
+            feed.posts.mark_read(post_id, true);
+        }
+        feed.log_reads(&post_ids, Utc::now());
+
+        // Save the read state in our database right away.
+        let feed_url = feed.url.as_str().into();
+        self.save(DatabaseRequest::MarkRead {
+            feed_url, post_ids, read: true
+        });
+    }
+
+    /// Copy the string `s` into the system clipboard using wl-copy.
+    fn to_clipboard(s: &str) -> io::Result<()> {
+        let mut child = process::Command::new("wl-copy")
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        // Close the stdin afterwards so that wl-copy knows the input is over.
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(s.as_bytes())?;
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(
+                format!("wl-copy exited with {}", status)));
+        }
+        Ok(())
+    }
+
+    /// Get the text in the clipboard using wl-paste, or `None` if there's no
+    /// text in it.
+    fn from_clipboard() -> io::Result<Option<String>> {
+        let output = process::Command::new("wl-paste")
+            .args(["--no-newline", "--type", "text"])
+            .stdin(Stdio::null())
+            .output()?;
+
+        // wl-paste fails when nothing or no text is copied.
+        Ok(output.status.success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+
+    /// Start or stop watching the clipboard for feed URLs.
+    fn toggle_clipboard_watch(&mut self) {
+        if self.clipboard_watch.take().is_some() {
+            self.message = Some("Stopped watching the clipboard".into());
+            return;
+        }
+
+        // Only the URLs copied from now on are offered.
+        match Self::from_clipboard() {
+            Ok(contents) => {
+                self.clipboard_watch = Some(ClipboardWatch {
+                    contents,
+                    read_at: Instant::now(),
+                });
+                self.message = Some("Watching the clipboard for feeds".into());
+            },
+            Err(err) => {
+                let message = format!("Couldn't read the clipboard: {}", err);
+                self.message = Some(message);
+            },
+        }
+    }
+
+    /// Read the clipboard if it's watched and it's time to, and offer to
+    /// subscribe to a newly copied feed URL.
+    fn poll_clipboard(&mut self) {
+        let Some(watch) = self.clipboard_watch.as_mut() else {
+            return;
+        };
+        if watch.read_at.elapsed() < CLIPBOARD_POLL {
+            return;
+        }
+        watch.read_at = Instant::now();
+
+        let contents = match Self::from_clipboard() {
+            Ok(contents) => contents,
+            Err(err) => {
+                self.clipboard_watch = None;
+                let message = format!("Stopped watching the clipboard: {}",
+                    err);
+                self.message = Some(message);
+                return;
+            },
+        };
+        if contents == watch.contents {
+            return;
+        }
+        watch.contents = contents;
+
+        // Ask only about the feeds that we can still subscribe to.
+        let Some(url) = watch.contents.as_deref()
+            .and_then(|text| Url::parse(text.trim()).ok())
+            .filter(looks_like_feed)
+        else {
+            return;
+        };
+        let subscribed = self.feed_state.feed_config.sections.iter()
+            .flat_map(|section| section.feeds.iter())
+            .any(|feed| feed.url == url);
+        let pending = matches!(self.feed_state.subscription(&url),
+            Some(Subscription::Discovering | Subscription::Subscribed(_)));
+        if !subscribed && !pending {
+            self.ask(Prompt::Subscribe(url));
+        }
+    }
+
+    /// Ask the question of the `prompt` once the ones before are answered.
+    fn ask(&mut self, prompt: Prompt) {
+        if !self.prompts.contains(&prompt) {
+            self.prompts.push_back(prompt);
+        }
+    }
+
+    /// Do what the `prompt` has been answered with the `key`, if anything.
+    fn answer(&mut self, prompt: Prompt, key: char) {
+        match prompt {
+            Prompt::Subscribe(url) if key == 'y' => self.subscribe(url),
+            Prompt::MarkBacklogRead(feed_id, _) if key == 'y' => {
+                self.mark_posts_read(&feed_id, |_| true);
+            },
+            Prompt::RemoveFeed(feed_id) if key == 'y' => {
+                if let Err(err) = self.remove_feed(&feed_id) {
+                    self.message = Some(err);
+                }
+            },
+            Prompt::UpdateMovedFeed(feed_id) if key == 'y' => {
+                if let Err(err) = self.update_moved_feed(&feed_id) {
+                    self.message = Some(err);
+                }
+            },
+            Prompt::MarkReadOlder(feed_id) => {
+                let age = match key {
+                    'd' => TimeDelta::days(1),
+                    'w' => TimeDelta::weeks(1),
+                    'm' => TimeDelta::days(30),
+                    _ => return,
+                };
+                self.mark_read_older(age, Some(&feed_id));
+            },
+            _ => {},
+        }
+    }
+
+    /// Mark the posts older than `age` as read, only those of the `feed` if
+    /// it's given.
+    fn mark_read_older(&mut self, age: TimeDelta, feed: Option<&FeedId>) {
+        let cutoff = Utc::now() - age;
+        let feed_ids: Vec<_> = match feed {
+            Some(feed_id) => vec![feed_id.clone()],
+            None => self.feed_state.feed_ids().collect(),
+        };
+        for feed_id in feed_ids {
+            self.mark_posts_read(&feed_id, |p| p.published < cutoff);
+        }
+    }
+
+    /// Go back from the currently shown page to the one before.
+    fn go_back(&mut self) {
+        if self.pages.len() > 1 {
+            self.pages.pop();
+        }
+    }
+
+    /// Go from the current page to a new page.
+    fn new_page(&mut self, mut page: Box<dyn Page>) {
+        let action = page.on_new(&mut self.feed_state);
+        self.pages.push(page);
+        self.handle_action(action);
+    }
+
+    /// Check whether posts are waiting to be saved in the database.
+    fn is_saving(&self) -> bool {
+        !self.pending_saves.is_empty() || self.database.backlog() > 0
+    }
+
+    /// Draw the page.
+    fn draw(&mut self, f: &mut Frame) {
+        if self.screensaver {
+            self.draw_screensaver(f);
+            return;
+        }
+
+        // Reserve the bottom line for the command line, the message or the
+        // saving indicator.
+        let mut area = f.area();
+        let saving = self.is_saving();
+        let bottom = if self.command_line.is_some() || self.message.is_some()
+            || !self.prompts.is_empty() || saving
+        {
+            area.height = area.height.saturating_sub(1);
+            Some(Rect { y: area.bottom(), height: 1, ..area })
+        } else {
+            None
+        };
+
+        self.pages.last_mut().unwrap().draw(f, area, &self.feed_state);
+
+        // Draw the bottom line.
+        let Some(bottom) = bottom else { return; };
+        if let Some(command_line) = &self.command_line {
+            command_line.draw(f, bottom);
+        } else if let Some(prompt) = self.prompts.front() {
+            let text = prompt.question(&self.feed_state);
+            f.render_widget(Paragraph::new(text), bottom);
+        } else if let Some(message) = &self.message {
+            f.render_widget(Paragraph::new(message.as_str()), bottom);
+        } else if saving {
+            let doing = if self.feed_state.is_loaded() {
+                "saving"
+            } else {
+                "loading"
+            };
+            let text = format!("{} {}…", self.feed_state.spinner.frame(),
+                doing);
+            f.render_widget(Paragraph::new(text), bottom);
+        }
+    }
+
+    /// Draw the screensaver instead of the page.
+    fn draw_screensaver(&self, f: &mut Frame) {
+        if self.feed_state.settings.idle.screensaver != Screensaver::Clock {
+            return;
+        }
+
+        let unread: usize = self.feed_state.feed_config.sections.iter()
+            .flat_map(|section| section.feeds.iter())
+            .map(|feed| feed.posts.unread())
+            .sum();
+
+        let text = vec![
+            Line::from(chrono::Local::now().format("%H:%M").to_string()),
+            Line::from(format!("{} unread", unread)),
+        ];
+
+        let [_, area, _] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(text.len() as u16),
+            Constraint::Fill(1),
+        ]).areas(f.area());
+
+        f.render_widget(Paragraph::new(text).centered(), area);
+    }
+
+    /// Send the `request` to the downloader, telling the user if it has
+    /// stopped.
+    fn request_download(&mut self, request: DownloadRequest) {
+        if self.download.send(request).is_err() {
+            tracing::error!("The downloader has stopped");
+            self.message = Some("The downloader has stopped, restart nia to \
+                download again".into());
+        }
+    }
+
+    /// Start downloading a single feed.
+    fn start_download(&mut self, feed: FeedId) {
+        if self.is_read_only() {
+            self.message = Some(READ_ONLY.into());
+            return;
+        }
+        if self.feed_state.is_loading(&feed) {
+            self.message = Some(LOADING.into());
+            return;
+        }
+        if self.feed_state.is_load_failed(&feed) {
+            // It's downloaded once they've been loaded.
+            self.message = Some(LOAD_RETRY.into());
+            self.load_added_feeds(&[feed]);
+            return;
+        }
+        let Some(source) = self.feed_state.get_feed(&feed) else {
+            return;
+        };
+        if !source.is_downloadable() {
+            self.message = Some(NOT_DOWNLOADABLE.into());
+            return;
+        }
+        let source = source.into();
+
+        // Mark the feed as queued up for download.
+        self.feed_state.queue_download(&feed);
+
+        // Send the request to the downloader.
+        self.request_download(DownloadRequest::Feed { feed, source });
+    }
+
+    /// Start downloading the feeds of the section at `section_idx`.
+    fn download_section(&mut self, section_idx: usize) {
+        if self.is_read_only() {
+            self.message = Some(READ_ONLY.into());
+            return;
+        }
+        if !self.feed_state.is_loaded() {
+            self.message = Some(LOADING.into());
+            return;
+        }
+
+        let state = &self.feed_state;
+        let Some(section) = state.get_section(section_idx) else {
+            return;
+        };
+        let feeds: Vec<(FeedId, FeedSource)> = section.feeds.iter()
+            .enumerate()
+            .filter(|(_, feed)| feed.is_downloadable())
+            .filter(|(_, feed)| !state.load_failed.contains(feed.url.as_str()))
+            .map(|(feed_idx, feed)| {
+                (FeedId { section_idx, feed_idx }, feed.into())
+            })
+            .collect();
+
+        // Mark the feeds as queued up, unless they're being downloaded.
+        for (feed, _) in feeds.iter() {
+            self.feed_state.queue_download(feed);
+        }
+
+        // Send the request to the downloader.
+        self.request_download(DownloadRequest::Section(feeds));
+    }
+
+    /// Check the reachability of `urls` in the background.
+    fn check_links(&mut self, urls: Vec<Url>) {
+        for url in urls.iter() {
+            self.feed_state.link_health
+                .insert(url.clone(), LinkHealth::Checking);
+        }
+
+        self.request_download(DownloadRequest::CheckLinks(urls));
+    }
+
+    /// Look for the feed at the `url` in the background and subscribe to it.
+    fn subscribe(&mut self, url: Url) {
+        if self.is_read_only() {
+            self.message = Some(READ_ONLY.into());
+            return;
+        }
+
+        self.feed_state.subscriptions
+            .insert(url.clone(), Subscription::Discovering);
+
+        self.request_download(DownloadRequest::Discover(url));
+    }
+
+    /// Save the `url` to the read-it-later services in the background.
+    fn read_later(&mut self, url: Url) {
+        let services = crate::read_later::configured(&self.feed_state.settings);
+        if services.is_empty() {
+            let message = "No read-it-later service is configured";
+            self.message = Some(message.into());
+            return;
+        }
+
+        self.message = Some(format!("Saving {}…", url));
+        self.request_download(DownloadRequest::ReadLater(url, services));
+    }
+
+    /// Download the image at `url` to preview it, unless it has been already.
+    fn preview_image(&mut self, url: Url) {
+        if self.image_protocol.is_none() {
+            let message = "This terminal can't show images, set `images` in \
+                the [ui] table of config.toml if it can";
+            self.message = Some(message.into());
+            return;
+        }
+
+        if matches!(self.feed_state.images.get(&url),
+            None | Some(ImagePreview::Failed(_)))
+        {
+            self.feed_state.images.insert(url.clone(), ImagePreview::Loading);
+            self.request_download(DownloadRequest::Image(url));
+        }
+    }
+
+    /// Draw the image previewed on the page over it, or take the image that
+    /// isn't previewed anymore away, if either has changed.
+    fn draw_image<B>(&mut self, terminal: &mut Terminal<B>) -> io::Result<()>
+    where
+        B: Backend,
+        io::Error: From<B::Error>,
+    {
+        let Some(protocol) = self.image_protocol else {
+            return Ok(());
+        };
+        let wanted = self.pages.last().unwrap().image()
+            .map(|(url, area)| (url.clone(), area));
+        if wanted == self.shown_image {
+            return Ok(());
+        }
+
+        // Kitty images are drawn above the text, while sixels replace it and
+        // stay until the page is drawn over them again.
+        let mut stdout = io::stdout();
+        if self.shown_image.take().is_some() {
+            match protocol {
+                Protocol::Kitty => stdout.write_all(image::KITTY_DELETE)?,
+                Protocol::Sixel => {
+                    terminal.clear()?;
+                    terminal.draw(|f| self.draw(f))?;
+                },
+            }
+        }
+
+        if let Some((url, area)) = wanted {
+            let Some(ImagePreview::Loaded(bytes)) =
+                self.feed_state.images.get(&url)
+            else {
+                return Ok(());
+            };
+            match image::encode(protocol, bytes, area) {
+                Ok(encoded) => {
+                    crossterm::queue!(stdout,
+                        crossterm::cursor::MoveTo(area.x, area.y))?;
+                    stdout.write_all(&encoded)?;
+                },
+                Err(err) => {
+                    self.message = Some(format!("Couldn't show the image: {}",
+                        err));
+                },
+            }
+            self.shown_image = Some((url, area));
+        }
+        stdout.flush()
+    }
+
+    /// Show the `text` in the pager of `$PAGER`, or `less`.
+    fn show_in_pager(&mut self, text: String) {
+        let command = std::env::var("PAGER").ok()
+            .filter(|pager| !pager.trim().is_empty())
+            .unwrap_or_else(|| "less".into());
+        self.pending_action = Some(ExternalCommand {
+            command, stdin: Some(text)
+        });
     }
 
+    /// Write the post as a Markdown note into the notes directory.
+    fn write_note(&mut self, feed_id: &FeedId, post_id: &PostId) {
+        let Some(feed) = self.feed_state.get_feed(feed_id) else {
+            return;
+        };
+        let Some(post) = feed.posts.get_by_id(post_id) else {
+            return;
+        };
+
+        let path = self.feed_state.settings.notes.dir()
+            .and_then(|dir| crate::notes::write(&dir, post, feed));
+        self.message = Some(match path {
+            Ok(path) => format!("Wrote the note to {}", path.display()),
+            Err(err) => format!("Couldn't write the note: {}", err),
+        });
+    }
+
+    /// Play the media at the `url` with the player of the settings,
+    /// remembering that it has been played.
+    fn play_media(&mut self, feed_id: &FeedId, url: Url) {
+        let player = &self.feed_state.settings.media.player;
+        let command = actions::command_line(player, &url);
+        self.pending_action = Some(ExternalCommand { command, stdin: None });
+
+        let Some(feed) = self.feed_state.get_feed_mut(feed_id) else {
+            return;
+        };
+        if feed.played.insert(url.clone()) {
+            let feed_url = feed.url.as_str().into();
+            self.save(DatabaseRequest::MarkPlayed { feed_url, url });
+        }
+    }
+
+    /// Remember that the `url` has been opened from the post of the feed.
+    fn mark_opened(&mut self, feed_id: &FeedId, post_id: PostId, url: Url) {
+        let Some(feed) = self.feed_state.get_feed_mut(feed_id) else {
+            return;
+        };
+        let opened = feed.opened.entry(post_id.clone()).or_default();
+        if opened.insert(url.clone()) {
+            let feed_url = feed.url.as_str().into();
+            self.save(DatabaseRequest::MarkOpened {
+                feed_url, post_id, url
+            });
+        }
+    }
+
+    /// Download the article of the post to archive its text once it
+    /// arrives.
+    fn archive_article(&mut self, feed_id: FeedId, post_id: PostId) {
+        let Some(feed) = self.feed_state.get_feed(&feed_id) else {
+            return;
+        };
+        let Some(url) = feed.posts.get_by_id(&post_id)
+            .and_then(|post| post.urls.first())
+            .cloned()
+        else {
+            return;
+        };
+        let rule = feed.options.article.clone();
+
+        self.message = Some(format!("Archiving {}…", url));
+        let request = DownloadRequest::Archive(feed_id, post_id, url, rule);
+        self.request_download(request);
+    }
+
+    /// Download the article at `url`, linked from a post of the feed, to
+    /// show its text in the pager once it arrives.
+    fn read_article(&mut self, feed_id: &FeedId, url: Url) {
+        let rule = self.feed_state.get_feed(feed_id)
+            .and_then(|feed| feed.options.article.clone());
+
+        self.message = Some(format!("Downloading {}…", url));
+        self.request_download(DownloadRequest::Article(url, rule));
+    }
+
+    /// Run the shell command of an external action in the terminal, which
+    /// is suspended until the command exits.
+    ///
+    /// The `input` reader is paused meanwhile, so that the keys go to the
+    /// command.
+    fn run_action<B>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        input: &InputReader,
+        external: &ExternalCommand,
+    ) -> io::Result<()>
+    where
+        B: Backend,
+        io::Error: From<B::Error>,
+    {
+        let command = &external.command;
+        input.pause();
+        disable_raw_mode()?;
+        crossterm::execute!(io::stdout(), LeaveAlternateScreen, Show)?;
+
+        // The text is written from another thread, in case the command
+        // doesn't read all of it.
+        let mut child = process::Command::new("sh");
+        child.arg("-c").arg(command);
+        if external.stdin.is_some() {
+            child.stdin(Stdio::piped());
+        }
+        let status = child.spawn().and_then(|mut child| {
+            if let (Some(mut stdin), Some(text)) =
+                (child.stdin.take(), external.stdin.clone())
+            {
+                std::thread::spawn(move || stdin.write_all(text.as_bytes()));
+            }
+            child.wait()
+        });
+
+        enable_raw_mode()?;
+        crossterm::execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        input.resume();
+        terminal.clear()?;
+        self.shown_image = None;
+
+        match status {
+            Ok(status) if status.success() => {},
+            Ok(status) => {
+                self.message = Some(format!("`{}` failed: {}", command,
+                    status));
+            },
+            Err(err) => {
+                self.message = Some(format!("Couldn't run `{}`: {}", command,
+                    err));
+            },
+        }
+        Ok(())
+    }
+
+    /// Add a discovered feed to the feed file and start downloading it.
+    fn add_discovered_feed(&mut self, feed: DiscoveredFeed)
+        -> Result<Arc<str>, String>
+    {
+        let title: Arc<str> = feed.title
+            .or_else(|| feed.url.host_str().map(String::from))
+            .unwrap_or_else(|| feed.url.to_string())
+            .into();
+
+        self.add_feed(None, title.clone(), feed.url)?;
+        Ok(title)
+    }
+
+    /// Load what's stored of the added feeds with the `feed_ids`, e.g. the
+    /// posts of a feed that was removed and added back, and download them
+    /// once it's loaded, so that the downloaded posts are merged with the
+    /// stored ones instead of replacing them.
+    fn load_added_feeds(&mut self, feed_ids: &[FeedId]) {
+        let urls: HashSet<Arc<str>> = feed_ids.iter()
+            .filter_map(|feed_id| self.feed_state.get_feed(feed_id))
+            .map(|feed| feed.url.as_str().into())
+            .collect();
+        if urls.is_empty() {
+            return;
+        }
+
+        if !self.is_read_only() {
+            self.download_on_load.extend(urls.iter().cloned());
+        }
+        self.feed_state.loading.extend(urls.iter().cloned());
+        self.database.send(DatabaseRequest::LoadFeeds(urls.into_iter()
+            .collect()));
+    }
+
+    /// Add a feed to the `section` of the feed file, or its last section, and
+    /// start downloading it once its stored posts have been loaded.
+    fn add_feed(&mut self, section: Option<&str>, title: Arc<str>, url: Url)
+        -> Result<(), String>
+    {
+        self.check_feed_file()?;
+        let section = FeedConfig::add_feed(section, &title, &url)
+            .map_err(|err| err.to_string())?;
+
+        // The new feed is appended to its section, so the IDs of the other
+        // feeds don't change. Only the main page has to be rebuilt.
+        let feed = Feed::new(title, url);
+        let feed_id = self.feed_state.feed_config.push_feed(&section, feed);
+        let main_page = main::MainPage::new(&self.feed_state.feed_config);
+        self.pages[0] = Box::new(main_page);
+        self.load_added_feeds(&[feed_id]);
+
+        Ok(())
+    }
+
+    /// Change the title and the URL of the feed in the feed file, moving it
+    /// to the end of the `section` if it's in another one.
+    fn edit_feed(&mut self, feed_id: FeedId, section: &str, title: Arc<str>,
+        url: Url) -> Result<(), String>
+    {
+        self.check_feed_file()?;
+        let Some(old_section) = self.feed_state.get_section(feed_id.section_idx)
+        else {
+            return Err(FEED_GONE.into());
+        };
+        let moved = !old_section.title.eq_ignore_ascii_case(section.trim());
+
+        // Moving a feed changes the IDs the downloads are tracked by.
+        if moved && !self.feed_state.downloading.is_empty() {
+            let err = "Wait for the downloads to finish to move a feed";
+            return Err(err.into());
+        }
+
+        let Some(feed) = self.feed_state.get_feed_mut(&feed_id) else {
+            return Err(FEED_GONE.into());
+        };
+        FeedConfig::edit_feed(&feed.url, moved.then_some(section), &title, &url)
+            .map_err(|err| err.to_string())?;
+        feed.title = title;
+
+        // The posts are stored under the URL of their feed, so they're stored
+        // again under the new one. Those under the old one are left for
+        // `nia clean`.
+        let url_changed = feed.url != url;
+        if url_changed {
+            feed.url = url;
+            feed.health = None;
+            let feed_url: Arc<str> = feed.url.as_str().into();
+            let posts = feed.posts.clone();
+            let view = feed.view;
+            if !posts.is_empty() {
+                self.save(DatabaseRequest::SavePosts {
+                    feed_url: feed_url.clone(), posts
+                });
+            }
+            self.save(DatabaseRequest::SaveView { feed_url, view });
+        }
+
+        let feed_id = if moved
+            && let Some(feed) = self.feed_state.feed_config.take_feed(&feed_id)
+        {
+            let feed_id = self.feed_state.feed_config.push_feed(section, feed);
+            self.rebuild_pages(Some(&feed_id));
+            feed_id
+        } else {
+            feed_id
+        };
+
+        if url_changed {
+            self.start_download(feed_id);
+        }
+        Ok(())
+    }
+
+    /// Remove the feed from the feed file. Its posts are left in the database
+    /// for `nia clean`.
+    fn remove_feed(&mut self, feed_id: &FeedId) -> Result<(), String> {
+        self.check_feed_file()?;
+
+        // Removing a feed changes the IDs the downloads are tracked by.
+        if !self.feed_state.downloading.is_empty() {
+            let err = "Wait for the downloads to finish to remove a feed";
+            return Err(err.into());
+        }
+
+        let Some(feed) = self.feed_state.get_feed(feed_id) else {
+            return Err(FEED_GONE.into());
+        };
+        FeedConfig::remove_feed(&feed.url).map_err(|err| err.to_string())?;
+        self.feed_state.feed_config.take_feed(feed_id);
+        self.rebuild_pages(None);
+        Ok(())
+    }
+
+    /// Change the URL of the feed in the feed file to where it has
+    /// permanently moved to, and move what's stored of it there too.
+    fn update_moved_feed(&mut self, feed_id: &FeedId) -> Result<(), String> {
+        self.check_feed_file()?;
+        let Some(feed) = self.feed_state.get_feed_mut(feed_id) else {
+            return Ok(());
+        };
+        let Some(url) = feed.moved.clone() else {
+            return Ok(());
+        };
+        FeedConfig::edit_feed(&feed.url, None, &feed.title, &url)
+            .map_err(|err| err.to_string())?;
+
+        let from = feed.url.as_str().into();
+        let to = url.as_str().into();
+        feed.url = url;
+        feed.moved = None;
+        self.save(DatabaseRequest::MoveFeed { from, to });
+        Ok(())
+    }
+
+    /// Swap two feeds of the same section in the feed file, keeping the
+    /// first one selected.
+    fn swap_feeds(&mut self, a: &FeedId, b: &FeedId) -> Result<(), String> {
+        self.check_feed_file()?;
+
+        // Swapping feeds changes the IDs the downloads are tracked by.
+        if !self.feed_state.downloading.is_empty() {
+            let err = "Wait for the downloads to finish to move a feed";
+            return Err(err.into());
+        }
+
+        let (Some(feed_a), Some(feed_b)) =
+            (self.feed_state.get_feed(a), self.feed_state.get_feed(b))
+        else {
+            return Err(FEED_GONE.into());
+        };
+        FeedConfig::swap_feeds(&feed_a.url, &feed_b.url)
+            .map_err(|err| err.to_string())?;
+        self.feed_state.feed_config.swap(a, b);
+        self.rebuild_pages(Some(b));
+        Ok(())
+    }
+
+    /// Go back to a rebuilt main page after the IDs of the feeds have
+    /// changed, forgetting the questions about them. The `selected` feed is
+    /// selected again.
+    fn rebuild_pages(&mut self, selected: Option<&FeedId>) {
+        self.pages.truncate(1);
+        self.rebuild_main_page(selected);
+        self.prompts.clear();
+    }
+
+    /// Open the pages of the `session` the application last quit with, as
+    /// far as their feeds and posts are still there.
+    fn restore(&mut self, session: Session) {
+        for page in session.pages {
+            let found = |url: &str| self.feed_state.feed_ids().find(|id| {
+                self.feed_state.get_feed(id)
+                    .is_some_and(|feed| feed.url.as_str() == url)
+            });
+            let page: Box<dyn Page> = match page {
+                PageSession::Main { feed, offset } => {
+                    let feed_id = feed.and_then(|url| found(&url));
+                    let mut main_page =
+                        main::MainPage::new(&self.feed_state.feed_config);
+                    if let Some(feed_id) = &feed_id {
+                        main_page.select_feed(feed_id);
+                    }
+                    main_page.set_offset(offset);
+                    self.pages[0] = Box::new(main_page);
+                    continue;
+                },
+                PageSession::Feed { feed, post, offset } => {
+                    let Some(feed_id) = found(&feed) else {
+                        break;
+                    };
+                    Box::new(feed::FeedPage::restored(feed_id, post, offset))
+                },
+                PageSession::Post { feed, post } => {
+                    let Some(feed_id) = found(&feed) else {
+                        break;
+                    };
+                    let stored = self.feed_state.get_feed(&feed_id)
+                        .is_some_and(|feed| feed.posts.get_by_id(&post)
+                            .is_some());
+                    if !stored {
+                        break;
+                    }
+                    Box::new(post::PostPage::new(feed_id, post))
+                },
+            };
+            self.new_page(page);
+        }
+    }
+
+    /// Remember where the application is, to be opened there again on the
+    /// next launch.
+    fn save_session(&mut self) {
+        // Keep the last session if it hasn't been opened yet.
+        if let Some(session) = self.session.take() {
+            self.database.send(DatabaseRequest::SaveSession(session));
+            return;
+        }
+
+        let pages = self.pages.iter()
+            .map_while(|page| page.session(&self.feed_state))
+            .collect();
+        self.database.send(DatabaseRequest::SaveSession(Session { pages }));
+    }
+
+    /// Rebuild the main page after feeds have been added or renamed, with
+    /// the `selected` feed selected.
+    fn rebuild_main_page(&mut self, selected: Option<&FeedId>) {
+        let mut main_page = main::MainPage::new(&self.feed_state.feed_config);
+        if let Some(feed_id) = selected {
+            main_page.select_feed(feed_id);
+        }
+        self.pages[0] = Box::new(main_page);
+    }
+
+    /// Merge in the changes of the feed file once it's been saved.
+    ///
+    /// The added feeds are downloaded once what's stored of them is loaded.
+    fn reload_feed_file(&mut self) {
+        let Some(watch) = self.feed_file.as_mut() else {
+            return;
+        };
+
+        // The downloads are tracked by the IDs of the feeds, which may
+        // change. The file is read once they've finished.
+        if !watch.changed || !self.feed_state.downloading.is_empty() {
+            return;
+        }
+        watch.changed = false;
+
+        let config = match FeedConfig::parse_file(&watch.path) {
+            Ok(config) => config,
+            Err(err) => {
+                let message = format!("Couldn't reload the feed file: {}", err);
+                self.message = Some(message);
+                return;
+            },
+        };
+
+        // Keep the same feed selected on the main page, where the timelines
+        // are listed before the feeds.
+        let old = SourceMap::from(&self.feed_state.feed_config);
+        let selected = self.pages[0].list().selected()
+            .checked_sub(Timeline::ALL.len())
+            .and_then(|idx| self.feed_state.feed_ids().nth(idx))
+            .and_then(|feed_id| self.feed_state.get_feed(&feed_id))
+            .map(|feed| feed.url.clone());
+
+        let added = self.feed_state.feed_config.merge_from(config);
+
+        let selected = selected.and_then(|url| self.feed_state.feed_ids()
+            .find(|feed_id| self.feed_state.get_feed(feed_id)
+                .is_some_and(|feed| feed.url == url)));
+
+        // The pages showing the feeds whose IDs have changed are closed.
+        let new = SourceMap::from(&self.feed_state.feed_config);
+        let moved = old.0.iter().enumerate().any(|(section_idx, sources)| {
+            sources.iter().enumerate().any(|(feed_idx, source)| {
+                new.0.get(section_idx)
+                    .and_then(|sources| sources.get(feed_idx))
+                    .is_none_or(|new| new.url != source.url)
+            })
+        });
+        if moved {
+            self.rebuild_pages(selected.as_ref());
+        } else {
+            self.rebuild_main_page(selected.as_ref());
+        }
+
+        self.load_added_feeds(&added);
+    }
+
+    /// Download the feeds whose refresh intervals have passed since they were
+    /// last downloaded, if it's time to check.
+    fn poll_refresh(&mut self) {
+        if self.refresh_checked_at.elapsed() < REFRESH_POLL
+            || self.is_read_only()
+        {
+            return;
+        }
+        self.refresh_checked_at = Instant::now();
+
+        let now = Utc::now();
+        let state = &self.feed_state;
+        let due: Vec<FeedId> = state.feed_ids()
+            .filter(|feed_id| !state.downloading.contains_key(feed_id))
+            .filter(|feed_id| !state.is_loading(feed_id))
+            .filter(|feed_id| !state.is_load_failed(feed_id))
+            .filter(|feed_id| state.get_feed(feed_id)
+                .is_some_and(|feed| {
+                    feed.is_due(&state.settings.refresh, now)
+                }))
+            .collect();
+        for feed_id in due {
+            self.start_download(feed_id);
+        }
+    }
+
+    /// Download all feeds.
+    ///
+    /// One downloader is spawned for each section.
+    fn download_all(&mut self) {
+        if self.is_read_only() {
+            self.message = Some(READ_ONLY.into());
+            return;
+        }
+        if !self.feed_state.is_loaded() {
+            self.message = Some(LOADING.into());
+            return;
+        }
+
+        // The feeds that are the most out of date are downloaded first.
+        let state = &self.feed_state;
+        let mut sections = stalest_first(&state.feed_config);
+        for section in sections.iter_mut() {
+            section.retain(|(feed_id, _)| !state.is_load_failed(feed_id));
+        }
+
+        // Queue up all feeds, in the order each section's downloader goes
+        // through them. A feed that's already downloading keeps its state in
+        // the queue, but is still sent along with its section, so it's
+        // fetched again and both results are taken in turn.
+        for section in sections.iter() {
+            for (feed_id, _) in section.iter() {
+                self.feed_state.queue_download(feed_id);
+            }
+        }
+
+        // Send the request to the downloader.
+        self.request_download(DownloadRequest::All(sections));
+    }
+
+    /// Send the `request` to the database, or queue it behind the requests
+    /// waiting for room in the database queue.
+    fn save(&mut self, request: DatabaseRequest) {
+        if !self.pending_saves.is_empty() {
+            self.pending_saves.push_back(request);
+        } else if let Err(request) = self.database.try_send(request) {
+            self.pending_saves.push_back(request);
+        }
+    }
+
+    /// Send the requests that didn't fit into the database queue, as long as
+    /// there's room now, and let the next downloaded feed through once
+    /// they're all sent.
+    fn flush_pending_saves(&mut self) {
+        while let Some(request) = self.pending_saves.pop_front() {
+            if let Err(request) = self.database.try_send(request) {
+                self.pending_saves.push_front(request);
+                return;
+            }
+        }
+        if std::mem::take(&mut self.downloads_held) {
+            self.events.resume_downloads();
+        }
+    }
+
+    /// Handle a response from the database.
+    fn handle_database(&mut self, response: DatabaseResponse) {
+        let feed_url = match response {
+            DatabaseResponse::Loaded(feed_url, stored) => {
+                // The same feed can be in several sections.
+                let state = &mut self.feed_state;
+                let feed_ids: Vec<FeedId> = state.feed_ids()
+                    .filter(|feed_id| state.get_feed(feed_id)
+                        .is_some_and(|feed| *feed.url.as_str() == *feed_url))
+                    .collect();
+                for feed_id in feed_ids {
+                    let Some(feed) = state.get_feed_mut(&feed_id) else {
+                        continue;
+                    };
+                    stored.as_ref().clone().restore(feed);
+                    self.read_at_start += feed.posts.len()
+                        - feed.posts.unread();
+                }
+                state.load_failed.remove(&feed_url);
+                feed_url
+            },
+            DatabaseResponse::LoadFailed(feed_url, reason) => {
+                self.message = Some(format!("Couldn't load {}: {}",
+                    feed_url, reason));
+                self.download_on_load.remove(&feed_url);
+                self.feed_state.load_failed.insert(feed_url.clone());
+                feed_url
+            },
+        };
+
+        self.feed_state.loading.remove(&feed_url);
+        if self.download_on_load.remove(&feed_url) {
+            let state = &self.feed_state;
+            let feed_ids: Vec<FeedId> = state.feed_ids()
+                .filter(|feed_id| state.get_feed(feed_id)
+                    .is_some_and(|feed| *feed.url.as_str() == *feed_url))
+                .collect();
+            for feed_id in feed_ids {
+                self.start_download(feed_id);
+            }
+        }
+        if self.feed_state.is_loaded() && !self.loaded {
+            self.on_loaded();
+        }
+    }
+
+    /// Handle a response from the background downloader _in a non-blocking
+    /// manner_.
+    ///
+    /// If the database queue is full, the next downloaded feed is held back
+    /// until the posts fit, which makes the downloaders wait until the
+    /// database catches up.
+    fn handle_download(&mut self, response: DownloadResponse) {
+        match response {
+            DownloadResponse::Started(feed) => {
+                self.feed_state.start_download(&feed);
+            },
+            DownloadResponse::Waiting(feed) => {
+                self.feed_state.wait_download(&feed);
+                self.message = Some("No network, the downloads continue \
+                    once it's back (x on :downloads gives up)".into());
+            },
+            DownloadResponse::Moved(feed, url) => {
+                // Ask only once about where it has moved to.
+                if let Some(state) = self.feed_state.get_feed_mut(&feed)
+                    && state.moved.as_ref() != Some(&url)
+                {
+                    state.moved = Some(url);
+                    self.ask(Prompt::UpdateMovedFeed(feed));
+                }
+            },
+            DownloadResponse::Received(feed, bytes) => {
+                if let Some(download) = self.feed_state.download_mut(&feed) {
+                    download.bytes = Some(bytes);
+                }
+            },
+            DownloadResponse::Failed(feed, reason) => {
+                self.feed_state
+                    .end_download(&feed, DownloadStatus::Failed(reason));
+            },
+            DownloadResponse::Fetched(feed, health) => {
+                let request = self.feed_state.record_fetch(&feed, health);
+                if let Some(request) = request {
+                    self.save(request);
+                }
+            },
+            DownloadResponse::Described(feed, meta) => {
+                let request = self.feed_state.record_meta(&feed, meta);
+                if let Some(request) = request {
+                    self.save(request);
+                }
+            },
+            DownloadResponse::Finished { feed, posts } => {
+                self.feed_state.end_download(&feed, DownloadStatus::Done);
+                let unread_edited = self.feed_state.settings.unread_edited;
+                let Some(state) = self.feed_state.get_feed_mut(&feed) else {
+                    self.events.resume_downloads();
+                    return;
+                };
+
+                // Save only the new posts in the feed, and those that have
+                // been edited. The backlog of a feed downloaded for the
+                // first time isn't new to the hooks.
+                let first = state.posts.is_empty();
+                let edited = state.update_posts(&posts, unread_edited);
+                let gone = state.trim_pruned(&posts);
+                let mut posts = state.merge_posts(posts);
+                let feed_url: Arc<str> = state.url.as_str().into();
+                if !first {
+                    self.hooks.run(state, &posts);
+
+                    // Tag them as new until the feed is opened.
+                    let url = state.url.clone();
+                    self.feed_state.fresh.entry(url).or_default()
+                        .extend(posts.as_ref().iter()
+                            .map(|post| post.id.clone()));
+                }
+
+                // A feed downloaded for the first time can bring a long
+                // backlog, which might not be worth reading.
+                let unread = posts.unread();
+                if first && unread > 1 {
+                    self.ask(Prompt::MarkBacklogRead(feed.clone(), unread));
+                }
+
+                // Save them in the database.
+                if !gone.is_empty() {
+                    self.save(DatabaseRequest::ForgetPruned {
+                        feed_url: feed_url.clone(), post_ids: gone
+                    });
+                }
+                posts.append(edited);
+                let request = DatabaseRequest::SavePosts {
+                    feed_url, posts
+                };
+                self.save(request);
+                if self.pending_saves.is_empty() {
+                    self.events.resume_downloads();
+                } else {
+                    self.downloads_held = true;
+                }
+                self.feeds_refreshed += 1;
+            },
+            DownloadResponse::LinkChecked(url, health) => {
+                self.feed_state.link_health.insert(url, health);
+            },
+            DownloadResponse::Discovered(url, feed) => {
+                let subscription = match feed
+                    .and_then(|feed| self.add_discovered_feed(feed))
+                {
+                    Ok(title) => Subscription::Subscribed(title),
+                    Err(err) => Subscription::Failed(err),
+                };
+                self.feed_state.subscriptions.insert(url, subscription);
+            },
+            DownloadResponse::Image(url, image) => {
+                self.feed_state.images.insert(url, image);
+            },
+            DownloadResponse::Article(url, text) => match text {
+                Ok(text) => {
+                    self.message = None;
+                    self.show_in_pager(text);
+                },
+                Err(err) => {
+                    self.message = Some(format!("Couldn't download {}: {}",
+                        url, err));
+                },
+            },
+            DownloadResponse::Archived(feed_id, post_id, text) => {
+                let text = match text {
+                    Ok(text) => Arc::from(text),
+                    Err(err) => {
+                        self.message = Some(format!("Couldn't archive the \
+                            article: {}", err));
+                        return;
+                    },
+                };
+                let Some(feed) = self.feed_state.get_feed_mut(&feed_id) else {
+                    return;
+                };
+
+                feed.articles.insert(post_id.clone(), Arc::clone(&text));
+                let feed_url = feed.url.as_str().into();
+                self.save(DatabaseRequest::SaveArticle {
+                    feed_url, post_id, text
+                });
+                self.message = Some("Archived the article".into());
+            },
+            DownloadResponse::SavedForLater(service, result) => {
+                self.message = Some(match result {
+                    Ok(()) => format!("Saved to {}", service),
+                    Err(err) => format!("Couldn't save to {}: {}",
+                        service, err),
+                });
+            },
+        }
+
+        // Offer to catch up once the posts from the break are in.
+        if self.feeds_refreshed > 0 && self.feed_state.downloading.is_empty()
+            && let Some(since) = self.catch_up_since.take()
+            && let Some(page) = CatchUpPage::new(since, &self.feed_state)
+        {
+            self.new_page(Box::new(page));
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_post as post;
+
+    #[test]
+    fn marks_backlogs_read_once_confirmed() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let cfg = FeedConfig::single_feed("A", url, vec![
+            post("a1", "https://a.example/1", 1),
+            post("a2", "https://a.example/2", 2),
+        ].into());
+        let mut app = App::new_ephemeral(cfg, Settings::default());
+        let feed_id = app.feed_state.feed_ids().next().unwrap();
+
+        // The same question is asked only once.
+        app.ask(Prompt::MarkBacklogRead(feed_id.clone(), 2));
+        app.ask(Prompt::MarkBacklogRead(feed_id.clone(), 2));
+        assert_eq!(app.prompts.len(), 1);
+
+        let key = |c| Event::Key(KeyCode::Char(c).into());
+        app.handle_input(key('y'));
+        assert!(app.prompts.is_empty());
+        let feed = app.feed_state.get_feed(&feed_id).unwrap();
+        assert_eq!(feed.posts.unread(), 0);
+    }
+
+    #[test]
+    fn marks_selected_posts_read() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let cfg = FeedConfig::single_feed("A", url, (1..=4)
+            .map(|i| post(&format!("a{}", i), "https://a.example/", i))
+            .collect::<Vec<_>>()
+            .into());
+        let mut app = App::new_ephemeral(cfg, Settings::default());
+        let feed_id = app.feed_state.feed_ids().next().unwrap();
+        let mut terminal = Terminal::new(backend::TestBackend::new(80, 20))
+            .unwrap();
+
+        // The posts are listed newest first once the feed page is drawn.
+        let key = |c| Event::Key(KeyCode::Char(c).into());
+        app.handle_input(key('l'));
+        terminal.draw(|f| app.draw(f)).unwrap();
+
+        // Select the newest post, then the range of the two oldest ones.
+        for c in [' ', 'j', 'V', 'j', 'r'] {
+            app.handle_input(key(c));
+        }
+        let feed = app.feed_state.get_feed(&feed_id).unwrap();
+        let unread: Vec<&str> = feed.posts.as_ref().iter()
+            .filter(|post| !post.read)
+            .map(|post| post.id.0.as_ref())
+            .collect();
+        assert_eq!(unread, ["a3"]);
+    }
+
+    #[test]
+    fn ignores_the_count_for_page_actions() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let cfg = FeedConfig::single_feed("A", url, (1..=3)
+            .map(|i| post(&format!("a{}", i), "https://a.example/", i))
+            .collect::<Vec<_>>()
+            .into());
+        let mut app = App::new_ephemeral(cfg, Settings::default());
+        let feed_id = app.feed_state.feed_ids().next().unwrap();
+        let mut terminal = Terminal::new(backend::TestBackend::new(80, 20))
+            .unwrap();
+
+        let key = |c| Event::Key(KeyCode::Char(c).into());
+        app.handle_input(key('l'));
+        terminal.draw(|f| app.draw(f)).unwrap();
+
+        // The count moves the selection, but toggles the post only once.
+        for c in ['2', 'j', '2', 'r'] {
+            app.handle_input(key(c));
+        }
+        let feed = app.feed_state.get_feed(&feed_id).unwrap();
+        let read: Vec<&str> = feed.posts.as_ref().iter()
+            .filter(|post| post.read)
+            .map(|post| post.id.0.as_ref())
+            .collect();
+        assert_eq!(read, ["a1"]);
+    }
+
+    #[test]
+    fn advances_to_the_next_unread_post() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let cfg = FeedConfig::single_feed("A", url, (1..=3)
+            .map(|i| post(&format!("a{}", i), "https://a.example/", i))
+            .collect::<Vec<_>>()
+            .into());
+        let settings = Settings { auto_advance: true, ..Settings::default() };
+        let mut app = App::new_ephemeral(cfg, settings);
+        let feed_id = app.feed_state.feed_ids().next().unwrap();
+        let mut terminal = Terminal::new(backend::TestBackend::new(80, 20))
+            .unwrap();
+
+        let key = |c| Event::Key(KeyCode::Char(c).into());
+        app.handle_input(key('l'));
+        terminal.draw(|f| app.draw(f)).unwrap();
+
+        // Each post marked read moves the selection on to an older one.
+        app.handle_input(key('r'));
+        app.handle_input(key('r'));
+        let feed = app.feed_state.get_feed(&feed_id).unwrap();
+        let unread: Vec<&str> = feed.posts.as_ref().iter()
+            .filter(|post| !post.read)
+            .map(|post| post.id.0.as_ref())
+            .collect();
+        assert_eq!(unread, ["a1"]);
+    }
+
+    #[test]
+    fn marks_posts_read_once_a_link_is_opened() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let cfg = FeedConfig::single_feed("A", url,
+            vec![post("a", "https://a.example/1", 1)].into());
+        let settings = Settings {
+            mark_read: MarkRead::Link,
+            ..Settings::default()
+        };
+        let mut app = App::new_ephemeral(cfg, settings);
+        let feed_id = app.feed_state.feed_ids().next().unwrap();
+        let mut terminal = Terminal::new(backend::TestBackend::new(80, 20))
+            .unwrap();
+        let unread = |app: &App| {
+            app.feed_state.get_feed(&feed_id).unwrap().posts.unread()
+        };
+
+        // With links marking posts read, opening the post page itself leaves
+        // the post unread.
+        let key = |c| Event::Key(KeyCode::Char(c).into());
+        for _ in 0..2 {
+            app.handle_input(key('l'));
+            terminal.draw(|f| app.draw(f)).unwrap();
+        }
+        assert_eq!(unread(&app), 1);
+
+        // Opening its link through an action marks it read.
+        app.handle_input(key('x'));
+        assert_eq!(unread(&app), 0);
+    }
+
+    #[test]
+    fn marks_old_posts_of_a_feed_read() {
+        let day = 24 * 3600;
+        let now = Utc::now().timestamp();
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let cfg = FeedConfig::single_feed("A", url, vec![
+            post("old", "https://a.example/1", now - 10 * day),
+            post("new", "https://a.example/2", now - 2 * day),
+        ].into());
+        let mut app = App::new_ephemeral(cfg, Settings::default());
+        let feed_id = app.feed_state.feed_ids().next().unwrap();
+
+        // Anything but the ages dismisses the question.
+        let key = |c| Event::Key(KeyCode::Char(c).into());
+        for c in ['R', 'x', 'R', 'w'] {
+            app.handle_input(key(c));
+        }
+        let feed = app.feed_state.get_feed(&feed_id).unwrap();
+        let unread: Vec<&str> = feed.posts.as_ref().iter()
+            .filter(|post| !post.read)
+            .map(|post| post.id.0.as_ref())
+            .collect();
+        assert_eq!(unread, ["new"]);
+    }
+
+    #[test]
+    fn never_downloads_the_feed_read_from_stdin() {
+        let url = Url::parse(crate::config::STDIN_URL).unwrap();
+        let cfg = FeedConfig::single_feed("stdin", url, Posts::new());
+        let mut settings = Settings::default();
+        settings.refresh.adaptive = true;
+        let mut app = App::new_ephemeral(cfg, settings);
+        let feed_id = app.feed_state.feed_ids().next().unwrap();
+
+        app.start_download(feed_id.clone());
+        assert!(!app.feed_state.is_downloading(&feed_id));
+        assert_eq!(app.message.as_deref(), Some(NOT_DOWNLOADABLE));
+
+        let feed = app.feed_state.get_feed(&feed_id).unwrap();
+        assert!(!feed.is_due(&app.feed_state.settings.refresh, Utc::now()));
+    }
+
+    #[test]
+    fn loads_posts_in_the_background() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let cfg = FeedConfig::single_feed("A", url.clone(), Posts::new());
+        let mut app = App::new_ephemeral(cfg, Settings::default());
+        let feed_id = app.feed_state.feed_ids().next().unwrap();
+        let feed_url: Arc<str> = url.as_str().into();
+        app.feed_state.loading.insert(feed_url.clone());
+
+        // The feed isn't downloaded before its posts are there to be merged
+        // with the downloaded ones.
+        app.start_download(feed_id.clone());
+        assert!(!app.feed_state.is_downloading(&feed_id));
+        assert_eq!(app.message.as_deref(), Some(LOADING));
+
+        let mut read = post("a1", "https://a.example/1", 1);
+        read.read = true;
+        let stored = StoredFeed {
+            posts: vec![read, post("a2", "https://a.example/2", 2)].into(),
+            ..StoredFeed::default()
+        };
+        app.handle_database(DatabaseResponse::Loaded(feed_url,
+            Box::new(stored)));
+        assert!(app.feed_state.is_loaded());
+        assert_eq!(app.feed_state.get_feed(&feed_id).unwrap().posts.len(), 2);
+        assert_eq!(app.read_at_start, 1);
+    }
+
+    #[test]
+    fn keeps_the_read_state_of_feeds_added_back() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let cfg = FeedConfig::single_feed("A", url.clone(), Posts::new());
+        let mut app = App::new_ephemeral(cfg, Settings::default());
+        let feed_id = app.feed_state.feed_ids().next().unwrap();
+        let feed_url: Arc<str> = url.as_str().into();
+
+        // The feed was removed, with its posts left in the database, and has
+        // been added back. It's downloaded only once they're loaded.
+        app.load_added_feeds(std::slice::from_ref(&feed_id));
+        assert!(app.feed_state.is_loading(&feed_id));
+        assert!(!app.feed_state.downloading.contains_key(&feed_id));
+
+        let mut read = post("a1", "https://a.example/1", 1);
+        read.read = true;
+        let stored = StoredFeed {
+            posts: vec![read].into(),
+            ..StoredFeed::default()
+        };
+        app.handle_database(DatabaseResponse::Loaded(feed_url,
+            Box::new(stored)));
+        assert!(app.feed_state.downloading.contains_key(&feed_id));
+
+        let posts = vec![
+            post("a1", "https://a.example/1", 1),
+            post("a2", "https://a.example/2", 2),
+        ].into();
+        app.handle_download(DownloadResponse::Finished {
+            feed: feed_id.clone(), posts
+        });
+        let feed = app.feed_state.get_feed(&feed_id).unwrap();
+        assert_eq!(feed.posts.len(), 2);
+        assert!(feed.posts.get_by_id(&PostId(Arc::from("a1"))).unwrap().read);
+        assert_eq!(feed.posts.unread(), 1);
+    }
+
+    #[test]
+    fn downloads_feeds_only_once_their_posts_are_loaded() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let cfg = FeedConfig::single_feed("A", url.clone(), Posts::new());
+        let mut app = App::new_ephemeral(cfg, Settings::default());
+        let feed_id = app.feed_state.feed_ids().next().unwrap();
+        let feed_url: Arc<str> = url.as_str().into();
+
+        // The stored posts would be overwritten by the downloaded ones.
+        app.load_added_feeds(std::slice::from_ref(&feed_id));
+        app.handle_database(DatabaseResponse::LoadFailed(feed_url.clone(),
+            "corrupted".into()));
+        assert!(app.feed_state.is_load_failed(&feed_id));
+        assert!(!app.feed_state.downloading.contains_key(&feed_id));
+
+        // Downloading the feed loads its posts again first.
+        app.start_download(feed_id.clone());
+        assert!(app.feed_state.is_loading(&feed_id));
+        assert!(!app.feed_state.downloading.contains_key(&feed_id));
+
+        app.handle_database(DatabaseResponse::Loaded(feed_url,
+            Box::default()));
+        assert!(!app.feed_state.is_load_failed(&feed_id));
+        assert!(app.feed_state.downloading.contains_key(&feed_id));
+    }
+
+    #[test]
+    fn loads_the_feeds_added_to_the_feed_file() {
+        let dir = std::env::temp_dir()
+            .join(format!("nia-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("feeds");
+        std::fs::write(&path, "# A\nA | https://a.example/feed.xml\n\
+            B | https://b.example/feed.xml\n").unwrap();
+
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let cfg = FeedConfig::single_feed("A", url, Posts::new());
+        let mut app = App::new_ephemeral(cfg, Settings::default());
+        app.feed_file = Some(FeedFileWatch { path, changed: true });
+        app.reload_feed_file();
+
+        let added = app.feed_state.feed_ids().nth(1).unwrap();
+        assert!(app.feed_state.is_loading(&added));
+        assert!(!app.feed_state.downloading.contains_key(&added));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn offers_catching_up_after_a_break_in_refreshing() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        // Posts stored before their arrival was kept arrived when published.
+        let cfg = FeedConfig::single_feed("A", url, vec![
+            post("a1", "https://a.example/1", 1),
+        ].into());
+        let app = App::new_ephemeral(cfg, Settings::default());
+        let mut state = app.feed_state;
+        assert_eq!(state.long_break(), None);
+
+        let feed_id = state.feed_ids().next().unwrap();
+        let refreshed = Utc::now() - TimeDelta::days(10);
+        let health = |succeeded| FeedHealth {
+            fetched: Utc::now(),
+            duration_ms: 100,
+            status: None,
+            error: Some("Timed out".into()),
+            failures: 1,
+            succeeded: Some(succeeded),
+        };
+        let feed = state.get_feed_mut(&feed_id).unwrap();
+        feed.health = Some(health(refreshed));
+        assert_eq!(state.long_break(), Some(refreshed));
+
+        let feed = state.get_feed_mut(&feed_id).unwrap();
+        feed.health = Some(health(Utc::now()));
+        assert_eq!(state.long_break(), None);
+    }
+
+    #[test]
+    fn formats_session_summaries() {
+        let summary = SessionSummary {
+            posts_read: 3,
+            feeds_refreshed: 12,
+            duration: Duration::from_secs(25 * 60 + 3),
+        };
+        assert_eq!(summary.to_string(),
+            "Read 3 posts, refreshed 12 feeds in 25m 3s");
+
+        let summary = SessionSummary {
+            duration: Duration::from_secs(2 * 3600 + 60),
+            ..summary
+        };
+        assert_eq!(summary.to_string(),
+            "Read 3 posts, refreshed 12 feeds in 2h 1m");
+    }
 }
@@ -0,0 +1,217 @@
+//! Export and import of the starred posts as Netscape bookmarks.
+//!
+//! This is the HTML format browsers and bookmark managers use to exchange
+//! bookmarks. Folders are ignored on import, only the links matter.
+
+use std::io::{self, Write};
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use url::Url;
+use crate::config::{Post, Posts};
+use crate::database::Database;
+
+/// A link read from a bookmarks file.
+#[derive(Debug, PartialEq)]
+pub struct Bookmark {
+    /// The bookmarked URL.
+    pub url: String,
+
+    /// The title of the bookmark.
+    pub title: String,
+
+    /// When the bookmark was added, if the file says.
+    pub added: Option<DateTime<Utc>>,
+}
+
+/// Write the starred posts in the database to `out` as bookmarks, newest
+/// first.
+///
+/// Posts without a URL can't be bookmarked and are left out. Returns the
+/// number of exported bookmarks.
+pub fn export<W: Write>(db: &Database, mut out: W) -> io::Result<usize> {
+    let mut posts: Vec<_> = db.all_posts().map_err(io::Error::other)?
+        .into_iter()
+        .map(|(_, post)| post)
+        .filter(|post| post.starred && !post.urls.is_empty())
+        .collect();
+    posts.sort_by_key(|post| std::cmp::Reverse(post.published));
+
+    writeln!(out, "<!DOCTYPE NETSCAPE-Bookmark-file-1>")?;
+    writeln!(out, "<META HTTP-EQUIV=\"Content-Type\" \
+        CONTENT=\"text/html; charset=UTF-8\">")?;
+    writeln!(out, "<TITLE>Bookmarks</TITLE>")?;
+    writeln!(out, "<H1>Starred posts</H1>")?;
+    writeln!(out, "<DL><p>")?;
+    for post in posts.iter() {
+        writeln!(out, "    <DT><A HREF=\"{}\" ADD_DATE=\"{}\">{}</A>",
+            escape(post.urls[0].as_str()), post.published.timestamp(),
+            escape(&post.title))?;
+    }
+    writeln!(out, "</DL><p>")?;
+
+    out.flush()?;
+    Ok(posts.len())
+}
+
+/// Parse the links of a bookmarks file.
+///
+/// The format is HTML rather than XML, so it's only scanned for the links
+/// instead of being parsed strictly.
+pub fn parse(input: &str) -> Vec<Bookmark> {
+    // Lowercasing ASCII doesn't move anything, so the offsets match.
+    let lower = input.to_ascii_lowercase();
+    let mut bookmarks = Vec::new();
+
+    let mut rest = 0;
+    while let Some(start) = lower[rest..].find("<a ").map(|idx| rest + idx) {
+        let Some(tag_end) = lower[start..].find('>').map(|idx| start + idx)
+        else {
+            break;
+        };
+        let Some(end) = lower[tag_end..].find("</a>").map(|idx| tag_end + idx)
+        else {
+            break;
+        };
+        rest = end;
+
+        let tag = &input[start + 3..tag_end];
+        let Some(url) = attribute(tag, "href") else {
+            continue;
+        };
+        let added = attribute(tag, "add_date")
+            .and_then(|secs| secs.parse().ok())
+            .and_then(|secs| DateTime::from_timestamp(secs, 0));
+
+        bookmarks.push(Bookmark {
+            url,
+            title: unescape(input[tag_end + 1..end].trim()),
+            added,
+        });
+    }
+
+    bookmarks
+}
+
+/// Star the stored posts that the bookmarks in the `input` link to.
+///
+/// A bookmark matches the posts whose first URL it is. Returns the number of
+/// starred posts and the number of bookmarks that didn't match any post.
+pub fn import(db: &Database, input: &str) -> Result<(usize, usize), String> {
+    let bookmarks = parse(input);
+    if bookmarks.is_empty() {
+        return Err("No bookmarks found".into());
+    }
+
+    // The stored posts by their first URL.
+    let mut by_url: HashMap<String, Vec<(String, Post)>> = HashMap::new();
+    for (feed_url, post) in db.all_posts()? {
+        if let Some(url) = post.urls.first() {
+            by_url.entry(url.to_string()).or_default().push((feed_url, post));
+        }
+    }
+
+    let mut unmatched = 0;
+    let mut starred: HashMap<String, Posts> = HashMap::new();
+    for bookmark in bookmarks {
+        // Compare the URLs the way they're stored.
+        let url = Url::parse(&bookmark.url)
+            .map(String::from)
+            .unwrap_or(bookmark.url);
+        let Some(posts) = by_url.remove(&url) else {
+            unmatched += 1;
+            continue;
+        };
+
+        for (feed_url, mut post) in posts {
+            post.starred = true;
+            starred.entry(feed_url).or_default().insert(post);
+        }
+    }
+
+    let count = starred.values().map(|posts| posts.len()).sum();
+    for (feed_url, posts) in starred {
+        db.save_posts(&feed_url, posts)?;
+    }
+
+    Ok((count, unmatched))
+}
+
+/// Get the value of the attribute called `name` of an HTML tag.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let pattern = format!("{}=\"", name);
+
+    let mut from = 0;
+    while let Some(idx) = lower[from..].find(&pattern).map(|idx| from + idx) {
+        from = idx + pattern.len();
+
+        // Don't match the end of another attribute's name.
+        let starts_name = lower[..idx].ends_with(char::is_whitespace)
+            || idx == 0;
+        if !starts_name {
+            continue;
+        }
+
+        let value = &tag[from..];
+        let end = value.find('"')?;
+        return Some(unescape(&value[..end]));
+    }
+
+    None
+}
+
+/// Escape the text for HTML.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Replace the HTML entities that bookmark files use with their characters.
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_browser_bookmarks() {
+        let input = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3 ADD_DATE="1700000000">Reading</H3>
+    <DL><p>
+        <DT><a href="https://example.com/a?x=1&amp;y=2" add_date="1700000000"
+            ICON="data:image/png;base64,AAA">A &lt;post&gt;</a>
+        <DT><A LAST_MODIFIED="1" HREF="https://example.com/b">B</A>
+        <DT><A NAME="no link">C</A>
+    </DL><p>
+</DL><p>
+"#;
+
+        assert_eq!(parse(input), [
+            Bookmark {
+                url: "https://example.com/a?x=1&y=2".into(),
+                title: "A <post>".into(),
+                added: DateTime::from_timestamp(1_700_000_000, 0),
+            },
+            Bookmark {
+                url: "https://example.com/b".into(),
+                title: "B".into(),
+                added: None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn escapes_exported_titles() {
+        let title = "Fish & \"chips\" <3";
+        assert_eq!(unescape(&escape(title)), title);
+    }
+}
@@ -0,0 +1,116 @@
+//! The bodies of the feeds as they were last downloaded, kept on disk so that
+//! the feeds that haven't changed aren't parsed again and so that the feeds
+//! can be read again without network access.
+
+use std::io;
+use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::database::Database;
+use crate::error::Result;
+use crate::settings::Settings;
+
+/// The body of a feed as it was last downloaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedBody {
+    /// The hash of the body, to tell whether a download has changed.
+    pub hash: String,
+
+    /// When the body was downloaded.
+    #[serde(with = "crate::config::datetime_serde")]
+    pub fetched: DateTime<Utc>,
+
+    /// The body itself.
+    pub body: String,
+}
+
+/// The directory the bodies of the feeds are kept in, one file for each
+/// feed named after the hash of its URL.
+#[derive(Debug, Clone)]
+pub struct BodyCache {
+    /// The directory of the files.
+    dir: PathBuf,
+
+    /// Whether the feeds are read from the cache instead of being
+    /// downloaded.
+    offline: bool,
+}
+
+impl BodyCache {
+    /// Keep the bodies in the `dir`, reading the feeds from them instead of
+    /// downloading them if `offline` is set.
+    pub fn new(dir: PathBuf, offline: bool) -> Self {
+        Self { dir, offline }
+    }
+
+    /// Keep the bodies in the data directory of the database of the
+    /// `settings`, offline if they say so.
+    pub fn open(settings: &Settings) -> Result<Self> {
+        let dir = Database::data_dir(&settings.storage)?.join("bodies");
+        Ok(Self::new(dir, settings.offline))
+    }
+
+    /// Whether the feeds are read from the cache instead of being
+    /// downloaded.
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Get the path of the file of the feed at `url`.
+    fn path(&self, url: &Url) -> PathBuf {
+        self.dir.join(format!("{}.json", crate::hash(url.as_str())))
+    }
+
+    /// Load the body of the feed at `url` as it was last downloaded, if it
+    /// has been cached.
+    pub fn load(&self, url: &Url) -> Option<CachedBody> {
+        let json = std::fs::read_to_string(self.path(url)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// Cache the `body` of the feed at `url`, downloaded at `fetched`,
+    /// replacing the one cached before.
+    pub fn save(&self, url: &Url, body: &str, fetched: DateTime<Utc>)
+        -> io::Result<()>
+    {
+        let cached = CachedBody {
+            hash: crate::hash(body),
+            fetched,
+            body: body.to_string(),
+        };
+        std::fs::create_dir_all(&self.dir)?;
+
+        // Write it aside first so that a crash doesn't leave half of it.
+        let path = self.path(url);
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, serde_json::to_string(&cached)?)?;
+        std::fs::rename(&partial, &path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caches_bodies_by_url() {
+        let dir = std::env::temp_dir()
+            .join(format!("nia-cache-{}", std::process::id()));
+        let cache = BodyCache::new(dir.clone(), false);
+        let url = Url::parse("https://blog.example/feed.xml").unwrap();
+        let other = Url::parse("https://other.example/feed.xml").unwrap();
+        let fetched = DateTime::from_timestamp(60, 0).unwrap();
+        assert_eq!(cache.load(&url), None);
+
+        cache.save(&url, "<rss/>", fetched).unwrap();
+        cache.save(&url, "<rss></rss>", fetched).unwrap();
+        let cached = cache.load(&url).unwrap();
+        assert_eq!(cached.body, "<rss></rss>");
+        assert_eq!(cached.hash, crate::hash("<rss></rss>"));
+        assert_eq!(cached.fetched, fetched);
+        assert_eq!(cache.load(&other), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,432 @@
+//! Command line parsing.
+
+/// Exit codes of the command line interface, so that scripts can react to
+/// failures.
+///
+/// The codes follow `sysexits.h` where applicable.
+pub mod exit_code {
+    /// Everything went fine.
+    pub const OK: u8 = 0;
+
+    /// At least one of the feeds couldn't be fetched.
+    pub const FEEDS_FAILED: u8 = 1;
+
+    /// The command line arguments were invalid.
+    pub const USAGE: u8 = 64;
+
+    /// The feed read from stdin isn't a feed.
+    pub const DATA_ERROR: u8 = 65;
+
+    /// A remote couldn't be synced with.
+    pub const REMOTE_FAILED: u8 = 69;
+
+    /// The database, the terminal or another file couldn't be used.
+    pub const IO_ERROR: u8 = 74;
+
+    /// The feed file or the settings couldn't be read or parsed.
+    pub const CONFIG_ERROR: u8 = 78;
+}
+
+/// The usage string printed on invalid arguments.
+pub const USAGE: &str = "\
+Usage: nia [--read-only] [--offline] [--verbose] [--config <dir>]
+           [--data-dir <dir>] [--stdin]
+       nia fetch [--quiet]
+       nia add <url> [--section <name>] [--title <title>]
+       nia unread [--json]
+       nia sync
+       nia clean [--archive <file>] [--yes]
+       nia db export [--ndjson] [<file>]
+       nia db import [<file>]
+       nia search <query>...
+       nia bookmarks export [<file>]
+       nia bookmarks import [<file>]
+       nia state export [<file>]
+       nia state import [<file>]
+
+With --read-only before the command, the database is never written to and
+nothing is downloaded. Only the TUI, unread, search and the exports can
+be used then. With --offline, the TUI and fetch read the feeds from their
+bodies cached when they were last downloaded instead of downloading them.
+--verbose also logs the debug messages to $XDG_STATE_HOME/nia/nia.log.
+
+--config <dir> reads the feed file and config.toml from the directory
+instead of $XDG_CONFIG_HOME/nia, and --data-dir <dir> keeps the database in
+the directory instead of $XDG_DATA_HOME/nia, so that several profiles can be
+kept apart. $NIA_CONFIG_DIR and $NIA_DATA_DIR do the same.";
+
+/// The command line arguments.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Args {
+    /// What to do.
+    pub command: Cli,
+
+    /// Never write to the database and don't download anything.
+    pub read_only: bool,
+
+    /// Read the feeds from the cached bodies instead of downloading them.
+    pub offline: bool,
+
+    /// Log the debug messages too.
+    pub verbose: bool,
+
+    /// The config directory of the profile, instead of the default one.
+    pub config: Option<String>,
+
+    /// The directory of the database of the profile, instead of the data
+    /// directory.
+    pub data_dir: Option<String>,
+}
+
+impl Args {
+    /// Parse the command line arguments, without the program name.
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into).peekable();
+
+        // The options of nia itself come before the command.
+        let mut read_only = false;
+        let mut offline = false;
+        let mut verbose = false;
+        let mut config = None;
+        let mut data_dir = None;
+        while let Some(arg) = args.next_if(|arg| {
+            matches!(arg.as_str(), "--read-only" | "--offline" | "--verbose"
+                | "--config" | "--data-dir")
+        }) {
+            let mut value = || args.next()
+                .ok_or_else(|| format!("Missing value for {}", arg));
+
+            match arg.as_str() {
+                "--read-only" => read_only = true,
+                "--offline" => offline = true,
+                "--verbose" => verbose = true,
+                "--config" => config = Some(value()?),
+                _ => data_dir = Some(value()?),
+            }
+        }
+        let command = Cli::parse(args)?;
+
+        if read_only && !command.is_read_only() {
+            return Err("The command can't be used with --read-only".into());
+        }
+
+        Ok(Self { command, read_only, offline, verbose, config, data_dir })
+    }
+}
+
+/// What the user has asked us to do.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Cli {
+    /// Browse the configured feeds in the TUI.
+    Tui,
+
+    /// Browse a feed document read from stdin in the TUI.
+    Stdin,
+
+    /// Download all feeds without starting the TUI.
+    Fetch {
+        /// Only print the feeds that failed.
+        quiet: bool,
+    },
+
+    /// Subscribe to a feed.
+    Add {
+        /// URL of the feed or of a page that links to it.
+        url: String,
+
+        /// Section to add the feed to.
+        section: Option<String>,
+
+        /// Title of the feed.
+        title: Option<String>,
+    },
+
+    /// Print the unread posts.
+    Unread {
+        /// Print them as JSON instead of tab separated values.
+        json: bool,
+    },
+
+    /// Sync the feeds and the read state with the configured remotes.
+    Sync,
+
+    /// Delete the posts of feeds that aren't subscribed to anymore.
+    Clean {
+        /// File to save the posts to before they're deleted.
+        archive: Option<String>,
+
+        /// Don't ask for confirmation.
+        yes: bool,
+    },
+
+    /// Write all stored posts in a portable format.
+    DbExport {
+        /// Write one post per line instead of a JSON array.
+        ndjson: bool,
+
+        /// File to write to instead of stdout.
+        file: Option<String>,
+    },
+
+    /// Store the posts written by `DbExport`.
+    DbImport {
+        /// File to read from instead of stdin.
+        file: Option<String>,
+    },
+
+    /// Write the starred posts as Netscape bookmarks.
+    BookmarksExport {
+        /// File to write to instead of stdout.
+        file: Option<String>,
+    },
+
+    /// Star the posts linked to by Netscape bookmarks.
+    BookmarksImport {
+        /// File to read from instead of stdin.
+        file: Option<String>,
+    },
+
+    /// Write which stored posts are read or starred.
+    StateExport {
+        /// File to write to instead of stdout.
+        file: Option<String>,
+    },
+
+    /// Mark the stored posts read or starred as `StateExport` wrote.
+    StateImport {
+        /// File to read from instead of stdin.
+        file: Option<String>,
+    },
+
+    /// Print the stored posts whose titles match a query.
+    Search {
+        /// The words to search for.
+        query: String,
+    },
+}
+
+impl Cli {
+    /// Whether the command can run without writing to the database.
+    fn is_read_only(&self) -> bool {
+        matches!(self, Cli::Tui | Cli::Stdin | Cli::Unread { .. }
+            | Cli::Search { .. } | Cli::DbExport { .. }
+            | Cli::BookmarksExport { .. } | Cli::StateExport { .. })
+    }
+
+    /// Parse the command line arguments, without the program name.
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into);
+
+        let Some(command) = args.next() else {
+            return Ok(Cli::Tui);
+        };
+
+        let cli = match command.as_str() {
+            "--stdin" => Cli::Stdin,
+
+            "fetch" => {
+                let mut quiet = false;
+                for arg in args.by_ref() {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => quiet = true,
+                        _ => return Err(format!("Unknown argument: {}", arg)),
+                    }
+                }
+                Cli::Fetch { quiet }
+            },
+
+            "add" => {
+                let mut url = None;
+                let mut section = None;
+                let mut title = None;
+
+                while let Some(arg) = args.next() {
+                    let mut value = || args.next()
+                        .ok_or_else(|| format!("Missing value for {}", arg));
+
+                    match arg.as_str() {
+                        "--section" => section = Some(value()?),
+                        "--title" => title = Some(value()?),
+                        _ if url.is_none() && !arg.starts_with('-') => {
+                            url = Some(arg)
+                        },
+                        _ => return Err(format!("Unknown argument: {}", arg)),
+                    }
+                }
+
+                let url = url.ok_or("Missing the URL of the feed")?;
+                Cli::Add { url, section, title }
+            },
+
+            "unread" => match args.next().as_deref() {
+                None => Cli::Unread { json: false },
+                Some("--json") => Cli::Unread { json: true },
+                Some(arg) => return Err(format!("Unknown argument: {}", arg)),
+            },
+
+            "sync" => Cli::Sync,
+
+            "clean" => {
+                let mut archive = None;
+                let mut yes = false;
+
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--archive" => archive = Some(args.next()
+                            .ok_or("Missing value for --archive")?),
+                        "-y" | "--yes" => yes = true,
+                        _ => return Err(format!("Unknown argument: {}", arg)),
+                    }
+                }
+                Cli::Clean { archive, yes }
+            },
+
+            "db" => match args.next().as_deref() {
+                Some("export") => {
+                    let mut ndjson = false;
+                    let mut file = None;
+                    for arg in args.by_ref() {
+                        match arg.as_str() {
+                            "--ndjson" => ndjson = true,
+                            _ if file.is_none() && !arg.starts_with('-') => {
+                                file = Some(arg)
+                            },
+                            _ => {
+                                return Err(format!("Unknown argument: {}", arg))
+                            },
+                        }
+                    }
+                    Cli::DbExport { ndjson, file }
+                },
+                Some("import") => Cli::DbImport { file: args.next() },
+                Some(arg) => return Err(format!("Unknown command: db {}", arg)),
+                None => return Err("Missing the db command".into()),
+            },
+
+            "bookmarks" => match args.next().as_deref() {
+                Some("export") => Cli::BookmarksExport { file: args.next() },
+                Some("import") => Cli::BookmarksImport { file: args.next() },
+                Some(arg) => {
+                    return Err(format!("Unknown command: bookmarks {}", arg))
+                },
+                None => return Err("Missing the bookmarks command".into()),
+            },
+
+            "state" => match args.next().as_deref() {
+                Some("export") => Cli::StateExport { file: args.next() },
+                Some("import") => Cli::StateImport { file: args.next() },
+                Some(arg) => {
+                    return Err(format!("Unknown command: state {}", arg))
+                },
+                None => return Err("Missing the state command".into()),
+            },
+
+            "search" => {
+                let query = args.by_ref().collect::<Vec<_>>().join(" ");
+                if query.trim().is_empty() {
+                    return Err("Missing the search query".into());
+                }
+                Cli::Search { query }
+            },
+
+            _ => return Err(format!("Unknown command: {}", command)),
+        };
+
+        // Nothing should be left over.
+        match args.next() {
+            Some(arg) => Err(format!("Unknown argument: {}", arg)),
+            None => Ok(cli),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(Cli::parse::<_, &str>([]), Ok(Cli::Tui));
+        assert_eq!(Cli::parse(["--stdin"]), Ok(Cli::Stdin));
+        assert_eq!(Cli::parse(["fetch", "-q"]), Ok(Cli::Fetch { quiet: true }));
+        assert_eq!(Cli::parse(["sync"]), Ok(Cli::Sync));
+        assert_eq!(Cli::parse(["clean", "--archive", "old.json", "-y"]),
+            Ok(Cli::Clean { archive: Some("old.json".into()), yes: true }));
+        assert_eq!(Cli::parse(["db", "export", "--ndjson"]),
+            Ok(Cli::DbExport { ndjson: true, file: None }));
+        assert_eq!(Cli::parse(["db", "import", "posts.json"]),
+            Ok(Cli::DbImport { file: Some("posts.json".into()) }));
+        assert_eq!(Cli::parse(["bookmarks", "export", "starred.html"]),
+            Ok(Cli::BookmarksExport { file: Some("starred.html".into()) }));
+        assert_eq!(Cli::parse(["bookmarks", "import"]),
+            Ok(Cli::BookmarksImport { file: None }));
+        assert_eq!(Cli::parse(["state", "export", "state.ndjson"]),
+            Ok(Cli::StateExport { file: Some("state.ndjson".into()) }));
+        assert_eq!(Cli::parse(["state", "import"]),
+            Ok(Cli::StateImport { file: None }));
+        assert_eq!(Cli::parse(["search", "rust", "kernel"]),
+            Ok(Cli::Search { query: "rust kernel".into() }));
+        assert_eq!(
+            Cli::parse(["add", "https://a.b", "--section", "News"]),
+            Ok(Cli::Add {
+                url: "https://a.b".into(),
+                section: Some("News".into()),
+                title: None,
+            }));
+    }
+
+    #[test]
+    fn parses_read_only() {
+        let args = |command, read_only, verbose| Ok(Args {
+            command, read_only, offline: false, verbose, config: None,
+            data_dir: None,
+        });
+        assert_eq!(Args::parse(["--read-only"]), args(Cli::Tui, true, false));
+        assert_eq!(Args::parse(["--read-only", "unread"]),
+            args(Cli::Unread { json: false }, true, false));
+        assert_eq!(Args::parse(["sync"]), args(Cli::Sync, false, false));
+        assert_eq!(Args::parse(["--verbose", "--read-only", "--stdin"]),
+            args(Cli::Stdin, true, true));
+        assert!(Args::parse(["--read-only", "fetch"]).is_err());
+        assert!(Args::parse(["unread", "--read-only"]).is_err());
+        assert!(Args::parse(["fetch", "--verbose"]).is_err());
+        assert!(Args::parse(["--offline", "fetch"]).unwrap().offline);
+    }
+
+    #[test]
+    fn parses_profiles() {
+        let args = Args::parse(["--config", "~/work", "--data-dir", "/tmp/w",
+            "--read-only", "unread"]).unwrap();
+        assert_eq!(args.config.as_deref(), Some("~/work"));
+        assert_eq!(args.data_dir.as_deref(), Some("/tmp/w"));
+        assert!(args.read_only);
+        assert_eq!(args.command, Cli::Unread { json: false });
+
+        assert!(Args::parse(["--config"]).is_err());
+        assert!(Args::parse(["fetch", "--data-dir", "/tmp/w"]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(Cli::parse(["frobnicate"]).is_err());
+        assert!(Cli::parse(["--stdin", "extra"]).is_err());
+        assert!(Cli::parse(["fetch", "--loud"]).is_err());
+        assert!(Cli::parse(["add"]).is_err());
+        assert!(Cli::parse(["add", "https://a.b", "--title"]).is_err());
+        assert!(Cli::parse(["unread", "--json", "--json"]).is_err());
+        assert!(Cli::parse(["clean", "--archive"]).is_err());
+        assert!(Cli::parse(["db"]).is_err());
+        assert!(Cli::parse(["search"]).is_err());
+        assert!(Cli::parse(["bookmarks", "sync"]).is_err());
+        assert!(Cli::parse(["state"]).is_err());
+        assert!(Cli::parse(["db", "export", "a.json", "b.json"]).is_err());
+    }
+}
@@ -1,12 +1,25 @@
 //! Config parsing and stuff.
 
-use std::collections::HashSet;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
 use std::io::{self, BufRead};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use url::Url;
-use chrono::{DateTime, Utc};
-use serde::{Serialize, Deserialize};
+use chrono::{DateTime, TimeDelta, Utc};
+use regex::Regex;
+use serde::{Serialize, Deserialize, Deserializer};
+use unicode_width::UnicodeWidthStr;
+use crate::settings::RefreshSettings;
+
+/// The number of newest posts the posting cadence of a feed is measured over.
+const CADENCE_POSTS: usize = 20;
+
+/// The URL of a feed read from stdin, which can't be downloaded again.
+pub const STDIN_URL: &str = "stdin:";
+
+/// The config directory set by `FeedConfig::set_config_dir`, used instead
+/// of the default one.
+static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
 
 /// A parsed config file.
 #[derive(Debug, Clone)]
@@ -36,6 +49,352 @@ pub struct Feed {
 
     /// The posts in the feed.
     pub posts: Posts,
+
+    /// How the posts are shown.
+    pub view: FeedView,
+
+    /// How the last download went, if the feed has been downloaded yet.
+    pub health: Option<FeedHealth>,
+
+    /// What the feed said about itself when it was last downloaded.
+    pub meta: Option<Arc<FeedMeta>>,
+
+    /// The archived texts of the articles of the posts, by post ID.
+    pub articles: HashMap<PostId, Arc<str>>,
+
+    /// The URLs of the media linked from the posts that have been played.
+    pub played: HashSet<Url>,
+
+    /// The URLs that have been opened from the posts, by post ID.
+    pub opened: HashMap<PostId, HashSet<Url>>,
+
+    /// When the posts have been marked read, oldest first.
+    pub reads: Vec<Read>,
+
+    /// The IDs of the posts removed for being past the retention, so that
+    /// they aren't merged back in when they're downloaded again.
+    pub pruned: HashSet<PostId>,
+
+    /// Where the feed has permanently moved to, if its URL redirects there.
+    /// It's downloaded from there instead.
+    pub moved: Option<Url>,
+
+    /// The settings of the feed from `feeds.toml`.
+    pub options: Arc<FeedOptions>,
+}
+
+/// The settings of a single feed, which only `feeds.toml` can give.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FeedOptions {
+    /// How often the feed is downloaded while the TUI runs, in minutes.
+    pub refresh: Option<u32>,
+
+    /// Tags shown next to the title of the feed.
+    pub tags: Vec<String>,
+
+    /// The credentials the feed is downloaded with.
+    pub auth: Option<FeedAuth>,
+
+    /// Headers sent along with the downloads of the feed, e.g. a token.
+    pub headers: BTreeMap<String, String>,
+
+    /// If any are given, only the downloaded posts whose titles match one of
+    /// these are kept.
+    #[serde(deserialize_with = "deserialize_filters")]
+    pub include: Vec<PostFilter>,
+
+    /// The downloaded posts whose titles match any of these are dropped.
+    #[serde(deserialize_with = "deserialize_filters")]
+    pub exclude: Vec<PostFilter>,
+
+    /// Shell commands the URLs of the posts can be run through, in addition
+    /// to the actions of the settings.
+    pub actions: BTreeMap<String, String>,
+
+    /// Where the article is on the pages the posts link to, for the feeds
+    /// that only publish summaries.
+    #[serde(deserialize_with = "deserialize_article_rule")]
+    pub article: Option<ArticleRule>,
+}
+
+/// A pattern the titles or the URLs of the posts are matched against.
+#[derive(Debug, Clone)]
+pub enum PostFilter {
+    /// A substring, matched regardless of the case. Stored in lowercase.
+    Contains(String),
+
+    /// A regex, given between slashes.
+    Regex(Regex),
+}
+
+impl PostFilter {
+    /// Parse a filter, a regex if it's between slashes and a substring
+    /// otherwise.
+    pub fn parse(pattern: &str) -> Result<Self, regex::Error> {
+        let regex = pattern.strip_prefix('/')
+            .and_then(|pattern| pattern.strip_suffix('/'));
+        match regex {
+            Some(regex) => Ok(Self::Regex(Regex::new(regex)?)),
+            None => Ok(Self::Contains(pattern.to_lowercase())),
+        }
+    }
+
+    /// Whether the `text` matches the filter.
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Contains(substring) => {
+                text.to_lowercase().contains(substring.as_str())
+            },
+            Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// Where the article is on the page a post links to.
+#[derive(Debug, Clone)]
+pub enum ArticleRule {
+    /// A simple CSS selector: a tag name, an `#id`, a `.class` or a tag name
+    /// with either, e.g. `div.post-body`. The first matching element is the
+    /// article.
+    Selector {
+        tag: Option<String>,
+        id: Option<String>,
+        class: Option<String>,
+    },
+
+    /// A regex, given between slashes. Its first group, or the whole match
+    /// if it has none, is the article.
+    Regex(Regex),
+}
+
+impl ArticleRule {
+    /// Parse a rule, a regex if it's between slashes and a selector
+    /// otherwise.
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let regex = rule.strip_prefix('/')
+            .and_then(|rule| rule.strip_suffix('/'));
+        if let Some(regex) = regex {
+            return Regex::new(regex)
+                .map(Self::Regex)
+                .map_err(|err| err.to_string());
+        }
+
+        let is_name = |s: &str| !s.is_empty() && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        let invalid = || format!("Invalid selector \"{}\", expected a tag, \
+            an #id or a .class", rule);
+
+        let rule = rule.trim();
+        let (tag, rest) = rule.split_at(rule.find(['#', '.'])
+            .unwrap_or(rule.len()));
+        let tag = Some(tag.to_ascii_lowercase()).filter(|tag| !tag.is_empty());
+        let (mut id, mut class) = (None, None);
+        if let Some(name) = rest.strip_prefix('#') {
+            id = Some(name.to_string());
+        } else if let Some(name) = rest.strip_prefix('.') {
+            class = Some(name.to_string());
+        }
+
+        let names = [&tag, &id, &class];
+        let valid = names.iter().any(|name| name.is_some())
+            && names.iter().all(|name| name.as_deref().is_none_or(is_name));
+        if !valid {
+            return Err(invalid());
+        }
+        Ok(Self::Selector { tag, id, class })
+    }
+}
+
+/// The HTTP basic authentication of a feed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeedAuth {
+    /// The name of the user.
+    pub user: String,
+
+    /// The password of the user.
+    pub password: Option<String>,
+}
+
+/// The feed file in TOML, `feeds.toml`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlFeedFile {
+    /// The sections, in the order they're shown in.
+    #[serde(default, rename = "section")]
+    sections: Vec<TomlSection>,
+}
+
+/// A section of `feeds.toml`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlSection {
+    /// Title of the section.
+    title: String,
+
+    /// The feeds in the section.
+    #[serde(default, rename = "feed")]
+    feeds: Vec<TomlFeed>,
+}
+
+/// A feed of `feeds.toml`.
+#[derive(Deserialize)]
+struct TomlFeed {
+    /// Title of the feed.
+    title: String,
+
+    /// The URL of the feed.
+    url: String,
+
+    /// The settings of the feed.
+    #[serde(flatten)]
+    options: FeedOptions,
+}
+
+/// How the posts of a feed are shown on its page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedView {
+    /// The order of the posts.
+    pub sort: SortOrder,
+
+    /// Whether only the unread posts are listed.
+    pub unread_only: bool,
+
+    /// How the posts are grouped.
+    pub grouping: Grouping,
+}
+
+/// The order of the posts on a feed page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    /// Newest posts first.
+    #[default]
+    Newest,
+
+    /// Oldest posts first.
+    Oldest,
+
+    /// Alphabetically by title.
+    Title,
+}
+
+impl SortOrder {
+    /// Get the order that comes after this one.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Newest => Self::Oldest,
+            Self::Oldest => Self::Title,
+            Self::Title => Self::Newest,
+        }
+    }
+}
+
+/// How the posts on a feed page are grouped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Grouping {
+    /// All posts in one list.
+    #[default]
+    None,
+
+    /// Under a header for each day they were published on.
+    Day,
+}
+
+impl Grouping {
+    /// Get the grouping that comes after this one.
+    pub fn next(self) -> Self {
+        match self {
+            Self::None => Self::Day,
+            Self::Day => Self::None,
+        }
+    }
+}
+
+/// What a feed says about itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedMeta {
+    /// The description of the feed.
+    pub description: Option<String>,
+
+    /// The website the feed belongs to.
+    #[serde(with = "option_url_serde")]
+    pub site: Option<Url>,
+
+    /// When the feed was last built or updated, as it says.
+    #[serde(with = "option_datetime_serde")]
+    pub updated: Option<DateTime<Utc>>,
+
+    /// The icon or the logo of the feed.
+    #[serde(with = "option_url_serde")]
+    pub icon: Option<Url>,
+}
+
+/// A post having been marked read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Read {
+    /// The post that was marked read.
+    pub post_id: PostId,
+
+    /// When it was marked read.
+    pub at: DateTime<Utc>,
+}
+
+/// How the last download of a feed went.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedHealth {
+    /// When the download started.
+    #[serde(with = "datetime_serde")]
+    pub fetched: DateTime<Utc>,
+
+    /// How long the download took, in milliseconds.
+    pub duration_ms: u64,
+
+    /// The HTTP status of the response, if the server responded.
+    pub status: Option<u16>,
+
+    /// Why the download failed, if it did.
+    pub error: Option<String>,
+
+    /// The number of downloads in a row that have failed, this one included.
+    pub failures: u32,
+
+    /// When the last successful download started, if there's been one.
+    #[serde(default, with = "option_datetime_serde")]
+    pub succeeded: Option<DateTime<Utc>>,
+}
+
+/// The health of a feed as stored in the database before the time of the
+/// last successful download was kept.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LegacyHealth {
+    #[serde(with = "datetime_serde")]
+    fetched: DateTime<Utc>,
+
+    duration_ms: u64,
+
+    status: Option<u16>,
+
+    error: Option<String>,
+
+    failures: u32,
+}
+
+impl From<LegacyHealth> for FeedHealth {
+    fn from(health: LegacyHealth) -> Self {
+        let LegacyHealth { fetched, duration_ms, status, error, failures } =
+            health;
+        // Only the last download is known to have succeeded.
+        let succeeded = error.is_none().then_some(fetched);
+        Self { fetched, duration_ms, status, error, failures, succeeded }
+    }
+}
+
+impl FeedHealth {
+    /// Whether the last download failed.
+    pub fn is_failing(&self) -> bool {
+        self.error.is_some()
+    }
 }
 
 /// A vector of posts.
@@ -54,7 +413,7 @@ pub struct Posts {
 impl From<Vec<Post>> for Posts {
     fn from(mut v: Vec<Post>) -> Self {
         // Sort newest first
-        v.sort_unstable_by(|a, b| b.published.cmp(&a.published));
+        v.sort_unstable_by_key(|post| std::cmp::Reverse(post.published));
 
         let mut ids = HashSet::with_capacity(v.len());
         let mut deduped = Vec::with_capacity(v.len());
@@ -91,6 +450,18 @@ impl From<Post> for Posts {
     }
 }
 
+impl Default for Posts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsRef<[Post]> for Posts {
+    fn as_ref(&self) -> &[Post] {
+        &self.inner
+    }
+}
+
 impl Posts {
     /// Create a new post vector.
     pub fn new() -> Self {
@@ -102,8 +473,34 @@ impl Posts {
     }
 
     /// Append posts from `other` to this vector.
+    ///
+    /// The posts this vector has already are skipped. Both vectors are sorted,
+    /// so they're merged in one pass instead of inserting the posts one by
+    /// one.
     pub fn append(&mut self, other: Posts) {
-        other.inner.into_iter().for_each(|post| self.insert(post));
+        let new: Vec<Post> = other.inner.into_iter()
+            .filter(|post| self.ids.insert(post.id.clone()))
+            .collect();
+        if new.is_empty() {
+            return;
+        }
+        self.unread += new.iter().filter(|post| !post.read).count();
+
+        // Of the posts published at the same time, the old ones come first.
+        let old = std::mem::take(&mut self.inner);
+        self.inner = Vec::with_capacity(old.len() + new.len());
+        let mut old = old.into_iter().peekable();
+        let mut new = new.into_iter().peekable();
+        while let (Some(o), Some(n)) = (old.peek(), new.peek()) {
+            let next = if n.published > o.published {
+                new.next()
+            } else {
+                old.next()
+            };
+            self.inner.extend(next);
+        }
+        self.inner.extend(old);
+        self.inner.extend(new);
     }
 
     /// only retain elements specified by the predicate.
@@ -150,6 +547,16 @@ impl Posts {
         self.inner.insert(idx, post);
     }
 
+    /// Replace the title of each post with what `f` makes of it.
+    pub fn map_titles<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str) -> String
+    {
+        for post in self.inner.iter_mut() {
+            post.title = f(&post.title).into();
+        }
+    }
+
     /// Check if the vector contains `post` already.
     pub fn contains(&self, post: &Post) -> bool {
         self.ids.contains(&post.id)
@@ -160,6 +567,11 @@ impl Posts {
         self.inner.len()
     }
 
+    /// Check whether the posts vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
     /// Get the number of unread posts within this feed.
     pub fn unread(&self) -> usize {
         self.unread
@@ -204,6 +616,63 @@ impl Posts {
         }
     }
 
+    /// Star or unstar a post.
+    pub fn toggle_starred(&mut self, post_id: &PostId) {
+        if let Some(post) = self.get_by_id_mut(post_id) {
+            post.starred = !post.starred;
+        }
+    }
+
+    /// Star a post or take its star away.
+    pub fn mark_starred(&mut self, post_id: &PostId, starred: bool) {
+        if let Some(post) = self.get_by_id_mut(post_id) {
+            post.starred = starred;
+        }
+    }
+
+    /// Update the stored posts with the IDs of the `revisions` to them, if
+    /// they're edits of them, marking them edited and unread again if
+    /// `unread` is set. A stored post without the hash or the update time of
+    /// its revision, e.g. one stored before the hashes were kept, just takes
+    /// them. Returns the updated posts.
+    ///
+    /// When the posts were published and arrived, and whether they're
+    /// starred, stay as they are.
+    pub fn revise(&mut self, revisions: &Posts, unread: bool) -> Posts {
+        let revisions: HashMap<&PostId, &Post> = revisions.inner.iter()
+            .map(|post| (&post.id, post))
+            .collect();
+
+        let mut revised = Vec::new();
+        for post in &mut self.inner {
+            let Some(&revision) = revisions.get(&post.id) else {
+                continue;
+            };
+            let edited = revision.is_edit_of(post);
+            let unknown = (post.hash.is_none() && revision.hash.is_some())
+                || (post.updated.is_none() && revision.updated.is_some());
+            if !edited && !unknown {
+                continue;
+            }
+
+            let read = post.read && !(edited && unread);
+            if post.read && !read {
+                self.unread += 1;
+            }
+            *post = Post {
+                published: post.published,
+                arrived: post.arrived,
+                read,
+                starred: post.starred,
+                edited: post.edited || edited,
+                ..revision.clone()
+            };
+            revised.push(post.clone());
+        }
+
+        revised.into()
+    }
+
     /// Get a reference to post given its ID.
     pub fn get_by_id(&self, id: &PostId) -> Option<&Post> {
         self.inner.iter().find(|p| &p.id == id)
@@ -213,11 +682,6 @@ impl Posts {
     fn get_by_id_mut(&mut self, id: &PostId) -> Option<&mut Post> {
         self.inner.iter_mut().find(|p| &p.id == id)
     }
-
-    /// Get a reference to the inner vector.
-    pub fn as_ref(&self) -> &[Post] {
-        &self.inner
-    }
 }
 
 /// A post identifier.
@@ -234,6 +698,24 @@ impl From<String> for PostId {
     }
 }
 
+impl PostId {
+    /// Make the ID of a post that the feed gives none for from the `link` to
+    /// its page, so that it stays the same when the post is edited.
+    ///
+    /// The URL of the feed isn't part of it, since the posts are stored
+    /// under it already and it changes when the feed moves.
+    pub fn from_link(link: &Url) -> Option<Self> {
+        canonical_url(link).map(|url| crate::hash(&url).into())
+    }
+
+    /// Make the ID of a post that the feed gives neither an ID nor a link for
+    /// from when it was `published` and its `title`. The posts without IDs
+    /// got these before the links were used.
+    pub fn from_title(published: DateTime<Utc>, title: &str) -> Self {
+        crate::hash(&format!("{:?} {:?}", published, title)).into()
+    }
+}
+
 /// A single post in a feed.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Post {
@@ -254,134 +736,932 @@ pub struct Post {
 
     /// Whether this post has been read or not.
     pub read: bool,
+
+    /// Time when the post has been downloaded for the first time.
+    #[serde(with = "datetime_serde")]
+    pub arrived: DateTime<Utc>,
+
+    /// Whether the post has been starred to be kept and found again.
+    pub starred: bool,
+
+    /// The page with the comments on the post.
+    #[serde(with = "option_url_serde")]
+    pub comments: Option<Url>,
+
+    /// The feed of the comments on the post.
+    #[serde(with = "option_url_serde")]
+    pub comment_feed: Option<Url>,
+
+    /// The names of the authors of the post.
+    pub authors: Vec<String>,
+
+    /// The categories or tags of the post.
+    pub categories: Vec<String>,
+
+    /// The first sentence of the description of the post, as plain text.
+    pub summary: Option<String>,
+
+    /// When the feed says the post was last updated, if it does.
+    #[serde(with = "option_datetime_serde")]
+    pub updated: Option<DateTime<Utc>>,
+
+    /// The hash of the title and the content of the post as it was last
+    /// downloaded, if it has been since the hashes were kept.
+    pub hash: Option<String>,
+
+    /// Whether the post has been edited since it first arrived.
+    pub edited: bool,
 }
 
-impl PartialEq for Post {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
+/// A post as stored in the database before the arrival time was tracked.
+#[derive(Deserialize)]
+pub(crate) struct LegacyPost {
+    id: PostId,
+
+    #[serde(with = "arc_str_serde")]
+    title: Arc<str>,
+
+    #[serde(with = "vec_url_serde")]
+    urls: Vec<Url>,
+
+    #[serde(with = "datetime_serde")]
+    published: DateTime<Utc>,
+
+    read: bool,
+}
+
+/// Old posts are assumed to have arrived when they were published.
+impl From<LegacyPost> for UnstarredPost {
+    fn from(post: LegacyPost) -> Self {
+        let LegacyPost { id, title, urls, published, read } = post;
+        Self { id, title, urls, published, read, arrived: published }
     }
 }
 
-impl Eq for Post {}
+/// A post as stored in the database before posts could be starred.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct UnstarredPost {
+    id: PostId,
 
-impl PartialOrd for Post {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.published.partial_cmp(&other.published)
-    }
+    #[serde(with = "arc_str_serde")]
+    title: Arc<str>,
+
+    #[serde(with = "vec_url_serde")]
+    urls: Vec<Url>,
+
+    #[serde(with = "datetime_serde")]
+    published: DateTime<Utc>,
+
+    read: bool,
+
+    #[serde(with = "datetime_serde")]
+    arrived: DateTime<Utc>,
 }
 
-impl Ord for Post {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.published.cmp(&other.published)
+impl From<UnstarredPost> for UncommentedPost {
+    fn from(post: UnstarredPost) -> Self {
+        let UnstarredPost { id, title, urls, published, read, arrived } = post;
+        Self { id, title, urls, published, read, arrived, starred: false }
     }
 }
 
-/// Feed index information.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct FeedId {
-    /// Index into `FeedConfig.sections`.
-    pub section_idx: usize,
+/// A post as stored in the database before the comment links were kept.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct UncommentedPost {
+    id: PostId,
 
-    /// Index into `Section.feeds`.
-    pub feed_idx: usize,
-}
+    #[serde(with = "arc_str_serde")]
+    title: Arc<str>,
 
-impl FeedConfig {
-    /// Parse a config from any buffered reader.
-    pub fn parse_reader<R: BufRead>(reader: R) -> io::Result<Self> {
-        // Read the sections.
-        let mut sections: Vec<Section> = Vec::new();
-        let mut current_section: Option<Section> = None;
+    #[serde(with = "vec_url_serde")]
+    urls: Vec<Url>,
 
-        for line in reader.lines() {
-            let line = line?;
-            let line = line.trim();
+    #[serde(with = "datetime_serde")]
+    published: DateTime<Utc>,
 
-            // Skip empty lines.
-            if line.is_empty() {
-                continue;
-            }
+    read: bool,
 
-            // If the line starts with '#', it's a section
-            if line.starts_with('#') {
-                // Save the previous section, if any, before starting a new one.
-                if let Some(section) = current_section.take() {
-                    sections.push(section);
-                }
+    #[serde(with = "datetime_serde")]
+    arrived: DateTime<Utc>,
 
-                // Create a new section.
-                let title = line.trim_start_matches('#').trim().to_string();
-                current_section = Some(Section::new(title))
-            } else if let Some(section) = &mut current_section {
-                // It's a feed line in the current section.
-                section.feeds.push(Feed::parse(line)?);
-            }
-        }
+    starred: bool,
+}
 
-        // If there is an unfinished section, add it.
-        if let Some(section) = current_section {
-            sections.push(section);
+impl From<UncommentedPost> for UncategorizedPost {
+    fn from(post: UncommentedPost) -> Self {
+        let UncommentedPost {
+            id, title, urls, published, read, arrived, starred
+        } = post;
+        Self {
+            id, title, urls, published, read, arrived, starred,
+            comments: None, comment_feed: None,
         }
-
-        Ok(Self { sections })
     }
+}
 
-    /// Parse the feed file.
-    pub fn parse_feed_file() -> io::Result<Option<Self>> {
-        let Some(feed_file) = Self::get_feed_file()? else {
-            return Ok(None);
-        };
+/// A post as stored in the database before the authors and the categories
+/// were kept.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct UncategorizedPost {
+    id: PostId,
 
-        let file = std::fs::File::open(feed_file)?;
-        let reader = io::BufReader::new(file);
-        Ok(Some(Self::parse_reader(reader)?))
-    }
+    #[serde(with = "arc_str_serde")]
+    title: Arc<str>,
 
-    /// Get path to the config directory.
-    ///
-    /// If it doesn't exist, will create an empty one.
-    pub fn get_config_dir() -> io::Result<PathBuf> {
-        // Get a path to the config directory.
-        let config_dir = match std::env::var("XDG_CONFIG_HOME") {
-            Ok(dir) => PathBuf::new().join(dir),
-            Err(_) => std::env::home_dir()
-                .expect("Couldn't get home directory")
-                .join(".config")
-        };
+    #[serde(with = "vec_url_serde")]
+    urls: Vec<Url>,
 
-        // Use the compile time project name as the config dir.
-        let config_dir = config_dir.join(env!("CARGO_PKG_NAME"));
+    #[serde(with = "datetime_serde")]
+    published: DateTime<Utc>,
 
-        // If the directory doesn't exist, create it.
-        if !config_dir.exists() {
-            std::fs::DirBuilder::new().recursive(true).create(&config_dir)?;
-        }
+    read: bool,
 
-        Ok(config_dir)
-    }
+    #[serde(with = "datetime_serde")]
+    arrived: DateTime<Utc>,
 
-    /// Get path to the feed file, creating the config directory if it doesn't
-    /// exist yet.
+    starred: bool,
+
+    #[serde(with = "option_url_serde")]
+    comments: Option<Url>,
+
+    #[serde(with = "option_url_serde")]
+    comment_feed: Option<Url>,
+}
+
+impl From<UncategorizedPost> for UnsummarizedPost {
+    fn from(post: UncategorizedPost) -> Self {
+        let UncategorizedPost {
+            id, title, urls, published, read, arrived, starred, comments,
+            comment_feed,
+        } = post;
+        Self {
+            id, title, urls, published, read, arrived, starred, comments,
+            comment_feed, authors: Vec::new(), categories: Vec::new(),
+        }
+    }
+}
+
+/// A post as stored in the database before the summaries were kept.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct UnsummarizedPost {
+    id: PostId,
+
+    #[serde(with = "arc_str_serde")]
+    title: Arc<str>,
+
+    #[serde(with = "vec_url_serde")]
+    urls: Vec<Url>,
+
+    #[serde(with = "datetime_serde")]
+    published: DateTime<Utc>,
+
+    read: bool,
+
+    #[serde(with = "datetime_serde")]
+    arrived: DateTime<Utc>,
+
+    starred: bool,
+
+    #[serde(with = "option_url_serde")]
+    comments: Option<Url>,
+
+    #[serde(with = "option_url_serde")]
+    comment_feed: Option<Url>,
+
+    authors: Vec<String>,
+
+    categories: Vec<String>,
+}
+
+impl From<UnsummarizedPost> for UneditedPost {
+    fn from(post: UnsummarizedPost) -> Self {
+        let UnsummarizedPost {
+            id, title, urls, published, read, arrived, starred, comments,
+            comment_feed, authors, categories,
+        } = post;
+        Self {
+            id, title, urls, published, read, arrived, starred, comments,
+            comment_feed, authors, categories, summary: None,
+        }
+    }
+}
+
+/// A post as stored in the database before the edits were tracked.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct UneditedPost {
+    id: PostId,
+
+    #[serde(with = "arc_str_serde")]
+    title: Arc<str>,
+
+    #[serde(with = "vec_url_serde")]
+    urls: Vec<Url>,
+
+    #[serde(with = "datetime_serde")]
+    published: DateTime<Utc>,
+
+    read: bool,
+
+    #[serde(with = "datetime_serde")]
+    arrived: DateTime<Utc>,
+
+    starred: bool,
+
+    #[serde(with = "option_url_serde")]
+    comments: Option<Url>,
+
+    #[serde(with = "option_url_serde")]
+    comment_feed: Option<Url>,
+
+    authors: Vec<String>,
+
+    categories: Vec<String>,
+
+    summary: Option<String>,
+}
+
+impl From<UneditedPost> for Post {
+    fn from(post: UneditedPost) -> Self {
+        let UneditedPost {
+            id, title, urls, published, read, arrived, starred, comments,
+            comment_feed, authors, categories, summary,
+        } = post;
+        Self {
+            id, title, urls, published, read, arrived, starred, comments,
+            comment_feed, authors, categories, summary, updated: None,
+            hash: None, edited: false,
+        }
+    }
+}
+
+/// Get the fragment of the `url` if it tells pages apart, as the routes of
+/// single-page apps and the hashbangs, which start with `/` or `!`, do.
+pub(crate) fn page_fragment(url: &Url) -> Option<&str> {
+    url.fragment().filter(|fragment| fragment.starts_with(['/', '!']))
+}
+
+/// Get the `url` without the scheme, a leading `www.`, the fragment unless
+/// it's a `page_fragment`, the tracking parameters and a trailing slash, so
+/// that the same page is recognized under its variants.
+fn canonical_url(url: &Url) -> Option<String> {
+    let url = crate::download::normalize_url(url.clone());
+    let host = url.host_str()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+
+    let mut canonical = host.to_string();
+    if let Some(port) = url.port() {
+        canonical += &format!(":{}", port);
+    }
+    canonical += url.path().trim_end_matches('/');
+    if let Some(query) = url.query() {
+        canonical += &format!("?{}", query);
+    }
+    if let Some(fragment) = page_fragment(&url) {
+        canonical += &format!("#{}", fragment);
+    }
+
+    Some(canonical)
+}
+
+impl Post {
+    /// Get the URL of the page of the post, the first link the feed gives
+    /// for it. The links of the feed come before those found in the text of
+    /// the post, which are only used if the feed gives none.
+    pub fn primary_url(&self) -> Option<&Url> {
+        self.urls.first()
+    }
+
+    /// Whether the ID of the post was made from when it was published and
+    /// its title, see `PostId::from_title`.
+    pub fn has_title_id(&self) -> bool {
+        self.id == PostId::from_title(self.published, &self.title)
+    }
+
+    /// Get the URL identifying the article of the post, so that the same
+    /// article in several feeds can be recognized.
+    ///
+    /// It's the canonical form of the first URL of the post.
+    pub fn canonical_url(&self) -> Option<String> {
+        canonical_url(self.urls.first()?)
+    }
+
+    /// Check whether this post is an edited version of the `old` one: it
+    /// says it has been updated since, or its content has changed.
+    pub fn is_edit_of(&self, old: &Post) -> bool {
+        let newer = matches!((self.updated, old.updated),
+            (Some(new), Some(old)) if new > old);
+        let changed = matches!((&self.hash, &old.hash),
+            (Some(new), Some(old)) if new != old);
+        newer || changed
+    }
+}
+
+/// Make a post for the tests, titled by its `id`, linking to the `url` unless
+/// it's empty, and published and arrived `published` seconds after the epoch.
+#[cfg(test)]
+pub(crate) fn test_post(id: &str, url: &str, published: i64) -> Post {
+    let published = DateTime::from_timestamp(published, 0).unwrap();
+    Post {
+        id: PostId(Arc::from(id)),
+        title: Arc::from(id),
+        urls: Url::parse(url).into_iter().collect(),
+        published,
+        read: false,
+        arrived: published,
+        starred: false,
+        comments: None,
+        comment_feed: None,
+        authors: Vec::new(),
+        categories: Vec::new(),
+        summary: None,
+        updated: None,
+        hash: None,
+        edited: false,
+    }
+}
+
+impl PartialEq for Post {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Post {}
+
+impl PartialOrd for Post {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Post {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.published.cmp(&other.published)
+    }
+}
+
+/// Feed index information.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FeedId {
+    /// Index into `FeedConfig.sections`.
+    pub section_idx: usize,
+
+    /// Index into `Section.feeds`.
+    pub feed_idx: usize,
+}
+
+/// A post shown once in a view merging several feeds, even if it's in more
+/// of them.
+pub struct MergedPost {
+    /// The feeds the post is in along with its ID there, the feed of the
+    /// newest copy first.
+    pub sources: Vec<(FeedId, PostId)>,
+
+    /// When the newest copy was published.
+    pub published: DateTime<Utc>,
+}
+
+impl FeedConfig {
+    /// Create a config with a single section containing a single feed.
+    pub fn single_feed(title: &str, url: Url, posts: Posts) -> Self {
+        let mut section = Section::new(title);
+        section.feeds.push(Feed { posts, ..Feed::new(title.into(), url) });
+        Self { sections: vec![section] }
+    }
+
+    /// Replace the sections and the feeds with those of the `new` config,
+    /// e.g. parsed from the edited feed file.
+    ///
+    /// The feeds whose URLs are still there keep everything loaded from the
+    /// database. Returns the IDs of the feeds that weren't there before.
+    pub fn merge_from(&mut self, mut new: FeedConfig) -> Vec<FeedId> {
+        let mut old: HashMap<Url, Feed> = self.sections.drain(..)
+            .flat_map(|section| section.feeds)
+            .map(|feed| (feed.url.clone(), feed))
+            .collect();
+
+        let mut added = Vec::new();
+        for (section_idx, section) in new.sections.iter_mut().enumerate() {
+            for (feed_idx, feed) in section.feeds.iter_mut().enumerate() {
+                match old.remove(&feed.url) {
+                    Some(old) => {
+                        feed.posts = old.posts;
+                        feed.view = old.view;
+                        feed.health = old.health;
+                        feed.meta = old.meta;
+                        feed.articles = old.articles;
+                        feed.played = old.played;
+                        feed.opened = old.opened;
+                        feed.reads = old.reads;
+                        feed.pruned = old.pruned;
+                        feed.moved = old.moved;
+                    },
+                    None => added.push(FeedId { section_idx, feed_idx }),
+                }
+            }
+        }
+
+        *self = new;
+        added
+    }
+
+    /// Append a new feed to the end of the section with the `section` title,
+    /// creating the section if it doesn't exist. Like in the feed file, the
+    /// case of the title doesn't matter.
+    ///
+    /// The IDs of the other feeds stay the same.
+    pub fn push_feed(&mut self, section: &str, feed: Feed) -> FeedId {
+        let section_idx = self.sections.iter()
+            .position(|s| s.title.eq_ignore_ascii_case(section.trim()))
+            .unwrap_or_else(|| {
+                self.sections.push(Section::new(section));
+                self.sections.len() - 1
+            });
+
+        let feeds = &mut self.sections[section_idx].feeds;
+        feeds.push(feed);
+        FeedId { section_idx, feed_idx: feeds.len() - 1 }
+    }
+
+    /// Take the feed out of its section. The feeds after it in the section
+    /// move up, which changes their IDs.
+    pub fn take_feed(&mut self, feed_id: &FeedId) -> Option<Feed> {
+        let feeds = &mut self.sections.get_mut(feed_id.section_idx)?.feeds;
+        (feed_id.feed_idx < feeds.len())
+            .then(|| feeds.remove(feed_id.feed_idx))
+    }
+
+    /// Swap two feeds of the same section, along with their IDs.
+    pub fn swap(&mut self, a: &FeedId, b: &FeedId) {
+        if a.section_idx == b.section_idx
+            && let Some(section) = self.sections.get_mut(a.section_idx)
+        {
+            section.feeds.swap(a.feed_idx, b.feed_idx);
+        }
+    }
+
+    /// Get a reference to a feed.
+    pub fn get_feed(&self, feed_id: &FeedId) -> Option<&Feed> {
+        self.sections.get(feed_id.section_idx)
+            .and_then(|section| section.feeds.get(feed_id.feed_idx))
+    }
+
+    /// Get a mutable reference to a feed.
+    pub fn get_feed_mut(&mut self, feed_id: &FeedId) -> Option<&mut Feed> {
+        self.sections.get_mut(feed_id.section_idx)
+            .and_then(|section| section.feeds.get_mut(feed_id.feed_idx))
+    }
+
+    /// Iterate over the IDs of all feeds, in the order of the feed file.
+    pub fn feed_ids(&self) -> impl Iterator<Item = FeedId> + '_ {
+        self.sections.iter().enumerate()
+            .flat_map(|(section_idx, section)| {
+                (0..section.feeds.len())
+                    .map(move |feed_idx| FeedId { section_idx, feed_idx })
+            })
+    }
+
+    /// Merge the posts of all feeds, newest first.
+    ///
+    /// The posts of different feeds that link to the same article are
+    /// collapsed into one. Posts without a URL are never collapsed.
+    pub fn merged_posts(&self) -> Vec<MergedPost> {
+        let mut posts: Vec<(FeedId, &Post)> = self.feed_ids()
+            .flat_map(|feed_id| {
+                let posts = self.get_feed(&feed_id)
+                    .map(|feed| feed.posts.as_ref())
+                    .unwrap_or_default();
+                posts.iter().map(move |post| (feed_id.clone(), post))
+            })
+            .collect();
+        posts.sort_by_key(|(_, post)| std::cmp::Reverse(post.published));
+
+        let mut merged: Vec<MergedPost> = Vec::new();
+        let mut by_url: HashMap<String, usize> = HashMap::new();
+        for (feed_id, post) in posts {
+            let source = (feed_id, post.id.clone());
+            if let Some(url) = post.canonical_url() {
+                if let Some(&idx) = by_url.get(&url) {
+                    merged[idx].sources.push(source);
+                    continue;
+                }
+                by_url.insert(url, merged.len());
+            }
+
+            merged.push(MergedPost {
+                sources: vec![source],
+                published: post.published,
+            });
+        }
+
+        merged
+    }
+
+    /// Parse a config from any buffered reader.
+    pub fn parse_reader<R: BufRead>(reader: R) -> io::Result<Self> {
+        // Read the sections.
+        let mut sections: Vec<Section> = Vec::new();
+        let mut current_section: Option<Section> = None;
+
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+
+            // Skip empty lines.
+            if line.is_empty() {
+                continue;
+            }
+
+            // If the line starts with '#', it's a section
+            if line.starts_with('#') {
+                // Save the previous section, if any, before starting a new one.
+                if let Some(section) = current_section.take() {
+                    sections.push(section);
+                }
+
+                // Create a new section.
+                let title = line.trim_start_matches('#').trim().to_string();
+                current_section = Some(Section::new(title))
+            } else if let Some(section) = &mut current_section {
+                // It's a feed line in the current section.
+                section.feeds.push(Feed::parse(line)?);
+            }
+        }
+
+        // If there is an unfinished section, add it.
+        if let Some(section) = current_section {
+            sections.push(section);
+        }
+
+        Ok(Self { sections })
+    }
+
+    /// Parse a config from the contents of `feeds.toml`.
+    pub fn parse_toml(contents: &str) -> io::Result<Self> {
+        let file: TomlFeedFile = toml::from_str(contents)
+            .map_err(io::Error::other)?;
+
+        let mut sections = Vec::new();
+        for toml in file.sections {
+            let mut section = Section::new(toml.title.trim());
+            for feed in toml.feeds {
+                let url = Url::parse(&feed.url).map_err(|err| {
+                    let err = format!("Invalid URL \"{}\": {}", feed.url, err);
+                    io::Error::other(err)
+                })?;
+                section.feeds.push(Feed {
+                    options: Arc::new(feed.options),
+                    ..Feed::new(feed.title.trim().into(), url)
+                });
+            }
+            sections.push(section);
+        }
+
+        Ok(Self { sections })
+    }
+
+    /// Parse the feed file at `path`, as TOML if its name says so.
+    pub fn parse_file(path: &Path) -> io::Result<Self> {
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            Self::parse_toml(&std::fs::read_to_string(path)?)
+        } else {
+            let file = std::fs::File::open(path)?;
+            Self::parse_reader(io::BufReader::new(file))
+        }
+    }
+
+    /// Parse the feed file.
+    pub fn parse_feed_file() -> io::Result<Option<Self>> {
+        let Some(feed_file) = Self::get_feed_file()? else {
+            return Ok(None);
+        };
+        Ok(Some(Self::parse_file(&feed_file)?))
+    }
+
+    /// Use `dir` as the config directory from now on, e.g. for another
+    /// profile given by `--config`.
+    ///
+    /// Only the first directory given is used.
+    pub fn set_config_dir(dir: PathBuf) {
+        let _ = CONFIG_DIR.set(dir);
+    }
+
+    /// Get path to the config directory.
+    ///
+    /// If it doesn't exist, will create an empty one.
+    pub fn get_config_dir() -> io::Result<PathBuf> {
+        let config_dir = match CONFIG_DIR.get() {
+            Some(dir) => dir.clone(),
+            None => {
+                // Get a path to the config directory.
+                let config_dir = match std::env::var("XDG_CONFIG_HOME") {
+                    Ok(dir) => PathBuf::new().join(dir),
+                    Err(_) => home_dir()?.join(".config"),
+                };
+
+                // Use the compile time project name as the config dir.
+                config_dir.join(env!("CARGO_PKG_NAME"))
+            },
+        };
+
+        // If the directory doesn't exist, create it.
+        if !config_dir.exists() {
+            std::fs::DirBuilder::new().recursive(true).create(&config_dir)?;
+        }
+
+        Ok(config_dir)
+    }
+
+    /// Get path to the feed file, creating the config directory if it doesn't
+    /// exist yet.
     ///
-    /// Returns `None` if the file doesn't exist.
+    /// `feeds.toml` is used instead of the `feeds` file when it exists.
+    /// Returns `None` if neither does.
     pub fn get_feed_file() -> io::Result<Option<PathBuf>> {
         // Get the config dir.
         let config_dir = Self::get_config_dir()?;
-        let config_file = config_dir.join("feeds");
-
-        // Make sure it's a file.
-        config_file.metadata()
-            .map(|metadata| {
-                if metadata.is_file() {
-                    Ok(Some(config_file))
-                } else {
+
+        for name in ["feeds.toml", "feeds"] {
+            let config_file = config_dir.join(name);
+
+            // Make sure it's a file.
+            match config_file.metadata() {
+                Ok(metadata) if metadata.is_file() => {
+                    return Ok(Some(config_file));
+                },
+                Ok(_) => {
                     let err = format!("Path exists but isn't a file: {}",
                         config_file.display());
-                    Err(io::Error::new(io::ErrorKind::Other, err))
-                }
-            })
-            .unwrap_or(Ok(None))
+                    return Err(io::Error::other(err));
+                },
+                Err(_) => {},
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Add a feed line to the feed file, creating the file if it doesn't exist
+    /// yet.
+    ///
+    /// If `section` is `None`, the feed is added to the last section. Returns
+    /// the title of the section the feed was added to.
+    pub fn add_feed(section: Option<&str>, title: &str, url: &Url)
+        -> io::Result<Arc<str>>
+    {
+        let (feed_file, contents) = Self::read_feed_file()?;
+
+        // Don't subscribe to the same feed twice.
+        let config = Self::parse_reader(io::Cursor::new(&contents))?;
+        if let Some(feed) = config.find_url(url) {
+            let err = format!("Already subscribed as \"{}\"", feed.title);
+            return Err(io::Error::other(err));
+        }
+
+        let section = section
+            .map(Arc::from)
+            .or_else(|| config.sections.last().map(|s| s.title.clone()))
+            .unwrap_or_else(|| "Feeds".into());
+
+        let contents = insert_feed_line(&contents, &section, title, url)
+            .map_err(io::Error::other)?;
+        std::fs::write(feed_file, contents)?;
+
+        Ok(section)
+    }
+
+    /// Change the title and the URL of the feed with the `url` in the feed
+    /// file.
+    ///
+    /// If `section` is given, the feed is moved to the end of it, otherwise
+    /// it stays where it is.
+    pub fn edit_feed(url: &Url, section: Option<&str>, title: &str,
+        new_url: &Url) -> io::Result<()>
+    {
+        let (feed_file, contents) = Self::read_feed_file()?;
+
+        // Don't subscribe to the same feed twice.
+        let config = Self::parse_reader(io::Cursor::new(&contents))?;
+        if new_url != url && let Some(feed) = config.find_url(new_url) {
+            let err = format!("Already subscribed as \"{}\"", feed.title);
+            return Err(io::Error::other(err));
+        }
+
+        let contents = match section {
+            Some(section) => remove_feed_line(&contents, url)
+                .and_then(|contents| {
+                    insert_feed_line(&contents, section, title, new_url)
+                }),
+            None => replace_feed_line(&contents, url, title, new_url),
+        };
+        std::fs::write(feed_file, contents.map_err(io::Error::other)?)
+    }
+
+    /// Swap the lines of the feeds with the URLs `a` and `b` in the feed file.
+    pub fn swap_feeds(a: &Url, b: &Url) -> io::Result<()> {
+        let (feed_file, contents) = Self::read_feed_file()?;
+        let contents = swap_feed_lines(&contents, a, b)
+            .map_err(io::Error::other)?;
+        std::fs::write(feed_file, contents)
+    }
+
+    /// Remove the feed with the `url` from the feed file.
+    ///
+    /// Its section stays in the file, even if it's empty.
+    pub fn remove_feed(url: &Url) -> io::Result<()> {
+        let (feed_file, contents) = Self::read_feed_file()?;
+        let contents = remove_feed_line(&contents, url)
+            .map_err(io::Error::other)?;
+        std::fs::write(feed_file, contents)
+    }
+
+    /// Get the path to the feed file and its contents, which are empty if
+    /// it doesn't exist yet.
+    ///
+    /// Fails if the feeds are configured in `feeds.toml`, which is only
+    /// edited by hand.
+    fn read_feed_file() -> io::Result<(PathBuf, String)> {
+        let config_dir = Self::get_config_dir()?;
+        if config_dir.join("feeds.toml").exists() {
+            return Err(io::Error::other("The feeds are configured in \
+                feeds.toml, edit it instead"));
+        }
+
+        let feed_file = config_dir.join("feeds");
+        match std::fs::read_to_string(&feed_file) {
+            Ok(contents) => Ok((feed_file, contents)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                Ok((feed_file, String::new()))
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Find the feed with the `url`.
+    fn find_url(&self, url: &Url) -> Option<&Feed> {
+        self.sections.iter()
+            .flat_map(|section| section.feeds.iter())
+            .find(|feed| &feed.url == url)
+    }
+}
+
+/// Find the line of the feed with the `url` in the feed file `lines`.
+fn find_feed_line(lines: &[&str], url: &Url) -> Result<usize, String> {
+    lines.iter()
+        .position(|line| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+                && Feed::parse(line).is_ok_and(|feed| &feed.url == url)
+        })
+        .ok_or_else(|| format!("{} isn't in the feed file", url))
+}
+
+/// Remove the line of the feed with the `url` from the feed file `contents`.
+fn remove_feed_line(contents: &str, url: &Url) -> Result<String, String> {
+    let mut lines: Vec<&str> = contents.lines().collect();
+    lines.remove(find_feed_line(&lines, url)?);
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Swap the lines of the feeds with the URLs `a` and `b` in the feed file
+/// `contents`.
+fn swap_feed_lines(contents: &str, a: &Url, b: &Url) -> Result<String, String> {
+    let mut lines: Vec<&str> = contents.lines().collect();
+    let a = find_feed_line(&lines, a)?;
+    let b = find_feed_line(&lines, b)?;
+    lines.swap(a, b);
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Replace the line of the feed with the `url` in the feed file `contents`
+/// with a `title | new_url` line, keeping the alignment of its separator.
+fn replace_feed_line(contents: &str, url: &Url, title: &str, new_url: &Url)
+    -> Result<String, String>
+{
+    if title.contains(['|', '\n']) || title.trim().is_empty() {
+        return Err(format!("Invalid feed title: \"{}\"", title));
+    }
+
+    let mut lines: Vec<&str> = contents.lines().collect();
+    let idx = find_feed_line(&lines, url)?;
+    let width = separator_column(lines[idx]).unwrap_or(0);
+    let line = feed_line(title.trim(), new_url, width);
+    lines[idx] = &line;
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Insert a `title | url` line at the end of `section` in the feed file
+/// `contents`, creating the section if it doesn't exist.
+///
+/// The separator is aligned with the other feeds in the section.
+fn insert_feed_line(contents: &str, section: &str, title: &str, url: &Url)
+    -> Result<String, String>
+{
+    if title.contains(['|', '\n']) || title.trim().is_empty() {
+        return Err(format!("Invalid feed title: \"{}\"", title));
     }
+    if section.contains('\n') || section.trim().is_empty() {
+        return Err(format!("Invalid section title: \"{}\"", section));
+    }
+
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+    let is_section = |line: &str| line.trim().starts_with('#');
+
+    // Find the section.
+    let header = lines.iter().position(|line| {
+        is_section(line) && line.trim().trim_start_matches('#').trim()
+            .eq_ignore_ascii_case(section.trim())
+    });
+
+    let Some(header) = header else {
+        // Separate the new section from the previous one.
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(format!("# {}", section.trim()));
+        lines.push(feed_line(title.trim(), url, 0));
+        return Ok(lines.join("\n") + "\n");
+    };
+
+    // The section ends at the next section.
+    let end = lines[header + 1..].iter()
+        .position(|line| is_section(line))
+        .map(|idx| header + 1 + idx)
+        .unwrap_or(lines.len());
+    let feeds = &lines[header + 1..end];
+
+    // Align the separator with the other feeds.
+    let width = feeds.iter()
+        .filter_map(|line| separator_column(line))
+        .max()
+        .unwrap_or(0);
+
+    // Insert right after the last feed of the section.
+    let idx = feeds.iter()
+        .rposition(|line| !line.trim().is_empty())
+        .map(|idx| header + 2 + idx)
+        .unwrap_or(header + 1);
+    lines.insert(idx, feed_line(title.trim(), url, width));
+
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Get the width of the title column of a `title | url` line, up to the
+/// space before the separator, as it's displayed.
+fn separator_column(line: &str) -> Option<usize> {
+    let idx = line.find('|')?;
+    Some(line[..idx].width().saturating_sub(1))
+}
+
+/// Format a `title | url` line, padding the `title` to the display `width`.
+fn feed_line(title: &str, url: &Url, width: usize) -> String {
+    let padding = width.saturating_sub(title.width());
+    format!("{}{} | {}", title, " ".repeat(padding), url)
+}
+
+/// Make a title found in a feed fit for the feed file, where it can't
+/// contain separators or line breaks.
+///
+/// Returns `None` if nothing but those is left.
+pub(crate) fn feed_file_title(title: &str) -> Option<String> {
+    let title = title.replace('|', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+/// Deserialize a list of post filters.
+pub(crate) fn deserialize_filters<'de, D>(deserializer: D)
+    -> Result<Vec<PostFilter>, D::Error>
+where
+    D: Deserializer<'de>
+{
+    Vec::<String>::deserialize(deserializer)?.iter()
+        .map(|pattern| {
+            PostFilter::parse(pattern).map_err(serde::de::Error::custom)
+        })
+        .collect()
+}
+
+/// Deserialize the article rule of a feed.
+fn deserialize_article_rule<'de, D>(deserializer: D)
+    -> Result<Option<ArticleRule>, D::Error>
+where
+    D: Deserializer<'de>
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|rule| ArticleRule::parse(&rule))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Get the home directory of the user, under which the XDG directories are
+/// by default.
+pub(crate) fn home_dir() -> io::Result<PathBuf> {
+    std::env::home_dir().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "Couldn't get home directory")
+    })
 }
 
 impl Section {
@@ -395,6 +1675,132 @@ impl Section {
 }
 
 impl Feed {
+    /// Create a new feed without any posts or anything else loaded of it.
+    pub fn new(title: Arc<str>, url: Url) -> Self {
+        Self {
+            title,
+            url,
+            posts: Posts::new(),
+            view: FeedView::default(),
+            health: None,
+            meta: None,
+            articles: HashMap::new(),
+            played: HashSet::new(),
+            opened: HashMap::new(),
+            reads: Vec::new(),
+            pruned: HashSet::new(),
+            moved: None,
+            options: Default::default(),
+        }
+    }
+
+    /// Whether the feed can be downloaded, unlike one read from stdin.
+    pub fn is_downloadable(&self) -> bool {
+        self.url.as_str() != STDIN_URL
+    }
+
+    /// Remember that the posts with the `post_ids` were marked read `at`.
+    pub fn log_reads(&mut self, post_ids: &[PostId], at: DateTime<Utc>) {
+        self.reads.extend(post_ids.iter().map(|post_id| Read {
+            post_id: post_id.clone(), at,
+        }));
+    }
+
+    /// Remember how the last download went, counting the failures in a row.
+    ///
+    /// The `failures` and the `succeeded` of the `health` are ignored.
+    pub fn record_fetch(&mut self, mut health: FeedHealth) {
+        let last = self.health.as_ref();
+        if health.is_failing() {
+            health.failures = last.map_or(0, |last| last.failures) + 1;
+            health.succeeded = last.and_then(|last| last.succeeded);
+        } else {
+            health.failures = 0;
+            health.succeeded = Some(health.fetched);
+        }
+        self.health = Some(health);
+    }
+
+    /// Get the average time between the posts of the feed as of `now`, over
+    /// its newest posts and the time since the newest one, so that a feed
+    /// that has gone quiet slows down. Feeds without posts have none.
+    pub fn cadence(&self, now: DateTime<Utc>) -> Option<TimeDelta> {
+        let posts = self.posts.as_ref();
+        let recent = &posts[..posts.len().min(CADENCE_POSTS)];
+        let oldest = recent.iter().map(|post| post.published).min()?;
+        Some((now - oldest).max(TimeDelta::zero()) / recent.len() as i32)
+    }
+
+    /// Get how often the feed is downloaded while the TUI runs: its own
+    /// refresh interval, or half of its cadence as of `now` within the bounds
+    /// of the `refresh` settings if they're adaptive.
+    pub fn refresh_interval(&self, refresh: &RefreshSettings,
+        now: DateTime<Utc>) -> Option<TimeDelta>
+    {
+        if let Some(minutes) = self.options.refresh {
+            return (minutes > 0)
+                .then(|| TimeDelta::minutes(minutes.into()));
+        }
+        if !refresh.adaptive {
+            return None;
+        }
+
+        // Feeds that have never posted are checked the least often.
+        let min = TimeDelta::minutes(refresh.min_minutes.into());
+        let max = TimeDelta::minutes(refresh.max_minutes.into()).max(min);
+        let interval = self.cadence(now).map_or(max, |cadence| cadence / 2);
+        Some(interval.clamp(min, max))
+    }
+
+    /// Whether the refresh interval of the feed has passed at `now` since
+    /// the feed was last downloaded. Feeds without one are never due.
+    pub fn is_due(&self, refresh: &RefreshSettings, now: DateTime<Utc>)
+        -> bool
+    {
+        if !self.is_downloadable() {
+            return false;
+        }
+        let Some(interval) = self.refresh_interval(refresh, now) else {
+            return false;
+        };
+        self.health.as_ref()
+            .is_none_or(|health| now - health.fetched >= interval)
+    }
+
+    /// Update the posts the feed has already to the freshly downloaded
+    /// `posts` that are edits of them, marking them unread again if
+    /// `unread` is set. Returns the updated posts, to be saved.
+    pub fn update_posts(&mut self, posts: &Posts, unread: bool) -> Posts {
+        self.posts.revise(posts, unread)
+    }
+
+    /// Forget the pruned posts that the freshly downloaded `posts` don't
+    /// carry anymore, as they can't come back. Returns their IDs, to be
+    /// forgotten in the database too.
+    pub fn trim_pruned(&mut self, posts: &Posts) -> Vec<PostId> {
+        let carried: HashSet<&PostId> = posts.as_ref().iter()
+            .map(|post| &post.id)
+            .collect();
+        let gone: Vec<PostId> = self.pruned.iter()
+            .filter(|id| !carried.contains(id))
+            .cloned()
+            .collect();
+        for id in gone.iter() {
+            self.pruned.remove(id);
+        }
+        gone
+    }
+
+    /// Merge freshly downloaded `posts` into the feed, returning those that
+    /// it didn't have yet. The posts that have been pruned are left out.
+    pub fn merge_posts(&mut self, mut posts: Posts) -> Posts {
+        posts.retain(|post| {
+            !self.posts.contains(post) && !self.pruned.contains(&post.id)
+        });
+        self.posts.append(posts.clone());
+        posts
+    }
+
     /// Parse a line into a feed if it matches the expected format.
     fn parse(line: &str) -> io::Result<Self> {
         // Split on the pipe character.
@@ -403,11 +1809,13 @@ impl Feed {
         // We expect `title | url`.
         if parts.len() == 2 {
             let title = parts[0].to_string().into();
-            let url = Url::parse(parts[1])
-                .expect("Invalid URL specified for feed");
-            Ok(Feed { title, url, posts: Posts::new() })
+            let url = Url::parse(parts[1]).map_err(|err| {
+                let err = format!("Invalid URL \"{}\": {}", parts[1], err);
+                io::Error::other(err)
+            })?;
+            Ok(Feed::new(title, url))
         } else {
-            Err(io::Error::new(io::ErrorKind::Other,
+            Err(io::Error::other(
                 "Invalid line. Expected \"<title> | <url>\""))
         }
     }
@@ -417,6 +1825,7 @@ impl Feed {
 mod tests {
     use super::*;
     use std::io::Cursor;
+    use super::test_post as post;
 
     fn parse_str(input: &str) -> io::Result<FeedConfig> {
         let cursor = Cursor::new(input);
@@ -434,7 +1843,7 @@ Rust Blog | https://blog.rust-lang.org
 
         assert_eq!(config.sections.len(), 1);
         let section = &config.sections[0];
-        assert_eq!(section.name, "News");
+        assert_eq!(&*section.title, "News");
         assert_eq!(section.feeds.len(), 1);
     }
 
@@ -481,6 +1890,340 @@ not a feed
         assert_eq!(err.kind(), std::io::ErrorKind::Other);
     }
 
+    #[test]
+    fn inserts_aligned_feed_line() {
+        let cfg = "# Tech\nHN    | https://news.ycombinator.com\n\n# Comics\n";
+        let url = Url::parse("https://lobste.rs/rss").unwrap();
+
+        let out = insert_feed_line(cfg, "tech", "Lobsters", &url).unwrap();
+        assert_eq!(out, "# Tech\nHN    | https://news.ycombinator.com\n\
+            Lobsters | https://lobste.rs/rss\n\n# Comics\n");
+
+        let out = insert_feed_line(cfg, "Comics", "L", &url).unwrap();
+        assert!(out.ends_with("# Comics\nL | https://lobste.rs/rss\n"));
+
+        let url = Url::parse("https://xkcd.com/rss.xml").unwrap();
+        let out = insert_feed_line(cfg, "Tech", "xkcd", &url).unwrap();
+        assert!(out.contains("\nxkcd  | https://xkcd.com/rss.xml\n"));
+
+        // The columns are aligned as they're displayed.
+        let cfg = "# Blogs\n日本語 | https://example.jp/feed\n";
+        let out = insert_feed_line(cfg, "Blogs", "xkcd", &url).unwrap();
+        assert!(out.ends_with("\nxkcd   | https://xkcd.com/rss.xml\n"));
+    }
+
+    #[test]
+    fn cleans_feed_file_titles() {
+        assert_eq!(feed_file_title("News | Site\n").as_deref(),
+            Some("News Site"));
+        assert_eq!(feed_file_title(" | "), None);
+    }
+
+    #[test]
+    fn inserts_new_section() {
+        let url = Url::parse("https://lobste.rs/rss").unwrap();
+
+        let out = insert_feed_line("", "News", "Lobsters", &url).unwrap();
+        assert_eq!(out, "# News\nLobsters | https://lobste.rs/rss\n");
+
+        let out = insert_feed_line("# A\nx | https://x.org", "B", "L", &url);
+        assert_eq!(out.unwrap(),
+            "# A\nx | https://x.org\n\n# B\nL | https://lobste.rs/rss\n");
+
+        assert!(insert_feed_line("", "News", "a | b", &url).is_err());
+    }
+
+    #[test]
+    fn edits_swaps_and_removes_feed_lines() {
+        let cfg = "# Tech\nHN    | https://news.ycombinator.com\n\
+            xkcd  | https://xkcd.com/rss.xml\n";
+        let hn = Url::parse("https://news.ycombinator.com").unwrap();
+        let xkcd = Url::parse("https://xkcd.com/rss.xml").unwrap();
+        let lobsters = Url::parse("https://lobste.rs/rss").unwrap();
+
+        let out = replace_feed_line(cfg, &hn, "L", &lobsters).unwrap();
+        assert_eq!(out, "# Tech\nL     | https://lobste.rs/rss\n\
+            xkcd  | https://xkcd.com/rss.xml\n");
+
+        let out = remove_feed_line(cfg, &hn).unwrap();
+        assert_eq!(out, "# Tech\nxkcd  | https://xkcd.com/rss.xml\n");
+
+        let out = swap_feed_lines(cfg, &hn, &xkcd).unwrap();
+        assert_eq!(out, "# Tech\nxkcd  | https://xkcd.com/rss.xml\n\
+            HN    | https://news.ycombinator.com\n");
+
+        assert!(remove_feed_line(cfg, &lobsters).is_err());
+        assert!(replace_feed_line(cfg, &hn, "a | b", &hn).is_err());
+    }
+
+    #[test]
+    fn keeps_posts_of_reloaded_feeds() {
+        let mut config = parse_str("# A\nx | https://x.org\n\
+            y | https://y.org\n").unwrap();
+        config.sections[0].feeds[1].posts =
+            vec![post("a", "https://y.org/a", 1)].into();
+
+        let new = parse_str("# B\nz | https://z.org\n\
+            Why | https://y.org\n").unwrap();
+        let added = config.merge_from(new);
+        assert_eq!(added, [FeedId { section_idx: 0, feed_idx: 0 }]);
+
+        let feed = &config.sections[0].feeds[1];
+        assert_eq!((feed.title.as_ref(), feed.posts.len()), ("Why", 1));
+        assert_eq!(config.sections[0].title.as_ref(), "B");
+    }
+
+    #[test]
+    fn pushes_feeds_to_the_end_of_sections() {
+        let mut config = parse_str("# A\nx | https://x.org\n# B\n").unwrap();
+        let feed = |url: &str| {
+            Feed::new("New".into(), Url::parse(url).unwrap())
+        };
+
+        let id = config.push_feed("a", feed("https://a.org"));
+        assert_eq!(id, FeedId { section_idx: 0, feed_idx: 1 });
+
+        let id = config.push_feed("C", feed("https://c.org"));
+        assert_eq!(id, FeedId { section_idx: 2, feed_idx: 0 });
+        assert_eq!(&*config.sections[2].title, "C");
+    }
+
+    #[test]
+    fn collapses_duplicates_of_merged_posts() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let mut cfg = FeedConfig::single_feed("A", url, vec![
+            post("a1", "https://www.news.example/story/#top", 3),
+            post("a2", "https://news.example/other", 1),
+        ].into());
+
+        let url = Url::parse("https://b.example/feed.xml").unwrap();
+        let b = Feed {
+            posts: vec![
+                post("b1", "http://news.example/story", 2),
+                post("b2", "", 0),
+            ].into(),
+            ..Feed::new("B".into(), url)
+        };
+        let b = cfg.push_feed("B", b);
+
+        let merged = cfg.merged_posts();
+        let ids: Vec<Vec<&str>> = merged.iter()
+            .map(|post| post.sources.iter()
+                .map(|(_, id)| id.0.as_ref())
+                .collect())
+            .collect();
+        assert_eq!(ids, [vec!["a1", "b1"], vec!["a2"], vec!["b2"]]);
+        assert_eq!(merged[2].sources[0].0, b);
+    }
+
+    #[test]
+    fn merges_only_new_posts() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let mut cfg = FeedConfig::single_feed("A", url, vec![
+            post("a1", "https://a.example/1", 1),
+        ].into());
+        let id = cfg.feed_ids().next().unwrap();
+        let feed = cfg.get_feed_mut(&id).unwrap();
+
+        let new = feed.merge_posts(vec![
+            post("a1", "https://a.example/1", 1),
+            post("a2", "https://a.example/2", 2),
+        ].into());
+        assert_eq!(new.len(), 1);
+        assert_eq!(feed.posts.len(), 2);
+        assert!(feed.merge_posts(new).is_empty());
+    }
+
+    #[test]
+    fn updates_edited_posts() {
+        let url = Url::parse("https://a.example/feed.xml").unwrap();
+        let hashed = |id, hash: &str, read| Post {
+            hash: Some(hash.into()),
+            read,
+            ..post(id, "https://a.example/1", 1)
+        };
+        let mut cfg = FeedConfig::single_feed("A", url, vec![
+            Post { starred: true, ..hashed("a", "1", true) },
+            hashed("b", "1", true),
+            post("c", "https://a.example/1", 1),
+        ].into());
+        let id = cfg.feed_ids().next().unwrap();
+        let feed = cfg.get_feed_mut(&id).unwrap();
+
+        // Only the changed post is edited, and the one stored before the
+        // hashes were kept just takes its hash.
+        let edited = Post { title: "Fixed".into(), ..hashed("a", "2", false) };
+        let downloaded = vec![
+            edited, hashed("b", "1", false), hashed("c", "1", false),
+        ].into();
+        let updated = feed.update_posts(&downloaded, true);
+        let ids: Vec<_> = updated.as_ref().iter()
+            .map(|post| (post.id.0.as_ref(), post.edited))
+            .collect();
+        assert_eq!(ids, [("a", true), ("c", false)]);
+
+        let a = feed.posts.get_by_id(&PostId("a".into())).unwrap();
+        assert_eq!(&*a.title, "Fixed");
+        assert!(a.starred && !a.read);
+        let c = feed.posts.get_by_id(&PostId("c".into())).unwrap();
+        assert_eq!(c.hash.as_deref(), Some("1"));
+        assert_eq!(feed.posts.unread(), 2);
+
+        // An update time that's newer is an edit too, and the read posts
+        // stay read unless asked.
+        let b = Post {
+            updated: Some(DateTime::from_timestamp(60, 0).unwrap()),
+            ..hashed("b", "1", false)
+        };
+        let revised = feed.update_posts(&b.clone().into(), false);
+        assert!(!revised.as_ref()[0].edited);
+        assert!(feed.update_posts(&b.clone().into(), false).is_empty());
+        let b = Post {
+            updated: Some(DateTime::from_timestamp(120, 0).unwrap()),
+            ..b
+        };
+        assert!(feed.update_posts(&b.into(), false).as_ref()[0].edited);
+        assert_eq!(feed.posts.unread(), 2);
+    }
+
+    #[test]
+    fn appends_sorted_posts() {
+        let mut posts: Posts = vec![
+            post("a", "https://a.example/a", 5),
+            post("c", "https://a.example/c", 3),
+            post("e", "https://a.example/e", 1),
+        ].into();
+        posts.mark_read(&PostId("c".into()), true);
+
+        posts.append(vec![
+            post("f", "https://a.example/f", 6),
+            post("c", "https://a.example/c", 3),
+            post("d", "https://a.example/d", 2),
+            post("b", "https://a.example/b", 5),
+        ].into());
+
+        let ids: Vec<&str> = posts.as_ref().iter()
+            .map(|post| post.id.0.as_ref())
+            .collect();
+        assert_eq!(ids, ["f", "a", "b", "c", "d", "e"]);
+        assert_eq!(posts.unread(), 5);
+    }
+
+    #[test]
+    fn parses_feed_options_from_toml() {
+        let toml = r#"
+[[section]]
+title = "News"
+
+[[section.feed]]
+title = "Example"
+url = "https://example.com/feed.xml"
+refresh = 30
+tags = ["daily"]
+exclude = ["/(?i)^sponsored/", "[ad]"]
+auth = { user = "me", password = "secret" }
+headers = { "X-Token" = "abc" }
+actions = { mpv = "mpv %u" }
+
+[[section.feed]]
+title = "Plain"
+url = "https://plain.example/"
+"#;
+
+        let config = FeedConfig::parse_toml(toml).unwrap();
+        let feeds = &config.sections[0].feeds;
+        assert_eq!(feeds.len(), 2);
+
+        let options = &feeds[0].options;
+        assert_eq!(options.refresh, Some(30));
+        assert_eq!(options.tags, ["daily"]);
+        assert!(options.exclude[0].is_match("Sponsored: a thing"));
+        assert!(options.exclude[1].is_match("[AD] Buy this"));
+        assert_eq!(options.auth.as_ref().unwrap().user, "me");
+        assert_eq!(options.headers["X-Token"], "abc");
+        assert_eq!(options.actions["mpv"], "mpv %u");
+        assert!(feeds[1].options.exclude.is_empty());
+
+        assert!(FeedConfig::parse_toml(&toml.replace("(?i)", "(")).is_err());
+        let url = "https://example.com/feed.xml";
+        assert!(FeedConfig::parse_toml(&toml.replace(url, "nope")).is_err());
+    }
+
+    #[test]
+    fn refreshes_feeds_after_their_intervals() {
+        let toml = r#"
+[[section]]
+title = "A"
+feed = [
+    { title = "Timed", url = "https://a.example/", refresh = 10 },
+    { title = "Untimed", url = "https://b.example/" },
+]
+"#;
+        let mut config = FeedConfig::parse_toml(toml).unwrap();
+        let now = Utc::now();
+        let refresh = RefreshSettings::default();
+        let feeds = &mut config.sections[0].feeds;
+        assert!(feeds[0].is_due(&refresh, now));
+        assert!(!feeds[1].is_due(&refresh, now));
+
+        feeds[0].record_fetch(FeedHealth {
+            fetched: now,
+            duration_ms: 0,
+            status: Some(200),
+            error: None,
+            failures: 0,
+            succeeded: None,
+        });
+        assert!(!feeds[0].is_due(&refresh, now + TimeDelta::minutes(9)));
+        assert!(feeds[0].is_due(&refresh, now + TimeDelta::minutes(10)));
+    }
+
+    #[test]
+    fn adapts_refresh_intervals_to_cadences() {
+        let mut config = FeedConfig::parse_toml(r#"
+[[section]]
+title = "A"
+feed = [{ title = "Adaptive", url = "https://a.example/" }]
+"#).unwrap();
+        let now = Utc::now();
+        let refresh = RefreshSettings { adaptive: true, ..Default::default() };
+        let interval = |feed: &Feed| feed.refresh_interval(&refresh, now)
+            .map(|interval| interval.num_minutes());
+
+        // Feeds without posts are checked once a day.
+        let feed = &mut config.sections[0].feeds[0];
+        assert_eq!(feed.cadence(now), None);
+        assert_eq!(interval(feed), Some(24 * 60));
+
+        // Four posts in the last 12 hours are checked for every 90 minutes.
+        let post = |minutes_ago: i64| Post {
+            published: now - TimeDelta::minutes(minutes_ago),
+            ..test_post(&minutes_ago.to_string(), "", now.timestamp())
+        };
+        let posts = |minutes: &[i64]| Posts::from(minutes.iter()
+            .map(|&minutes_ago| post(minutes_ago))
+            .collect::<Vec<_>>());
+        feed.posts = posts(&[0, 240, 480, 720]);
+        assert_eq!(feed.cadence(now), Some(TimeDelta::hours(3)));
+        assert_eq!(interval(feed), Some(90));
+
+        // Busier feeds are held back by the bounds, and a feed that posted
+        // a lot a month ago has slowed down.
+        feed.posts = posts(&[0, 1, 2, 3, 4]);
+        assert_eq!(interval(feed), Some(15));
+        let month = 30 * 24 * 60;
+        feed.posts = posts(&[month, month + 1, month + 2]);
+        assert_eq!(interval(feed), Some(24 * 60));
+
+        // The feed's own interval wins, and the feeds are only adapted when
+        // it's wanted.
+        Arc::make_mut(&mut feed.options).refresh = Some(10);
+        assert_eq!(interval(feed), Some(10));
+        Arc::make_mut(&mut feed.options).refresh = None;
+        let fixed = RefreshSettings::default();
+        assert_eq!(feed.refresh_interval(&fixed, now), None);
+    }
+
     #[test]
     fn empty_input_produces_no_sections() {
         let config = parse_str("").unwrap();
@@ -510,7 +2253,7 @@ mod vec_url_serde {
     use serde::{Serializer, Deserializer, Deserialize, Serialize};
     use url::Url;
 
-    pub fn serialize<S>(urls: &Vec<Url>, serializer: S) -> Result<S::Ok, S::Error>
+    pub fn serialize<S>(urls: &[Url], serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer
     {
@@ -531,7 +2274,52 @@ mod vec_url_serde {
     }
 }
 
-mod datetime_serde {
+mod option_url_serde {
+    use serde::{Serializer, Deserializer, Deserialize, Serialize};
+    use url::Url;
+
+    pub fn serialize<S>(url: &Option<Url>, serializer: S)
+        -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        url.as_ref().map(Url::as_str).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D)
+        -> Result<Option<Url>, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| Url::parse(&s).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+mod option_datetime_serde {
+    use serde::{Serializer, Deserializer, Deserialize, Serialize};
+    use chrono::{DateTime, Utc};
+
+    pub fn serialize<S>(dt: &Option<DateTime<Utc>>, serializer: S)
+        -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        dt.map(|dt| dt.timestamp()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D)
+        -> Result<Option<DateTime<Utc>>, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        Ok(Option::<i64>::deserialize(deserializer)?
+            .and_then(|ts| DateTime::from_timestamp(ts, 0)))
+    }
+}
+
+pub(crate) mod datetime_serde {
     use serde::{Serializer, Deserializer, Deserialize};
     use chrono::{DateTime, Utc, TimeZone};
 
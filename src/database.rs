@@ -1,9 +1,32 @@
-use std::sync::mpsc;
+mod sled_storage;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::fs::{File, OpenOptions, TryLockError};
 use std::path::{Path, PathBuf};
 use std::thread;
-use std::sync::Arc;
-use std::io;
-use crate::config::{Post, FeedConfig, Posts};
+use std::time::{Duration, Instant};
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::io::{self, Write};
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Serialize, Deserialize};
+use tracing::{debug, error, info};
+use url::Url;
+use crate::config::{
+    Feed, Post, PostId, FeedConfig, FeedHealth, FeedMeta, FeedView, Posts,
+    Read,
+};
+use crate::error::{Error, Result};
+use crate::events::AppEvent;
+use crate::settings::{
+    RetentionSettings, Settings, StorageBackend, StorageSettings
+};
+use sled_storage::SledStorage;
+#[cfg(feature = "sqlite")]
+use sqlite::SqliteStorage;
 
 /// A database request from the application to the database.
 pub enum DatabaseRequest {
@@ -12,78 +35,831 @@ pub enum DatabaseRequest {
         feed_url: Arc<str>,
         posts: Posts,
     },
+
+    /// Mark the stored posts of the feed as read or unread, leaving the rest
+    /// of them as they are stored.
+    MarkRead {
+        feed_url: Arc<str>,
+        post_ids: Vec<PostId>,
+        read: bool,
+    },
+
+    /// Save how the posts of the feed are shown.
+    SaveView {
+        feed_url: Arc<str>,
+        view: FeedView,
+    },
+
+    /// Save how the last download of the feed went.
+    SaveHealth {
+        feed_url: Arc<str>,
+        health: FeedHealth,
+    },
+
+    /// Save what the feed says about itself.
+    SaveMeta {
+        feed_url: Arc<str>,
+        meta: Arc<FeedMeta>,
+    },
+
+    /// Archive the text of the article of a post of the feed.
+    SaveArticle {
+        feed_url: Arc<str>,
+        post_id: PostId,
+        text: Arc<str>,
+    },
+
+    /// Remember that the media linked from the feed has been played.
+    MarkPlayed {
+        feed_url: Arc<str>,
+        url: Url,
+    },
+
+    /// Remember that a URL has been opened from a post of the feed.
+    MarkOpened {
+        feed_url: Arc<str>,
+        post_id: PostId,
+        url: Url,
+    },
+
+    /// Forget that the posts of the feed have been pruned, as the feed
+    /// doesn't carry them anymore.
+    ForgetPruned {
+        feed_url: Arc<str>,
+        post_ids: Vec<PostId>,
+    },
+
+    /// Move everything stored of the feed at `from` to `to`, as the feed
+    /// has moved there.
+    MoveFeed {
+        from: Arc<str>,
+        to: Arc<str>,
+    },
+
+    /// Remember where the application is as it quits.
+    SaveSession(Session),
+
+    /// Write what has been saved so far to the disk. The database thread
+    /// flushes by itself once it's idle.
+    Flush,
+
+    /// Load everything stored of the feeds, answering with a response for
+    /// each of them as it's loaded.
+    LoadFeeds(Vec<Arc<str>>),
+}
+
+/// A response from the database thread, sent through the events of the
+/// application.
+pub enum DatabaseResponse {
+    /// Everything stored of the feed has been loaded.
+    Loaded(Arc<str>, Box<StoredFeed>),
+
+    /// The feed couldn't be loaded for the given reason.
+    LoadFailed(Arc<str>, String),
+}
+
+/// Everything stored of a feed.
+#[derive(Clone, Default)]
+pub struct StoredFeed {
+    pub posts: Posts,
+    pub view: FeedView,
+    pub health: Option<FeedHealth>,
+    pub meta: Option<Arc<FeedMeta>>,
+    pub articles: HashMap<PostId, Arc<str>>,
+    pub played: HashSet<Url>,
+    pub opened: HashMap<PostId, HashSet<Url>>,
+    pub reads: Vec<Read>,
+    pub pruned: HashSet<PostId>,
+}
+
+impl StoredFeed {
+    /// Fill in the `feed` with what's stored of it.
+    pub fn restore(self, feed: &mut Feed) {
+        feed.posts = self.posts;
+        feed.view = self.view;
+        feed.health = self.health;
+        feed.meta = self.meta;
+        feed.articles = self.articles;
+        feed.played = self.played;
+        feed.opened = self.opened;
+        feed.reads = self.reads;
+        feed.pruned = self.pruned;
+    }
+}
+
+impl DatabaseRequest {
+    /// Whether the request changes the stored posts of the feed at `url`
+    /// other than by saving them.
+    fn changes_posts_of(&self, url: &str) -> bool {
+        match self {
+            Self::MarkRead { feed_url, .. } => feed_url.as_ref() == url,
+            Self::MoveFeed { from, to } => {
+                from.as_ref() == url || to.as_ref() == url
+            },
+            // The posts are pruned as they're loaded.
+            Self::LoadFeeds(urls) => {
+                urls.iter().any(|feed_url| feed_url.as_ref() == url)
+            },
+            _ => false,
+        }
+    }
+}
+
+/// How long the database thread waits for more requests before flushing.
+const FLUSH_DELAY: Duration = Duration::from_millis(500);
+
+/// The longest the database thread goes without flushing while the requests
+/// keep coming.
+const MAX_FLUSH_DELAY: Duration = Duration::from_secs(5);
+
+/// Merge the requests that save the posts of the same feed into the first of
+/// them, unless the posts have been changed otherwise in between, so that
+/// each feed is written once. Of the posts saved several times, the last
+/// save wins.
+fn coalesce(requests: Vec<DatabaseRequest>) -> Vec<DatabaseRequest> {
+    let mut coalesced: Vec<DatabaseRequest> = Vec::new();
+    for request in requests {
+        let DatabaseRequest::SavePosts { feed_url, posts } = request else {
+            coalesced.push(request);
+            continue;
+        };
+
+        let earlier = coalesced.iter_mut().rev()
+            .take_while(|request| !request.changes_posts_of(&feed_url))
+            .find_map(|request| match request {
+                DatabaseRequest::SavePosts { feed_url: url, posts }
+                    if *url == feed_url => Some(posts),
+                _ => None,
+            });
+        match earlier {
+            Some(earlier) => {
+                let mut later = posts;
+                later.append(std::mem::take(earlier));
+                *earlier = later;
+            },
+            None => {
+                coalesced.push(DatabaseRequest::SavePosts { feed_url, posts });
+            },
+        }
+    }
+
+    coalesced
 }
 
 /// The application end of the channel between the channel and the feed
 /// database.
+///
+/// At most `queue_size` requests are queued up at once, so that a large
+/// refresh can't fill the memory with posts waiting to be saved.
 pub struct DatabaseChannel {
     /// Channel for database requests from the application to the database.
-    pub request_tx: mpsc::Sender<DatabaseRequest>,
+    request_tx: mpsc::SyncSender<DatabaseRequest>,
+
+    /// The number of requests sent but not yet handled.
+    backlog: Arc<AtomicUsize>,
+
+    /// Channel for the errors of the requests that couldn't be handled.
+    error_rx: mpsc::Receiver<Error>,
+
+    /// Reports the requests that couldn't be sent to the database thread.
+    error_tx: mpsc::Sender<Error>,
+
+    /// Handle of the database thread.
+    handle: thread::JoinHandle<()>,
+
+    /// Told about every handled request and given the responses, once set.
+    notify: Arc<OnceLock<mpsc::Sender<AppEvent>>>,
 }
 
 impl DatabaseChannel {
     /// Spawn the background database thread that will handle all permanent
-    /// feed storage accesses.
-    pub fn spawn_database_thread(cfg: &mut FeedConfig) -> Self {
-        // Spawn the channels for the database requests and responses.
-        let (request_tx, request_rx) = mpsc::channel::<DatabaseRequest>();
+    /// feed storage accesses, loading everything stored of the feeds of the
+    /// `cfg` into it first.
+    ///
+    /// The posts past the retention in the `settings` are removed as they're
+    /// loaded. A read-only database is only loaded from. Returns the session
+    /// the application last quit with too.
+    pub fn spawn_database_thread(cfg: &mut FeedConfig, settings: &Settings)
+        -> Result<(Self, Session)>
+    {
+        let db = Database::open(&settings.storage)?;
+        let saved = db.saved_posts()?;
+        for feed in cfg.sections.iter_mut().flat_map(|s| s.feeds.iter_mut()) {
+            Self::load_feed(&db, feed.url.as_str(), settings, &saved)?
+                .restore(feed);
+        }
+        let session = db.load_session()?;
+        Ok((Self::serve(db, settings, saved), session))
+    }
 
-        // Spawn the database.
-        let db = Database::with_default_data_dir();
+    /// Spawn the database thread like `spawn_database_thread`, but without
+    /// loading anything of the feeds, so that the application can be shown
+    /// while they're loaded with [`DatabaseRequest::LoadFeeds`].
+    pub fn spawn_loading_thread(settings: &Settings)
+        -> Result<(Self, Session)>
+    {
+        let db = Database::open(&settings.storage)?;
+        let saved = db.saved_posts()?;
+        let session = db.load_session()?;
+        Ok((Self::serve(db, settings, saved), session))
+    }
 
-        // Load all posts into the feed config.
-        for section in &mut cfg.sections {
-            for feed in &mut section.feeds {
-                let feed_url = feed.url.as_str();
-                let posts = db.load_feed(feed_url);
-                feed.posts = posts.into();
-            }
+    /// Load everything stored of the feed at `feed_url` from the `db`.
+    ///
+    /// Unless the database is read-only, the posts past the retention are
+    /// removed first, so that they aren't shown. The posts whose IDs are in
+    /// `saved` are kept.
+    fn load_feed(db: &Database, feed_url: &str, settings: &Settings,
+        saved: &HashMap<String, HashSet<PostId>>) -> Result<StoredFeed>
+    {
+        if !settings.storage.read_only {
+            db.prune_feed(feed_url, &settings.retention, saved)?;
         }
+        db.load_stored(feed_url)
+    }
 
-        // Spawn the database thread.
-        thread::spawn(move || {
-            while let Ok(request) = request_rx.recv() {
-                match request {
-                    DatabaseRequest::SavePosts { feed_url, posts } => {
-                        db.save_posts(&feed_url, posts)
-                    },
+    /// Spawn the database thread handling the requests with the `db`.
+    fn serve(db: Database, settings: &Settings,
+        saved: HashMap<String, HashSet<PostId>>) -> Self
+    {
+        let settings = settings.clone();
+        let read_only = settings.storage.read_only;
+        let queue_size = settings.queues.database;
+        Self::spawn(queue_size, move |request, respond| match request {
+            DatabaseRequest::LoadFeeds(urls) => {
+                debug!("Loading {} feeds", urls.len());
+                for feed_url in urls {
+                    let loaded =
+                        Self::load_feed(&db, &feed_url, &settings, &saved);
+                    respond(match loaded {
+                        Ok(feed) => {
+                            DatabaseResponse::Loaded(feed_url, Box::new(feed))
+                        },
+                        Err(err) => {
+                            error!("Couldn't load {}: {}", feed_url, err);
+                            let reason = err.to_string();
+                            DatabaseResponse::LoadFailed(feed_url, reason)
+                        },
+                    });
+                }
+                Ok(())
+            },
+
+            // A read-only database is only loaded from.
+            _ if read_only => Ok(()),
+
+            DatabaseRequest::SavePosts { feed_url, posts } => {
+                debug!("Saving {} posts of {}", posts.len(), feed_url);
+                db.save_posts(&feed_url, posts)?;
+                db.prune_feed(&feed_url, &settings.retention, &saved)?;
+                Ok(())
+            },
+            DatabaseRequest::MarkRead { feed_url, post_ids, read } => {
+                debug!("Marking {} posts of {} as {}", post_ids.len(),
+                    feed_url, if read { "read" } else { "unread" });
+                db.mark_read(&feed_url, &post_ids, read)?;
+                if !read {
+                    return Ok(());
+                }
+                db.log_reads(&feed_url, &post_ids, Utc::now())
+            },
+            DatabaseRequest::SaveView { feed_url, view } => {
+                debug!("Saving the view of {}", feed_url);
+                db.save_view(&feed_url, &view)
+            },
+            DatabaseRequest::SaveHealth { feed_url, health } => {
+                debug!("Saving the health of {}", feed_url);
+                db.save_health(&feed_url, &health)
+            },
+            DatabaseRequest::SaveMeta { feed_url, meta } => {
+                debug!("Saving the metadata of {}", feed_url);
+                db.save_meta(&feed_url, &meta)
+            },
+            DatabaseRequest::SaveArticle { feed_url, post_id, text } => {
+                debug!("Archiving the article of {} of {}", post_id.0,
+                    feed_url);
+                db.save_article(&feed_url, &post_id, &text)
+            },
+            DatabaseRequest::MarkPlayed { feed_url, url } => {
+                debug!("Marking {} of {} as played", url, feed_url);
+                db.mark_played(&feed_url, &url)
+            },
+            DatabaseRequest::MarkOpened { feed_url, post_id, url } => {
+                debug!("Marking {} of {} of {} as opened", url, post_id.0,
+                    feed_url);
+                db.mark_opened(&feed_url, &post_id, &url)
+            },
+            DatabaseRequest::ForgetPruned { feed_url, post_ids } => {
+                debug!("Forgetting {} pruned posts of {}", post_ids.len(),
+                    feed_url);
+                db.forget_pruned(&feed_url, &post_ids)
+            },
+            DatabaseRequest::MoveFeed { from, to } => {
+                debug!("Moving {} to {}", from, to);
+                db.move_feed(&from, &to)
+            },
+            DatabaseRequest::SaveSession(session) => {
+                debug!("Saving the session of {} pages", session.pages.len());
+                db.save_session(&session)
+            },
+            DatabaseRequest::Flush => {
+                debug!("Flushing the database");
+                db.flush()
+            },
+        })
+    }
+
+    /// Spawn a background thread that discards all database requests.
+    ///
+    /// Used for feeds which shouldn't be stored permanently. Nothing is ever
+    /// stored of the feeds it's asked to load.
+    pub fn spawn_ephemeral_thread() -> Self {
+        Self::spawn(1, |request, respond| {
+            if let DatabaseRequest::LoadFeeds(urls) = request {
+                for feed_url in urls {
+                    respond(DatabaseResponse::Loaded(feed_url,
+                        Box::default()));
                 }
             }
-        });
+            Ok(())
+        })
+    }
+
+    /// Spawn the database thread which handles the requests with `handler`.
+    ///
+    /// The requests queued up meanwhile are handled together, saving the
+    /// posts of each feed once, and the thread asks the handler to flush once
+    /// it's idle. The handler answers through the function it's given, and
+    /// its errors are passed back to the application.
+    fn spawn<F>(queue_size: usize, handler: F) -> Self
+    where
+        F: Fn(DatabaseRequest, &dyn Fn(DatabaseResponse)) -> Result<()>
+            + Send + 'static
+    {
+        // Spawn the channels for the database requests and their errors.
+        let (request_tx, request_rx) = mpsc::sync_channel(queue_size.max(1));
+        let (error_tx, error_rx) = mpsc::channel();
+        let backlog = Arc::new(AtomicUsize::new(0));
+        let notify = Arc::new(OnceLock::<mpsc::Sender<AppEvent>>::new());
+
+        // Spawn the database thread.
+        let handle = {
+            let backlog = backlog.clone();
+            let error_tx = error_tx.clone();
+            let notify = notify.clone();
+            let events = notify.clone();
+            let respond = move |response| {
+                if let Some(events) = events.get() {
+                    let _ = events.send(AppEvent::Database(response));
+                }
+            };
+            let run = move |request| {
+                if let Err(err) = handler(request, &respond) {
+                    error!("{}", err);
+                    let _ = error_tx.send(err);
+                }
+            };
+            thread::spawn(move || {
+                // When the oldest write that hasn't been flushed was made.
+                let mut unflushed: Option<Instant> = None;
+                loop {
+                    let request = match unflushed {
+                        None => request_rx.recv()
+                            .map_err(|_| RecvTimeoutError::Disconnected),
+                        Some(_) => request_rx.recv_timeout(FLUSH_DELAY),
+                    };
+                    let request = match request {
+                        Ok(request) => request,
+                        Err(RecvTimeoutError::Timeout) => {
+                            run(DatabaseRequest::Flush);
+                            unflushed = None;
+                            continue;
+                        },
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
+
+                    // Handle the requests queued meanwhile along with it.
+                    let requests: Vec<_> = std::iter::once(request)
+                        .chain(request_rx.try_iter())
+                        .collect();
+                    let count = requests.len();
+                    coalesce(requests).into_iter().for_each(&run);
+
+                    let since = *unflushed.get_or_insert_with(Instant::now);
+                    if since.elapsed() >= MAX_FLUSH_DELAY {
+                        run(DatabaseRequest::Flush);
+                        unflushed = None;
+                    }
+                    backlog.fetch_sub(count, Ordering::Relaxed);
+                    if let Some(events) = notify.get() {
+                        let _ = events.send(AppEvent::Saved);
+                    }
+                }
+
+                if unflushed.is_some() {
+                    run(DatabaseRequest::Flush);
+                }
+            })
+        };
 
         // Return the application end.
-        Self { request_tx }
+        Self { request_tx, backlog, error_rx, error_tx, handle, notify }
+    }
+
+    /// Send an event through `events` after every handled request, so that
+    /// the application can wait for the room in the queue, and the responses
+    /// of the database.
+    pub fn notify(&self, events: mpsc::Sender<AppEvent>) {
+        let _ = self.notify.set(events);
+    }
+
+    /// Send a request to the database, waiting while the queue is full.
+    ///
+    /// If the database thread has stopped, the request is dropped and the
+    /// error is reported through `errors`.
+    pub fn send(&self, request: DatabaseRequest) {
+        self.backlog.fetch_add(1, Ordering::Relaxed);
+        if self.request_tx.send(request).is_err() {
+            self.stopped();
+        }
+    }
+
+    /// Send a request to the database unless the queue is full, in which case
+    /// the request is given back.
+    pub fn try_send(&self, request: DatabaseRequest)
+        -> Result<(), DatabaseRequest>
+    {
+        self.backlog.fetch_add(1, Ordering::Relaxed);
+        match self.request_tx.try_send(request) {
+            Ok(()) => Ok(()),
+            Err(mpsc::TrySendError::Full(request)) => {
+                self.backlog.fetch_sub(1, Ordering::Relaxed);
+                Err(request)
+            },
+            Err(mpsc::TrySendError::Disconnected(_)) => {
+                self.stopped();
+                Ok(())
+            },
+        }
     }
+
+    /// Report a request that couldn't be sent because the database thread
+    /// has stopped.
+    fn stopped(&self) {
+        self.backlog.fetch_sub(1, Ordering::Relaxed);
+        let err = Error::Database("The database thread has stopped".into());
+        error!("{}", err);
+        let _ = self.error_tx.send(err);
+    }
+
+    /// Get the number of requests that haven't been handled yet.
+    pub fn backlog(&self) -> usize {
+        self.backlog.load(Ordering::Relaxed)
+    }
+
+    /// Get the errors of the requests that have failed since the last call.
+    pub fn errors(&self) -> impl Iterator<Item = Error> + '_ {
+        self.error_rx.try_iter()
+    }
+
+    /// Close the channel and wait until the database thread has handled and
+    /// flushed all of the requests sent so far.
+    ///
+    /// Returns the errors of the requests that have failed meanwhile.
+    pub fn close(self) -> Vec<Error> {
+        let Self { request_tx, handle, error_rx, .. } = self;
+        drop(request_tx);
+        let _ = handle.join();
+        error_rx.try_iter().collect()
+    }
+}
+
+/// Load all posts stored in the database into the feed config, without
+/// spawning the database thread.
+pub fn load_posts(cfg: &mut FeedConfig, settings: &StorageSettings)
+    -> Result<()>
+{
+    Database::open(settings)?.load_config(cfg)
+}
+
+/// The state of the synchronization with a remote backend.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RemoteState {
+    /// The highest item ID that has been downloaded so far.
+    pub since_id: u64,
+
+    /// The feed URL and the post ID of every downloaded item, by item ID.
+    pub items: HashMap<u64, (String, PostId)>,
+
+    /// The IDs of the items saved on the remote.
+    pub saved: HashSet<u64>,
+
+    /// The subscriptions of a remote that manages them instead of the feed
+    /// file.
+    pub feeds: Vec<SubscribedFeed>,
+}
+
+/// Where the application was when it last quit, so that it can be opened
+/// there again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// The open pages, the main page first.
+    pub pages: Vec<PageSession>,
+}
+
+/// Where a page was when the application last quit. The feeds are told
+/// apart by their URLs, so that the feed file can change in between.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageSession {
+    /// The main page and the feed selected on it.
+    Main {
+        feed: Option<String>,
+        offset: usize,
+    },
+
+    /// The page of a feed and the post selected on it.
+    Feed {
+        feed: String,
+        post: Option<PostId>,
+        offset: usize,
+    },
+
+    /// The page of a post of a feed.
+    Post {
+        feed: String,
+        post: PostId,
+    },
+}
+
+/// A feed subscribed to on a remote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscribedFeed {
+    /// Title of the section the feed belongs to.
+    pub section: String,
+
+    /// Title of the feed.
+    pub title: String,
+
+    /// URL of the feed.
+    pub url: String,
+}
+
+/// A store of the posts, the remote states and the views.
+pub trait Storage: Send {
+    /// Load all posts of a feed.
+    fn load_feed(&self, feed_url: &str) -> Result<Posts>;
+
+    /// Save the posts of a feed, replacing the stored ones with the same IDs.
+    fn save_posts(&self, feed_url: &str, posts: &Posts) -> Result<()>;
+
+    /// Mark the stored posts of a feed with the `post_ids` as `read`.
+    fn mark_read(&self, feed_url: &str, post_ids: &[PostId], read: bool)
+        -> Result<()>;
+
+    /// Remove the posts of a feed with the `post_ids`.
+    fn remove_posts(&self, feed_url: &str, post_ids: &[PostId]) -> Result<()>;
+
+    /// Remove all posts of a feed.
+    fn remove_feed_posts(&self, feed_url: &str) -> Result<()>;
+
+    /// Get the URLs of all feeds with stored posts, sorted, along with the
+    /// number of their posts.
+    fn feeds(&self) -> Result<Vec<(String, usize)>>;
+
+    /// Get all stored posts along with the URLs of their feeds.
+    fn all_posts(&self) -> Result<Vec<(String, Post)>>;
+
+    /// Find the posts whose titles match the `query`.
+    fn search(&self, query: &str) -> Result<Vec<(String, Post)>>;
+
+    /// Load the sync state of the remote called `name`.
+    fn remote_state(&self, name: &str) -> Result<RemoteState>;
+
+    /// Load the sync states of all remotes.
+    fn remote_states(&self) -> Result<Vec<RemoteState>>;
+
+    /// Save the sync state of the remote called `name`.
+    fn save_remote_state(&self, name: &str, state: &RemoteState)
+        -> Result<()>;
+
+    /// Load how the posts of a feed are shown.
+    fn load_view(&self, feed_url: &str) -> Result<FeedView>;
+
+    /// Save how the posts of a feed are shown.
+    fn save_view(&self, feed_url: &str, view: &FeedView) -> Result<()>;
+
+    /// Forget how the posts of a feed are shown.
+    fn remove_view(&self, feed_url: &str) -> Result<()>;
+
+    /// Load how the last download of a feed went, if it has been downloaded.
+    fn load_health(&self, feed_url: &str) -> Result<Option<FeedHealth>>;
+
+    /// Save how the last download of a feed went.
+    fn save_health(&self, feed_url: &str, health: &FeedHealth) -> Result<()>;
+
+    /// Forget how the last download of a feed went.
+    fn remove_health(&self, feed_url: &str) -> Result<()>;
+
+    /// Load what a feed said about itself, if it has been downloaded.
+    fn load_meta(&self, feed_url: &str) -> Result<Option<FeedMeta>>;
+
+    /// Save what a feed says about itself.
+    fn save_meta(&self, feed_url: &str, meta: &FeedMeta) -> Result<()>;
+
+    /// Forget what a feed said about itself.
+    fn remove_meta(&self, feed_url: &str) -> Result<()>;
+
+    /// Load the archived articles of the posts of a feed, by post ID.
+    fn load_articles(&self, feed_url: &str)
+        -> Result<HashMap<PostId, Arc<str>>>;
+
+    /// Archive the text of the article of a post of a feed, replacing the
+    /// one archived before.
+    fn save_article(&self, feed_url: &str, post_id: &PostId, text: &str)
+        -> Result<()>;
+
+    /// Remove the archived articles of the posts of a feed with the
+    /// `post_ids`.
+    fn remove_articles(&self, feed_url: &str, post_ids: &[PostId])
+        -> Result<()>;
+
+    /// Load the URLs of the media linked from a feed that have been played.
+    fn load_played(&self, feed_url: &str) -> Result<HashSet<Url>>;
+
+    /// Remember that the media at the `url` linked from a feed has been
+    /// played.
+    fn mark_played(&self, feed_url: &str, url: &Url) -> Result<()>;
+
+    /// Forget which media linked from a feed have been played.
+    fn remove_played(&self, feed_url: &str) -> Result<()>;
+
+    /// Load the URLs opened from the posts of a feed, by post ID.
+    fn load_opened(&self, feed_url: &str)
+        -> Result<HashMap<PostId, HashSet<Url>>>;
+
+    /// Remember that the `url` has been opened from a post of a feed.
+    fn mark_opened(&self, feed_url: &str, post_id: &PostId, url: &Url)
+        -> Result<()>;
+
+    /// Forget which URLs have been opened from the posts of a feed with the
+    /// `post_ids`.
+    fn remove_opened(&self, feed_url: &str, post_ids: &[PostId])
+        -> Result<()>;
+
+    /// Load when the posts of a feed have been marked read, oldest first.
+    fn load_reads(&self, feed_url: &str) -> Result<Vec<Read>>;
+
+    /// Remember that the posts of a feed with the `post_ids` were marked
+    /// read `at`.
+    fn log_reads(&self, feed_url: &str, post_ids: &[PostId],
+        at: DateTime<Utc>) -> Result<()>;
+
+    /// Forget when the posts of a feed have been marked read.
+    fn remove_reads(&self, feed_url: &str) -> Result<()>;
+
+    /// Load the IDs of the posts of a feed that have been pruned.
+    fn load_pruned(&self, feed_url: &str) -> Result<HashSet<PostId>>;
+
+    /// Remember that the posts of a feed with the `post_ids` have been
+    /// pruned.
+    fn mark_pruned(&self, feed_url: &str, post_ids: &[PostId]) -> Result<()>;
+
+    /// Forget that the posts of a feed with the `post_ids` have been pruned.
+    fn unmark_pruned(&self, feed_url: &str, post_ids: &[PostId])
+        -> Result<()>;
+
+    /// Forget which posts of a feed have been pruned.
+    fn remove_pruned(&self, feed_url: &str) -> Result<()>;
+
+    /// Get the URLs of the feeds stored before the IDs of the posts were made
+    /// from their links, whose post IDs haven't been migrated yet.
+    fn legacy_id_feeds(&self) -> Result<Vec<String>>;
+
+    /// Remember that the post IDs of a feed have been migrated.
+    fn remove_legacy_id_feed(&self, feed_url: &str) -> Result<()>;
+
+    /// Load where the application was when it last quit.
+    fn load_session(&self) -> Result<Session>;
+
+    /// Save where the application is as it quits.
+    fn save_session(&self, session: &Session) -> Result<()>;
+
+    /// Write the changes that haven't been written to the disk yet.
+    fn flush(&self) -> Result<()>;
 }
 
 /// Implementation of the database.
-struct Database {
-    /// The internal sled database state.
-    db: sled::Db,
+///
+/// The TUI accesses it only from the database thread. One-shot commands, such
+/// as syncing with a remote, can use it directly.
+pub struct Database {
+    /// The backend the data is stored in.
+    storage: Box<dyn Storage>,
+
+    /// The lock on the data directory, held while the database is open so
+    /// that no other instance writes to it meanwhile. Read-only databases
+    /// don't take it.
+    _lock: Option<File>,
 }
 
 impl Database {
-    /// Create a new database.
-    fn new<P: AsRef<Path>>(data_dir: P) -> Self {
-        let db = sled::open(data_dir).expect("Failed to open sled db");
-        Self { db }
-    }
-
-    /// Get path to the data directory.
-    fn get_data_dir() -> io::Result<PathBuf> {
-        // Get a path to the data directory.
-        let data_dir = match std::env::var("XDG_DATA_HOME") {
-            Ok(dir) => PathBuf::new().join(dir),
-            Err(_) => std::env::home_dir()
-                .expect("Couldn't get home directory")
-                .join(".local/share")
+    /// Open the database of the configured backend in the data directory.
+    ///
+    /// A read-only database is never written to, not even to migrate it.
+    pub fn open(settings: &StorageSettings) -> Result<Self> {
+        let read_only = settings.read_only;
+        let data_dir = Self::get_data_dir(settings.dir.clone(), !read_only)?;
+        let lock = match read_only {
+            true => None,
+            false => Some(Self::lock_data_dir(&data_dir)?),
+        };
+        let storage: Box<dyn Storage> = match settings.backend {
+            // Sled writes to the database when opening it, so use a copy.
+            StorageBackend::Sled if read_only => {
+                Box::new(SledStorage::open_copy(data_dir)?)
+            },
+            StorageBackend::Sled => Box::new(SledStorage::open(data_dir)?
+                .compressed(settings.compress)),
+
+            #[cfg(feature = "sqlite")]
+            StorageBackend::Sqlite => {
+                let path = data_dir.join("nia.sqlite");
+                Box::new(SqliteStorage::open(path, read_only)?)
+            },
+
+            // The settings refuse the backend when it's not built in, but
+            // the library can be given any settings.
+            #[cfg(not(feature = "sqlite"))]
+            StorageBackend::Sqlite => return Err(Error::Database(
+                "nia was built without SQLite support".into())),
         };
 
-        // Use the compile time project name as the config dir.
-        let data_dir = data_dir.join(env!("CARGO_PKG_NAME"));
+        info!("Opened the {:?} database{}", settings.backend,
+            if read_only { " read-only" } else { "" });
+        let db = Self { storage, _lock: lock };
+        if !read_only {
+            db.migrate_legacy_ids()?;
+        }
+        Ok(db)
+    }
+
+    /// Get the data directory of the database of the `settings`, creating it
+    /// unless the database is read-only.
+    pub fn data_dir(settings: &StorageSettings) -> Result<PathBuf> {
+        Ok(Self::get_data_dir(settings.dir.clone(), !settings.read_only)?)
+    }
+
+    /// Lock the `data_dir` for this process, failing if another one has
+    /// locked it already.
+    ///
+    /// The lock is released when the returned file is closed, even if the
+    /// process is killed, so it never goes stale.
+    fn lock_data_dir(data_dir: &Path) -> Result<File> {
+        let path = data_dir.join("nia.lock");
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        match file.try_lock() {
+            Ok(()) => {},
+            Err(TryLockError::WouldBlock) => {
+                let pid = std::fs::read_to_string(&path).ok()
+                    .and_then(|pid| pid.trim().parse().ok());
+                return Err(Error::Locked(data_dir.to_path_buf(), pid));
+            },
+            Err(TryLockError::Error(err)) => return Err(err.into()),
+        }
+
+        // Tell the other instances who holds the lock.
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        Ok(file)
+    }
+
+    /// Get path to the data directory, or to the given `dir` instead,
+    /// creating it if it doesn't exist and `create` is set.
+    fn get_data_dir(dir: Option<PathBuf>, create: bool)
+        -> io::Result<PathBuf>
+    {
+        let data_dir = match dir {
+            Some(dir) => dir,
+            None => {
+                // Get a path to the data directory.
+                let data_dir = match std::env::var("XDG_DATA_HOME") {
+                    Ok(dir) => PathBuf::new().join(dir),
+                    Err(_) => crate::config::home_dir()?.join(".local/share"),
+                };
+
+                // Use the compile time project name as the config dir.
+                data_dir.join(env!("CARGO_PKG_NAME"))
+            },
+        };
 
         // If the directory doesn't exist, create it.
-        if !data_dir.exists() {
+        if create && !data_dir.exists() {
             std::fs::DirBuilder::new().recursive(true).create(&data_dir)?;
         }
 
@@ -95,73 +871,880 @@ impl Database {
                 } else {
                     let err = format!("Path exists but isn't a directory: {}",
                         data_dir.display());
-                    Err(io::Error::new(io::ErrorKind::Other, err))
+                    Err(io::Error::other(err))
                 }
+            })?
+    }
+
+    /// Save posts to the database.
+    pub fn save_posts(&self, feed_url: &str, posts: Posts) -> Result<()> {
+        self.storage.save_posts(feed_url, &posts)
+    }
+
+    /// Mark the stored posts of a feed as read or unread.
+    pub fn mark_read(&self, feed_url: &str, post_ids: &[PostId], read: bool)
+        -> Result<()>
+    {
+        self.storage.mark_read(feed_url, post_ids, read)
+    }
+
+    /// Remove the read posts of a feed that are past the `retention`, unless
+    /// they're starred or their IDs are in `keep` under the feed URL. Their
+    /// IDs are remembered, so that they aren't downloaded again.
+    ///
+    /// Returns the number of removed posts.
+    pub fn prune_feed(&self, feed_url: &str, retention: &RetentionSettings,
+        keep: &HashMap<String, HashSet<PostId>>) -> Result<usize>
+    {
+        if !retention.prunes() {
+            return Ok(0);
+        }
+
+        // The posts are sorted newest first, so the newest ones are kept.
+        let posts = self.load_feed(feed_url)?;
+        let keep = keep.get(feed_url);
+
+        let cutoff = (retention.max_age_days > 0).then(|| {
+            Utc::now() - TimeDelta::days(retention.max_age_days.into())
+        });
+
+        let max_posts = retention.max_posts;
+        let removed: Vec<PostId> = posts.as_ref().iter()
+            .enumerate()
+            .filter(|(idx, post)| {
+                let too_many = max_posts > 0 && *idx >= max_posts;
+                let too_old = cutoff
+                    .is_some_and(|cutoff| post.published < cutoff);
+                let kept = !post.read || post.starred
+                    || keep.is_some_and(|ids| ids.contains(&post.id));
+                !kept && (too_many || too_old)
             })
-        .flatten()
+            .map(|(_, post)| post.id.clone())
+            .collect();
+
+        if !removed.is_empty() {
+            info!("Removed {} old posts of {}", removed.len(), feed_url);
+            self.storage.remove_posts(feed_url, &removed)?;
+            self.storage.remove_articles(feed_url, &removed)?;
+            self.storage.remove_opened(feed_url, &removed)?;
+            self.storage.mark_pruned(feed_url, &removed)?;
+        }
+
+        Ok(removed.len())
     }
 
-    /// Create a new database using the default database directory.
-    fn with_default_data_dir() -> Self {
-        // Get path to the data dir.
-        let data_dir = Self::get_data_dir().expect("Couldn't get data dir");
-        Self::new(data_dir)
+    /// Get all stored posts along with the URLs of their feeds.
+    pub fn all_posts(&self) -> Result<Vec<(String, Post)>> {
+        self.storage.all_posts()
     }
 
-    /// Open (or create) the "posts" tree.
-    fn posts_tree(&self) -> sled::Tree {
-        self.db.open_tree("posts").expect("Failed to open posts tree")
+    /// Find the stored posts whose titles match the `query`, along with the
+    /// URLs of their feeds.
+    pub fn search(&self, query: &str) -> Result<Vec<(String, Post)>> {
+        self.storage.search(query)
     }
 
-    /// Make a sled key for a post.
-    fn make_key(feed_url: &str, post: &Post) -> Vec<u8> {
-        let mut key = Vec::with_capacity(
-            feed_url.len() + post.id.0.len() + 1);
+    /// Get the URLs of the feeds with stored posts that aren't in the `cfg`
+    /// anymore, along with the number of their posts.
+    pub fn orphaned_feeds(&self, cfg: &FeedConfig)
+        -> Result<Vec<(String, usize)>>
+    {
+        let subscribed: HashSet<&str> = cfg.sections.iter()
+            .flat_map(|section| section.feeds.iter())
+            .map(|feed| feed.url.as_str())
+            .collect();
 
-        // Feed URL bytes.
-        key.extend_from_slice(feed_url.as_bytes());
+        Ok(self.storage.feeds()?.into_iter()
+            .filter(|(url, _)| !subscribed.contains(url.as_str()))
+            .collect())
+    }
 
-        // Separator to avoid collisions
-        key.push(0);
+    /// Remove all posts of a feed and return them.
+    pub fn remove_feed(&self, feed_url: &str) -> Result<Posts> {
+        let posts = self.load_feed(feed_url)?;
+        let ids: Vec<PostId> = posts.as_ref().iter()
+            .map(|post| post.id.clone())
+            .collect();
 
-        // Post ID.
-        key.extend_from_slice(post.id.0.as_bytes());
+        self.storage.remove_feed_posts(feed_url)?;
+        self.storage.remove_articles(feed_url, &ids)?;
+        self.storage.remove_opened(feed_url, &ids)?;
+        self.storage.remove_reads(feed_url)?;
+        self.storage.remove_view(feed_url)?;
+        self.storage.remove_health(feed_url)?;
+        self.storage.remove_meta(feed_url)?;
+        self.storage.remove_played(feed_url)?;
+        self.storage.remove_pruned(feed_url)?;
+        Ok(posts)
+    }
 
-        key
+    /// Move all posts of a feed and everything else stored of it from the
+    /// URL `from` to the URL `to`, merging them with those stored there.
+    pub fn move_feed(&self, from: &str, to: &str) -> Result<()> {
+        self.save_posts(to, self.load_feed(from)?)?;
+        self.save_view(to, &self.load_view(from)?)?;
+        if let Some(health) = self.load_health(from)? {
+            self.save_health(to, &health)?;
+        }
+        if let Some(meta) = self.load_meta(from)? {
+            self.save_meta(to, &meta)?;
+        }
+        for (post_id, text) in self.load_articles(from)? {
+            self.save_article(to, &post_id, &text)?;
+        }
+        for url in self.load_played(from)? {
+            self.mark_played(to, &url)?;
+        }
+        for (post_id, urls) in self.load_opened(from)? {
+            for url in urls {
+                self.mark_opened(to, &post_id, &url)?;
+            }
+        }
+        for read in self.load_reads(from)? {
+            self.log_reads(to, &[read.post_id], read.at)?;
+        }
+        let pruned: Vec<PostId> = self.load_pruned(from)?.into_iter().collect();
+        self.storage.mark_pruned(to, &pruned)?;
+
+        self.remove_feed(from).map(|_| ())
     }
 
-    /// Get the prefix for scanning all posts of a feed.
-    fn feed_prefix(feed_url: &str) -> Vec<u8> {
-        let mut prefix = feed_url.as_bytes().to_vec();
-        prefix.push(0);
-        prefix
+    /// Migrate the post IDs of the feeds stored before the IDs were made from
+    /// the links of the posts, see `migrate_post_ids`. The migrations of the
+    /// backends list those feeds once, so each of them is migrated once.
+    fn migrate_legacy_ids(&self) -> Result<()> {
+        for feed_url in self.storage.legacy_id_feeds()? {
+            self.migrate_post_ids(&feed_url)?;
+            self.storage.remove_legacy_id_feed(&feed_url)?;
+        }
+
+        Ok(())
     }
 
-    /// Save posts to the database.
-    pub fn save_posts(&self, feed_url: &str, posts: Posts) {
-        let tree = self.posts_tree();
+    /// Give the posts of a feed whose IDs were made from their titles the IDs
+    /// made from their links instead, see `PostId::from_link`, along with
+    /// their archived articles, opened URLs and reads. The posts that turn
+    /// out to be the same are merged. Returns the number of posts given new
+    /// IDs.
+    ///
+    /// The IDs made from the titles are told by making them again, since
+    /// the IDs the feeds give may look like hashes too. The titles can't
+    /// have been revised since, as a revised title made another ID. The
+    /// posts synced from the remotes, whose IDs were their links, are given
+    /// the IDs made from them too.
+    pub fn migrate_post_ids(&self, feed_url: &str) -> Result<usize> {
+        let posts = self.load_feed(feed_url)?;
+        let ids: HashMap<PostId, PostId> = posts.as_ref().iter()
+            .filter(|post| post.has_title_id() || post.primary_url()
+                .is_some_and(|url| *post.id.0 == *url.as_str()))
+            .filter_map(|post| {
+                let id = PostId::from_link(post.primary_url()?)?;
+                (id != post.id).then(|| (post.id.clone(), id))
+            })
+            .collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
 
-        for post in posts.as_ref().iter() {
-            let key = Self::make_key(feed_url, &post);
-            let value = postcard::to_stdvec(&post)
-                .expect("Failed to serialize post");
+        // The newest of the same posts is kept, read or starred if any of
+        // them is.
+        let mut moved: HashMap<PostId, Post> = HashMap::new();
+        for post in posts.as_ref() {
+            let Some(id) = ids.get(&post.id) else {
+                continue;
+            };
+            let kept = moved.entry(id.clone())
+                .or_insert_with(|| Post { id: id.clone(), ..post.clone() });
+            kept.read |= post.read;
+            kept.starred |= post.starred;
+        }
+        self.save_posts(feed_url, moved.into_values().collect::<Vec<_>>()
+            .into())?;
 
-            tree.insert(key, value).expect("Failed to insert post");
+        for (post_id, text) in self.load_articles(feed_url)? {
+            if let Some(id) = ids.get(&post_id) {
+                self.save_article(feed_url, id, &text)?;
+            }
+        }
+        for (post_id, urls) in self.load_opened(feed_url)? {
+            let Some(id) = ids.get(&post_id) else {
+                continue;
+            };
+            for url in urls {
+                self.mark_opened(feed_url, id, &url)?;
+            }
         }
+        let reads = self.load_reads(feed_url)?;
+        if reads.iter().any(|read| ids.contains_key(&read.post_id)) {
+            self.storage.remove_reads(feed_url)?;
+            for read in reads {
+                let id = ids.get(&read.post_id).unwrap_or(&read.post_id);
+                self.log_reads(feed_url, std::slice::from_ref(id), read.at)?;
+            }
+        }
+
+        let old: Vec<PostId> = ids.into_keys().collect();
+        self.storage.remove_posts(feed_url, &old)?;
+        self.storage.remove_articles(feed_url, &old)?;
+        self.storage.remove_opened(feed_url, &old)?;
+        info!("Gave {} posts of {} IDs made from their links", old.len(),
+            feed_url);
+        Ok(old.len())
+    }
+
+    /// Get the IDs of the posts saved on any of the remotes, by feed URL.
+    fn saved_posts(&self) -> Result<HashMap<String, HashSet<PostId>>> {
+        let mut saved: HashMap<String, HashSet<PostId>> = HashMap::new();
+        for state in self.storage.remote_states()? {
+            let items = state.saved.iter()
+                .filter_map(|id| state.items.get(id));
+            for (url, post_id) in items {
+                saved.entry(url.clone()).or_default().insert(post_id.clone());
+            }
+        }
+
+        Ok(saved)
+    }
+
+    /// Load the sync state of the remote called `name`.
+    pub fn remote_state(&self, name: &str) -> Result<RemoteState> {
+        self.storage.remote_state(name)
+    }
+
+    /// Save the sync state of the remote called `name`.
+    pub fn save_remote_state(&self, name: &str, state: &RemoteState)
+        -> Result<()>
+    {
+        self.storage.save_remote_state(name, state)
+    }
+
+    /// Load where the application was when it last quit.
+    pub fn load_session(&self) -> Result<Session> {
+        self.storage.load_session()
+    }
+
+    /// Save where the application is as it quits.
+    pub fn save_session(&self, session: &Session) -> Result<()> {
+        self.storage.save_session(session)
+    }
 
-        tree.flush().expect("Failed to flush posts tree");
+    /// Write the changes that haven't been written to the disk yet.
+    pub fn flush(&self) -> Result<()> {
+        self.storage.flush()
+    }
+
+    /// Load all posts, views, health, metadata, archived articles, played
+    /// media, opened URLs, reads and pruned posts of all feeds in the feed
+    /// config.
+    pub fn load_config(&self, cfg: &mut FeedConfig) -> Result<()> {
+        for section in &mut cfg.sections {
+            for feed in &mut section.feeds {
+                self.load_stored(feed.url.as_str())?.restore(feed);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Load everything stored of a feed.
+    pub fn load_stored(&self, feed_url: &str) -> Result<StoredFeed> {
+        Ok(StoredFeed {
+            posts: self.load_feed(feed_url)?,
+            view: self.load_view(feed_url)?,
+            health: self.load_health(feed_url)?,
+            meta: self.load_meta(feed_url)?,
+            articles: self.load_articles(feed_url)?,
+            played: self.load_played(feed_url)?,
+            opened: self.load_opened(feed_url)?,
+            reads: self.load_reads(feed_url)?,
+            pruned: self.load_pruned(feed_url)?,
+        })
+    }
+
+    /// Load how the posts of a feed are shown.
+    pub fn load_view(&self, feed_url: &str) -> Result<FeedView> {
+        self.storage.load_view(feed_url)
+    }
+
+    /// Save how the posts of a feed are shown.
+    pub fn save_view(&self, feed_url: &str, view: &FeedView) -> Result<()> {
+        self.storage.save_view(feed_url, view)
+    }
+
+    /// Load how the last download of a feed went.
+    pub fn load_health(&self, feed_url: &str) -> Result<Option<FeedHealth>> {
+        self.storage.load_health(feed_url)
+    }
+
+    /// Save how the last download of a feed went.
+    pub fn save_health(&self, feed_url: &str, health: &FeedHealth)
+        -> Result<()>
+    {
+        self.storage.save_health(feed_url, health)
+    }
+
+    /// Load what a feed said about itself, if it has been downloaded.
+    pub fn load_meta(&self, feed_url: &str)
+        -> Result<Option<Arc<FeedMeta>>>
+    {
+        Ok(self.storage.load_meta(feed_url)?.map(Arc::new))
+    }
+
+    /// Save what a feed says about itself.
+    pub fn save_meta(&self, feed_url: &str, meta: &FeedMeta) -> Result<()> {
+        self.storage.save_meta(feed_url, meta)
+    }
+
+    /// Load the archived articles of the posts of a feed, by post ID.
+    pub fn load_articles(&self, feed_url: &str)
+        -> Result<HashMap<PostId, Arc<str>>>
+    {
+        self.storage.load_articles(feed_url)
+    }
+
+    /// Archive the text of the article of a post of a feed.
+    pub fn save_article(&self, feed_url: &str, post_id: &PostId, text: &str)
+        -> Result<()>
+    {
+        self.storage.save_article(feed_url, post_id, text)
+    }
+
+    /// Load the URLs of the media linked from a feed that have been played.
+    pub fn load_played(&self, feed_url: &str) -> Result<HashSet<Url>> {
+        self.storage.load_played(feed_url)
+    }
+
+    /// Remember that the media at the `url` linked from a feed has been
+    /// played.
+    pub fn mark_played(&self, feed_url: &str, url: &Url) -> Result<()> {
+        self.storage.mark_played(feed_url, url)
+    }
+
+    /// Load the URLs opened from the posts of a feed, by post ID.
+    pub fn load_opened(&self, feed_url: &str)
+        -> Result<HashMap<PostId, HashSet<Url>>>
+    {
+        self.storage.load_opened(feed_url)
+    }
+
+    /// Remember that the `url` has been opened from a post of a feed.
+    pub fn mark_opened(&self, feed_url: &str, post_id: &PostId, url: &Url)
+        -> Result<()>
+    {
+        self.storage.mark_opened(feed_url, post_id, url)
+    }
+
+    /// Load when the posts of a feed have been marked read, oldest first.
+    pub fn load_reads(&self, feed_url: &str) -> Result<Vec<Read>> {
+        self.storage.load_reads(feed_url)
+    }
+
+    /// Remember that the posts of a feed with the `post_ids` were marked
+    /// read `at`.
+    pub fn log_reads(&self, feed_url: &str, post_ids: &[PostId],
+        at: DateTime<Utc>) -> Result<()>
+    {
+        self.storage.log_reads(feed_url, post_ids, at)
+    }
+
+    /// Load the IDs of the posts of a feed that have been pruned.
+    pub fn load_pruned(&self, feed_url: &str) -> Result<HashSet<PostId>> {
+        self.storage.load_pruned(feed_url)
+    }
+
+    /// Forget that the posts of a feed with the `post_ids` have been pruned,
+    /// as the feed doesn't carry them anymore.
+    pub fn forget_pruned(&self, feed_url: &str, post_ids: &[PostId])
+        -> Result<()>
+    {
+        self.storage.unmark_pruned(feed_url, post_ids)
     }
 
     /// Load all posts for a feed.
-    pub fn load_feed(&self, feed_url: &str) -> Posts {
-        let tree = self.posts_tree();
-        let prefix = Self::feed_prefix(feed_url);
+    pub fn load_feed(&self, feed_url: &str) -> Result<Posts> {
+        self.storage.load_feed(feed_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::config::test_post;
+
+    fn post(id: &str, days_old: i64, read: bool) -> Post {
+        let published = Utc::now() - TimeDelta::days(days_old);
+        Post { read, ..test_post(id, "", published.timestamp()) }
+    }
+
+    /// Empty databases of every backend.
+    fn databases() -> Vec<Database> {
+        let storages: Vec<Box<dyn Storage>> = vec![
+            Box::new(SledStorage::temporary()),
+            #[cfg(feature = "sqlite")]
+            Box::new(SqliteStorage::in_memory()),
+        ];
+
+        storages.into_iter()
+            .map(|storage| Database { storage, _lock: None })
+            .collect()
+    }
+
+    #[test]
+    fn locks_the_data_dir() {
+        let dir = std::env::temp_dir()
+            .join(format!("nia-lock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let lock = Database::lock_data_dir(&dir).unwrap();
+        let pid = std::process::id();
+        assert!(matches!(Database::lock_data_dir(&dir),
+            Err(Error::Locked(_, Some(other))) if other == pid));
+
+        drop(lock);
+        assert!(Database::lock_data_dir(&dir).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prunes_old_read_posts() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+
+            db.save_posts(url, vec![
+                post("new", 0, true),
+                post("newer", 1, true),
+                post("old", 10, true),
+                post("old-unread", 20, false),
+                post("old-saved", 30, true),
+                Post { starred: true, ..post("old-starred", 40, true) },
+            ].into()).unwrap();
+
+            let keep = HashMap::from([(
+                url.to_string(),
+                HashSet::from([PostId(Arc::from("old-saved"))]),
+            )]);
+            let retention = RetentionSettings {
+                max_posts: 0,
+                max_age_days: 5,
+            };
+            assert_eq!(db.prune_feed(url, &retention, &keep).unwrap(), 1);
+
+            let retention = RetentionSettings {
+                max_posts: 1,
+                max_age_days: 0,
+            };
+            assert_eq!(db.prune_feed(url, &retention, &keep).unwrap(), 1);
+
+            let mut left: Vec<_> = db.load_feed(url).unwrap().as_ref().iter()
+                .map(|post| post.id.0.to_string())
+                .collect();
+            left.sort();
+            let kept = ["new", "old-saved", "old-starred", "old-unread"];
+            assert_eq!(left, kept);
+        }
+    }
+
+    #[test]
+    fn keeps_pruned_posts_out() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+            db.save_posts(url, vec![
+                post("new", 0, true),
+                post("old", 10, true),
+            ].into()).unwrap();
+
+            let retention = RetentionSettings {
+                max_posts: 0,
+                max_age_days: 5,
+            };
+            assert_eq!(db.prune_feed(url, &retention, &HashMap::new())
+                .unwrap(), 1);
+
+            let mut cfg = FeedConfig::single_feed("Feed",
+                Url::parse(url).unwrap(), Posts::default());
+            let id = cfg.feed_ids().next().unwrap();
+            let feed = cfg.get_feed_mut(&id).unwrap();
+            db.load_stored(url).unwrap().restore(feed);
+            assert_eq!(feed.posts.len(), 1);
+
+            // The feed still carries the pruned post.
+            let new = feed.merge_posts(vec![
+                post("new", 0, false),
+                post("old", 10, false),
+                post("newer", 0, false),
+            ].into());
+            assert_eq!(new.len(), 1);
+            assert_eq!(feed.posts.len(), 2);
+
+            // Once the feed stops carrying it, it's forgotten.
+            let gone = feed.trim_pruned(&vec![post("new", 0, false)].into());
+            assert_eq!(gone, vec![PostId(Arc::from("old"))]);
+            assert!(feed.pruned.is_empty());
+            db.forget_pruned(url, &gone).unwrap();
+            assert!(db.load_pruned(url).unwrap().is_empty());
+
+            db.remove_feed(url).unwrap();
+            assert!(db.load_pruned(url).unwrap().is_empty());
+        }
+    }
 
-        let posts = tree.scan_prefix(prefix)
-            .filter_map(|res| res.ok())
-            .filter_map(|(_, v)| postcard::from_bytes::<Post>(&v).ok())
-            .collect::<Vec<Post>>();
+    #[test]
+    fn marks_posts_read() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+            let starred = Post { starred: true, ..post("a", 0, false) };
+            db.save_posts(url, vec![starred, post("b", 0, false)].into())
+                .unwrap();
 
-        posts.into()
+            // Posts that aren't stored are skipped.
+            let ids = [PostId(Arc::from("a")), PostId(Arc::from("c"))];
+            db.mark_read(url, &ids, true).unwrap();
+
+            let posts = db.load_feed(url).unwrap();
+            let post = posts.get_by_id(&ids[0]).unwrap();
+            assert!(post.read && post.starred);
+            assert_eq!(posts.unread(), 1);
+            assert_eq!(posts.len(), 2);
+        }
+    }
+
+    #[test]
+    fn saves_views() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+            assert_eq!(db.load_view(url).unwrap(), FeedView::default());
+
+            let view = FeedView { unread_only: true, ..FeedView::default() };
+            db.save_view(url, &view).unwrap();
+            assert_eq!(db.load_view(url).unwrap(), view);
+        }
+    }
+
+    #[test]
+    fn saves_health() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+            assert_eq!(db.load_health(url).unwrap(), None);
+
+            let health = FeedHealth {
+                fetched: chrono::DateTime::from_timestamp(1000, 0).unwrap(),
+                duration_ms: 250,
+                status: Some(404),
+                error: Some("Not Found".into()),
+                failures: 3,
+                succeeded: None,
+            };
+            db.save_health(url, &health).unwrap();
+            assert_eq!(db.load_health(url).unwrap(), Some(health));
+
+            db.remove_feed(url).unwrap();
+            assert_eq!(db.load_health(url).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn saves_meta() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+            assert_eq!(db.load_meta(url).unwrap(), None);
+
+            let meta = FeedMeta {
+                description: Some("Posts about things".into()),
+                site: Some(Url::parse("https://example.com/").unwrap()),
+                updated: chrono::DateTime::from_timestamp(1000, 0),
+                icon: None,
+            };
+            db.save_meta(url, &meta).unwrap();
+            assert_eq!(db.load_meta(url).unwrap().as_deref(), Some(&meta));
+
+            db.remove_feed(url).unwrap();
+            assert_eq!(db.load_meta(url).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn archives_articles() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+            let other = "https://example.com/feed.xml.old";
+            db.save_posts(url,
+                vec![post("a", 0, true), post("b", 10, true)].into()).unwrap();
+
+            let a = PostId(Arc::from("a"));
+            let b = PostId(Arc::from("b"));
+            db.save_article(url, &a, "draft").unwrap();
+            db.save_article(url, &a, "A").unwrap();
+            db.save_article(url, &b, "B").unwrap();
+            db.save_article(other, &a, "other").unwrap();
+
+            let articles = db.load_articles(url).unwrap();
+            assert_eq!(articles.len(), 2);
+            assert_eq!(articles[&a].as_ref(), "A");
+
+            // The articles of the pruned posts go along with them.
+            let retention = RetentionSettings {
+                max_posts: 0,
+                max_age_days: 5,
+            };
+            let keep = HashMap::new();
+            assert_eq!(db.prune_feed(url, &retention, &keep).unwrap(), 1);
+            let articles = db.load_articles(url).unwrap();
+            assert_eq!(articles.keys().collect::<Vec<_>>(), [&a]);
+
+            db.remove_feed(url).unwrap();
+            assert!(db.load_articles(url).unwrap().is_empty());
+            assert_eq!(db.load_articles(other).unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn marks_media_played() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+            let other = "https://example.com/feed.xml.old";
+            let media = Url::parse("https://example.com/episode.mp3").unwrap();
+            assert!(db.load_played(url).unwrap().is_empty());
+
+            db.mark_played(url, &media).unwrap();
+            db.mark_played(url, &media).unwrap();
+            db.mark_played(other, &media).unwrap();
+            assert_eq!(db.load_played(url).unwrap(), HashSet::from([media]));
+
+            db.remove_feed(url).unwrap();
+            assert!(db.load_played(url).unwrap().is_empty());
+            assert_eq!(db.load_played(other).unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn marks_urls_opened() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+            let link = Url::parse("https://example.com/a?b=c").unwrap();
+            db.save_posts(url, vec![post("1", 0, false), post("2", 0, false)]
+                .into()).unwrap();
+            assert!(db.load_opened(url).unwrap().is_empty());
+
+            let id = |id: &str| PostId(Arc::from(id));
+            db.mark_opened(url, &id("1"), &link).unwrap();
+            db.mark_opened(url, &id("1"), &link).unwrap();
+            db.mark_opened(url, &id("2"), &link).unwrap();
+            let opened = db.load_opened(url).unwrap();
+            assert_eq!(opened.len(), 2);
+            assert_eq!(opened[&id("1")], HashSet::from([link]));
+
+            db.remove_feed(url).unwrap();
+            assert!(db.load_opened(url).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn logs_reads() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+            let other = "https://example.com/feed.xml.old";
+            let id = |id: &str| PostId(Arc::from(id));
+            let at = |secs| DateTime::from_timestamp(secs, 0).unwrap();
+            assert!(db.load_reads(url).unwrap().is_empty());
+
+            db.log_reads(url, &[id("2")], at(200)).unwrap();
+            db.log_reads(url, &[id("1"), id("3")], at(100)).unwrap();
+            db.log_reads(other, &[id("1")], at(100)).unwrap();
+            let reads = db.load_reads(url).unwrap();
+            let logged: Vec<_> = reads.iter()
+                .map(|read| (read.post_id.0.as_ref(), read.at.timestamp()))
+                .collect();
+            assert_eq!(logged, [("1", 100), ("3", 100), ("2", 200)]);
+
+            db.remove_feed(url).unwrap();
+            assert!(db.load_reads(url).unwrap().is_empty());
+            assert_eq!(db.load_reads(other).unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn saves_sessions() {
+        for db in databases() {
+            assert_eq!(db.load_session().unwrap(), Session::default());
+
+            let feed = "https://example.com/feed.xml".to_string();
+            let session = Session { pages: vec![
+                PageSession::Main { feed: Some(feed.clone()), offset: 3 },
+                PageSession::Feed {
+                    feed: feed.clone(),
+                    post: Some(PostId(Arc::from("1"))),
+                    offset: 12,
+                },
+                PageSession::Post { feed, post: PostId(Arc::from("1")) },
+            ]};
+            db.save_session(&session).unwrap();
+            db.save_session(&session).unwrap();
+            assert_eq!(db.load_session().unwrap(), session);
+        }
+    }
+
+    #[test]
+    fn finds_orphaned_feeds() {
+        for db in databases() {
+            let kept = "https://example.com/feed.xml";
+            let removed = "https://example.com/feed.xml.old";
+
+            db.save_posts(kept, post("a", 0, false).into()).unwrap();
+            db.save_posts(removed,
+                vec![post("a", 0, false), post("b", 0, true)].into()).unwrap();
+
+            let url = url::Url::parse(kept).unwrap();
+            let cfg = FeedConfig::single_feed("kept", url, Posts::new());
+            let orphaned = db.orphaned_feeds(&cfg).unwrap();
+            assert_eq!(orphaned, [(removed.to_string(), 2)]);
+
+            assert_eq!(db.remove_feed(removed).unwrap().len(), 2);
+            assert!(db.orphaned_feeds(&cfg).unwrap().is_empty());
+            assert_eq!(db.load_feed(kept).unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn saves_remote_states() {
+        for db in databases() {
+            let mut state = db.remote_state("fever").unwrap();
+            assert_eq!(state.since_id, 0);
+
+            state.since_id = 7;
+            state.items.insert(7, ("https://example.com/feed.xml".into(),
+                PostId(Arc::from("a"))));
+            state.saved.insert(7);
+            db.save_remote_state("fever", &state).unwrap();
+
+            assert_eq!(db.remote_state("fever").unwrap().since_id, 7);
+            let saved = db.saved_posts().unwrap();
+            assert!(saved["https://example.com/feed.xml"]
+                .contains(&PostId(Arc::from("a"))));
+        }
+    }
+
+    #[test]
+    fn moves_feeds() {
+        for db in databases() {
+            let old = "http://example.com/feed.xml";
+            let new = "https://example.com/feed.xml";
+            let id = PostId(Arc::from("a"));
+            let at = chrono::DateTime::from_timestamp(1000, 0).unwrap();
+            let view = FeedView { unread_only: true, ..FeedView::default() };
+            let link = Url::parse("https://example.com/a").unwrap();
+            db.save_posts(old, vec![post("a", 0, true)].into()).unwrap();
+            db.save_view(old, &view).unwrap();
+            db.save_article(old, &id, "Text").unwrap();
+            db.mark_opened(old, &id, &link).unwrap();
+            db.log_reads(old, std::slice::from_ref(&id), at).unwrap();
+
+            db.move_feed(old, new).unwrap();
+            assert!(db.load_feed(old).unwrap().is_empty());
+            assert_eq!(db.load_view(old).unwrap(), FeedView::default());
+            assert!(db.load_reads(old).unwrap().is_empty());
+
+            assert!(db.load_feed(new).unwrap().get_by_id(&id).is_some());
+            assert_eq!(db.load_view(new).unwrap(), view);
+            assert_eq!(db.load_articles(new).unwrap()[&id].as_ref(), "Text");
+            assert!(db.load_opened(new).unwrap()[&id].contains(&link));
+            let reads = db.load_reads(new).unwrap();
+            assert_eq!(reads.len(), 1);
+            assert_eq!((&reads[0].post_id, reads[0].at), (&id, at));
+        }
+    }
+
+    #[test]
+    fn migrates_post_ids() {
+        for db in databases() {
+            let url = "https://example.com/feed.xml";
+            let link = Url::parse("https://example.com/post").unwrap();
+            let at = chrono::DateTime::from_timestamp(1000, 0).unwrap();
+            let titled = |title: &str, read| Post {
+                id: PostId::from_title(at, title),
+                title: Arc::from(title),
+                urls: vec![link.clone()],
+                published: at,
+                ..post("", 1, read)
+            };
+            // The edited title made a second post.
+            let old = titled("Tpyo", true);
+            let edited = titled("Typo", false);
+            // The feed gives a numeric ID, which looks like a hash.
+            let numeric = Post {
+                id: PostId(Arc::from("1234567890")),
+                urls: vec![Url::parse("https://example.com/other").unwrap()],
+                ..post("", 2, true)
+            };
+            // A remote gave its link as the ID.
+            let synced_link = Url::parse("https://example.com/synced")
+                .unwrap();
+            let synced = Post {
+                id: PostId(Arc::from(synced_link.as_str())),
+                urls: vec![synced_link.clone()],
+                ..post("", 3, true)
+            };
+            db.save_posts(url, vec![
+                old.clone(), edited, numeric.clone(), synced,
+                post("a", 0, false),
+            ].into()).unwrap();
+            db.save_article(url, &old.id, "Text").unwrap();
+            db.log_reads(url, std::slice::from_ref(&old.id), at).unwrap();
+
+            assert_eq!(db.migrate_post_ids(url).unwrap(), 3);
+            assert_eq!(db.migrate_post_ids(url).unwrap(), 0);
+
+            let id = PostId::from_link(&link).unwrap();
+            let posts = db.load_feed(url).unwrap();
+            assert_eq!(posts.len(), 4);
+            let synced_id = PostId::from_link(&synced_link).unwrap();
+            assert!(posts.get_by_id(&synced_id).unwrap().read);
+            assert!(posts.get_by_id(&numeric.id).unwrap().read);
+            assert!(posts.get_by_id(&id).unwrap().read);
+            assert!(posts.get_by_id(&old.id).is_none());
+            assert_eq!(db.load_articles(url).unwrap()[&id].as_ref(), "Text");
+            assert_eq!(db.load_reads(url).unwrap()[0].post_id, id);
+        }
+    }
+
+    #[test]
+    fn coalesces_saves_of_the_same_feed() {
+        let save = |url: &str, posts: Vec<Post>| DatabaseRequest::SavePosts {
+            feed_url: Arc::from(url), posts: posts.into(),
+        };
+        let a = "https://a.example/feed.xml";
+        let b = "https://b.example/feed.xml";
+        let requests = vec![
+            save(a, vec![post("1", 0, false)]),
+            save(b, vec![post("1", 0, false)]),
+            save(a, vec![post("1", 0, true), post("2", 0, false)]),
+            DatabaseRequest::MarkRead {
+                feed_url: Arc::from(a),
+                post_ids: vec![PostId(Arc::from("2"))],
+                read: true,
+            },
+            // Saving it again after marking posts read would undo that.
+            save(a, vec![post("2", 0, false)]),
+        ];
+
+        let saves: Vec<_> = coalesce(requests).into_iter()
+            .map(|request| match request {
+                DatabaseRequest::SavePosts { feed_url, posts } => {
+                    let mut posts: Vec<_> = posts.as_ref().iter()
+                        .map(|post| (post.id.0.to_string(), post.read))
+                        .collect();
+                    posts.sort();
+                    Some((feed_url.to_string(), posts))
+                },
+                _ => None,
+            })
+            .collect();
+        let post = |id: &str, read| (id.to_string(), read);
+        assert_eq!(saves, [
+            Some((a.into(), vec![post("1", true), post("2", false)])),
+            Some((b.into(), vec![post("1", false)])),
+            None,
+            Some((a.into(), vec![post("2", false)])),
+        ]);
     }
 }
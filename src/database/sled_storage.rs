@@ -0,0 +1,972 @@
+//! Storage in a sled database.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use serde::{Serialize, de::DeserializeOwned};
+use tracing::{info, warn};
+use chrono::{DateTime, Utc};
+use url::Url;
+use crate::config::{
+    FeedHealth, FeedMeta, LegacyHealth, LegacyPost, Post, PostId, FeedView,
+    Posts, Read, UncategorizedPost, UncommentedPost, UneditedPost,
+    UnstarredPost, UnsummarizedPost,
+};
+use crate::database::{RemoteState, Session, Storage};
+use crate::error::{Error, Result};
+
+/// The version of the layout of the stored data.
+///
+/// Whenever the serialization of anything stored changes, bump it and add the
+/// migration from the previous version to `MIGRATIONS`.
+const SCHEMA_VERSION: u32 = 9;
+
+/// A migration of the stored data from one schema version to the next.
+type Migration = fn(&SledStorage) -> Result<()>;
+
+/// Migrations of the stored data from each version to the next, starting
+/// with the one from version 0.
+const MIGRATIONS: &[Migration] = &[
+    SledStorage::migrate_arrival_times,
+    // Posts can be starred.
+    SledStorage::convert_posts::<UnstarredPost, UncommentedPost>,
+    // Posts link to their comments.
+    SledStorage::convert_posts::<UncommentedPost, UncategorizedPost>,
+    // Posts have authors and categories.
+    SledStorage::convert_posts::<UncategorizedPost, UnsummarizedPost>,
+    // Posts have summaries.
+    SledStorage::convert_posts::<UnsummarizedPost, UneditedPost>,
+    // The health of feeds says when they were last downloaded successfully.
+    SledStorage::convert_health,
+    // Posts know when they were updated and whether they've been edited.
+    SledStorage::convert_posts::<UneditedPost, Post>,
+    // The posts of each feed have a tree of their own.
+    SledStorage::split_posts,
+    // The posts without IDs are told by their links instead of their titles.
+    SledStorage::list_legacy_id_feeds,
+];
+
+const _: () = assert!(MIGRATIONS.len() == SCHEMA_VERSION as usize);
+
+/// Posts, remote states and views stored in sled trees.
+///
+/// The posts of each feed are stored under their IDs in a tree of their own,
+/// named after the hash of the feed URL, so that the posts of a feed are read
+/// without scanning those of the others and removed by dropping the tree. The
+/// "feeds" tree maps the feed URLs to the names of their trees. The archived
+/// articles of the posts are stored in the "articles" tree under the feed URL
+/// and the post ID, separated by a zero byte. The played media are the keys of
+/// the "played" tree, the feed URL and the URL of the media separated by a zero
+/// byte. The opened URLs are the keys of the "opened" tree, the feed URL, the
+/// post ID and the URL separated by zero bytes. The reads are the keys of the
+/// "reads" tree, the feed URL and a zero byte followed by the big-endian
+/// seconds since the epoch and the post ID, so that they're sorted by time. The
+/// IDs of the pruned posts are the keys of the "pruned" tree, made like those
+/// of the articles. The session is stored in the "session" tree and the URLs of
+/// the feeds whose post IDs are yet to be migrated are the keys of the
+/// "legacy_ids" tree.
+///
+/// The posts and the articles are compressed with zstd if the database is
+/// told to, as long as that makes them smaller. They're told apart from the
+/// uncompressed ones by the magic number of zstd, which neither a post nor
+/// UTF-8 text can start with, so the ones stored either way are read.
+///
+/// What's saved while reading and refreshing isn't flushed right away. sled
+/// flushes it in the background and the database thread once it's idle, so
+/// that refreshing all feeds doesn't write to the disk for every one of them.
+pub struct SledStorage {
+    /// The internal sled database state.
+    db: sled::Db,
+
+    /// Whether the posts and the articles are compressed as they're saved.
+    compression: bool,
+}
+
+/// The magic number zstd frames start with.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+impl SledStorage {
+    /// Open the database in `data_dir`, migrating the stored data to the
+    /// current schema.
+    pub fn open<P: AsRef<Path>>(data_dir: P) -> Result<Self> {
+        Self::with_db(sled::open(data_dir)?)
+    }
+
+    /// Open a copy of the database in `data_dir`, so that the original is
+    /// never written to. The copy is removed when it's dropped.
+    pub fn open_copy<P: AsRef<Path>>(data_dir: P) -> Result<Self> {
+        // Several copies can be open at once.
+        static COPIES: AtomicUsize = AtomicUsize::new(0);
+        let copy = std::env::temp_dir().join(format!("nia-read-only-{}-{}",
+            std::process::id(), COPIES.fetch_add(1, Ordering::Relaxed)));
+
+        copy_dir(data_dir.as_ref(), &copy)?;
+        let db = sled::Config::new()
+            .path(copy)
+            .temporary(true)
+            .open()?;
+        Self::with_db(db)
+    }
+
+    /// Compress the posts and the articles saved from now on if
+    /// `compression` is set.
+    pub fn compressed(self, compression: bool) -> Self {
+        Self { compression, ..self }
+    }
+
+    /// Use the already opened `db`.
+    fn with_db(db: sled::Db) -> Result<Self> {
+        let storage = Self { db, compression: false };
+        storage.migrate().map_err(|err| Error::Database(format!(
+            "Failed to migrate the database: {}", err)))?;
+        Ok(storage)
+    }
+
+    /// Create a database that is removed when it's dropped.
+    #[cfg(test)]
+    pub fn temporary() -> Self {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        Self::with_db(db).unwrap()
+    }
+
+    /// Open (or create) a tree.
+    fn tree(&self, name: &str) -> Result<sled::Tree> {
+        Ok(self.db.open_tree(name)?)
+    }
+
+    /// Compress the `bytes` to be stored if the database compresses them and
+    /// they get smaller.
+    fn compress(&self, bytes: Vec<u8>) -> Result<Vec<u8>> {
+        if !self.compression {
+            return Ok(bytes);
+        }
+        let compressed = zstd::encode_all(bytes.as_slice(),
+            zstd::DEFAULT_COMPRESSION_LEVEL)?;
+        Ok(if compressed.len() < bytes.len() { compressed } else { bytes })
+    }
+
+    /// Encode the `post` to be stored.
+    fn encode_post(&self, post: &Post) -> Result<Vec<u8>> {
+        self.compress(postcard::to_stdvec(post)?)
+    }
+
+    /// Get the schema version of the stored data.
+    ///
+    /// Databases created before the version was stored are at version 0.
+    fn schema_version(&self) -> Result<u32> {
+        Ok(self.tree("meta")?
+            .get("schema_version")?
+            .and_then(|v| v.as_ref().try_into().ok())
+            .map(u32::from_be_bytes)
+            .unwrap_or(0))
+    }
+
+    /// Run the migrations that haven't been run yet.
+    ///
+    /// The version is stored after each migration, so an interrupted one is
+    /// run again the next time.
+    fn migrate(&self) -> Result<()> {
+        let version = self.schema_version()?;
+        if version > SCHEMA_VERSION {
+            return Err(Error::Database(format!("The database has schema \
+                version {}, but this version of nia only knows up to {}",
+                version, SCHEMA_VERSION)));
+        }
+
+        let meta = self.tree("meta")?;
+        for (from, migration) in MIGRATIONS.iter()
+            .enumerate()
+            .skip(version as usize)
+        {
+            migration(self)?;
+
+            let to = from as u32 + 1;
+            meta.insert("schema_version", &to.to_be_bytes())?;
+            meta.flush()?;
+            info!("Migrated the database to schema version {}", to);
+        }
+
+        Ok(())
+    }
+
+    /// Migration to version 1: posts gained the arrival time.
+    ///
+    /// Posts that can't be decoded at all are moved to the "undecodable" tree
+    /// instead of being dropped.
+    fn migrate_arrival_times(&self) -> Result<()> {
+        let tree = self.tree("posts")?;
+        let undecodable = self.tree("undecodable")?;
+
+        let mut moved = 0;
+        for res in tree.iter() {
+            let (key, value) = res?;
+            if postcard::from_bytes::<UnstarredPost>(&value).is_ok() {
+                continue;
+            }
+
+            let result = match postcard::from_bytes::<LegacyPost>(&value) {
+                Ok(post) => {
+                    let post = UnstarredPost::from(post);
+                    let value = postcard::to_stdvec(&post)?;
+                    tree.insert(key, value).map(|_| ())
+                },
+                Err(_) => {
+                    moved += 1;
+                    undecodable.insert(&key, value)
+                        .and_then(|_| tree.remove(&key))
+                        .map(|_| ())
+                },
+            };
+            result?;
+        }
+
+        if moved > 0 {
+            warn!("Moved {} posts that couldn't be read to the \
+                \"undecodable\" tree of the database", moved);
+        }
+
+        undecodable.flush()?;
+        tree.flush()?;
+        Ok(())
+    }
+
+    /// Migrate the health of the feeds to the layout with the time of the
+    /// last successful download.
+    ///
+    /// The health that can't be decoded is dropped, as it would be when
+    /// it's loaded.
+    fn convert_health(&self) -> Result<()> {
+        let tree = self.tree("health")?;
+
+        for res in tree.iter() {
+            let (key, value) = res?;
+            match postcard::from_bytes::<LegacyHealth>(&value) {
+                Ok(health) => {
+                    let value = postcard::to_stdvec(&FeedHealth::from(health))?;
+                    tree.insert(key, value)?;
+                },
+                Err(_) => {
+                    tree.remove(key)?;
+                },
+            }
+        }
+
+        tree.flush()?;
+        Ok(())
+    }
+
+    /// Migrate the posts from the "posts" tree of all feeds into the trees
+    /// of their feeds.
+    fn split_posts(&self) -> Result<()> {
+        let tree = self.tree("posts")?;
+
+        for res in tree.iter() {
+            let (key, value) = res?;
+            let Some(url) = Self::key_feed_url(&key) else {
+                continue;
+            };
+            let id = &key[url.len() + 1..];
+            self.feed_tree(url)?.insert(id, value)?;
+        }
+
+        self.db.drop_tree("posts")?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// List the stored feeds in the "legacy_ids" tree, so that the IDs of
+    /// their posts are migrated to those made from the links once.
+    fn list_legacy_id_feeds(&self) -> Result<()> {
+        let tree = self.tree("legacy_ids")?;
+        for (url, _) in self.feed_trees()? {
+            tree.insert(url, &[])?;
+        }
+
+        tree.flush()?;
+        Ok(())
+    }
+
+    /// Migrate the posts stored as `Old` to the `New` layout.
+    ///
+    /// The posts are in the "posts" tree of all feeds, as they were before
+    /// they were split into the trees of their feeds.
+    fn convert_posts<Old, New>(&self) -> Result<()>
+    where
+        Old: DeserializeOwned,
+        New: Serialize + From<Old>,
+    {
+        let tree = self.tree("posts")?;
+
+        for res in tree.iter() {
+            let (key, value) = res?;
+            let post = postcard::from_bytes::<Old>(&value)?;
+            let value = postcard::to_stdvec(&New::from(post))?;
+            tree.insert(key, value)?;
+        }
+
+        tree.flush()?;
+        Ok(())
+    }
+
+    /// Get the name of the tree of the posts of the feed at `feed_url`.
+    fn feed_tree_name(feed_url: &str) -> String {
+        format!("posts/{}", crate::hash(feed_url))
+    }
+
+    /// Open the tree of the posts of the feed at `feed_url`, creating it if
+    /// the feed has none yet.
+    fn feed_tree(&self, feed_url: &str) -> Result<sled::Tree> {
+        let name = Self::feed_tree_name(feed_url);
+        let feeds = self.tree("feeds")?;
+        if !feeds.contains_key(feed_url)? {
+            feeds.insert(feed_url, name.as_bytes())?;
+        }
+        self.tree(&name)
+    }
+
+    /// Open the tree of the posts of the feed at `feed_url`, if it has one.
+    fn existing_feed_tree(&self, feed_url: &str)
+        -> Result<Option<sled::Tree>>
+    {
+        match self.tree("feeds")?.get(feed_url)? {
+            Some(name) => Ok(Some(self.db.open_tree(name)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the URLs of all feeds with posts along with the trees of their
+    /// posts, sorted by URL.
+    fn feed_trees(&self) -> Result<Vec<(String, sled::Tree)>> {
+        let mut trees = Vec::new();
+        for res in self.tree("feeds")?.iter() {
+            let (url, name) = res?;
+            let url = String::from_utf8_lossy(&url).into_owned();
+            trees.push((url, self.db.open_tree(name)?));
+        }
+
+        Ok(trees)
+    }
+
+    /// Make a sled key for a post.
+    fn make_key(feed_url: &str, post_id: &PostId) -> Vec<u8> {
+        let mut key = Vec::with_capacity(
+            feed_url.len() + post_id.0.len() + 1);
+
+        // Feed URL bytes.
+        key.extend_from_slice(feed_url.as_bytes());
+
+        // Separator to avoid collisions
+        key.push(0);
+
+        // Post ID.
+        key.extend_from_slice(post_id.0.as_bytes());
+
+        key
+    }
+
+    /// Get the prefix for scanning all posts of a feed.
+    fn feed_prefix(feed_url: &str) -> Vec<u8> {
+        let mut prefix = feed_url.as_bytes().to_vec();
+        prefix.push(0);
+        prefix
+    }
+
+    /// Get the feed URL from a post key.
+    fn key_feed_url(key: &[u8]) -> Option<&str> {
+        key.split(|&b| b == 0).next()
+            .and_then(|url| std::str::from_utf8(url).ok())
+    }
+}
+
+/// Copy the sled files in the directory `from` into the new directory `to`.
+///
+/// The databases of the other backends are left out.
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with("nia.sqlite") {
+            continue;
+        }
+
+        let to = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to)?;
+        } else {
+            fs::copy(entry.path(), to)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Decode a post stored in the database.
+///
+/// The migrations make sure that all posts are stored in the current layout.
+fn decode_post(bytes: &[u8]) -> Option<Post> {
+    postcard::from_bytes::<Post>(&decompress(bytes)?).ok()
+}
+
+/// Decompress the stored `bytes` if they have been compressed.
+fn decompress(bytes: &[u8]) -> Option<Cow<'_, [u8]>> {
+    if !bytes.starts_with(&ZSTD_MAGIC) {
+        return Some(Cow::Borrowed(bytes));
+    }
+    zstd::decode_all(bytes).ok().map(Cow::Owned)
+}
+
+impl Storage for SledStorage {
+    fn load_feed(&self, feed_url: &str) -> Result<Posts> {
+        let Some(tree) = self.existing_feed_tree(feed_url)? else {
+            return Ok(Posts::new());
+        };
+        let mut posts = Vec::new();
+        for res in tree.iter() {
+            let (_, value) = res?;
+            posts.extend(decode_post(&value));
+        }
+
+        Ok(posts.into())
+    }
+
+    fn save_posts(&self, feed_url: &str, posts: &Posts) -> Result<()> {
+        if posts.is_empty() {
+            return Ok(());
+        }
+        let mut batch = sled::Batch::default();
+        for post in posts.as_ref().iter() {
+            batch.insert(post.id.0.as_bytes(), self.encode_post(post)?);
+        }
+
+        self.feed_tree(feed_url)?.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn mark_read(&self, feed_url: &str, post_ids: &[PostId], read: bool)
+        -> Result<()>
+    {
+        let Some(tree) = self.existing_feed_tree(feed_url)? else {
+            return Ok(());
+        };
+
+        let mut batch = sled::Batch::default();
+        for post_id in post_ids {
+            let key = post_id.0.as_bytes();
+            let Some(mut post) = tree.get(key)?.and_then(|v| decode_post(&v))
+            else {
+                continue;
+            };
+
+            post.read = read;
+            batch.insert(key, self.encode_post(&post)?);
+        }
+
+        tree.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn remove_posts(&self, feed_url: &str, post_ids: &[PostId]) -> Result<()> {
+        let Some(tree) = self.existing_feed_tree(feed_url)? else {
+            return Ok(());
+        };
+
+        for post_id in post_ids {
+            tree.remove(post_id.0.as_bytes())?;
+        }
+
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn remove_feed_posts(&self, feed_url: &str) -> Result<()> {
+        let feeds = self.tree("feeds")?;
+        let Some(name) = feeds.remove(feed_url)? else {
+            return Ok(());
+        };
+
+        self.db.drop_tree(name)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn feeds(&self) -> Result<Vec<(String, usize)>> {
+        Ok(self.feed_trees()?.into_iter()
+            .map(|(url, tree)| (url, tree.len()))
+            .filter(|(_, count)| *count > 0)
+            .collect())
+    }
+
+    fn all_posts(&self) -> Result<Vec<(String, Post)>> {
+        let mut posts = Vec::new();
+        for (url, tree) in self.feed_trees()? {
+            for res in tree.iter() {
+                let (_, value) = res?;
+                if let Some(post) = decode_post(&value) {
+                    posts.push((url.clone(), post));
+                }
+            }
+        }
+
+        Ok(posts)
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<(String, Post)>> {
+        // There's no index, so just look through all of the titles.
+        let query = query.to_lowercase();
+        Ok(self.all_posts()?.into_iter()
+            .filter(|(_, post)| post.title.to_lowercase().contains(&query))
+            .collect())
+    }
+
+    fn remote_state(&self, name: &str) -> Result<RemoteState> {
+        Ok(self.tree("remotes")?
+            .get(name)?
+            .and_then(|v| postcard::from_bytes(&v).ok())
+            .unwrap_or_default())
+    }
+
+    fn remote_states(&self) -> Result<Vec<RemoteState>> {
+        let mut states = Vec::new();
+        for res in self.tree("remotes")?.iter() {
+            let (_, value) = res?;
+            states.extend(postcard::from_bytes(&value).ok());
+        }
+
+        Ok(states)
+    }
+
+    fn save_remote_state(&self, name: &str, state: &RemoteState)
+        -> Result<()>
+    {
+        let tree = self.tree("remotes")?;
+        tree.insert(name, postcard::to_stdvec(state)?)?;
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn load_view(&self, feed_url: &str) -> Result<FeedView> {
+        Ok(self.tree("views")?
+            .get(feed_url)?
+            .and_then(|v| postcard::from_bytes(&v).ok())
+            .unwrap_or_default())
+    }
+
+    fn save_view(&self, feed_url: &str, view: &FeedView) -> Result<()> {
+        let tree = self.tree("views")?;
+        tree.insert(feed_url, postcard::to_stdvec(view)?)?;
+        Ok(())
+    }
+
+    fn remove_view(&self, feed_url: &str) -> Result<()> {
+        let tree = self.tree("views")?;
+        tree.remove(feed_url)?;
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn load_health(&self, feed_url: &str) -> Result<Option<FeedHealth>> {
+        Ok(self.tree("health")?
+            .get(feed_url)?
+            .and_then(|v| postcard::from_bytes(&v).ok()))
+    }
+
+    fn save_health(&self, feed_url: &str, health: &FeedHealth) -> Result<()> {
+        let tree = self.tree("health")?;
+        tree.insert(feed_url, postcard::to_stdvec(health)?)?;
+        Ok(())
+    }
+
+    fn remove_health(&self, feed_url: &str) -> Result<()> {
+        let tree = self.tree("health")?;
+        tree.remove(feed_url)?;
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn load_meta(&self, feed_url: &str) -> Result<Option<FeedMeta>> {
+        Ok(self.tree("feed_meta")?
+            .get(feed_url)?
+            .and_then(|v| postcard::from_bytes(&v).ok()))
+    }
+
+    fn save_meta(&self, feed_url: &str, meta: &FeedMeta) -> Result<()> {
+        let tree = self.tree("feed_meta")?;
+        tree.insert(feed_url, postcard::to_stdvec(meta)?)?;
+        Ok(())
+    }
+
+    fn remove_meta(&self, feed_url: &str) -> Result<()> {
+        let tree = self.tree("feed_meta")?;
+        tree.remove(feed_url)?;
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn load_articles(&self, feed_url: &str)
+        -> Result<HashMap<PostId, Arc<str>>>
+    {
+        let prefix = Self::feed_prefix(feed_url);
+        let mut articles = HashMap::new();
+        for res in self.tree("articles")?.scan_prefix(&prefix) {
+            let (key, value) = res?;
+            let id = String::from_utf8_lossy(&key[prefix.len()..]);
+            let Some(text) = decompress(&value) else {
+                continue;
+            };
+            let text = String::from_utf8_lossy(&text);
+            articles.insert(PostId(Arc::from(id)), Arc::from(text));
+        }
+
+        Ok(articles)
+    }
+
+    fn save_article(&self, feed_url: &str, post_id: &PostId, text: &str)
+        -> Result<()>
+    {
+        let tree = self.tree("articles")?;
+        let text = self.compress(text.as_bytes().to_vec())?;
+        tree.insert(Self::make_key(feed_url, post_id), text)?;
+        Ok(())
+    }
+
+    fn remove_articles(&self, feed_url: &str, post_ids: &[PostId])
+        -> Result<()>
+    {
+        let tree = self.tree("articles")?;
+
+        for post_id in post_ids {
+            tree.remove(Self::make_key(feed_url, post_id))?;
+        }
+
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn load_played(&self, feed_url: &str) -> Result<HashSet<Url>> {
+        let prefix = Self::feed_prefix(feed_url);
+        let mut played = HashSet::new();
+        for key in self.tree("played")?.scan_prefix(&prefix).keys() {
+            let key = key?;
+            let url = std::str::from_utf8(&key[prefix.len()..]).ok()
+                .and_then(|url| Url::parse(url).ok());
+            played.extend(url);
+        }
+
+        Ok(played)
+    }
+
+    fn mark_played(&self, feed_url: &str, url: &Url) -> Result<()> {
+        let mut key = Self::feed_prefix(feed_url);
+        key.extend_from_slice(url.as_str().as_bytes());
+
+        let tree = self.tree("played")?;
+        tree.insert(key, &[])?;
+        Ok(())
+    }
+
+    fn remove_played(&self, feed_url: &str) -> Result<()> {
+        let tree = self.tree("played")?;
+        for key in tree.scan_prefix(Self::feed_prefix(feed_url)).keys() {
+            tree.remove(key?)?;
+        }
+
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn load_opened(&self, feed_url: &str)
+        -> Result<HashMap<PostId, HashSet<Url>>>
+    {
+        let prefix = Self::feed_prefix(feed_url);
+        let mut opened: HashMap<PostId, HashSet<Url>> = HashMap::new();
+        for key in self.tree("opened")?.scan_prefix(&prefix).keys() {
+            let key = key?;
+            let Some((id, url)) = std::str::from_utf8(&key[prefix.len()..])
+                .ok()
+                .and_then(|rest| rest.split_once('\0'))
+            else {
+                continue;
+            };
+            if let Ok(url) = Url::parse(url) {
+                opened.entry(PostId(Arc::from(id))).or_default().insert(url);
+            }
+        }
+
+        Ok(opened)
+    }
+
+    fn mark_opened(&self, feed_url: &str, post_id: &PostId, url: &Url)
+        -> Result<()>
+    {
+        let mut key = Self::make_key(feed_url, post_id);
+        key.push(0);
+        key.extend_from_slice(url.as_str().as_bytes());
+
+        let tree = self.tree("opened")?;
+        tree.insert(key, &[])?;
+        Ok(())
+    }
+
+    fn remove_opened(&self, feed_url: &str, post_ids: &[PostId])
+        -> Result<()>
+    {
+        let tree = self.tree("opened")?;
+        for post_id in post_ids {
+            let mut prefix = Self::make_key(feed_url, post_id);
+            prefix.push(0);
+            for key in tree.scan_prefix(prefix).keys() {
+                tree.remove(key?)?;
+            }
+        }
+
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn load_reads(&self, feed_url: &str) -> Result<Vec<Read>> {
+        let prefix = Self::feed_prefix(feed_url);
+        let mut reads = Vec::new();
+        for key in self.tree("reads")?.scan_prefix(&prefix).keys() {
+            let key = key?;
+            let Some((secs, id)) = key[prefix.len()..].split_first_chunk()
+            else {
+                continue;
+            };
+            let secs = i64::from_be_bytes(*secs);
+            let id = String::from_utf8_lossy(id);
+            reads.push(Read {
+                post_id: PostId(Arc::from(id)),
+                at: DateTime::from_timestamp(secs, 0).unwrap_or_default(),
+            });
+        }
+
+        Ok(reads)
+    }
+
+    fn log_reads(&self, feed_url: &str, post_ids: &[PostId],
+        at: DateTime<Utc>) -> Result<()>
+    {
+        let mut batch = sled::Batch::default();
+        for post_id in post_ids {
+            let mut key = Self::feed_prefix(feed_url);
+            key.extend_from_slice(&at.timestamp().to_be_bytes());
+            key.extend_from_slice(post_id.0.as_bytes());
+            batch.insert(key, &[]);
+        }
+
+        self.tree("reads")?.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn remove_reads(&self, feed_url: &str) -> Result<()> {
+        let tree = self.tree("reads")?;
+        for key in tree.scan_prefix(Self::feed_prefix(feed_url)).keys() {
+            tree.remove(key?)?;
+        }
+
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn load_pruned(&self, feed_url: &str) -> Result<HashSet<PostId>> {
+        let prefix = Self::feed_prefix(feed_url);
+        let mut pruned = HashSet::new();
+        for key in self.tree("pruned")?.scan_prefix(&prefix).keys() {
+            let key = key?;
+            let id = String::from_utf8_lossy(&key[prefix.len()..]);
+            pruned.insert(PostId(Arc::from(id)));
+        }
+
+        Ok(pruned)
+    }
+
+    fn mark_pruned(&self, feed_url: &str, post_ids: &[PostId]) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for post_id in post_ids {
+            batch.insert(Self::make_key(feed_url, post_id), &[]);
+        }
+
+        self.tree("pruned")?.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn unmark_pruned(&self, feed_url: &str, post_ids: &[PostId])
+        -> Result<()>
+    {
+        let mut batch = sled::Batch::default();
+        for post_id in post_ids {
+            batch.remove(Self::make_key(feed_url, post_id));
+        }
+
+        self.tree("pruned")?.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn remove_pruned(&self, feed_url: &str) -> Result<()> {
+        let tree = self.tree("pruned")?;
+        for key in tree.scan_prefix(Self::feed_prefix(feed_url)).keys() {
+            tree.remove(key?)?;
+        }
+
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn legacy_id_feeds(&self) -> Result<Vec<String>> {
+        self.tree("legacy_ids")?.iter().keys()
+            .map(|url| Ok(String::from_utf8_lossy(&url?).into_owned()))
+            .collect()
+    }
+
+    fn remove_legacy_id_feed(&self, feed_url: &str) -> Result<()> {
+        let tree = self.tree("legacy_ids")?;
+        tree.remove(feed_url)?;
+        tree.flush()?;
+        Ok(())
+    }
+
+    fn load_session(&self) -> Result<Session> {
+        Ok(self.tree("session")?
+            .get("session")?
+            .and_then(|v| postcard::from_bytes(&v).ok())
+            .unwrap_or_default())
+    }
+
+    fn save_session(&self, session: &Session) -> Result<()> {
+        let tree = self.tree("session")?;
+        tree.insert("session", postcard::to_stdvec(session)?)?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::config::test_post;
+
+    #[test]
+    fn migrates_posts_without_arrival_times() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let storage = SledStorage { db, compression: false };
+        let tree = storage.tree("posts").unwrap();
+
+        // Laid out like the fields of a post without the arrival time.
+        let urls = vec!["https://example.com/a"];
+        let legacy = ("1", "Old", urls, 1_700_000_000i64, true);
+        let feed = "https://example.com/feed.xml";
+        let key = SledStorage::make_key(feed, &PostId(Arc::from("1")));
+        tree.insert(&key, postcard::to_stdvec(&legacy).unwrap()).unwrap();
+        tree.insert(b"garbage", &[0xff][..]).unwrap();
+
+        storage.migrate().unwrap();
+        assert_eq!(storage.schema_version().unwrap(), SCHEMA_VERSION);
+
+        let posts = storage.load_feed(feed).unwrap();
+        let post = &posts.as_ref()[0];
+        assert_eq!(post.arrived, post.published);
+        assert!(post.read);
+        assert!(!post.starred);
+
+        let undecodable = storage.tree("undecodable").unwrap();
+        assert!(undecodable.contains_key(b"garbage").unwrap());
+
+        // The posts have moved to the tree of their feed.
+        assert_eq!(storage.all_posts().unwrap().len(), 1);
+        let posts = sled::IVec::from("posts");
+        assert!(!storage.db.tree_names().contains(&posts));
+        assert_eq!(storage.legacy_id_feeds().unwrap(), [feed]);
+
+        // Running the migrations again doesn't do anything.
+        storage.migrate().unwrap();
+        assert_eq!(storage.load_feed(feed).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn migrates_health_without_successes() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let storage = SledStorage { db, compression: false };
+        storage.tree("meta").unwrap()
+            .insert("schema_version", &5u32.to_be_bytes()).unwrap();
+
+        // Laid out like the fields of the health before the successes.
+        let tree = storage.tree("health").unwrap();
+        let ok = (1_700_000_000i64, 100u64, Some(200u16), None::<String>, 0u32);
+        let failing = (1_700_000_000i64, 100u64, None::<u16>,
+            Some("Timed out"), 3u32);
+        tree.insert("ok", postcard::to_stdvec(&ok).unwrap()).unwrap();
+        tree.insert("failing", postcard::to_stdvec(&failing).unwrap())
+            .unwrap();
+
+        storage.migrate().unwrap();
+        let ok = storage.load_health("ok").unwrap().unwrap();
+        assert_eq!(ok.succeeded, Some(ok.fetched));
+        let failing = storage.load_health("failing").unwrap().unwrap();
+        assert_eq!(failing.failures, 3);
+        assert_eq!(failing.succeeded, None);
+    }
+
+    #[test]
+    fn keeps_the_posts_of_feeds_apart() {
+        let storage = SledStorage::temporary();
+        let post = |id: &str| test_post(id, "", 0);
+        let a = "https://a.example/feed.xml";
+        let b = "https://b.example/feed.xml";
+        storage.save_posts(a, &vec![post("1"), post("2")].into()).unwrap();
+        storage.save_posts(b, &post("1").into()).unwrap();
+        assert_eq!(storage.feeds().unwrap(),
+            [(a.to_string(), 2), (b.to_string(), 1)]);
+
+        // Removing the posts of a feed drops its tree.
+        storage.remove_feed_posts(a).unwrap();
+        assert!(storage.load_feed(a).unwrap().is_empty());
+        assert_eq!(storage.load_feed(b).unwrap().len(), 1);
+        let tree = sled::IVec::from(SledStorage::feed_tree_name(a).as_str());
+        assert!(!storage.db.tree_names().contains(&tree));
+        assert_eq!(storage.feeds().unwrap(), [(b.to_string(), 1)]);
+    }
+
+    #[test]
+    fn reads_compressed_and_uncompressed_posts() {
+        let storage = SledStorage::temporary();
+        let text = "All work and no play makes Jack a dull boy. ".repeat(50);
+        let post = |id: &str| Post {
+            title: Arc::from(text.as_str()),
+            ..test_post(id, "", 0)
+        };
+        let url = "https://a.example/feed.xml";
+        let (old, new) = (PostId(Arc::from("old")), PostId(Arc::from("new")));
+        storage.save_posts(url, &post("old").into()).unwrap();
+        storage.save_article(url, &old, &text).unwrap();
+
+        let storage = storage.compressed(true);
+        storage.save_posts(url, &post("new").into()).unwrap();
+        storage.save_article(url, &new, &text).unwrap();
+        storage.mark_read(url, std::slice::from_ref(&old), true).unwrap();
+
+        // The posts saved again are compressed too.
+        let tree = storage.existing_feed_tree(url).unwrap().unwrap();
+        for id in ["old", "new"] {
+            let value = tree.get(id).unwrap().unwrap();
+            assert!(value.starts_with(&ZSTD_MAGIC));
+            assert!(value.len() < text.len());
+        }
+
+        let posts = storage.load_feed(url).unwrap();
+        assert_eq!(posts.len(), 2);
+        assert!(posts.get_by_id(&old).unwrap().read);
+        assert_eq!(posts.get_by_id(&new).unwrap().title.as_ref(), text);
+        let articles = storage.load_articles(url).unwrap();
+        assert_eq!(articles[&old].as_ref(), text);
+        assert_eq!(articles[&new].as_ref(), text);
+    }
+}
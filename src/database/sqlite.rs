@@ -0,0 +1,801 @@
+//! Storage in an SQLite database.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Row, params};
+use tracing::info;
+use url::Url;
+use crate::config::{
+    Post, PostId, FeedHealth, FeedMeta, FeedView, Posts, Read,
+};
+use crate::database::{RemoteState, Session, Storage};
+use crate::error::{Error, Result};
+
+/// Migrations of the schema from each version to the next, starting with the
+/// one from version 0, the empty database.
+///
+/// The schema version is stored in the `user_version` pragma.
+const MIGRATIONS: &[&str] = &["
+    CREATE TABLE posts (
+        rowid INTEGER PRIMARY KEY,
+        feed TEXT NOT NULL,
+        id TEXT NOT NULL,
+        title TEXT NOT NULL,
+        urls TEXT NOT NULL,
+        published INTEGER NOT NULL,
+        arrived INTEGER NOT NULL,
+        read INTEGER NOT NULL,
+        UNIQUE (feed, id)
+    );
+
+    CREATE VIRTUAL TABLE posts_fts USING fts5(
+        title, content = 'posts', content_rowid = 'rowid'
+    );
+
+    CREATE TRIGGER posts_insert AFTER INSERT ON posts BEGIN
+        INSERT INTO posts_fts (rowid, title) VALUES (new.rowid, new.title);
+    END;
+
+    CREATE TRIGGER posts_delete AFTER DELETE ON posts BEGIN
+        INSERT INTO posts_fts (posts_fts, rowid, title)
+            VALUES ('delete', old.rowid, old.title);
+    END;
+
+    CREATE TRIGGER posts_update AFTER UPDATE ON posts BEGIN
+        INSERT INTO posts_fts (posts_fts, rowid, title)
+            VALUES ('delete', old.rowid, old.title);
+        INSERT INTO posts_fts (rowid, title) VALUES (new.rowid, new.title);
+    END;
+
+    CREATE TABLE views (
+        feed TEXT PRIMARY KEY,
+        view TEXT NOT NULL
+    );
+
+    CREATE TABLE remotes (
+        name TEXT PRIMARY KEY,
+        state TEXT NOT NULL
+    );
+", "
+    ALTER TABLE posts ADD COLUMN starred INTEGER NOT NULL DEFAULT 0;
+", "
+    ALTER TABLE posts ADD COLUMN comments TEXT;
+    ALTER TABLE posts ADD COLUMN comment_feed TEXT;
+", "
+    CREATE TABLE health (
+        feed TEXT PRIMARY KEY,
+        health TEXT NOT NULL
+    );
+", "
+    CREATE TABLE articles (
+        feed TEXT NOT NULL,
+        id TEXT NOT NULL,
+        text TEXT NOT NULL,
+        PRIMARY KEY (feed, id)
+    );
+", "
+    CREATE TABLE played (
+        feed TEXT NOT NULL,
+        url TEXT NOT NULL,
+        PRIMARY KEY (feed, url)
+    );
+", "
+    ALTER TABLE posts ADD COLUMN authors TEXT NOT NULL DEFAULT '';
+    ALTER TABLE posts ADD COLUMN categories TEXT NOT NULL DEFAULT '';
+", "
+    CREATE TABLE feed_meta (
+        feed TEXT PRIMARY KEY,
+        meta TEXT NOT NULL
+    );
+", "
+    ALTER TABLE posts ADD COLUMN summary TEXT;
+", "
+    CREATE TABLE opened (
+        feed TEXT NOT NULL,
+        id TEXT NOT NULL,
+        url TEXT NOT NULL,
+        PRIMARY KEY (feed, id, url)
+    );
+", "
+    CREATE TABLE reads (
+        feed TEXT NOT NULL,
+        id TEXT NOT NULL,
+        at INTEGER NOT NULL
+    );
+    CREATE INDEX reads_feed ON reads (feed, at);
+", "
+    CREATE TABLE session (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        state TEXT NOT NULL
+    );
+", "
+    ALTER TABLE posts ADD COLUMN updated INTEGER;
+    ALTER TABLE posts ADD COLUMN hash TEXT;
+    ALTER TABLE posts ADD COLUMN edited INTEGER NOT NULL DEFAULT 0;
+", "
+    CREATE TABLE pruned (
+        feed TEXT NOT NULL,
+        id TEXT NOT NULL,
+        PRIMARY KEY (feed, id)
+    );
+", "
+    CREATE TABLE legacy_ids (
+        feed TEXT PRIMARY KEY
+    );
+    INSERT INTO legacy_ids (feed) SELECT DISTINCT feed FROM posts;
+", "
+    UPDATE posts SET published = published * 1000000,
+        arrived = arrived * 1000000, updated = updated * 1000000;
+    UPDATE reads SET at = at * 1000000;
+"];
+
+/// The columns of a post, in the order `post_from_row` reads them.
+const POST_COLUMNS: &str = "feed, id, title, urls, published, arrived, read, \
+    starred, comments, comment_feed, authors, categories, summary, updated, \
+    hash, edited";
+
+/// Posts, remote states and views stored in SQLite tables.
+///
+/// The titles of the posts are indexed in the `posts_fts` table for full-text
+/// search. The URLs, the authors and the categories of a post are separated
+/// by newlines, the times are in microseconds since the epoch and the views and
+/// remote states are JSON. The archived articles of the posts are in the
+/// `articles` table, the played media in the `played` table, the opened URLs
+/// in the `opened` table, the times the posts were marked read in the `reads`
+/// table and the IDs of the pruned posts in the `pruned` table. The feeds whose
+/// post IDs are yet to be migrated are in the `legacy_ids` table.
+pub struct SqliteStorage {
+    /// The connection to the database.
+    conn: Connection,
+}
+
+impl SqliteStorage {
+    /// Open the database at `path`, migrating it to the current schema
+    /// unless it's opened `read_only`.
+    pub fn open<P: AsRef<Path>>(path: P, read_only: bool) -> Result<Self> {
+        let conn = if read_only {
+            Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        } else {
+            Connection::open(path)
+        };
+        Self::with_connection(conn?, read_only)
+    }
+
+    /// Create a database that only lives in the memory.
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        let conn = Connection::open_in_memory().unwrap();
+        Self::with_connection(conn, false).unwrap()
+    }
+
+    /// Use the already opened `conn`.
+    fn with_connection(conn: Connection, read_only: bool) -> Result<Self> {
+        let storage = Self { conn };
+        storage.migrate(read_only).map_err(|err| Error::Database(format!(
+            "Failed to migrate the database: {}", err)))?;
+        Ok(storage)
+    }
+
+    /// Run the migrations that haven't been run yet, each in a transaction
+    /// along with the new schema version.
+    ///
+    /// A `read_only` database can't be migrated, so it must be up to date.
+    fn migrate(&self, read_only: bool) -> Result<(), String> {
+        let version: u32 = self.conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|err| err.to_string())?;
+        let latest = MIGRATIONS.len() as u32;
+        if version > latest {
+            return Err(format!("The database has schema version {}, but \
+                this version of nia only knows up to {}", version, latest));
+        }
+        if read_only && version < latest {
+            return Err(format!("The database has the old schema version {} \
+                and can't be migrated when it's read-only", version));
+        }
+
+        for (from, migration) in MIGRATIONS.iter()
+            .enumerate()
+            .skip(version as usize)
+        {
+            let migrate = || {
+                let tx = self.conn.unchecked_transaction()?;
+                tx.execute_batch(migration)?;
+                tx.pragma_update(None, "user_version", from as u32 + 1)?;
+                tx.commit()
+            };
+            migrate().map_err(|err: rusqlite::Error| err.to_string())?;
+            info!("Migrated the database to schema version {}", from + 1);
+        }
+
+        Ok(())
+    }
+
+    /// Query the posts with the `sql` statement selecting the `POST_COLUMNS`.
+    fn query_posts<P: rusqlite::Params>(&self, sql: &str, params: P)
+        -> rusqlite::Result<Vec<(String, Post)>>
+    {
+        let mut statement = self.conn.prepare_cached(sql)?;
+        statement.query_map(params, post_from_row)?.collect()
+    }
+}
+
+/// Read a row of the `POST_COLUMNS` along with the feed URL.
+fn post_from_row(row: &Row) -> rusqlite::Result<(String, Post)> {
+    let id: String = row.get(1)?;
+    let title: String = row.get(2)?;
+    let urls: String = row.get(3)?;
+    let date = |idx| row.get(idx).map(|micros| {
+        DateTime::from_timestamp_micros(micros).unwrap_or_default()
+    });
+    let url = |idx| row.get::<_, Option<String>>(idx).map(|url| {
+        url.and_then(|url| Url::parse(&url).ok())
+    });
+    let lines = |idx| row.get::<_, String>(idx).map(|text| {
+        text.lines().map(String::from).collect()
+    });
+
+    let post = Post {
+        id: PostId(Arc::from(id)),
+        title: Arc::from(title),
+        urls: urls.lines().filter_map(|url| Url::parse(url).ok()).collect(),
+        published: date(4)?,
+        arrived: date(5)?,
+        read: row.get(6)?,
+        starred: row.get(7)?,
+        comments: url(8)?,
+        comment_feed: url(9)?,
+        authors: lines(10)?,
+        categories: lines(11)?,
+        summary: row.get(12)?,
+        updated: row.get::<_, Option<i64>>(13)?
+            .and_then(DateTime::from_timestamp_micros),
+        hash: row.get(14)?,
+        edited: row.get(15)?,
+    };
+
+    Ok((row.get(0)?, post))
+}
+
+impl Storage for SqliteStorage {
+    fn load_feed(&self, feed_url: &str) -> Result<Posts> {
+        let sql = format!("SELECT {} FROM posts WHERE feed = ?1", POST_COLUMNS);
+        let posts = self.query_posts(&sql, [feed_url])?;
+
+        Ok(posts.into_iter().map(|(_, post)| post).collect::<Vec<_>>().into())
+    }
+
+    fn save_posts(&self, feed_url: &str, posts: &Posts) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut statement = tx.prepare_cached("
+                INSERT INTO posts
+                    (feed, id, title, urls, published, arrived, read, starred,
+                        comments, comment_feed, authors, categories, summary,
+                        updated, hash, edited)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12,
+                    ?13, ?14, ?15, ?16)
+                ON CONFLICT (feed, id) DO UPDATE SET
+                    title = excluded.title,
+                    urls = excluded.urls,
+                    published = excluded.published,
+                    arrived = excluded.arrived,
+                    read = excluded.read,
+                    starred = excluded.starred,
+                    comments = excluded.comments,
+                    comment_feed = excluded.comment_feed,
+                    authors = excluded.authors,
+                    categories = excluded.categories,
+                    summary = excluded.summary,
+                    updated = excluded.updated,
+                    hash = excluded.hash,
+                    edited = excluded.edited
+            ")?;
+
+            for post in posts.as_ref().iter() {
+                let urls = post.urls.iter()
+                    .map(|url| url.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                statement.execute(params![
+                    feed_url,
+                    post.id.0.as_ref(),
+                    post.title.as_ref(),
+                    urls,
+                    post.published.timestamp_micros(),
+                    post.arrived.timestamp_micros(),
+                    post.read,
+                    post.starred,
+                    post.comments.as_ref().map(Url::as_str),
+                    post.comment_feed.as_ref().map(Url::as_str),
+                    post.authors.join("\n"),
+                    post.categories.join("\n"),
+                    post.summary,
+                    post.updated.map(|updated| updated.timestamp_micros()),
+                    post.hash,
+                    post.edited,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn mark_read(&self, feed_url: &str, post_ids: &[PostId], read: bool)
+        -> Result<()>
+    {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut statement = tx.prepare_cached("
+                UPDATE posts SET read = ?3 WHERE feed = ?1 AND id = ?2
+            ")?;
+
+            for post_id in post_ids {
+                statement.execute(params![feed_url, post_id.0.as_ref(), read])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove_posts(&self, feed_url: &str, post_ids: &[PostId]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut statement = tx.prepare_cached(
+                "DELETE FROM posts WHERE feed = ?1 AND id = ?2")?;
+
+            for post_id in post_ids {
+                statement.execute([feed_url, post_id.0.as_ref()])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove_feed_posts(&self, feed_url: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM posts WHERE feed = ?1", [feed_url])?;
+        Ok(())
+    }
+
+    fn feeds(&self) -> Result<Vec<(String, usize)>> {
+        let mut statement = self.conn.prepare_cached("
+            SELECT feed, COUNT(*) FROM posts GROUP BY feed ORDER BY feed
+        ")?;
+
+        let feed = |row: &Row| {
+            Ok((row.get(0)?, row.get::<_, i64>(1)? as usize))
+        };
+        let feeds = statement.query_map([], feed)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(feeds)
+    }
+
+    fn all_posts(&self) -> Result<Vec<(String, Post)>> {
+        let sql = format!("SELECT {} FROM posts ORDER BY feed, id",
+            POST_COLUMNS);
+        Ok(self.query_posts(&sql, [])?)
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<(String, Post)>> {
+        // The query is in the FTS5 syntax, the best matches come first.
+        let columns = POST_COLUMNS.split(", ")
+            .map(|column| format!("posts.{}", column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!("
+            SELECT {} FROM posts_fts
+            JOIN posts ON posts.rowid = posts_fts.rowid
+            WHERE posts_fts MATCH ?1
+            ORDER BY rank
+        ", columns);
+
+        // Mistakes in the syntax are only noticed when the query is run.
+        self.query_posts(&sql, [query])
+            .map_err(|err| Error::InvalidQuery(err.to_string()))
+    }
+
+    fn remote_state(&self, name: &str) -> Result<RemoteState> {
+        Ok(self.conn
+            .query_row("SELECT state FROM remotes WHERE name = ?1", [name],
+                |row| row.get::<_, String>(0))
+            .optional()?
+            .and_then(|state| serde_json::from_str(&state).ok())
+            .unwrap_or_default())
+    }
+
+    fn remote_states(&self) -> Result<Vec<RemoteState>> {
+        let mut statement = self.conn
+            .prepare_cached("SELECT state FROM remotes")?;
+
+        let states = statement.query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(states.iter()
+            .filter_map(|state| serde_json::from_str(state).ok())
+            .collect())
+    }
+
+    fn save_remote_state(&self, name: &str, state: &RemoteState)
+        -> Result<()>
+    {
+        let state = serde_json::to_string(state)?;
+
+        self.conn.execute("
+            INSERT INTO remotes (name, state) VALUES (?1, ?2)
+            ON CONFLICT (name) DO UPDATE SET state = excluded.state
+        ", [name, &state])?;
+        Ok(())
+    }
+
+    fn load_view(&self, feed_url: &str) -> Result<FeedView> {
+        Ok(self.conn
+            .query_row("SELECT view FROM views WHERE feed = ?1", [feed_url],
+                |row| row.get::<_, String>(0))
+            .optional()?
+            .and_then(|view| serde_json::from_str(&view).ok())
+            .unwrap_or_default())
+    }
+
+    fn save_view(&self, feed_url: &str, view: &FeedView) -> Result<()> {
+        let view = serde_json::to_string(view)?;
+
+        self.conn.execute("
+            INSERT INTO views (feed, view) VALUES (?1, ?2)
+            ON CONFLICT (feed) DO UPDATE SET view = excluded.view
+        ", [feed_url, &view])?;
+        Ok(())
+    }
+
+    fn remove_view(&self, feed_url: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM views WHERE feed = ?1", [feed_url])?;
+        Ok(())
+    }
+
+    fn load_health(&self, feed_url: &str) -> Result<Option<FeedHealth>> {
+        Ok(self.conn
+            .query_row("SELECT health FROM health WHERE feed = ?1",
+                [feed_url], |row| row.get::<_, String>(0))
+            .optional()?
+            .and_then(|health| serde_json::from_str(&health).ok()))
+    }
+
+    fn save_health(&self, feed_url: &str, health: &FeedHealth) -> Result<()> {
+        let health = serde_json::to_string(health)?;
+
+        self.conn.execute("
+            INSERT INTO health (feed, health) VALUES (?1, ?2)
+            ON CONFLICT (feed) DO UPDATE SET health = excluded.health
+        ", [feed_url, &health])?;
+        Ok(())
+    }
+
+    fn remove_health(&self, feed_url: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM health WHERE feed = ?1", [feed_url])?;
+        Ok(())
+    }
+
+    fn load_meta(&self, feed_url: &str) -> Result<Option<FeedMeta>> {
+        Ok(self.conn
+            .query_row("SELECT meta FROM feed_meta WHERE feed = ?1",
+                [feed_url], |row| row.get::<_, String>(0))
+            .optional()?
+            .and_then(|meta| serde_json::from_str(&meta).ok()))
+    }
+
+    fn save_meta(&self, feed_url: &str, meta: &FeedMeta) -> Result<()> {
+        let meta = serde_json::to_string(meta)?;
+
+        self.conn.execute("
+            INSERT INTO feed_meta (feed, meta) VALUES (?1, ?2)
+            ON CONFLICT (feed) DO UPDATE SET meta = excluded.meta
+        ", [feed_url, &meta])?;
+        Ok(())
+    }
+
+    fn remove_meta(&self, feed_url: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM feed_meta WHERE feed = ?1",
+            [feed_url])?;
+        Ok(())
+    }
+
+    fn load_articles(&self, feed_url: &str)
+        -> Result<HashMap<PostId, Arc<str>>>
+    {
+        let mut statement = self.conn.prepare_cached(
+            "SELECT id, text FROM articles WHERE feed = ?1")?;
+        let articles = statement.query_map([feed_url], |row| {
+            let id: String = row.get(0)?;
+            let text: String = row.get(1)?;
+            Ok((PostId(Arc::from(id)), Arc::from(text)))
+        })?.collect::<rusqlite::Result<_>>()?;
+
+        Ok(articles)
+    }
+
+    fn save_article(&self, feed_url: &str, post_id: &PostId, text: &str)
+        -> Result<()>
+    {
+        self.conn.execute("
+            INSERT INTO articles (feed, id, text) VALUES (?1, ?2, ?3)
+            ON CONFLICT (feed, id) DO UPDATE SET text = excluded.text
+        ", [feed_url, post_id.0.as_ref(), text])?;
+        Ok(())
+    }
+
+    fn remove_articles(&self, feed_url: &str, post_ids: &[PostId])
+        -> Result<()>
+    {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut statement = tx.prepare_cached(
+                "DELETE FROM articles WHERE feed = ?1 AND id = ?2")?;
+
+            for post_id in post_ids {
+                statement.execute([feed_url, post_id.0.as_ref()])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_played(&self, feed_url: &str) -> Result<HashSet<Url>> {
+        let mut statement = self.conn.prepare_cached(
+            "SELECT url FROM played WHERE feed = ?1")?;
+        let urls = statement.query_map([feed_url], |row| {
+            row.get::<_, String>(0)
+        })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(urls.iter().filter_map(|url| Url::parse(url).ok()).collect())
+    }
+
+    fn mark_played(&self, feed_url: &str, url: &Url) -> Result<()> {
+        self.conn.execute("
+            INSERT INTO played (feed, url) VALUES (?1, ?2)
+            ON CONFLICT (feed, url) DO NOTHING
+        ", [feed_url, url.as_str()])?;
+        Ok(())
+    }
+
+    fn remove_played(&self, feed_url: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM played WHERE feed = ?1", [feed_url])?;
+        Ok(())
+    }
+
+    fn load_opened(&self, feed_url: &str)
+        -> Result<HashMap<PostId, HashSet<Url>>>
+    {
+        let mut statement = self.conn.prepare_cached(
+            "SELECT id, url FROM opened WHERE feed = ?1")?;
+        let rows = statement.query_map([feed_url], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut opened: HashMap<PostId, HashSet<Url>> = HashMap::new();
+        for (id, url) in rows {
+            if let Ok(url) = Url::parse(&url) {
+                opened.entry(PostId(Arc::from(id))).or_default().insert(url);
+            }
+        }
+        Ok(opened)
+    }
+
+    fn mark_opened(&self, feed_url: &str, post_id: &PostId, url: &Url)
+        -> Result<()>
+    {
+        self.conn.execute("
+            INSERT INTO opened (feed, id, url) VALUES (?1, ?2, ?3)
+            ON CONFLICT (feed, id, url) DO NOTHING
+        ", [feed_url, post_id.0.as_ref(), url.as_str()])?;
+        Ok(())
+    }
+
+    fn remove_opened(&self, feed_url: &str, post_ids: &[PostId])
+        -> Result<()>
+    {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut statement = tx.prepare_cached(
+                "DELETE FROM opened WHERE feed = ?1 AND id = ?2")?;
+
+            for post_id in post_ids {
+                statement.execute([feed_url, post_id.0.as_ref()])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_reads(&self, feed_url: &str) -> Result<Vec<Read>> {
+        let mut statement = self.conn.prepare_cached(
+            "SELECT id, at FROM reads WHERE feed = ?1 ORDER BY at")?;
+        let reads = statement.query_map([feed_url], |row| {
+            let id: String = row.get(0)?;
+            Ok(Read {
+                post_id: PostId(Arc::from(id)),
+                at: DateTime::from_timestamp_micros(row.get(1)?)
+                    .unwrap_or_default(),
+            })
+        })?.collect::<rusqlite::Result<_>>()?;
+
+        Ok(reads)
+    }
+
+    fn log_reads(&self, feed_url: &str, post_ids: &[PostId],
+        at: DateTime<Utc>) -> Result<()>
+    {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut statement = tx.prepare_cached(
+                "INSERT INTO reads (feed, id, at) VALUES (?1, ?2, ?3)")?;
+
+            for post_id in post_ids {
+                statement.execute(params![
+                    feed_url, post_id.0.as_ref(), at.timestamp_micros()
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove_reads(&self, feed_url: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM reads WHERE feed = ?1", [feed_url])?;
+        Ok(())
+    }
+
+    fn load_pruned(&self, feed_url: &str) -> Result<HashSet<PostId>> {
+        let mut statement = self.conn.prepare_cached(
+            "SELECT id FROM pruned WHERE feed = ?1")?;
+        let ids = statement.query_map([feed_url], |row| {
+            row.get::<_, String>(0)
+        })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(ids.into_iter().map(|id| PostId(Arc::from(id))).collect())
+    }
+
+    fn mark_pruned(&self, feed_url: &str, post_ids: &[PostId]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut statement = tx.prepare_cached("
+                INSERT INTO pruned (feed, id) VALUES (?1, ?2)
+                ON CONFLICT (feed, id) DO NOTHING
+            ")?;
+
+            for post_id in post_ids {
+                statement.execute([feed_url, post_id.0.as_ref()])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn unmark_pruned(&self, feed_url: &str, post_ids: &[PostId])
+        -> Result<()>
+    {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut statement = tx.prepare_cached(
+                "DELETE FROM pruned WHERE feed = ?1 AND id = ?2")?;
+
+            for post_id in post_ids {
+                statement.execute([feed_url, post_id.0.as_ref()])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove_pruned(&self, feed_url: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM pruned WHERE feed = ?1", [feed_url])?;
+        Ok(())
+    }
+
+    fn legacy_id_feeds(&self) -> Result<Vec<String>> {
+        let mut statement = self.conn.prepare_cached(
+            "SELECT feed FROM legacy_ids")?;
+        let feeds = statement.query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(feeds)
+    }
+
+    fn remove_legacy_id_feed(&self, feed_url: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM legacy_ids WHERE feed = ?1",
+            [feed_url])?;
+        Ok(())
+    }
+
+    fn load_session(&self) -> Result<Session> {
+        Ok(self.conn
+            .query_row("SELECT state FROM session WHERE id = 0", [],
+                |row| row.get::<_, String>(0))
+            .optional()?
+            .and_then(|state| serde_json::from_str(&state).ok())
+            .unwrap_or_default())
+    }
+
+    fn save_session(&self, session: &Session) -> Result<()> {
+        let state = serde_json::to_string(session)?;
+
+        self.conn.execute("
+            INSERT INTO session (id, state) VALUES (0, ?1)
+            ON CONFLICT (id) DO UPDATE SET state = excluded.state
+        ", [&state])?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        // Every write is committed as it's made.
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_post;
+
+    fn post(id: &str, title: &str) -> Post {
+        Post {
+            title: Arc::from(title),
+            urls: vec![
+                Url::parse("https://example.com/a").unwrap(),
+                Url::parse("https://example.com/b").unwrap(),
+            ],
+            arrived: DateTime::from_timestamp(1_700_000_100, 0).unwrap(),
+            ..test_post(id, "", 1_700_000_000)
+        }
+    }
+
+    #[test]
+    fn searches_titles() {
+        let storage = SqliteStorage::in_memory();
+        let url = "https://example.com/feed.xml";
+        storage.save_posts(url, &vec![
+            post("1", "Rust in the kernel"),
+            post("2", "Gardening tips"),
+        ].into()).unwrap();
+
+        // Updated titles are indexed again.
+        storage.save_posts(url, &post("2", "Rusty gardening tools").into())
+            .unwrap();
+
+        let found = storage.search("rust*").unwrap();
+        let mut ids: Vec<_> = found.iter()
+            .map(|(_, post)| post.id.0.to_string())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["1", "2"]);
+        assert_eq!(found[0].1.urls.len(), 2);
+
+        assert!(storage.search("gardening").unwrap().len() == 1);
+        let invalid = storage.search("\"unbalanced");
+        assert!(matches!(invalid, Err(Error::InvalidQuery(_))));
+
+        storage.remove_posts(url, &[PostId(Arc::from("1"))]).unwrap();
+        assert_eq!(storage.search("kernel").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn keeps_the_fractions_of_seconds() {
+        let storage = SqliteStorage::in_memory();
+        let url = "https://example.com/feed.xml";
+        let at = DateTime::from_timestamp(1_700_000_000, 123_456_000).unwrap();
+        let post = Post {
+            published: at,
+            arrived: at,
+            updated: Some(at),
+            ..post("1", "Precise")
+        };
+        storage.save_posts(url, &post.into()).unwrap();
+        storage.log_reads(url, &[PostId(Arc::from("1"))], at).unwrap();
+
+        let posts = storage.load_feed(url).unwrap();
+        let post = &posts.as_ref()[0];
+        assert_eq!((post.published, post.arrived, post.updated),
+            (at, at, Some(at)));
+        assert_eq!(storage.load_reads(url).unwrap()[0].at, at);
+    }
+}
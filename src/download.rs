@@ -1,17 +1,55 @@
+use std::fmt;
 use std::thread;
-use std::sync::mpsc;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Condvar, Mutex, mpsc};
+use std::time::{Duration, Instant};
 use atom_syndication::Feed as AtomFeed;
+use chrono::Utc;
 use rss::Channel as RssChannel;
+use serde::Deserialize;
+use tracing::{debug, info, warn};
 use url::Url;
-use crate::config::{FeedId, FeedConfig, Post, Posts};
+use crate::config::{
+    ArticleRule, Feed, FeedId, FeedConfig, FeedHealth, FeedMeta, FeedOptions,
+    Post, PostId, Posts, feed_file_title, page_fragment,
+};
+use crate::cache::BodyCache;
+use crate::error::Error;
 use crate::hash;
+use crate::read_later::Service;
+use crate::settings::{
+    NetworkSettings, PolitenessSettings, TimeoutSettings, TitleRule,
+};
 
-/// A map of sections to feeds to URLs.
+/// Where a feed is downloaded from, and how.
+#[derive(Debug, Clone)]
+pub struct FeedSource {
+    /// The URL of the feed, or where it has moved to.
+    pub url: Url,
+
+    /// The settings of the feed, e.g. its credentials.
+    pub options: Arc<FeedOptions>,
+
+    /// Whether the feed has any posts stored.
+    pub has_posts: bool,
+}
+
+impl From<&Feed> for FeedSource {
+    fn from(feed: &Feed) -> Self {
+        Self {
+            url: feed.moved.clone().unwrap_or_else(|| feed.url.clone()),
+            options: feed.options.clone(),
+            has_posts: !feed.posts.is_empty(),
+        }
+    }
+}
+
+/// A map of sections to feeds to their sources.
 #[derive(Debug)]
-pub struct UrlMap(pub Vec<Vec<Url>>);
+pub struct SourceMap(pub Vec<Vec<FeedSource>>);
 
-impl From<&FeedConfig> for UrlMap {
-    /// Given a feed config, create a `FeedId -> URL` map.
+impl From<&FeedConfig> for SourceMap {
+    /// Given a feed config, create a `FeedId -> FeedSource` map.
     fn from(feed_config: &FeedConfig) -> Self {
         let map = feed_config
             .sections
@@ -20,27 +58,102 @@ impl From<&FeedConfig> for UrlMap {
                 section
                     .feeds
                     .iter()
-                    .map(|feed| feed.url.clone())
-                    .collect::<Vec<Url>>()
+                    .map(FeedSource::from)
+                    .collect::<Vec<FeedSource>>()
             })
-            .collect::<Vec<Vec<Url>>>();
+            .collect::<Vec<Vec<FeedSource>>>();
 
         Self(map)
     }
 }
 
+/// Get the feeds of each section of the `feed_config` in the order they're
+/// downloaded in when all feeds are: the feeds that have gone the longest
+/// without a successful download first, and those that have never had one
+/// before all of them.
+pub fn stalest_first(feed_config: &FeedConfig)
+    -> Vec<Vec<(FeedId, FeedSource)>>
+{
+    feed_config.sections.iter().enumerate()
+        .map(|(section_idx, section)| {
+            let mut feeds: Vec<_> = section.feeds.iter().enumerate()
+                .filter(|(_, feed)| feed.is_downloadable())
+                .map(|(feed_idx, feed)| {
+                    (FeedId { section_idx, feed_idx }, feed)
+                })
+                .collect();
+            feeds.sort_by_key(|(_, feed)| feed.health.as_ref()
+                .and_then(|health| health.succeeded));
+            feeds.into_iter()
+                .map(|(feed_id, feed)| (feed_id, FeedSource::from(feed)))
+                .collect()
+        })
+        .collect()
+}
+
 /// A download request from the application to the downloader.
 pub enum DownloadRequest {
     /// Download a single feed.
     Feed {
         feed: FeedId,
-        url: Url,
+        source: FeedSource,
     },
 
-    /// Download all feeds.
-    ///
-    /// The map here is
-    All(UrlMap),
+    /// Download all feeds, the feeds of each section one after another in
+    /// the given order.
+    All(Vec<Vec<(FeedId, FeedSource)>>),
+
+    /// Download the feeds of a section, one after another.
+    Section(Vec<(FeedId, FeedSource)>),
+
+    /// Check whether the URLs are still reachable.
+    CheckLinks(Vec<Url>),
+
+    /// Find the feed at or advertised by the URL.
+    Discover(Url),
+
+    /// Save the URL to the read-it-later services.
+    ReadLater(Url, Vec<Service>),
+
+    /// Download the image at the URL to preview it.
+    Image(Url),
+
+    /// Download the article at the URL to read its text, found on the page
+    /// by the rule of its feed, if any.
+    Article(Url, Option<ArticleRule>),
+
+    /// Download the article of the post of the feed at the URL to archive
+    /// its text, found on the page by the rule of the feed, if any.
+    Archive(FeedId, PostId, Url, Option<ArticleRule>),
+
+    /// Stop the downloads waiting for the network, so that they fail.
+    StopWaiting,
+}
+
+/// Reachability of a URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkHealth {
+    /// The URL is being checked.
+    Checking,
+
+    /// The URL is reachable.
+    Alive,
+
+    /// The URL is gone or unreachable for the given reason.
+    Dead(String),
+}
+
+/// The download of an image to preview.
+#[derive(Debug, Clone)]
+pub enum ImagePreview {
+    /// The image is being downloaded.
+    Loading,
+
+    /// The contents of the image file.
+    Loaded(Arc<[u8]>),
+
+    /// The image couldn't be downloaded for the given reason.
+    Failed(String),
 }
 
 /// A response from the downloader to the app.
@@ -48,14 +161,95 @@ pub enum DownloadResponse {
     /// The downloader has started downloading a feed.
     Started(FeedId),
 
-    /// The downloader couldn't download the feed.
-    Failed(FeedId),
+    /// The downloader is waiting for the network to come back to download
+    /// a feed, and starts it again then.
+    Waiting(FeedId),
+
+    /// The downloader couldn't download the feed for the given reason.
+    Failed(FeedId, DownloadError),
+
+    /// The feed has permanently moved to the given URL, sent before it's
+    /// finished or failed.
+    Moved(FeedId, Url),
 
     /// The downloader has finished downloading a feed.
     Finished {
         feed: FeedId,
         posts: Posts,
     },
+
+    /// The downloader has received the given number of bytes of the feed,
+    /// sent before it's finished or failed.
+    Received(FeedId, usize),
+
+    /// How the download of a feed went, sent before it's finished or failed.
+    Fetched(FeedId, FeedHealth),
+
+    /// What the downloaded feed says about itself, sent before it's
+    /// finished.
+    Described(FeedId, Arc<FeedMeta>),
+
+    /// The downloader has checked the reachability of a URL.
+    LinkChecked(Url, LinkHealth),
+
+    /// The downloader has looked for the feed at the URL.
+    Discovered(Url, Result<DiscoveredFeed, String>),
+
+    /// The downloader has tried to save the URL to a read-it-later service,
+    /// given its name.
+    SavedForLater(&'static str, Result<(), String>),
+
+    /// The downloader has downloaded the image at the URL.
+    Image(Url, ImagePreview),
+
+    /// The downloader has downloaded the text of the article at the URL.
+    Article(Url, Result<String, String>),
+
+    /// The downloader has downloaded the text of the article of the post of
+    /// the feed to archive it.
+    Archived(FeedId, PostId, Result<String, String>),
+}
+
+/// Why a feed couldn't be downloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadError {
+    /// The server didn't answer in time, or didn't finish the download before
+    /// the deadline.
+    TimedOut(String),
+
+    /// The server couldn't be connected to, e.g. because there's no network.
+    Unreachable(String),
+
+    /// The download failed for the given reason.
+    Failed(String),
+}
+
+impl From<String> for DownloadError {
+    fn from(reason: String) -> Self {
+        Self::Failed(reason)
+    }
+}
+
+impl From<reqwest::Error> for DownloadError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::TimedOut(err.to_string())
+        } else if err.is_connect() {
+            Self::Unreachable(err.to_string())
+        } else {
+            Self::Failed(err.to_string())
+        }
+    }
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TimedOut(reason) => write!(f, "Timed out: {}", reason),
+            Self::Unreachable(reason) => write!(f, "Unreachable: {}", reason),
+            Self::Failed(reason) => write!(f, "{}", reason),
+        }
+    }
 }
 
 /// The application end of the channel between the application and the
@@ -70,36 +264,137 @@ pub struct DownloadChannel {
 
 impl DownloadChannel {
     /// Spawn the background thread that will handle downloads.
-    pub fn spawn_downloader_thread() -> Self {
+    ///
+    /// At most `queue_size` responses are queued up at once. The downloaders
+    /// wait until the application catches up, instead of filling the memory
+    /// with downloaded posts.
+    ///
+    /// The `titles` rules rewrite the titles of the downloaded posts and the
+    /// downloads of the feeds from the same host are spaced out as the
+    /// `politeness` settings say. The downloads of the feeds give up as the
+    /// `timeouts` say. The downloaded bodies are kept in the `cache`, and the
+    /// feeds are read from it instead when it's offline. Without a network,
+    /// the downloads wait for it as the `network` settings say.
+    pub fn spawn_downloader_thread(
+        queue_size: usize,
+        titles: Vec<TitleRule>,
+        politeness: PolitenessSettings,
+        timeouts: TimeoutSettings,
+        cache: Option<BodyCache>,
+        network: NetworkSettings,
+    ) -> Self {
+        let cache = cache.map(Arc::new);
+        let network = Arc::new(NetworkProbe::new(network));
+        let titles: Arc<[TitleRule]> = titles.into();
+        let hosts = Arc::new(HostLimiter::new(politeness));
+
         // Spawn the channels for download requests and responses.
         let (request_tx, request_rx) = mpsc::channel();
-        let (response_tx, response_rx) = mpsc::channel();
+        let (response_tx, response_rx) = mpsc::sync_channel(queue_size.max(1));
 
         // Spawn the downloader thread.
         thread::spawn(move || {
             while let Ok(request) = request_rx.recv() {
                 match request {
                     // Immediately start a downloader when downloading one feed.
-                    DownloadRequest::Feed { feed, url } => {
-                        let feed = vec![(feed, url)];
-                        spawn_feed_downloader(feed, response_tx.clone());
+                    DownloadRequest::Feed { feed, source } => {
+                        let feed = vec![(feed, source)];
+                        spawn_feed_downloader(feed, titles.clone(),
+                            hosts.clone(), timeouts, cache.clone(),
+                            network.clone(), response_tx.clone());
+                    },
+
+                    DownloadRequest::Section(feeds) => {
+                        spawn_feed_downloader(feeds, titles.clone(),
+                            hosts.clone(), timeouts, cache.clone(),
+                            network.clone(), response_tx.clone());
                     },
 
                     // Start one downloader per section when downloading all
                     // feeds.
-                    DownloadRequest::All(map) => {
-                        let map = map.0.into_iter();
-                        for (section_idx, section) in map.enumerate() {
-                            let feeds = section
-                                .into_iter()
-                                .enumerate()
-                                .map(|(feed_idx, url)| {
-                                    (FeedId { section_idx, feed_idx, }, url)
-                                }).collect::<Vec<(FeedId, Url)>>();
-
-                            spawn_feed_downloader(feeds, response_tx.clone());
+                    DownloadRequest::All(sections) => {
+                        for feeds in sections {
+                            spawn_feed_downloader(feeds, titles.clone(),
+                                hosts.clone(), timeouts, cache.clone(),
+                                network.clone(), response_tx.clone());
                         }
                     },
+
+                    DownloadRequest::CheckLinks(urls) => {
+                        spawn_link_checker(urls, response_tx.clone());
+                    },
+
+                    DownloadRequest::Discover(url) => {
+                        let response_tx = response_tx.clone();
+                        thread::spawn(move || {
+                            let feed = discover_feed(&url);
+                            match &feed {
+                                Ok(feed) => info!("Discovered the feed {} \
+                                    at {}", feed.url, url),
+                                Err(err) => warn!("Couldn't discover a feed \
+                                    at {}: {}", url, err),
+                            }
+                            let _ = response_tx
+                                .send(DownloadResponse::Discovered(url, feed));
+                        });
+                    },
+
+                    DownloadRequest::StopWaiting => network.stop_waiting(),
+
+                    DownloadRequest::Image(url) => {
+                        let response_tx = response_tx.clone();
+                        thread::spawn(move || {
+                            let image = match fetch_bytes(&url) {
+                                Ok(bytes) => ImagePreview::Loaded(bytes.into()),
+                                Err(err) => {
+                                    warn!("Couldn't download {}: {}", url, err);
+                                    ImagePreview::Failed(err)
+                                },
+                            };
+                            let _ = response_tx
+                                .send(DownloadResponse::Image(url, image));
+                        });
+                    },
+
+                    DownloadRequest::Article(url, rule) => {
+                        let response_tx = response_tx.clone();
+                        thread::spawn(move || {
+                            let text = fetch_article(&url, rule.as_ref());
+                            if let Err(err) = &text {
+                                warn!("Couldn't download {}: {}", url, err);
+                            }
+                            let _ = response_tx
+                                .send(DownloadResponse::Article(url, text));
+                        });
+                    },
+
+                    DownloadRequest::Archive(feed, post, url, rule) => {
+                        let response_tx = response_tx.clone();
+                        thread::spawn(move || {
+                            let text = fetch_article(&url, rule.as_ref());
+                            if let Err(err) = &text {
+                                warn!("Couldn't archive {}: {}", url, err);
+                            }
+                            let _ = response_tx.send(
+                                DownloadResponse::Archived(feed, post, text));
+                        });
+                    },
+
+                    DownloadRequest::ReadLater(url, services) => {
+                        let response_tx = response_tx.clone();
+                        thread::spawn(move || for service in services {
+                            let result = service.save(&url);
+                            match &result {
+                                Ok(()) => info!("Saved {} to {}", url,
+                                    service.name()),
+                                Err(err) => warn!("Couldn't save {} to {}: \
+                                    {}", url, service.name(), err),
+                            }
+                            let _ = response_tx.send(
+                                DownloadResponse::SavedForLater(
+                                    service.name(), result));
+                        });
+                    },
                 }
             }
         });
@@ -109,37 +404,317 @@ impl DownloadChannel {
     }
 }
 
+/// Spaces out the downloads from the same host across all downloaders.
+///
+/// Every host gets `burst` downloads that start right away, and one more
+/// every `delay` after that.
+struct HostLimiter {
+    /// The fewest time between the downloads from a host.
+    delay: Duration,
+
+    /// How far ahead of the spacing the downloads from a host may start.
+    tolerance: Duration,
+
+    /// When the downloads from each host would be caught up with the
+    /// spacing, by the name of the host.
+    hosts: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostLimiter {
+    /// Create a limiter spacing out the downloads as the `politeness`
+    /// settings say.
+    fn new(politeness: PolitenessSettings) -> Self {
+        let delay = Duration::from_millis(politeness.delay_ms);
+        Self {
+            delay,
+            tolerance: delay * politeness.burst.saturating_sub(1),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reserve the next download from the `host`, as of `now`. Returns when
+    /// the download may start.
+    fn reserve(&self, host: &str, now: Instant) -> Instant {
+        let mut hosts = self.hosts.lock().unwrap();
+        let due = hosts.get(host).map_or(now, |&due| due.max(now));
+        hosts.insert(host.to_string(), due + self.delay);
+        due.checked_sub(self.tolerance).map_or(now, |start| start.max(now))
+    }
+
+    /// Wait until a download from the host of the `url` may start. Local
+    /// sources have no host and are never waited for.
+    fn wait(&self, url: &Url) {
+        if self.delay.is_zero() {
+            return;
+        }
+        let Some(host) = url.host_str() else {
+            return;
+        };
+
+        let now = Instant::now();
+        let start = self.reserve(host, now);
+        if start > now {
+            debug!("Waiting {:?} to download from {}", start - now, host);
+            thread::sleep(start - now);
+        }
+    }
+}
+
+/// Tells whether there's a network by connecting to a probe, so that the
+/// downloads wait for it instead of failing when there's none.
+struct NetworkProbe {
+    /// The `host:port` connected to, or an empty string to never wait.
+    address: String,
+
+    /// The time between the connections while waiting for the network.
+    interval: Duration,
+
+    /// The longest the downloads wait for the network before they fail.
+    max_wait: Duration,
+
+    /// How many times the waiting has been stopped, which the waiting
+    /// downloads are woken up to notice.
+    stopped: Mutex<u64>,
+
+    /// Wakes up the waiting downloads once the waiting has been stopped.
+    woken: Condvar,
+}
+
+impl NetworkProbe {
+    /// The longest a connection to the probe is tried for.
+    const TIMEOUT: Duration = Duration::from_secs(3);
+
+    /// Create a probe as the `network` settings say.
+    fn new(network: NetworkSettings) -> Self {
+        Self {
+            address: network.probe,
+            interval: Duration::from_secs(network.probe_secs.max(1)),
+            max_wait: Duration::from_secs(network.max_wait_secs),
+            stopped: Mutex::new(0),
+            woken: Condvar::new(),
+        }
+    }
+
+    /// Whether the probe can be connected to. Without a probe, there's
+    /// always a network.
+    fn is_online(&self) -> bool {
+        use std::net::{TcpStream, ToSocketAddrs};
+
+        if self.address.is_empty() {
+            return true;
+        }
+        self.address.to_socket_addrs()
+            .into_iter()
+            .flatten()
+            .any(|addr| {
+                TcpStream::connect_timeout(&addr, Self::TIMEOUT).is_ok()
+            })
+    }
+
+    /// Wait until the probe can be connected to. Returns `false` if it
+    /// couldn't be within the longest wait, or the waiting was stopped.
+    fn wait_until_online(&self) -> bool {
+        let started = Instant::now();
+        let stopped = *self.stopped.lock().unwrap();
+        loop {
+            let remaining = self.max_wait.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                return false;
+            }
+
+            let guard = self.stopped.lock().unwrap();
+            let (guard, _) = self.woken
+                .wait_timeout_while(guard, self.interval.min(remaining),
+                    |now| *now == stopped)
+                .unwrap();
+            if *guard != stopped {
+                return false;
+            }
+            drop(guard);
+
+            if self.is_online() {
+                return true;
+            }
+        }
+    }
+
+    /// Stop the downloads waiting for the network, so that they fail.
+    fn stop_waiting(&self) {
+        *self.stopped.lock().unwrap() += 1;
+        self.woken.notify_all();
+    }
+}
+
 /// Spawn a thread that downloads `feeds` sequentially.
+///
+/// The downloads wait for the `hosts` to space them out and give up as the
+/// `timeouts` say. The downloaded bodies are kept in the `cache`, and the
+/// feeds are read from it instead when it's offline. When a server can't be
+/// reached, the `network` is checked and waited for if it's gone.
 fn spawn_feed_downloader(
-    feeds: Vec<(FeedId, Url)>,
-    response_tx: mpsc::Sender<DownloadResponse>,
+    feeds: Vec<(FeedId, FeedSource)>,
+    titles: Arc<[TitleRule]>,
+    hosts: Arc<HostLimiter>,
+    timeouts: TimeoutSettings,
+    cache: Option<Arc<BodyCache>>,
+    network: Arc<NetworkProbe>,
+    response_tx: mpsc::SyncSender<DownloadResponse>,
 ) {
     std::thread::spawn(move || {
-        for (feed, url) in feeds.into_iter() {
+        let client = reqwest::blocking::Client::builder()
+            .connect_timeout(Duration::from_secs(timeouts.connect_secs))
+            .redirect(redirect_policy())
+            .build()
+            .map_err(DownloadError::from);
+        let read = Duration::from_secs(timeouts.read_secs);
+        let deadline = Duration::from_secs(timeouts.deadline_secs);
+
+        let offline = cache.as_ref().is_some_and(|cache| cache.is_offline());
+
+        for (feed, source) in feeds.into_iter() {
+            let url = &source.url;
+
+            // Read the feed as it was last downloaded instead, without
+            // telling the app it has been downloaded.
+            if offline {
+                let _ = response_tx
+                    .send(DownloadResponse::Started(feed.clone()));
+                let cached = cache.as_ref().and_then(|cache| cache.load(url));
+                let Some(cached) = cached else {
+                    let err = "Not cached for offline use".to_string();
+                    let _ = response_tx
+                        .send(DownloadResponse::Failed(feed, err.into()));
+                    continue;
+                };
+                let mut posts = parse_feed_leniently(&cached.body)
+                    .map_or_else(Posts::new, |(posts, _)| posts);
+                filter_posts(&source.options, &mut posts);
+                rewrite_titles(&titles, url, &mut posts);
+                debug!("Read {} cached posts of {}", posts.len(), url);
+                let _ = response_tx
+                    .send(DownloadResponse::Finished { feed, posts });
+                continue;
+            }
+
+            hosts.wait(url);
+
             // Tell the app we have started the download.
             let _ = response_tx.send(DownloadResponse::Started(feed.clone()));
 
-            // Do the actual download.
-            let result = reqwest::blocking::get(String::from(url))
-                .and_then(|r| r.error_for_status())
-                .and_then(|r| r.text());
+            // Do the actual download. If we got an error for this feed, just
+            // go next.
+            debug!("Downloading {}", url);
+            let (fetched, started, response) = loop {
+                let fetched = Utc::now();
+                let started = Instant::now();
+                let response = match &client {
+                    Ok(client) => {
+                        fetch_source(client, &source, read, deadline)
+                    },
+                    Err(err) => SourceResponse {
+                        status: None,
+                        moved: None,
+                        body: Err(err.clone()),
+                    },
+                };
 
-            // If we got an error for this feed, just go next.
-            let Ok(body) = result else {
-                let _ = response_tx.send(
-                    DownloadResponse::Failed(feed.clone()));
-                continue;
+                // Without a network, wait for it to come back instead of
+                // failing every feed.
+                if matches!(response.body, Err(DownloadError::Unreachable(_)))
+                    && !network.is_online()
+                {
+                    warn!("No network to download {}, waiting for it", url);
+                    let _ = response_tx
+                        .send(DownloadResponse::Waiting(feed.clone()));
+                    let online = network.wait_until_online();
+                    let _ = response_tx
+                        .send(DownloadResponse::Started(feed.clone()));
+                    if online {
+                        continue;
+                    }
+                    warn!("Gave up waiting for the network to download {}",
+                        url);
+                }
+                break (fetched, started, response);
+            };
+            let mut health = FeedHealth {
+                fetched,
+                duration_ms: started.elapsed().as_millis() as u64,
+                status: response.status,
+                error: None,
+                failures: 0,
+                succeeded: None,
             };
 
-            // Extract the urls.
-            let posts = if let Ok(atom) = body.parse::<AtomFeed>() {
-                extract_from_atom(&atom)
-            } else if let Ok(rss) = body.parse::<RssChannel>() {
-                extract_from_rss(&rss)
-            } else {
-                Posts::new()
+            if let Some(moved) = response.moved {
+                info!("{} has permanently moved to {}", url, moved);
+                let _ = response_tx
+                    .send(DownloadResponse::Moved(feed.clone(), moved));
+            }
+
+            let body = match response.body {
+                Ok(body) => {
+                    let _ = response_tx
+                        .send(DownloadResponse::Received(feed.clone(),
+                            body.len()));
+                    body
+                },
+                Err(err) => {
+                    warn!("Couldn't download {}: {}", url, err);
+                    health.error = Some(err.to_string());
+                    let _ = response_tx
+                        .send(DownloadResponse::Fetched(feed.clone(), health));
+                    let _ = response_tx
+                        .send(DownloadResponse::Failed(feed, err));
+                    continue;
+                },
             };
 
+            // A feed that hasn't changed since it was last downloaded has no
+            // new posts, unless its posts have been deleted since.
+            let cached = cache.as_ref().and_then(|cache| cache.load(url));
+            if source.has_posts
+                && cached.is_some_and(|cached| cached.hash == hash(&body))
+            {
+                debug!("{} hasn't changed", url);
+                let _ = response_tx
+                    .send(DownloadResponse::Fetched(feed.clone(), health));
+                let posts = Posts::new();
+                let _ = response_tx
+                    .send(DownloadResponse::Finished { feed, posts });
+                continue;
+            }
+
+            // Extract the urls.
+            let parsed = parse_feed_leniently(&body);
+            if parsed.is_some()
+                && let Some(cache) = &cache
+                && let Err(err) = cache.save(url, &body, fetched)
+            {
+                warn!("Couldn't cache the body of {}: {}", url, err);
+            }
+            let (mut posts, skipped) = parsed
+                .unwrap_or_else(|| {
+                    warn!("{} is not an Atom, RSS or JSON feed", url);
+                    health.error = Some("Not an Atom, RSS or JSON feed".into());
+                    (Posts::new(), 0)
+                });
+            if skipped > 0 {
+                warn!("Skipped {} malformed posts of {}", skipped, url);
+                health.error = Some(format!("Skipped {} malformed posts",
+                    skipped));
+            }
+            if let Some(meta) = parse_feed_meta(&body) {
+                let meta = Arc::new(meta);
+                let _ = response_tx
+                    .send(DownloadResponse::Described(feed.clone(), meta));
+            }
+            let _ = response_tx
+                .send(DownloadResponse::Fetched(feed.clone(), health));
+            filter_posts(&source.options, &mut posts);
+            rewrite_titles(&titles, url, &mut posts);
+            debug!("Downloaded {} posts from {}", posts.len(), url);
+
             // Tell the app we have finished the download.
             let _ = response_tx
                 .send(DownloadResponse::Finished { feed, posts });
@@ -147,114 +722,2061 @@ fn spawn_feed_downloader(
     });
 }
 
-/// Parse a valid URL from `s` and push it into `acc`.
-fn push_url(acc: &mut Vec<Url>, s: &str) {
-    // TODO: Handle relative links.
+/// Download the body of `url`.
+fn fetch_body(url: &Url) -> Result<String, String> {
+    fetch_response(url).1
+}
 
-    // These checks are not expensive enough to warrant something more optimized
-    if let Ok(url) = Url::parse(s) {
-        if !acc.contains(&url) {
-            acc.push(url);
+/// Download the contents of the file at `url`.
+fn fetch_bytes(url: &Url) -> Result<Vec<u8>, String> {
+    reqwest::blocking::get(url.as_str())
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .map(|bytes| bytes.to_vec())
+        .map_err(|err| err.to_string())
+}
+
+/// Download the body of `url`, along with the HTTP status of the response if
+/// the server responded.
+fn fetch_response(url: &Url) -> (Option<u16>, Result<String, String>) {
+    into_body(reqwest::blocking::get(url.as_str()))
+}
+
+/// The most redirects a download of a feed follows.
+const MAX_REDIRECTS: usize = 10;
+
+/// Whether the `status` says that the resource has permanently moved.
+fn is_permanent_redirect(status: reqwest::StatusCode) -> bool {
+    matches!(status, reqwest::StatusCode::MOVED_PERMANENTLY
+        | reqwest::StatusCode::PERMANENT_REDIRECT)
+}
+
+/// The redirect policy of the clients that download feeds. The temporary
+/// redirects are followed, but the first permanent one is left to
+/// `fetch_source`, so that it can tell that the feed has moved.
+fn redirect_policy() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        let hops = attempt.previous().len();
+        if hops == 1 && is_permanent_redirect(attempt.status()) {
+            attempt.stop()
+        } else if hops > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
         }
-    }
+    })
 }
 
-/// Parse valid URLs from `s` and push them into `acc`.
-fn extract_urls_from_text(acc: &mut Vec<Url>, s: &str) {
-    let mut finder = linkify::LinkFinder::new();
-    finder.kinds(&[linkify::LinkKind::Url]);
+/// The response to the download of a feed by `fetch_source`.
+struct SourceResponse {
+    /// The HTTP status of the response, if the server responded.
+    status: Option<u16>,
 
-    for link in finder.links(s).map(|link| link.as_str()) {
-        push_url(acc, link);
-    }
+    /// Where the feed has permanently moved to, if it has.
+    moved: Option<Url>,
+
+    /// The body of the feed.
+    body: Result<String, DownloadError>,
 }
 
-/// Extract the posts from an Atom feed.
+/// Download the body of the feed at the `source` with its credentials and
+/// headers using the `client`. The download gives up once the server hasn't
+/// sent anything for the `read` timeout, or once it has taken longer than the
+/// `deadline` altogether.
 ///
-/// All of the posts will be marked as unread. It is up to the application to
-/// make sure that before read posts are marked as such.
-fn extract_from_atom(feed: &AtomFeed) -> Posts {
-    let mut posts = Vec::new();
+/// Permanent redirects are followed here so that the feed is known to have
+/// moved, provided the `client` stops at them, see `redirect_policy`, unless
+/// they lead anywhere but the web. The credentials and headers are only sent
+/// to the origin of the feed, i.e. the same scheme, host and port.
+///
+/// The feeds of `file:` and `exec:` sources are read locally instead.
+fn fetch_source(
+    client: &reqwest::blocking::Client,
+    source: &FeedSource,
+    read: Duration,
+    deadline: Duration,
+) -> SourceResponse {
+    let failed = |moved, err: DownloadError| SourceResponse {
+        status: None,
+        moved,
+        body: Err(err),
+    };
 
-    // Go through each post.
-    for entry in feed.entries() {
-        // Set the metadata for this post.
-        let id = entry.id.clone().into();
-        let title = entry.title.value.clone().into();
-        let published = entry.updated.to_utc();
+    if let Some(body) = read_local(&source.url) {
+        return SourceResponse {
+            status: None,
+            moved: None,
+            body: body.map_err(DownloadError::from),
+        };
+    }
 
-        // Parse the URLs from this post.
-        let mut urls = Vec::new();
+    let origin = match resolve_feed_url(&source.url) {
+        Ok(url) => url,
+        Err(err) => return failed(None, err.into()),
+    };
 
-        for link in entry.links() {
-            push_url(&mut urls, link.href())
+    let options = &source.options;
+    let started = Instant::now();
+    let mut url = origin.clone();
+    let mut moved = None;
+    for _ in 0..=MAX_REDIRECTS {
+        // The timeout of a blocking request bounds every wait for the server,
+        // i.e. for the answer and then for each chunk of the body.
+        let timeout = read.min(deadline.saturating_sub(started.elapsed()));
+        let mut request = client.get(url.as_str()).timeout(timeout);
+        if url.origin() == origin.origin() {
+            if let Some(auth) = &options.auth {
+                request = request.basic_auth(&auth.user,
+                    auth.password.as_ref());
+            }
+            for (name, value) in options.headers.iter() {
+                request = request.header(name, value);
+            }
         }
+        let response = match request.send() {
+            Ok(response) => response,
+            Err(err) => return failed(moved, err.into()),
+        };
 
-        if let Some(content) = entry.content().and_then(|c| c.value()) {
-            extract_urls_from_text(&mut urls, content);
-        }
+        let status = response.status();
+        let location = response.headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| response.url().join(location).ok());
+        if is_permanent_redirect(status)
+            && let Some(location) = location
+        {
+            // The feed would be read locally from there.
+            if !matches!(location.scheme(), "http" | "https") {
+                let err = format!("Refused the redirect to {}", location);
+                return failed(moved, err.into());
+            }
 
-        if let Some(summary) = entry.summary() {
-            extract_urls_from_text(&mut urls, summary);
+            // Where a YouTube channel has moved says nothing about the
+            // channel itself.
+            if origin == source.url {
+                moved = Some(location.clone());
+            }
+            url = location;
+            continue;
         }
 
-        // Save the post.
-        let read = false;
-        posts.push(Post { urls, id, title, published, read });
+        let body = response.error_for_status()
+            .map_err(DownloadError::from)
+            .and_then(|response| read_body(response, started, deadline));
+        return SourceResponse { status: Some(status.as_u16()), moved, body };
     }
+    failed(moved, "Too many redirects".to_string().into())
+}
 
-    posts.into()
+/// Read the body of the `response` in the charset it says it's in, giving up
+/// once the download that `started` has taken longer than the `deadline`.
+fn read_body(
+    mut response: reqwest::blocking::Response,
+    started: Instant,
+    deadline: Duration,
+) -> Result<String, DownloadError> {
+    use std::io::Read;
+    use encoding_rs::Encoding;
+
+    let charset = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';')
+            .filter_map(|param| param.trim().split_once('='))
+            .find(|(name, _)| name.eq_ignore_ascii_case("charset"))
+            .map(|(_, charset)| charset.trim_matches('"').to_string()));
+    let encoding = charset
+        .and_then(|charset| Encoding::for_label(charset.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+
+    let mut body = Vec::new();
+    let mut chunk = [0; 16 * 1024];
+    loop {
+        if started.elapsed() >= deadline {
+            let err = format!("{}: the download took too long", response.url());
+            return Err(DownloadError::TimedOut(err));
+        }
+        match response.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => body.extend_from_slice(&chunk[..read]),
+            Err(err) => {
+                let timed_out = err.get_ref()
+                    .and_then(|err| err.downcast_ref::<reqwest::Error>())
+                    .is_some_and(|err| err.is_timeout());
+                let err = format!("{}: {}", response.url(), err);
+                return Err(if timed_out {
+                    DownloadError::TimedOut(err)
+                } else {
+                    DownloadError::Failed(err)
+                });
+            },
+        }
+    }
+
+    let (body, _, _) = encoding.decode(&body);
+    Ok(body.into_owned())
 }
 
-/// Extract the posts from an RSS feed.
+/// Read the feed of a local source: the file of a `file:` URL, or what the
+/// command of an `exec:` URL prints, e.g. `exec:~/bin/scrape --rss`.
 ///
-/// All of the posts will be marked as unread. It is up to the application to
-/// make sure that before read posts are marked as such.
-fn extract_from_rss(channel: &RssChannel) -> Posts {
-    let mut posts = Vec::new();
+/// Returns `None` for the other URLs, which are downloaded.
+fn read_local(url: &Url) -> Option<Result<String, String>> {
+    match url.scheme() {
+        "file" => {
+            let body = url.to_file_path()
+                .map_err(|_| format!("{} isn't a local path", url))
+                .and_then(|path| std::fs::read_to_string(&path)
+                    .map_err(|err| {
+                        format!("Couldn't read {}: {}", path.display(), err)
+                    }));
+            Some(body)
+        },
+        "exec" => Some(run_source(&url.as_str()["exec:".len()..])),
+        _ => None,
+    }
+}
 
-    // Go through each post.
-    for item in channel.items() {
-        // Set the metadata for this post. Unlike Atom, RSS requires almost no
-        // metadata for posts. If we don't have much to work with, we'll do it
-        // ourselves.
-        let title = item.title.clone()
-            .or_else(|| item.description.as_ref()
-                .map(|d| truncate_chars(&d, 20)))
-            .unwrap_or_else(|| "Untitled".to_string())
-            .into();
-        let published = item.pub_date.as_ref()
-            .and_then(|date| chrono::DateTime::parse_from_rfc2822(&date).ok())
-            .map(|date| date.with_timezone(&chrono::Utc))
-            .unwrap_or_else(|| chrono::Utc::now());
-        let id = item.guid.as_ref().map(|g| g.value.clone())
-            .unwrap_or_else(|| hash(&format!("{:?} {:?}", published, title)))
-            .into();
+/// Run the `command` of an `exec:` source in the shell, returning what it
+/// prints.
+fn run_source(command: &str) -> Result<String, String> {
+    use std::process::{Command, Stdio};
 
-        // Parse the URLs from this post.
-        let mut urls = Vec::new();
+    debug!("Running `{}`", command);
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|err| format!("Couldn't run `{}`: {}", command, err))?;
 
-        if let Some(link) = item.link() {
-            push_url(&mut urls, link);
-        }
+    // Say why the command failed with the last thing it complained about.
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().rfind(|line| !line.trim().is_empty())
+            .map_or_else(|| output.status.to_string(), str::to_string);
+        return Err(format!("`{}` failed: {}", command, reason));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| format!("`{}` didn't print UTF-8", command))
+}
 
-        if let Some(desc) = item.description() {
-            extract_urls_from_text(&mut urls, desc);
-        }
+/// Get the body of the `response`, along with its HTTP status if the server
+/// responded.
+fn into_body(response: reqwest::Result<reqwest::blocking::Response>)
+    -> (Option<u16>, Result<String, String>)
+{
+    match response {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let body = response.error_for_status()
+                .and_then(|r| r.text())
+                .map_err(|err| err.to_string());
+            (Some(status), body)
+        },
+        Err(err) => (None, Err(err.to_string())),
+    }
+}
 
-        if let Some(content) = item.content() {
-            extract_urls_from_text(&mut urls, content);
+/// A feed found by `discover_feed`.
+#[derive(Debug)]
+pub struct DiscoveredFeed {
+    /// URL of the feed itself.
+    pub url: Url,
+
+    /// Title of the feed, if it has one.
+    pub title: Option<String>,
+}
+
+/// Find the feed at `url`.
+///
+/// `url` can either point to a feed directly, or to a web page that
+/// advertises its feeds through `<link rel="alternate">` tags. In both cases
+/// the feed is downloaded to verify that it can be parsed.
+pub fn discover_feed(url: &Url) -> Result<DiscoveredFeed, String> {
+    let body = fetch_body(&resolve_feed_url(url)?)?;
+
+    // The URL might be a feed already.
+    if parse_feed(&body).is_some() {
+        let title = parse_feed_title(&body);
+        return Ok(DiscoveredFeed { url: url.clone(), title });
+    }
+
+    // Otherwise try the feeds advertised by the page.
+    for candidate in find_feed_links(&body, url) {
+        if let Ok(body) = fetch_body(&candidate)
+            && parse_feed(&body).is_some()
+        {
+            let title = parse_feed_title(&body);
+            return Ok(DiscoveredFeed { url: candidate, title });
         }
+    }
 
-        // Save the post.
-        let read = false;
-        posts.push(Post { id, title, urls, published, read });
+    Err(format!("No feed found at {}", url))
+}
+
+/// Download the feed at `url` and parse its posts.
+///
+/// The posts aren't marked in any way, so they can be merged into a feed
+/// with `Feed::merge_posts`.
+pub fn fetch_feed(url: &Url) -> crate::error::Result<Posts> {
+    let body = resolve_feed_url(url)
+        .and_then(|feed_url| fetch_body(&feed_url))
+        .map_err(Error::Download)?;
+    parse_feed(&body).ok_or_else(|| {
+        Error::Download(format!("{} isn't an Atom, RSS or JSON feed", url))
+    })
+}
+
+/// The hosts of the YouTube pages.
+const YOUTUBE_HOSTS: &[&str] = &[
+    "youtube.com", "www.youtube.com", "m.youtube.com",
+];
+
+/// The feed URLs found on the pages of the YouTube channels with handles or
+/// custom names, by the URLs of the pages.
+static YOUTUBE_CHANNELS: Mutex<BTreeMap<Url, Url>> =
+    Mutex::new(BTreeMap::new());
+
+/// Translate the URL of a YouTube channel or playlist into the URL of its
+/// feed, e.g. `https://www.youtube.com/channel/UCxyz` into
+/// `https://www.youtube.com/feeds/videos.xml?channel_id=UCxyz`.
+///
+/// Returns `None` for the other URLs, including the channels with handles
+/// and custom names, whose IDs are only on their pages.
+pub fn youtube_feed_url(url: &Url) -> Option<Url> {
+    if !YOUTUBE_HOSTS.contains(&url.host_str()?) {
+        return None;
     }
 
-    posts.into()
+    let mut segments = url.path_segments()?.filter(|s| !s.is_empty());
+    let (key, value) = match (segments.next()?, segments.next()) {
+        ("channel", Some(id)) => ("channel_id", id.to_string()),
+        ("user", Some(name)) => ("user", name.to_string()),
+        ("playlist", None) => {
+            let list = url.query_pairs().find(|(key, _)| key == "list")?;
+            ("playlist_id", list.1.into_owned())
+        },
+        _ => return None,
+    };
+
+    let mut feed = Url::parse("https://www.youtube.com/feeds/videos.xml")
+        .ok()?;
+    feed.query_pairs_mut().append_pair(key, &value);
+    Some(feed)
 }
 
-// Utility function to truncate a string to at most `n` characters safely.
-fn truncate_chars(s: &str, n: usize) -> String {
-    s.chars().take(n).collect()
+/// Whether the `url` is the page of a YouTube channel with a handle, e.g.
+/// `https://www.youtube.com/@rustvideos`, or a custom name.
+fn is_youtube_handle(url: &Url) -> bool {
+    let host = url.host_str().unwrap_or_default();
+    let first = url.path_segments()
+        .and_then(|mut segments| segments.next())
+        .unwrap_or_default();
+    YOUTUBE_HOSTS.contains(&host) && (first.starts_with('@') || first == "c")
+}
+
+/// Get the URL the feed at `url` is downloaded from.
+///
+/// The URLs of YouTube channels and playlists are translated into the URLs
+/// of their feeds. The page of a channel with a handle is downloaded once to
+/// find the feed it advertises.
+fn resolve_feed_url(url: &Url) -> Result<Url, String> {
+    if let Some(feed) = youtube_feed_url(url) {
+        return Ok(feed);
+    }
+    if !is_youtube_handle(url) {
+        return Ok(url.clone());
+    }
+
+    let channels = || YOUTUBE_CHANNELS.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(feed) = channels().get(url) {
+        return Ok(feed.clone());
+    }
+
+    debug!("Looking for the feed of {}", url);
+    let feed = find_feed_links(&fetch_body(url)?, url).into_iter()
+        .next()
+        .ok_or_else(|| format!("No feed found at {}", url))?;
+    channels().insert(url.clone(), feed.clone());
+    Ok(feed)
+}
+
+/// Guess from the `url` alone whether it points to a feed, without
+/// downloading anything.
+pub fn looks_like_feed(url: &Url) -> bool {
+    const EXTENSIONS: &[&str] = &[".xml", ".rss", ".atom", ".rdf", ".json"];
+    const NAMES: &[&str] = &["feed", "feeds", "rss", "atom"];
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+
+    let path = url.path().to_ascii_lowercase();
+    let named = path.split('/').any(|segment| NAMES.contains(&segment));
+    let extension = EXTENSIONS.iter().any(|ext| path.ends_with(ext));
+    let query = url.query_pairs().any(|(key, value)| {
+        matches!(&*key, "feed" | "format") || matches!(&*value, "rss" | "atom")
+    });
+
+    named || extension || query
+}
+
+/// Find the URLs of the feeds advertised by the `html` page at `base`.
+fn find_feed_links(html: &str, base: &Url) -> Vec<Url> {
+    const FEED_TYPES: &[&str] = &[
+        "application/rss+xml",
+        "application/atom+xml",
+        "application/feed+json",
+        "application/json",
+    ];
+
+    // Lowercasing ASCII doesn't move any bytes around, so the offsets into
+    // the lowercase copy are valid in the original.
+    let lower = html.to_ascii_lowercase();
+    let mut links = Vec::new();
+
+    for (start, _) in lower.match_indices("<link") {
+        let tag = &html[start + "<link".len()..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        let attrs = html_attributes(tag);
+        let attr = |name: &str| attrs.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str());
+
+        let is_alternate = attr("rel").is_some_and(|rel| {
+            rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("alternate"))
+        });
+        let is_feed = attr("type").is_some_and(|ty| {
+            FEED_TYPES.iter().any(|t| ty.trim().eq_ignore_ascii_case(t))
+        });
+
+        if let (true, true, Some(href)) = (is_alternate, is_feed, attr("href"))
+            && let Ok(url) = base.join(&href.replace("&amp;", "&"))
+            && !links.contains(&url)
+        {
+            links.push(url);
+        }
+    }
+
+    links
+}
+
+/// Parse the attributes of an HTML tag into `(lowercase name, value)` pairs.
+///
+/// `tag` is the inside of the tag without the tag name, e.g.
+/// ` rel="alternate" href=/feed.xml`.
+fn html_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = tag;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            break;
+        }
+
+        // Read the name.
+        let name_end = rest.find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        // Read the value, which might be quoted, unquoted or missing.
+        let mut value = "";
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (quoted, end) = match after_eq.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &after_eq[1..];
+                    let end = inner.find(quote).unwrap_or(inner.len());
+                    (&inner[..end], (end + 2).min(after_eq.len()))
+                },
+                _ => {
+                    let end = after_eq.find(char::is_whitespace)
+                        .unwrap_or(after_eq.len());
+                    (&after_eq[..end], end)
+                },
+            };
+            value = quoted;
+            rest = &after_eq[end..];
+        }
+
+        attrs.push((name, value.to_string()));
+    }
+
+    attrs
+}
+
+/// Download the page at `url` and get the readable text of its article,
+/// found by the `rule` if there is one. Without a rule, or if it doesn't
+/// match, the text of the whole page is returned.
+fn fetch_article(url: &Url, rule: Option<&ArticleRule>)
+    -> Result<String, String>
+{
+    let html = fetch_body(url)?;
+    let article = rule.and_then(|rule| {
+        let article = extract_article(rule, &html);
+        if article.is_none() {
+            debug!("The article rule doesn't match {}", url);
+        }
+        article
+    });
+    Ok(html_to_text(article.unwrap_or(&html)))
+}
+
+/// Find the article on the `html` page by the `rule`: the inside of the
+/// first element the selector matches, or what the regex captures.
+fn extract_article<'a>(rule: &ArticleRule, html: &'a str) -> Option<&'a str> {
+    let (tag, id, class) = match rule {
+        ArticleRule::Regex(regex) => {
+            let captures = regex.captures(html)?;
+            return captures.get(1).or(captures.get(0))
+                .map(|article| article.as_str());
+        },
+        ArticleRule::Selector { tag, id, class } => (tag, id, class),
+    };
+
+    // Lowercasing ASCII doesn't move any bytes around, so the offsets into
+    // the lowercase copy are valid in the original.
+    let lower = html.to_ascii_lowercase();
+    let name_at = |start: usize| -> &str {
+        let rest = &lower[start..];
+        &rest[..rest.find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len())]
+    };
+
+    for (start, _) in lower.match_indices('<') {
+        let name = name_at(start + 1);
+        if name.is_empty() || tag.as_ref().is_some_and(|tag| tag != name) {
+            continue;
+        }
+
+        let Some(close) = html[start..].find('>').map(|idx| start + idx)
+        else {
+            break;
+        };
+        let attrs = html_attributes(&html[start + 1 + name.len()..close]);
+        let attr = |name: &str| attrs.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str());
+        if id.as_deref().is_some_and(|id| attr("id") != Some(id)) {
+            continue;
+        }
+        if class.as_deref().is_some_and(|class| !attr("class")
+            .is_some_and(|classes| classes.split_whitespace()
+                .any(|c| c == class)))
+        {
+            continue;
+        }
+
+        // The element ends where the tags of its name are balanced again.
+        let content = close + 1;
+        let mut depth = 1;
+        for (idx, _) in lower[content..].match_indices('<') {
+            let idx = content + idx;
+            if lower[idx + 1..].starts_with('/') {
+                if name_at(idx + 2) == name {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(&html[content..idx]);
+                    }
+                }
+            } else if name_at(idx + 1) == name {
+                depth += 1;
+            }
+        }
+        return Some(&html[content..]);
+    }
+
+    None
+}
+
+/// Get the readable text of the `html` page, with an empty line between the
+/// paragraphs.
+///
+/// The scripts, styles and tags are left out and the common entities are
+/// decoded. It's no reader mode, but enough to read an article in a pager.
+pub(crate) fn html_to_text(html: &str) -> String {
+    // The tags starting a new paragraph and those whose contents are skipped.
+    const BLOCKS: &[&str] = &[
+        "p", "div", "br", "li", "tr", "h1", "h2", "h3", "h4", "h5", "h6",
+        "pre", "blockquote", "section", "article", "header", "footer",
+        "title", "figcaption", "dt", "dd",
+    ];
+    const SKIPPED: &[&str] = &[
+        "script", "style", "noscript", "svg", "template",
+    ];
+
+    let lower = html.to_ascii_lowercase();
+    let mut text = String::new();
+    let mut rest = 0;
+    while let Some(start) = html[rest..].find('<').map(|idx| rest + idx) {
+        let segment = decode_entities(&html[rest..start]);
+        text += &segment.replace(['\r', '\n'], " ");
+
+        // Skip the comments as a whole.
+        if lower[start..].starts_with("<!--") {
+            rest = lower[start..].find("-->")
+                .map_or(html.len(), |idx| start + idx + "-->".len());
+            continue;
+        }
+
+        let close = lower[start..].find('>').map(|idx| start + idx);
+        let tag = &lower[start + 1..close.unwrap_or(html.len())];
+        rest = close.map_or(html.len(), |close| close + 1);
+
+        let closing = tag.starts_with('/');
+        let name: String = tag.trim_start_matches('/').chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        if !closing && SKIPPED.contains(&name.as_str()) {
+            let end = format!("</{}", name);
+            rest = lower[rest..].find(&end)
+                .and_then(|idx| lower[rest + idx..].find('>')
+                    .map(|close| rest + idx + close + 1))
+                .unwrap_or(html.len());
+        } else if BLOCKS.contains(&name.as_str()) {
+            text.push('\n');
+        }
+    }
+    text += &decode_entities(&html[rest..]).replace(['\r', '\n'], " ");
+
+    text.split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Hash the `title` and the `texts` of the content of a post as downloaded,
+/// to tell when the post has been edited.
+fn hash_content(title: &str, texts: &[Option<&str>]) -> String {
+    let mut content = title.to_string();
+    for text in texts.iter().flatten() {
+        content.push('\0');
+        content.push_str(text);
+    }
+    hash(&content)
+}
+
+/// The most graphemes of the summary of a post.
+const MAX_SUMMARY: usize = 200;
+
+/// Get the first sentence of the `html` description of a post as plain text,
+/// to be shown under its title.
+pub(crate) fn summarize(html: &str) -> Option<String> {
+    let text = html_to_text(html);
+    let paragraph = text.split('\n').next()?;
+
+    // A sentence ends with a punctuation mark followed by a space.
+    let end = paragraph.match_indices(['.', '!', '?'])
+        .map(|(idx, mark)| idx + mark.len())
+        .find(|&end| paragraph[end..].starts_with(' '))
+        .unwrap_or(paragraph.len());
+    let summary = truncate_chars(&paragraph[..end], MAX_SUMMARY);
+    Some(summary).filter(|summary| !summary.is_empty())
+}
+
+/// Decode the named entities that are common in articles and the numeric
+/// ones in `s`. Unknown entities are left as they are.
+fn decode_entities(s: &str) -> String {
+    let mut decoded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        decoded += &rest[..amp];
+        rest = &rest[amp..];
+
+        let end = rest.find(';').filter(|&end| end <= 10);
+        let c = end.and_then(|end| match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            name => name.strip_prefix("#x")
+                .or_else(|| name.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| name.strip_prefix('#')?.parse().ok())
+                .and_then(char::from_u32),
+        });
+        match (c, end) {
+            (Some(c), Some(end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            },
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded + rest
+}
+
+/// Parse the title of the feed in `body`, made fit for the feed file.
+fn parse_feed_title(body: &str) -> Option<String> {
+    let title = if let Ok(atom) = body.parse::<AtomFeed>() {
+        Some(atom.title().value.clone())
+    } else if let Ok(rss) = body.parse::<RssChannel>() {
+        Some(rss.title().to_string())
+    } else if let Ok(json) = serde_json::from_str::<JsonFeed>(body) {
+        json.title
+    } else {
+        None
+    };
+
+    title.and_then(|title| feed_file_title(&title))
+}
+
+/// Get what the feed in `body` says about itself: its description, the URL
+/// of its site, when it was last updated and the URL of its icon.
+pub fn parse_feed_meta(body: &str) -> Option<FeedMeta> {
+    let text = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+    let url = |s: &str| Url::parse(s.trim()).ok();
+
+    let meta = if let Ok(atom) = body.parse::<AtomFeed>() {
+        let site = atom.links().iter()
+            .find(|link| link.rel() == "alternate")
+            .and_then(|link| url(link.href()));
+        FeedMeta {
+            description: atom.subtitle().and_then(|s| text(&s.value)),
+            site,
+            updated: Some(atom.updated().to_utc()),
+            icon: atom.icon().or(atom.logo()).and_then(url),
+        }
+    } else if let Ok(rss) = body.parse::<RssChannel>() {
+        let updated = rss.last_build_date().or(rss.pub_date())
+            .and_then(|date| chrono::DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.to_utc());
+        FeedMeta {
+            description: text(rss.description()),
+            site: url(rss.link()),
+            updated,
+            icon: rss.image().and_then(|image| url(image.url())),
+        }
+    } else if let Ok(json) = serde_json::from_str::<JsonFeed>(body) {
+        FeedMeta {
+            description: json.description.as_deref().and_then(text),
+            site: json.home_page_url.as_deref().and_then(url),
+            updated: None,
+            icon: json.icon.or(json.favicon).as_deref().and_then(url),
+        }
+    } else {
+        return None;
+    };
+
+    Some(meta).filter(|meta| *meta != FeedMeta::default())
+}
+
+/// Spawn a thread that checks the reachability of `urls` sequentially.
+fn spawn_link_checker(
+    urls: Vec<Url>,
+    response_tx: mpsc::SyncSender<DownloadResponse>,
+) {
+    std::thread::spawn(move || {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build();
+
+        for url in urls.into_iter() {
+            let health = match &client {
+                Ok(client) => check_link(client, &url),
+                Err(err) => LinkHealth::Dead(err.to_string()),
+            };
+            debug!("Checked {}: {:?}", url, health);
+
+            let _ = response_tx
+                .send(DownloadResponse::LinkChecked(url, health));
+        }
+    });
+}
+
+/// Check whether `url` is reachable.
+///
+/// Only statuses that say the resource is gone count as dead. Anything else
+/// (e.g. 403 from bot protection) just means we can't tell.
+fn check_link(client: &reqwest::blocking::Client, url: &Url) -> LinkHealth {
+    use reqwest::StatusCode;
+
+    // Some servers don't implement HEAD, fall back to GET for those.
+    let response = client.head(url.as_str()).send()
+        .and_then(|response| match response.status() {
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED => {
+                client.get(url.as_str()).send()
+            },
+            _ => Ok(response),
+        });
+
+    match response {
+        Ok(response) => match response.status() {
+            status @ (StatusCode::NOT_FOUND | StatusCode::GONE) => {
+                LinkHealth::Dead(status.to_string())
+            },
+            _ => LinkHealth::Alive,
+        },
+        Err(err) if err.is_timeout() => LinkHealth::Dead("timed out".into()),
+        Err(err) => LinkHealth::Dead(err.to_string()),
+    }
+}
+
+/// Parse the posts from the `body` of an Atom, RSS or JSON feed.
+///
+/// XML that isn't well-formed is repaired first, e.g. its stray ampersands
+/// are escaped, and the posts that still can't be parsed are skipped.
+///
+/// Returns `None` if the body isn't any of the supported feed formats.
+pub fn parse_feed(body: &str) -> Option<Posts> {
+    parse_feed_leniently(body).map(|(posts, _)| posts)
+}
+
+/// Parse the posts like `parse_feed` does, along with the number of the
+/// malformed posts that had to be skipped.
+fn parse_feed_leniently(body: &str) -> Option<(Posts, usize)> {
+    if let Some(posts) = parse_well_formed(body) {
+        return Some((posts, 0));
+    }
+
+    let repaired = repair_xml(body);
+    if let Some(posts) = parse_well_formed(&repaired) {
+        debug!("Parsed a feed once its XML was repaired");
+        return Some((posts, 0));
+    }
+    parse_items_separately(&repaired)
+}
+
+/// The named entities of HTML that feeds use in XML most often, which
+/// doesn't know them, and the characters they stand for.
+const HTML_ENTITIES: &[(&str, char)] = &[
+    ("nbsp", '\u{a0}'), ("ndash", '–'), ("mdash", '—'), ("hellip", '…'),
+    ("lsquo", '‘'), ("rsquo", '’'), ("ldquo", '“'), ("rdquo", '”'),
+    ("laquo", '«'), ("raquo", '»'), ("copy", '©'), ("reg", '®'),
+    ("trade", '™'), ("euro", '€'),
+];
+
+/// Repair the common problems of the XML of feeds: the ampersands that
+/// don't start entities, the named entities of HTML and the characters that
+/// XML doesn't allow, e.g. stray control characters.
+///
+/// The CDATA sections and the comments are left as they are.
+fn repair_xml(body: &str) -> String {
+    // The XML declaration has to come first.
+    let body = body.trim_start_matches(|c: char| {
+        c.is_whitespace() || c == '\u{feff}'
+    });
+
+    let mut repaired = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(idx) = rest.find(['&', '<']) {
+        repaired.extend(rest[..idx].chars().filter(|&c| is_xml_char(c)));
+        rest = &rest[idx..];
+
+        // Copy the sections that are never escaped verbatim.
+        let verbatim = [("<![CDATA[", "]]>"), ("<!--", "-->")].iter()
+            .find(|(start, _)| rest.starts_with(start))
+            .map(|(_, end)| rest.find(end).map_or(rest.len(), |idx| {
+                idx + end.len()
+            }));
+        if let Some(end) = verbatim {
+            repaired += &rest[..end];
+            rest = &rest[end..];
+            continue;
+        }
+        if rest.starts_with('<') {
+            repaired.push('<');
+            rest = &rest[1..];
+            continue;
+        }
+
+        let name = rest[1..].find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let html = name.and_then(|name| HTML_ENTITIES.iter()
+            .find(|(entity, _)| *entity == name));
+        let numeric = name.and_then(|name| name.strip_prefix('#'))
+            .is_some_and(|number| number.strip_prefix('x')
+                .map_or_else(|| number.parse::<u32>().is_ok(),
+                    |hex| u32::from_str_radix(hex, 16).is_ok()));
+        let xml = name.is_some_and(|name| {
+            matches!(name, "amp" | "lt" | "gt" | "quot" | "apos")
+        });
+
+        match (html, name) {
+            (Some((_, c)), Some(name)) => {
+                repaired += &format!("&#{};", *c as u32);
+                rest = &rest[name.len() + 2..];
+            },
+            (None, Some(name)) if numeric || xml => {
+                repaired += &rest[..name.len() + 2];
+                rest = &rest[name.len() + 2..];
+            },
+            _ => {
+                repaired += "&amp;";
+                rest = &rest[1..];
+            },
+        }
+    }
+    repaired.extend(rest.chars().filter(|&c| is_xml_char(c)));
+    repaired
+}
+
+/// Whether XML allows the character `c` in a document.
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r')
+        || (c >= ' ' && !matches!(c, '\u{fffe}' | '\u{ffff}'))
+}
+
+/// Parse the posts of an XML feed one by one, each in a copy of the feed
+/// without the others, skipping those that can't be parsed.
+///
+/// Returns the posts along with the number of the skipped ones, or `None`
+/// if the feed can't be parsed even without its posts.
+fn parse_items_separately(body: &str) -> Option<(Posts, usize)> {
+    let tag = ["item", "entry"].into_iter().find(|tag| {
+        body.contains(&format!("</{}>", tag))
+    })?;
+    let (open, close) = (format!("<{}", tag), format!("</{}>", tag));
+
+    // The posts start at their opening tags, not at the tags whose names
+    // only start the same, e.g. `<items>`.
+    let starts: Vec<usize> = body.match_indices(&open)
+        .map(|(idx, _)| idx)
+        .filter(|&idx| body[idx + open.len()..].starts_with(['>', ' ', '\t',
+            '\r', '\n', '/']))
+        .collect();
+    let end = body.rfind(&close)? + close.len();
+    let (header, footer) = (&body[..*starts.first()?], &body[end..]);
+
+    let mut posts = Vec::new();
+    let mut skipped = 0;
+    for (i, &start) in starts.iter().enumerate() {
+        // A post that isn't closed ends where the next one starts.
+        let next = starts.get(i + 1).copied().unwrap_or(end);
+        let item = body[start..next].find(&close)
+            .map_or(&body[start..next], |idx| {
+                &body[start..start + idx + close.len()]
+            });
+
+        let single = parse_well_formed(&format!("{}{}{}", header, item,
+            footer));
+        match single {
+            Some(single) => posts.extend(single.as_ref().iter().cloned()),
+            None => skipped += 1,
+        }
+    }
+
+    if posts.is_empty() {
+        parse_well_formed(&format!("{}{}", header, footer))?;
+    }
+    Some((posts.into(), skipped))
+}
+
+/// Parse the posts from the `body` of a well-formed Atom, RSS or JSON feed.
+fn parse_well_formed(body: &str) -> Option<Posts> {
+    if let Ok(atom) = body.parse::<AtomFeed>() {
+        Some(extract_from_atom(&atom))
+    } else if let Ok(rss) = body.parse::<RssChannel>() {
+        Some(extract_from_rss(&rss))
+    } else if let Ok(json) = serde_json::from_str::<JsonFeed>(body) {
+        Some(extract_from_json(&json))
+    } else {
+        None
+    }
+}
+
+/// Rewrite the titles of the `posts` of the feed at `feed_url` with the
+/// `rules` that apply to it.
+pub fn rewrite_titles(rules: &[TitleRule], feed_url: &Url, posts: &mut Posts) {
+    for rule in rules.iter().filter(|rule| rule.applies_to(feed_url)) {
+        posts.map_titles(|title| rule.rewrite(title));
+    }
+}
+
+/// Keep only the `posts` that pass the include and exclude filters of the
+/// feed with the `options`.
+pub fn filter_posts(options: &FeedOptions, posts: &mut Posts) {
+    if options.include.is_empty() && options.exclude.is_empty() {
+        return;
+    }
+
+    posts.retain(|post| {
+        let title = post.title.as_ref();
+        (options.include.is_empty()
+            || options.include.iter().any(|filter| filter.is_match(title)))
+            && !options.exclude.iter().any(|filter| filter.is_match(title))
+    });
+}
+
+/// Parse a valid URL from `s` and push it into `acc`, unless it's there
+/// already once normalized.
+pub(crate) fn push_url(acc: &mut Vec<Url>, s: &str) {
+    // TODO: Handle relative links.
+
+    // These checks are not expensive enough to warrant something more optimized
+    if let Ok(url) = Url::parse(s).map(normalize_url)
+        && !acc.contains(&url)
+    {
+        acc.push(url);
+    }
+}
+
+/// Strip the fragment and the tracking parameters from `url` and lowercase
+/// its host, so that links to the same page compare equal. The fragments
+/// that tell pages apart are kept, see `page_fragment`.
+pub(crate) fn normalize_url(mut url: Url) -> Url {
+    if page_fragment(&url).is_none() {
+        url.set_fragment(None);
+    }
+
+    // The host of http URLs is lowercased by the parser already.
+    if let Some(host) = url.host_str()
+        && host.chars().any(|c| c.is_ascii_uppercase())
+    {
+        let host = host.to_ascii_lowercase();
+        let _ = url.set_host(Some(&host));
+    }
+
+    // Only rewrite the query when there's something to strip, because it
+    // gets re-encoded.
+    let tracking = |key: &str| key.starts_with("utm_")
+        || matches!(key, "fbclid" | "gclid" | "mc_cid" | "mc_eid");
+    if url.query_pairs().any(|(key, _)| tracking(&key)) {
+        let kept: Vec<(String, String)> = url.query_pairs()
+            .filter(|(key, _)| !tracking(key))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+
+    url
+}
+
+/// Parse valid URLs from `s` and push them into `acc`.
+pub(crate) fn extract_urls_from_text(acc: &mut Vec<Url>, s: &str) {
+    let mut finder = linkify::LinkFinder::new();
+    finder.kinds(&[linkify::LinkKind::Url]);
+
+    for link in finder.links(s).map(|link| link.as_str()) {
+        push_url(acc, link);
+    }
+}
+
+/// Extract the posts from an Atom feed.
+///
+/// All of the posts will be marked as unread. It is up to the application to
+/// make sure that before read posts are marked as such.
+fn extract_from_atom(feed: &AtomFeed) -> Posts {
+    let mut posts = Vec::new();
+
+    // Go through each post.
+    for entry in feed.entries() {
+        // Set the metadata for this post.
+        let id = entry.id.clone().into();
+        let title = entry.title.value.clone().into();
+        let published = entry.updated.to_utc();
+
+        // Parse the URLs from this post.
+        let mut urls = Vec::new();
+
+        // Replies are linked to as RFC 4685 describes, usually as a feed.
+        let mut comments = None;
+        let mut comment_feed = None;
+
+        // The page of the entry comes first, whatever order it's in.
+        let page = entry.links().iter()
+            .find(|link| link.rel() == "alternate");
+        for link in page.into_iter().chain(entry.links()) {
+            push_url(&mut urls, link.href());
+
+            if link.rel() != "replies" {
+                continue;
+            }
+            let page = matches!(link.mime_type(),
+                Some("text/html" | "application/xhtml+xml"));
+            let target = if page { &mut comments } else { &mut comment_feed };
+            if target.is_none() {
+                *target = Url::parse(link.href()).ok();
+            }
+        }
+
+        if let Some(content) = entry.content().and_then(|c| c.value()) {
+            extract_urls_from_text(&mut urls, content);
+        }
+
+        if let Some(summary) = entry.summary() {
+            extract_urls_from_text(&mut urls, summary);
+        }
+
+        // The summary is optional, the content is a fallback.
+        let summary = entry.summary()
+            .map(|summary| summary.value.as_str())
+            .or_else(|| entry.content().and_then(|c| c.value()))
+            .and_then(summarize);
+        let hash = hash_content(&entry.title.value, &[
+            entry.content().and_then(|c| c.value()),
+            entry.summary().map(|summary| summary.value.as_str()),
+        ]);
+
+        // The authors of the feed wrote the entries that don't say.
+        let mut authors = Vec::new();
+        let people = match entry.authors() {
+            [] => feed.authors(),
+            people => people,
+        };
+        for person in people {
+            push_name(&mut authors, person.name());
+        }
+
+        let mut categories = Vec::new();
+        for category in entry.categories() {
+            push_name(&mut categories,
+                category.label().unwrap_or(category.term()));
+        }
+
+        // Save the post.
+        let read = false;
+        let arrived = chrono::Utc::now();
+        posts.push(Post {
+            urls, id, title, published, read, arrived, starred: false,
+            comments, comment_feed, authors, categories, summary,
+            updated: Some(published), hash: Some(hash), edited: false,
+        });
+    }
+
+    posts.into()
+}
+
+/// The namespace of the `commentRss` element linking to the comment feed of
+/// an RSS item.
+const WFW_NAMESPACE: &str = "http://wellformedweb.org/CommentAPI/";
+
+/// Extract the posts from an RSS feed.
+///
+/// All of the posts will be marked as unread. It is up to the application to
+/// make sure that before read posts are marked as such.
+fn extract_from_rss(channel: &RssChannel) -> Posts {
+    let mut posts = Vec::new();
+
+    // Extensions are stored under the prefix the feed declares for them.
+    let wfw = channel.namespaces().iter()
+        .find(|(_, uri)| uri.as_str() == WFW_NAMESPACE)
+        .map_or("wfw", |(prefix, _)| prefix.as_str());
+
+    // Go through each post.
+    for item in channel.items() {
+        // Set the metadata for this post. Unlike Atom, RSS requires almost no
+        // metadata for posts. If we don't have much to work with, we'll do it
+        // ourselves.
+        let title: Arc<str> = item.title.clone()
+            .or_else(|| item.description.as_ref()
+                .map(|d| truncate_chars(d, 20)))
+            .unwrap_or_else(|| "Untitled".to_string())
+            .into();
+        let published = item.pub_date.as_ref()
+            .and_then(|date| chrono::DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.with_timezone(&chrono::Utc))
+            .unwrap_or_else(chrono::Utc::now);
+        // Without a GUID, the link is the next most stable thing to tell
+        // the item by, since the title can be edited.
+        let link = item.link()
+            .and_then(|link| Url::parse(link.trim()).ok());
+        let id = item.guid.as_ref()
+            .map(|guid| PostId::from(guid.value.clone()))
+            .or_else(|| link.as_ref().and_then(PostId::from_link))
+            .unwrap_or_else(|| PostId::from_title(published, &title));
+
+        // Parse the URLs from this post.
+        let mut urls = Vec::new();
+
+        // The GUID can be the link to the page of the item, if it has none.
+        let permalink = item.guid()
+            .filter(|guid| guid.is_permalink())
+            .map(|guid| guid.value());
+        if let Some(link) = item.link().or(permalink) {
+            push_url(&mut urls, link);
+        }
+
+        if let Some(desc) = item.description() {
+            extract_urls_from_text(&mut urls, desc);
+        }
+
+        if let Some(content) = item.content() {
+            extract_urls_from_text(&mut urls, content);
+        }
+
+        let summary = item.description()
+            .or_else(|| item.content())
+            .and_then(summarize);
+        let hash = hash_content(item.title().unwrap_or_default(),
+            &[item.description(), item.content()]);
+
+        // Find the links to the comments.
+        let comments = item.comments()
+            .and_then(|url| Url::parse(url.trim()).ok());
+        let comment_feed = item.extensions().get(wfw)
+            .and_then(|extensions| extensions.get("commentRss"))
+            .and_then(|extensions| extensions.first())
+            .and_then(|extension| extension.value())
+            .and_then(|url| Url::parse(url.trim()).ok());
+
+        // Authors are usually given by Dublin Core, since RSS asks for an
+        // email address.
+        let mut authors = Vec::new();
+        if let Some(author) = item.author() {
+            push_name(&mut authors, rss_author_name(author));
+        }
+        for creator in item.dublin_core_ext().iter()
+            .flat_map(|dc| dc.creators())
+        {
+            push_name(&mut authors, creator);
+        }
+
+        let mut categories = Vec::new();
+        for category in item.categories() {
+            push_name(&mut categories, category.name());
+        }
+
+        // Save the post.
+        let read = false;
+        let arrived = chrono::Utc::now();
+        posts.push(Post {
+            id, title, urls, published, read, arrived, starred: false,
+            comments, comment_feed, authors, categories, summary,
+            updated: None, hash: Some(hash), edited: false,
+        });
+    }
+
+    posts.into()
+}
+
+/// A JSON feed (<https://www.jsonfeed.org/version/1.1/>).
+///
+/// Only the fields we care about are deserialized.
+#[derive(Deserialize)]
+struct JsonFeed {
+    title: Option<String>,
+    description: Option<String>,
+    home_page_url: Option<String>,
+    icon: Option<String>,
+    favicon: Option<String>,
+
+    #[serde(default)]
+    items: Vec<JsonItem>,
+}
+
+/// A single item in a JSON feed.
+#[derive(Deserialize)]
+struct JsonItem {
+    /// The ID is required to be a string, but some feeds use numbers.
+    id: serde_json::Value,
+    url: Option<String>,
+    external_url: Option<String>,
+    title: Option<String>,
+    content_html: Option<String>,
+    content_text: Option<String>,
+    summary: Option<String>,
+    date_published: Option<String>,
+    date_modified: Option<String>,
+
+    /// Version 1.1 lists the authors, 1.0 has just one.
+    #[serde(default)]
+    authors: Vec<JsonAuthor>,
+    author: Option<JsonAuthor>,
+
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// An author of an item in a JSON feed.
+#[derive(Deserialize)]
+struct JsonAuthor {
+    name: Option<String>,
+}
+
+/// Extract the posts from a JSON feed.
+///
+/// All of the posts will be marked as unread. It is up to the application to
+/// make sure that before read posts are marked as such.
+fn extract_from_json(feed: &JsonFeed) -> Posts {
+    let mut posts = Vec::new();
+
+    // Go through each post.
+    for item in feed.items.iter() {
+        // Set the metadata for this post. Like RSS, JSON feeds don't require
+        // much metadata.
+        let id = match &item.id {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }.into();
+        let text = item.content_text.as_ref().or(item.summary.as_ref());
+        let title = item.title.clone()
+            .or_else(|| text.map(|t| truncate_chars(t, 20)))
+            .unwrap_or_else(|| "Untitled".to_string())
+            .into();
+        let date = |date: &String| chrono::DateTime::parse_from_rfc3339(date)
+            .ok()
+            .map(|date| date.with_timezone(&chrono::Utc));
+        let published = item.date_published.as_ref()
+            .or(item.date_modified.as_ref())
+            .and_then(date)
+            .unwrap_or_else(chrono::Utc::now);
+        let updated = item.date_modified.as_ref().and_then(date);
+
+        // Parse the URLs from this post.
+        let mut urls = Vec::new();
+
+        for link in [&item.url, &item.external_url].into_iter().flatten() {
+            push_url(&mut urls, link);
+        }
+
+        let content = [&item.content_html, &item.content_text, &item.summary];
+        for text in content.into_iter().flatten() {
+            extract_urls_from_text(&mut urls, text);
+        }
+
+        let summary = [&item.summary, &item.content_text, &item.content_html]
+            .into_iter()
+            .flatten()
+            .find_map(|text| summarize(text));
+        let hash = hash_content(item.title.as_deref().unwrap_or_default(),
+            &content.map(Option::as_deref));
+
+        let mut authors = Vec::new();
+        for author in item.authors.iter().chain(&item.author) {
+            if let Some(name) = &author.name {
+                push_name(&mut authors, name);
+            }
+        }
+
+        let mut categories = Vec::new();
+        for tag in item.tags.iter() {
+            push_name(&mut categories, tag);
+        }
+
+        // Save the post.
+        let read = false;
+        let arrived = chrono::Utc::now();
+        posts.push(Post {
+            id, title, urls, published, read, arrived, starred: false,
+            comments: None, comment_feed: None, authors, categories,
+            summary, updated, hash: Some(hash), edited: false,
+        });
+    }
+
+    posts.into()
+}
+
+/// Push the trimmed `name` of an author or a category into `acc`, unless
+/// it's empty or there already.
+fn push_name(acc: &mut Vec<String>, name: &str) {
+    let name = name.trim();
+    if !name.is_empty() && !acc.iter().any(|n| n == name) {
+        acc.push(name.to_string());
+    }
+}
+
+/// Get the name of the author of an RSS item from its `author` element,
+/// which is an email address followed by the name in parentheses, e.g.
+/// `jane@example.com (Jane Doe)`, if the feed follows the spec.
+fn rss_author_name(author: &str) -> &str {
+    let author = author.trim();
+    author.strip_suffix(')')
+        .and_then(|rest| rest.split_once('('))
+        .map_or(author, |(_, name)| name)
+}
+
+// Utility function to truncate a string to at most `n` graphemes safely, so
+// that no letter loses its accents.
+fn truncate_chars(s: &str, n: usize) -> String {
+    use unicode_segmentation::UnicodeSegmentation;
+    s.graphemes(true).take(n).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PostFilter;
+
+    #[test]
+    fn parses_json_feed() {
+        let body = r#"{
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": "Example",
+            "items": [
+                {
+                    "id": 1,
+                    "url": "https://example.com/1",
+                    "content_text": "See https://example.org/ref",
+                    "date_published": "2024-05-01T12:00:00Z"
+                },
+                {
+                    "id": "2",
+                    "title": "Second",
+                    "content_html": "<p>Hello</p>",
+                    "authors": [{"name": "Jane Doe"}, {"url": "/john"}],
+                    "tags": ["rust"]
+                }
+            ]
+        }"#;
+
+        let posts = parse_feed(body).unwrap();
+        assert_eq!(posts.len(), 2);
+
+        let first = posts.get_by_id(&"1".to_string().into()).unwrap();
+        assert_eq!(&*first.title, "See https://example.");
+        assert_eq!(first.urls.len(), 2);
+
+        let second = posts.get_by_id(&"2".to_string().into()).unwrap();
+        assert_eq!(&*second.title, "Second");
+        assert_eq!(second.authors, ["Jane Doe"]);
+        assert_eq!(second.categories, ["rust"]);
+    }
+
+    #[test]
+    fn reads_local_sources() {
+        let path = std::env::temp_dir()
+            .join(format!("nia-source-{}.xml", std::process::id()));
+        std::fs::write(&path, "<rss/>").unwrap();
+        let url = Url::from_file_path(&path).unwrap();
+        assert_eq!(read_local(&url), Some(Ok("<rss/>".into())));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(read_local(&url), Some(Err(_))));
+
+        let url = Url::parse("exec:printf '<rss/>' # comment").unwrap();
+        assert_eq!(read_local(&url), Some(Ok("<rss/>".into())));
+        let url = Url::parse("exec:echo oops >&2; exit 1").unwrap();
+        assert_eq!(read_local(&url),
+            Some(Err("`echo oops >&2; exit 1` failed: oops".into())));
+
+        let url = Url::parse("https://example.com/feed.xml").unwrap();
+        assert_eq!(read_local(&url), None);
+    }
+
+    #[test]
+    fn parses_feed_meta() {
+        let rss = r#"<rss version="2.0"><channel>
+            <title>Example</title>
+            <link>https://example.com/</link>
+            <description> Posts about things </description>
+            <lastBuildDate>Wed, 01 May 2024 12:00:00 +0000</lastBuildDate>
+            <image>
+                <url>https://example.com/icon.png</url>
+                <title>Example</title>
+                <link>https://example.com/</link>
+            </image>
+        </channel></rss>"#;
+        let meta = parse_feed_meta(rss).unwrap();
+        assert_eq!(meta.description.as_deref(), Some("Posts about things"));
+        assert_eq!(meta.site.unwrap().as_str(), "https://example.com/");
+        assert_eq!(meta.updated.unwrap().timestamp(), 1714564800);
+        assert_eq!(meta.icon.unwrap().as_str(),
+            "https://example.com/icon.png");
+
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+            <title>Example</title>
+            <id>urn:example</id>
+            <updated>2024-05-01T12:00:00Z</updated>
+            <subtitle>Posts about things</subtitle>
+            <link rel="self" href="https://example.com/feed.xml"/>
+            <link href="https://example.com/"/>
+            <logo>https://example.com/logo.png</logo>
+        </feed>"#;
+        let meta = parse_feed_meta(atom).unwrap();
+        assert_eq!(meta.description.as_deref(), Some("Posts about things"));
+        assert_eq!(meta.site.unwrap().as_str(), "https://example.com/");
+        assert_eq!(meta.updated.unwrap().timestamp(), 1714564800);
+        assert_eq!(meta.icon.unwrap().as_str(),
+            "https://example.com/logo.png");
+
+        let json = r#"{"version": "https://jsonfeed.org/version/1.1",
+            "title": "Example", "items": []}"#;
+        assert_eq!(parse_feed_meta(json), None);
+    }
+
+    #[test]
+    fn parses_authors_and_categories() {
+        let rss = r#"<rss version="2.0"
+            xmlns:dc="http://purl.org/dc/elements/1.1/"><channel>
+            <title>Example</title>
+            <item>
+                <guid>1</guid>
+                <author>jane@example.com (Jane Doe)</author>
+                <dc:creator>John Roe</dc:creator>
+                <category>Rust</category>
+                <category> Rust </category>
+                <category>Web</category>
+            </item>
+        </channel></rss>"#;
+        let posts = parse_feed(rss).unwrap();
+        let post = &posts.as_ref()[0];
+        assert_eq!(post.authors, ["Jane Doe", "John Roe"]);
+        assert_eq!(post.categories, ["Rust", "Web"]);
+
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+            <title>Example</title>
+            <id>urn:feed</id>
+            <updated>2024-05-01T12:00:00Z</updated>
+            <author><name>Jane Doe</name></author>
+            <entry>
+                <id>urn:1</id>
+                <title>First</title>
+                <updated>2024-05-01T12:00:00Z</updated>
+                <category term="rust" label="Rust"/>
+                <category term="web"/>
+            </entry>
+        </feed>"#;
+        let posts = parse_feed(atom).unwrap();
+        let post = &posts.as_ref()[0];
+        assert_eq!(post.authors, ["Jane Doe"]);
+        assert_eq!(post.categories, ["Rust", "web"]);
+
+        assert_eq!(rss_author_name("jane@example.com"), "jane@example.com");
+    }
+
+    #[test]
+    fn finds_primary_urls() {
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+            <title>Example</title>
+            <id>urn:feed</id>
+            <updated>2024-05-01T12:00:00Z</updated>
+            <entry>
+                <id>urn:1</id>
+                <title>First</title>
+                <updated>2024-05-01T12:00:00Z</updated>
+                <link rel="enclosure" href="https://cdn.example/1.mp3"/>
+                <link rel="alternate" href="https://blog.example/1"/>
+                <content type="html">See https://other.example/</content>
+            </entry>
+        </feed>"#;
+        let posts = parse_feed(atom).unwrap();
+        let url = posts.as_ref()[0].primary_url().map(Url::as_str);
+        assert_eq!(url, Some("https://blog.example/1"));
+
+        let rss = r#"<rss version="2.0"><channel>
+            <title>Example</title>
+            <item>
+                <guid>https://blog.example/2</guid>
+                <description>See https://other.example/</description>
+            </item>
+        </channel></rss>"#;
+        let posts = parse_feed(rss).unwrap();
+        let url = posts.as_ref()[0].primary_url().map(Url::as_str);
+        assert_eq!(url, Some("https://blog.example/2"));
+    }
+
+    #[test]
+    fn summarizes_posts() {
+        let rss = r#"<rss version="2.0"><channel>
+            <title>Example</title>
+            <item>
+                <guid>1</guid>
+                <description>&lt;p&gt;Version 2.0 is out! It&amp;apos;s
+                    faster.&lt;/p&gt;&lt;p&gt;More below.&lt;/p&gt;
+                </description>
+            </item>
+            <item><guid>2</guid></item>
+        </channel></rss>"#;
+        let posts = parse_feed(rss).unwrap();
+        let summary = |id: &str| posts.get_by_id(&id.to_string().into())
+            .and_then(|post| post.summary.clone());
+        assert_eq!(summary("1").as_deref(), Some("Version 2.0 is out!"));
+        assert_eq!(summary("2"), None);
+
+        assert_eq!(summarize("<p>No full stop</p><p>Next</p>").as_deref(),
+            Some("No full stop"));
+        assert_eq!(summarize("<img src=\"a.png\">"), None);
+    }
+
+    #[test]
+    fn tells_items_without_guids_by_their_links() {
+        let rss = |title: &str, link: &str| format!(r#"<rss version="2.0">
+            <channel><title>Example</title><item>
+                <title>{}</title><link>{}</link>
+                <pubDate>Mon, 01 Jan 2024 10:00:00 GMT</pubDate>
+            </item></channel></rss>"#, title, link);
+        let id = |title, link| parse_feed(&rss(title, link)).unwrap()
+            .as_ref()[0].id.clone();
+        let link = Url::parse("https://blog.example/post").unwrap();
+        assert_eq!(id("Tpyo", link.as_str()),
+            PostId::from_link(&link).unwrap());
+        assert_eq!(id("Tpyo", link.as_str()), id("Typo", link.as_str()));
+
+        // The routes of single-page apps are different pages.
+        assert_ne!(id("A", "https://app.example/#/posts/1"),
+            id("B", "https://app.example/#/posts/2"));
+        assert_eq!(id("A", "https://blog.example/post#comments"),
+            id("B", link.as_str()));
+
+        // The tracking parameters change from one download to the next.
+        assert_eq!(id("A", "https://blog.example/post?utm_source=rss"),
+            id("A", link.as_str()));
+        assert_ne!(id("A", "https://blog.example/post?p=2&utm_medium=rss"),
+            id("A", link.as_str()));
+
+        // Without a link, the title is all there is.
+        let published =
+            chrono::DateTime::from_timestamp(1704103200, 0).unwrap();
+        assert_eq!(id("Tpyo", ""), PostId::from_title(published, "Tpyo"));
+    }
+
+    #[test]
+    fn hashes_the_content_of_posts() {
+        let rss = |description: &str| format!(r#"<rss version="2.0"><channel>
+            <title>Example</title>
+            <item><guid>1</guid><description>{}</description></item>
+        </channel></rss>"#, description);
+        let hash = |description| parse_feed(&rss(description)).unwrap()
+            .as_ref()[0].hash.clone();
+        assert!(hash("First").is_some());
+        assert_eq!(hash("First"), hash("First"));
+        assert_ne!(hash("First"), hash("Fixed"));
+
+        let json = r#"{"version": "https://jsonfeed.org/version/1.1",
+            "items": [{"id": "1", "content_text": "Hi",
+                "date_modified": "2024-05-01T10:00:00Z"}]}"#;
+        let posts = parse_feed(json).unwrap();
+        assert_eq!(posts.as_ref()[0].updated.map(|date| date.timestamp()),
+            Some(1714557600));
+    }
+
+    #[test]
+    fn finds_advertised_feeds() {
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <LINK rel="alternate" type="application/rss+xml" href="/feed.xml">
+            <link href='https://other.org/atom?a=1&amp;b=2' rel=alternate
+                  type=application/atom+xml />
+            <link rel="alternate" type="text/html" href="/fr/">
+        </head></html>"#;
+
+        let base = Url::parse("https://example.com/blog/").unwrap();
+        let links: Vec<String> = find_feed_links(html, &base)
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        assert_eq!(links, [
+            "https://example.com/feed.xml",
+            "https://other.org/atom?a=1&b=2",
+        ]);
+    }
+
+    #[test]
+    fn extracts_article_text() {
+        let html = "<html><head><title>A &amp; B</title>
+            <style>p { color: red; }</style></head>
+            <body><!-- <p>hidden</p> --><script>let a = '<p>';</script>
+            <h1>Hello</h1><p>First
+            paragraph&nbsp;with <a href=\"/x\">a link</a>.</p>
+            <p>&#8220;Quoted&#x201D;, &bogus; &lt;3</p></body></html>";
+        assert_eq!(html_to_text(html), "A & B\n\nHello\n\n\
+            First paragraph with a link.\n\n\u{201c}Quoted\u{201d}, \
+            &bogus; <3");
+    }
+
+    #[test]
+    fn translates_youtube_urls() {
+        let feed = |url| {
+            youtube_feed_url(&Url::parse(url).unwrap())
+                .map(|url| url.to_string())
+        };
+        let videos = "https://www.youtube.com/feeds/videos.xml";
+        assert_eq!(feed("https://www.youtube.com/channel/UCxyz/videos"),
+            Some(format!("{}?channel_id=UCxyz", videos)));
+        assert_eq!(feed("https://youtube.com/user/someone"),
+            Some(format!("{}?user=someone", videos)));
+        assert_eq!(feed("https://m.youtube.com/playlist?list=PLxyz&si=1"),
+            Some(format!("{}?playlist_id=PLxyz", videos)));
+        assert_eq!(feed("https://www.youtube.com/playlist"), None);
+        assert_eq!(feed(videos), None);
+        assert_eq!(feed("https://example.com/channel/UCxyz"), None);
+
+        let url = Url::parse("https://www.youtube.com/@rustvideos").unwrap();
+        assert!(is_youtube_handle(&url));
+        assert!(!is_youtube_handle(&Url::parse(videos).unwrap()));
+    }
+
+    #[test]
+    fn guesses_feed_urls() {
+        let feed = |url| looks_like_feed(&Url::parse(url).unwrap());
+        assert!(feed("https://blog.rust-lang.org/feed.xml"));
+        assert!(feed("https://lobste.rs/t/rust.rss"));
+        assert!(feed("https://example.com/blog/feed/"));
+        assert!(feed("https://example.com/?format=rss"));
+        assert!(!feed("https://example.com/blog/post.html"));
+        assert!(!feed("https://example.com/feedback"));
+        assert!(!feed("file:///home/me/feed.xml"));
+    }
+
+    #[test]
+    fn finds_comment_links() {
+        let rss = r#"<rss version="2.0"
+            xmlns:c="http://wellformedweb.org/CommentAPI/"><channel>
+            <title>Example</title>
+            <item>
+                <guid>1</guid>
+                <link>https://example.com/1</link>
+                <comments>https://example.com/1#comments</comments>
+                <c:commentRss>https://example.com/1/feed</c:commentRss>
+            </item>
+        </channel></rss>"#;
+
+        let posts = parse_feed(rss).unwrap();
+        let post = &posts.as_ref()[0];
+        assert_eq!(post.comments.as_ref().unwrap().as_str(),
+            "https://example.com/1#comments");
+        assert_eq!(post.comment_feed.as_ref().unwrap().as_str(),
+            "https://example.com/1/feed");
+
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+            <title>Example</title>
+            <id>urn:example</id>
+            <updated>2024-05-01T12:00:00Z</updated>
+            <entry>
+                <id>urn:example:1</id>
+                <title>First</title>
+                <updated>2024-05-01T12:00:00Z</updated>
+                <link href="https://example.com/1"/>
+                <link rel="replies" type="application/atom+xml"
+                      href="https://example.com/1/feed"/>
+                <link rel="replies" type="text/html"
+                      href="https://example.com/1#comments"/>
+            </entry>
+        </feed>"#;
+
+        let posts = parse_feed(atom).unwrap();
+        let post = &posts.as_ref()[0];
+        assert_eq!(post.comments.as_ref().unwrap().as_str(),
+            "https://example.com/1#comments");
+        assert_eq!(post.comment_feed.as_ref().unwrap().as_str(),
+            "https://example.com/1/feed");
+    }
+
+    #[test]
+    fn normalizes_urls() {
+        let mut urls = Vec::new();
+        push_url(&mut urls, "https://News.example/story?id=1&utm_source=rss");
+        push_url(&mut urls, "https://news.example/story?id=1#comments");
+        push_url(&mut urls, "https://news.example/story?fbclid=abc");
+        push_url(&mut urls, "gemini://Capsule.example/post");
+        push_url(&mut urls, "https://app.example/#/posts/1");
+        push_url(&mut urls, "https://app.example/#!/posts/2");
+
+        let urls: Vec<&str> = urls.iter().map(Url::as_str).collect();
+        assert_eq!(urls, [
+            "https://news.example/story?id=1",
+            "https://news.example/story",
+            "gemini://capsule.example/post",
+            "https://app.example/#/posts/1",
+            "https://app.example/#!/posts/2",
+        ]);
+    }
+
+    #[test]
+    fn filters_downloaded_posts() {
+        let rss = r#"<rss version="2.0"><channel><title>Example</title>
+            <item><guid>1</guid><title>Rust 1.90</title></item>
+            <item><guid>2</guid><title>[Sponsor] Rust jobs</title></item>
+            <item><guid>3</guid><title>Go 1.30</title></item>
+        </channel></rss>"#;
+        let titles = |options: &FeedOptions| {
+            let mut posts = parse_feed(rss).unwrap();
+            filter_posts(options, &mut posts);
+            let mut titles: Vec<String> = posts.as_ref().iter()
+                .map(|post| post.title.to_string())
+                .collect();
+            titles.sort();
+            titles
+        };
+        let filters = |patterns: &[&str]| patterns.iter()
+            .map(|pattern| PostFilter::parse(pattern).unwrap())
+            .collect::<Vec<_>>();
+
+        let options = FeedOptions {
+            exclude: filters(&["[sponsor]"]),
+            ..Default::default()
+        };
+        assert_eq!(titles(&options), ["Go 1.30", "Rust 1.90"]);
+
+        let options = FeedOptions {
+            include: filters(&["/^Rust/"]),
+            ..options
+        };
+        assert_eq!(titles(&options), ["Rust 1.90"]);
+    }
+
+    #[test]
+    fn extracts_articles_by_rules() {
+        let html = r#"<html><body>
+            <div class="nav">Home</div>
+            <div id="main" class="post post-body">
+                <div>Hello</div> world
+            </div>
+            <footer>Bye</footer>
+        </body></html>"#;
+        let extract = |rule| {
+            let rule = ArticleRule::parse(rule).unwrap();
+            extract_article(&rule, html).map(str::trim)
+        };
+
+        let article = "<div>Hello</div> world";
+        assert_eq!(extract("div.post-body"), Some(article));
+        assert_eq!(extract("#main"), Some(article));
+        assert_eq!(extract(".post"), Some(article));
+        assert_eq!(extract("footer"), Some("Bye"));
+        assert_eq!(extract("/<footer>(.*)</footer>/"), Some("Bye"));
+        assert_eq!(extract("span.post"), None);
+        assert_eq!(extract(".missing"), None);
+
+        assert!(ArticleRule::parse("div > p").is_err());
+        assert!(ArticleRule::parse("/(/").is_err());
+    }
+
+    #[test]
+    fn parses_malformed_feeds() {
+        let rss = "\n<?xml version=\"1.0\"?><rss version=\"2.0\"><channel>
+            <title>Tom & Jerry</title>
+            <item>
+                <guid>1</guid>
+                <title>Cats &amp; dogs &mdash; \u{1}again</title>
+            </item>
+            <item><guid>2</guid><title><![CDATA[Q&A]]></title></item>
+        </channel></rss>";
+        assert!(parse_well_formed(rss).is_none());
+        let posts = parse_feed(rss).unwrap();
+        let mut titles: Vec<&str> = posts.as_ref().iter()
+            .map(|post| &*post.title)
+            .collect();
+        titles.sort();
+        assert_eq!(titles, ["Cats & dogs — again", "Q&A"]);
+
+        // The posts that can't be repaired are skipped, the rest are kept.
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+            <title>Example</title><id>urn:example</id>
+            <updated>2024-05-01T12:00:00Z</updated>
+            <entry><id>1</id><title>First</title>
+                <updated>2024-05-01T12:00:00Z</updated></entry>
+            <entry><id>2</id><title>Broken</b></title>
+                <updated>2024-05-01T12:00:00Z</updated></entry>
+            <entry><id>3</id><title>Third</title>
+                <updated>2024-05-01T12:00:00Z</updated></entry>
+        </feed>"#;
+        let (posts, skipped) = parse_feed_leniently(atom).unwrap();
+        assert_eq!(skipped, 1);
+        let mut ids: Vec<&str> = posts.as_ref().iter()
+            .map(|post| &*post.id.0)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["1", "3"]);
+    }
+
+    #[test]
+    fn rejects_unknown_formats() {
+        assert!(parse_feed("not a feed").is_none());
+    }
+
+    #[test]
+    fn downloads_the_stalest_feeds_first() {
+        let mut config = FeedConfig::parse_toml(r#"
+[[section]]
+title = "A"
+feed = [
+    { title = "Fresh", url = "https://a.example/" },
+    { title = "Stale", url = "https://b.example/" },
+    { title = "Failing", url = "https://c.example/" },
+    { title = "New", url = "https://d.example/" },
+]
+"#).unwrap();
+        let now = Utc::now();
+        let fetch = |hours_ago, error: Option<&str>| FeedHealth {
+            fetched: now - chrono::TimeDelta::hours(hours_ago),
+            duration_ms: 0,
+            status: None,
+            error: error.map(str::to_string),
+            failures: 0,
+            succeeded: None,
+        };
+        let feeds = &mut config.sections[0].feeds;
+        feeds[0].record_fetch(fetch(1, None));
+        feeds[1].record_fetch(fetch(5, None));
+        // Failing since it was last downloaded, 3 hours ago.
+        feeds[2].record_fetch(fetch(3, None));
+        feeds[2].record_fetch(fetch(0, Some("Timed out")));
+
+        let order: Vec<usize> = stalest_first(&config)[0].iter()
+            .map(|(feed_id, _)| feed_id.feed_idx)
+            .collect();
+        assert_eq!(order, [3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn spaces_out_downloads_from_the_same_host() {
+        let now = Instant::now();
+        let secs = Duration::from_secs;
+        let hosts = HostLimiter::new(PolitenessSettings {
+            delay_ms: 2000,
+            burst: 2,
+        });
+
+        // Two downloads start right away, the next ones 2 seconds apart.
+        assert_eq!(hosts.reserve("reddit.com", now), now);
+        assert_eq!(hosts.reserve("reddit.com", now), now);
+        assert_eq!(hosts.reserve("reddit.com", now), now + secs(2));
+        assert_eq!(hosts.reserve("reddit.com", now), now + secs(4));
+        // Other hosts don't wait for them.
+        assert_eq!(hosts.reserve("example.com", now), now);
+
+        // The burst is available again once the host has been left alone.
+        let later = now + secs(60);
+        assert_eq!(hosts.reserve("reddit.com", later), later);
+        assert_eq!(hosts.reserve("reddit.com", later), later);
+        assert_eq!(hosts.reserve("reddit.com", later), later + secs(2));
+    }
+
+    #[test]
+    fn times_out_hung_servers() {
+        // The server accepts the connection but never answers.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/feed.xml", listener.local_addr().unwrap());
+        let source = FeedSource {
+            url: Url::parse(&url).unwrap(),
+            options: Default::default(),
+            has_posts: false,
+        };
+        let client = reqwest::blocking::Client::new();
+
+        let response = fetch_source(&client, &source,
+            Duration::from_secs(5), Duration::from_millis(200));
+        assert_eq!(response.status, None);
+        assert!(matches!(response.body, Err(DownloadError::TimedOut(_))));
+    }
+
+    #[test]
+    fn times_out_stalled_servers() {
+        use std::io::{BufRead, BufReader, Write};
+
+        // The server answers, but stops sending in the middle of the body.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/feed.xml", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n\
+                <rss>").unwrap();
+            std::thread::sleep(Duration::from_secs(2));
+        });
+        let source = FeedSource {
+            url: Url::parse(&url).unwrap(),
+            options: Default::default(),
+            has_posts: false,
+        };
+        let client = reqwest::blocking::Client::new();
+
+        // The read timeout gives up on it well before the deadline.
+        let started = Instant::now();
+        let response = fetch_source(&client, &source,
+            Duration::from_millis(200), Duration::from_secs(60));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(response.status, Some(200));
+        assert!(matches!(response.body, Err(DownloadError::TimedOut(_))));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn follows_permanent_redirects() {
+        use std::io::{BufRead, BufReader, Write};
+
+        // The old feed has moved for good to the new one, which redirects
+        // elsewhere only for now.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut line = String::new();
+                BufReader::new(&stream).read_line(&mut line).unwrap();
+                let response = match line.split(' ').nth(1) {
+                    Some("/old.xml") => "301 Moved Permanently\r\n\
+                        Location: /new.xml",
+                    Some("/evil.xml") => "308 Permanent Redirect\r\n\
+                        Location: exec:touch /tmp/nia-pwned",
+                    Some("/new.xml") => "302 Found\r\nLocation: /now.xml",
+                    _ => "200 OK\r\nContent-Length: 6",
+                };
+                let _ = write!(stream, "HTTP/1.1 {}\r\n\
+                    Connection: close\r\n\r\n<rss/>", response);
+            }
+        });
+        let source = FeedSource {
+            url: Url::parse(&format!("{}/old.xml", address)).unwrap(),
+            options: Default::default(),
+            has_posts: false,
+        };
+        let client = reqwest::blocking::Client::builder()
+            .redirect(redirect_policy())
+            .build()
+            .unwrap();
+
+        let response = fetch_source(&client, &source, Duration::from_secs(5),
+            Duration::from_secs(5));
+        assert_eq!(response.status, Some(200));
+        assert_eq!(response.moved.unwrap().as_str(),
+            format!("{}/new.xml", address));
+        assert_eq!(response.body.unwrap(), "<rss/>");
+
+        // Nothing but the web is followed to.
+        let source = FeedSource {
+            url: Url::parse(&format!("{}/evil.xml", address)).unwrap(),
+            ..source
+        };
+        let response = fetch_source(&client, &source, Duration::from_secs(5),
+            Duration::from_secs(5));
+        assert_eq!(response.moved, None);
+        assert!(response.body.is_err());
+    }
+
+    #[test]
+    fn probes_the_network() {
+        let probe = |address: String| NetworkProbe::new(NetworkSettings {
+            probe: address,
+            probe_secs: 1,
+            max_wait_secs: 1,
+        });
+        assert!(probe(String::new()).is_online());
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        assert!(probe(address.clone()).is_online());
+
+        // Nothing listens on the port anymore.
+        drop(listener);
+        assert!(!probe(address.clone()).is_online());
+        assert!(!probe("not an address".into()).is_online());
+
+        // The downloads give up waiting after a while.
+        assert!(!probe(address.clone()).wait_until_online());
+
+        // Or once they're told to.
+        let probe = Arc::new(NetworkProbe::new(NetworkSettings {
+            probe: address,
+            probe_secs: 1,
+            max_wait_secs: 600,
+        }));
+        let waiting = {
+            let probe = probe.clone();
+            thread::spawn(move || probe.wait_until_online())
+        };
+        thread::sleep(Duration::from_millis(100));
+        probe.stop_waiting();
+        assert!(!waiting.join().unwrap());
+    }
 }
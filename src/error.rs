@@ -0,0 +1,99 @@
+//! The error type of nia.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// A result with nia's error type.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong outside of a single feed download.
+#[derive(Debug)]
+pub enum Error {
+    /// A file or the terminal couldn't be read or written.
+    Io(io::Error),
+
+    /// The database couldn't be opened, read or written.
+    Database(String),
+
+    /// Another instance is writing to the database in the directory, given
+    /// along with the ID of its process if it's known.
+    Locked(PathBuf, Option<u32>),
+
+    /// A search query the database doesn't understand.
+    InvalidQuery(String),
+
+    /// The settings of a remote are invalid.
+    Config(String),
+
+    /// A feed couldn't be downloaded or parsed.
+    Download(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Database(err) => write!(f, "Database error: {}", err),
+            Self::Locked(dir, pid) => {
+                write!(f, "Another nia")?;
+                if let Some(pid) = pid {
+                    write!(f, " (PID {})", pid)?;
+                }
+                write!(f, " is using the database in {}. Quit it, or use \
+                    --read-only to only read the database.", dir.display())
+            },
+            Self::InvalidQuery(err) => write!(f, "Invalid query: {}", err),
+            Self::Config(err) => write!(f, "{}", err),
+            Self::Download(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Where errors are only shown to the user, like when syncing with a remote,
+/// they're just messages.
+impl From<Error> for String {
+    fn from(err: Error) -> Self {
+        err.to_string()
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<sled::Error> for Error {
+    fn from(err: sled::Error) -> Self {
+        Self::Database(err.to_string())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::Database(err.to_string())
+    }
+}
+
+impl From<postcard::Error> for Error {
+    fn from(err: postcard::Error) -> Self {
+        Self::Database(format!("Couldn't encode the data: {}", err))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Database(format!("Couldn't encode the data: {}", err))
+    }
+}
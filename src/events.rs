@@ -0,0 +1,367 @@
+//! The events the application waits for, arriving through one channel from
+//! the terminal, the downloader, the database, the signal handler and the
+//! watcher of the feed file.
+
+use std::io;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use crossterm::event::{self, Event};
+use signal_hook::iterator::Signals;
+use crate::database::DatabaseResponse;
+use crate::download::DownloadResponse;
+
+/// How long the input reader waits for input before it checks whether it
+/// should pause or stop.
+const INPUT_POLL: Duration = Duration::from_millis(100);
+
+/// How often the feed file is checked for changes where they can't be
+/// watched for.
+#[cfg(not(target_os = "linux"))]
+const FEED_FILE_POLL: Duration = Duration::from_secs(1);
+
+/// An event the application reacts to.
+pub enum AppEvent {
+    /// An event has been read from the terminal.
+    Input(Event),
+
+    /// The terminal couldn't be read from.
+    InputFailed(io::Error),
+
+    /// A response from the downloader.
+    Download(DownloadResponse),
+
+    /// The database has handled a request, so there's room in its queue.
+    Saved,
+
+    /// A response from the database.
+    Database(DatabaseResponse),
+
+    /// A signal has asked the application to quit.
+    Quit,
+
+    /// The feed file has been saved.
+    FeedFileChanged,
+
+    /// Nothing has happened for a tick, time to animate or check the timers.
+    Tick,
+}
+
+/// The channel all of the events of the application arrive through.
+pub struct EventChannel {
+    /// The sending end, cloned for every source of events.
+    tx: mpsc::Sender<AppEvent>,
+
+    /// The receiving end, read by the event loop.
+    rx: mpsc::Receiver<AppEvent>,
+
+    /// Lets the forwarder of the download responses go on after a feed has
+    /// been downloaded.
+    resume_tx: Option<mpsc::Sender<()>>,
+}
+
+impl Default for EventChannel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventChannel {
+    /// Create a new event channel without any sources.
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self { tx, rx, resume_tx: None }
+    }
+
+    /// Get a sender for a new source of events.
+    pub fn sender(&self) -> mpsc::Sender<AppEvent> {
+        self.tx.clone()
+    }
+
+    /// Forward the download `responses` into the channel.
+    ///
+    /// After each downloaded feed, the forwarding waits until
+    /// `resume_downloads` is called, so that the downloaders wait while the
+    /// database catches up instead of filling the memory with posts.
+    pub fn forward_downloads(
+        &mut self,
+        responses: mpsc::Receiver<DownloadResponse>,
+    ) {
+        let (resume_tx, resume_rx) = mpsc::channel();
+        self.resume_tx = Some(resume_tx);
+
+        let tx = self.sender();
+        thread::spawn(move || {
+            for response in responses {
+                let finished = matches!(response,
+                    DownloadResponse::Finished { .. });
+                if tx.send(AppEvent::Download(response)).is_err() {
+                    return;
+                }
+                if finished && resume_rx.recv().is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Send a quit event into the channel whenever one of the `signals`
+    /// arrives.
+    pub fn forward_signals(&self, signals: &[i32]) -> io::Result<()> {
+        let mut signals = Signals::new(signals)?;
+
+        let tx = self.sender();
+        thread::spawn(move || {
+            for _ in signals.forever() {
+                if tx.send(AppEvent::Quit).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Send an event into the channel whenever the feed file at the `path`
+    /// is saved.
+    ///
+    /// Editors often save by renaming a new file over the old one, so the
+    /// directory of the file is watched with inotify rather than the file.
+    #[cfg(target_os = "linux")]
+    pub fn forward_feed_file_changes(&self, path: &Path) -> io::Result<()> {
+        use std::ffi::OsStr;
+        use std::mem::MaybeUninit;
+        use std::os::unix::ffi::OsStrExt;
+        use rustix::fs::inotify::{self, CreateFlags, WatchFlags};
+        use rustix::io::Errno;
+
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(io::Error::other("not a path to a file"));
+        };
+        let fd = inotify::init(CreateFlags::CLOEXEC)?;
+        inotify::add_watch(&fd, dir,
+            WatchFlags::CLOSE_WRITE | WatchFlags::MOVED_TO)?;
+
+        let name = name.to_owned();
+        let tx = self.sender();
+        thread::spawn(move || {
+            let mut buf = [MaybeUninit::uninit(); 4096];
+            let mut reader = inotify::Reader::new(&fd, &mut buf);
+            loop {
+                let event = match reader.next() {
+                    Ok(event) => event,
+                    Err(Errno::INTR) => continue,
+                    Err(_) => return,
+                };
+                let saved = event.file_name().is_some_and(|file| {
+                    OsStr::from_bytes(file.to_bytes()) == name
+                });
+                if saved && tx.send(AppEvent::FeedFileChanged).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Send an event into the channel whenever the feed file at the `path`
+    /// is saved.
+    ///
+    /// Without inotify, a thread checks when the file was last modified.
+    #[cfg(not(target_os = "linux"))]
+    pub fn forward_feed_file_changes(&self, path: &Path) -> io::Result<()> {
+        let modified_at = |path: &Path| std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified()).ok();
+
+        let path = path.to_owned();
+        let mut modified = modified_at(&path);
+        let tx = self.sender();
+        thread::spawn(move || loop {
+            thread::sleep(FEED_FILE_POLL);
+            let now = modified_at(&path);
+            if now != modified {
+                modified = now;
+                if tx.send(AppEvent::FeedFileChanged).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Let the next downloaded feed through, once the posts of the last one
+    /// have been handed to the database.
+    pub fn resume_downloads(&self) {
+        if let Some(resume_tx) = &self.resume_tx {
+            let _ = resume_tx.send(());
+        }
+    }
+
+    /// Wait for the next event, but no longer than the `timeout`, if any.
+    ///
+    /// A tick is returned once the timeout has passed without an event.
+    pub fn next(&self, timeout: Option<Duration>) -> AppEvent {
+        // The channel can't be closed while we hold a sender.
+        match timeout {
+            Some(timeout) => self.rx.recv_timeout(timeout).ok(),
+            None => self.rx.recv().ok(),
+        }.unwrap_or(AppEvent::Tick)
+    }
+
+    /// Get the next event if one has arrived already.
+    pub fn try_next(&self) -> Option<AppEvent> {
+        self.rx.try_recv().ok()
+    }
+}
+
+/// A request to the thread of the input reader.
+enum Control {
+    /// Stop reading the terminal until resumed.
+    Pause,
+
+    /// Start reading the terminal again.
+    Resume,
+
+    /// Stop reading the terminal for good.
+    Stop,
+}
+
+/// Reads the events of the terminal on a thread of its own and sends them
+/// through the event channel.
+///
+/// The thread stops once the reader is dropped.
+pub struct InputReader {
+    /// Requests to the thread.
+    control_tx: mpsc::Sender<Control>,
+
+    /// Confirms that the thread has paused.
+    paused_rx: mpsc::Receiver<()>,
+
+    /// Handle of the thread.
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl InputReader {
+    /// Spawn the thread that reads the terminal into the channel of `events`.
+    ///
+    /// Nothing else may read the terminal while the reader isn't paused.
+    pub fn spawn(events: mpsc::Sender<AppEvent>) -> Self {
+        let (control_tx, control_rx) = mpsc::channel();
+        let (paused_tx, paused_rx) = mpsc::channel();
+
+        let handle = thread::spawn(move || loop {
+            match control_rx.try_recv() {
+                Ok(Control::Pause) => loop {
+                    let _ = paused_tx.send(());
+                    match control_rx.recv() {
+                        Ok(Control::Resume) => break,
+                        Ok(Control::Pause) => {},
+                        Ok(Control::Stop) | Err(_) => return,
+                    }
+                },
+                Ok(Control::Resume) | Err(mpsc::TryRecvError::Empty) => {},
+                Ok(Control::Stop)
+                | Err(mpsc::TryRecvError::Disconnected) => return,
+            }
+
+            // Wake up every now and then to check the requests.
+            let event = match event::poll(INPUT_POLL) {
+                Ok(true) => event::read().map(AppEvent::Input),
+                Ok(false) => continue,
+                Err(err) => Err(err),
+            };
+            let failed = event.is_err();
+            let event = event.unwrap_or_else(AppEvent::InputFailed);
+            if events.send(event).is_err() || failed {
+                return;
+            }
+        });
+
+        Self { control_tx, paused_rx, handle: Some(handle) }
+    }
+
+    /// Stop reading the terminal, e.g. while another program runs in it.
+    ///
+    /// Returns once the reader has stopped.
+    pub fn pause(&self) {
+        if self.control_tx.send(Control::Pause).is_ok() {
+            let _ = self.paused_rx.recv();
+        }
+    }
+
+    /// Start reading the terminal again after a pause.
+    pub fn resume(&self) {
+        let _ = self.control_tx.send(Control::Resume);
+    }
+}
+
+impl Drop for InputReader {
+    fn drop(&mut self) {
+        let _ = self.control_tx.send(Control::Stop);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FeedId, Posts};
+
+    #[test]
+    fn holds_back_downloads_until_resumed() {
+        let feed = FeedId { section_idx: 0, feed_idx: 0 };
+        let (tx, rx) = mpsc::channel();
+        tx.send(DownloadResponse::Finished {
+            feed: feed.clone(), posts: Posts::new()
+        }).unwrap();
+        tx.send(DownloadResponse::Started(feed)).unwrap();
+
+        let mut events = EventChannel::new();
+        events.forward_downloads(rx);
+        let timeout = Some(Duration::from_millis(100));
+        assert!(matches!(events.next(timeout),
+            AppEvent::Download(DownloadResponse::Finished { .. })));
+        assert!(matches!(events.next(timeout), AppEvent::Tick));
+
+        events.resume_downloads();
+        assert!(matches!(events.next(timeout),
+            AppEvent::Download(DownloadResponse::Started(_))));
+    }
+
+    #[test]
+    fn forwards_signals() {
+        use signal_hook::consts::SIGUSR1;
+
+        let events = EventChannel::new();
+        events.forward_signals(&[SIGUSR1]).unwrap();
+        signal_hook::low_level::raise(SIGUSR1).unwrap();
+        let timeout = Some(Duration::from_secs(1));
+        assert!(matches!(events.next(timeout), AppEvent::Quit));
+    }
+
+    #[test]
+    fn forwards_feed_file_changes() {
+        let dir = std::env::temp_dir()
+            .join(format!("nia-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("feeds");
+
+        let events = EventChannel::new();
+        events.forward_feed_file_changes(&path).unwrap();
+        let timeout = Some(Duration::from_secs(3));
+
+        // A file saved in place.
+        std::fs::write(&path, "https://example.com/feed\n").unwrap();
+        assert!(matches!(events.next(timeout), AppEvent::FeedFileChanged));
+
+        // A file renamed over the old one.
+        let temp = dir.join("feeds.tmp");
+        std::fs::write(&temp, "https://example.org/feed\n").unwrap();
+        std::fs::rename(&temp, &path).unwrap();
+        assert!(matches!(events.next(timeout), AppEvent::FeedFileChanged));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+//! Export and import of the stored posts in a portable format.
+//!
+//! The posts are written as JSON objects, either all in one array or one per
+//! line (ndjson). Both are read back by the import.
+
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use url::Url;
+use crate::config::{Post, PostId, Posts};
+use crate::database::Database;
+
+/// A post as it's exported, along with the URL of its feed.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportedPost {
+    pub feed: String,
+    pub id: String,
+    pub title: String,
+    pub urls: Vec<String>,
+    pub published: String,
+    pub arrived: String,
+    pub read: bool,
+
+    /// Missing in the exports made before posts could be starred.
+    #[serde(default)]
+    pub starred: bool,
+
+    /// Missing in the exports made before the comment links were kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment_feed: Option<String>,
+
+    /// Missing in the exports made before the authors and the categories
+    /// were kept.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+
+    /// Missing in the exports made before the summaries were kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+
+    /// Missing in the exports made before the edits were tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+
+    #[serde(default)]
+    pub edited: bool,
+}
+
+impl ExportedPost {
+    /// Create the exported form of a `post` of the feed at `feed_url`.
+    pub fn new(feed_url: &str, post: &Post) -> Self {
+        Self {
+            feed: feed_url.to_string(),
+            id: post.id.0.to_string(),
+            title: post.title.to_string(),
+            urls: post.urls.iter().map(|url| url.to_string()).collect(),
+            published: post.published.to_rfc3339(),
+            arrived: post.arrived.to_rfc3339(),
+            read: post.read,
+            starred: post.starred,
+            comments: post.comments.as_ref().map(|url| url.to_string()),
+            comment_feed: post.comment_feed.as_ref()
+                .map(|url| url.to_string()),
+            authors: post.authors.clone(),
+            categories: post.categories.clone(),
+            summary: post.summary.clone(),
+            updated: post.updated.map(|updated| updated.to_rfc3339()),
+            hash: post.hash.clone(),
+            edited: post.edited,
+        }
+    }
+
+    /// Convert the exported post back into a post.
+    pub fn into_post(self) -> Result<Post, String> {
+        let date = |s: &str| DateTime::parse_from_rfc3339(s)
+            .map(|date| date.with_timezone(&Utc))
+            .map_err(|err| format!("Invalid date \"{}\": {}", s, err));
+
+        let url = |url: &str| Url::parse(url)
+            .map_err(|err| format!("Invalid URL \"{}\": {}", url, err));
+        let urls = self.urls.iter()
+            .map(|s| url(s))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Post {
+            id: PostId(Arc::from(self.id)),
+            title: Arc::from(self.title),
+            urls,
+            published: date(&self.published)?,
+            arrived: date(&self.arrived)?,
+            read: self.read,
+            starred: self.starred,
+            comments: self.comments.as_deref().map(url).transpose()?,
+            comment_feed: self.comment_feed.as_deref().map(url).transpose()?,
+            authors: self.authors,
+            categories: self.categories,
+            summary: self.summary,
+            updated: self.updated.as_deref().map(date).transpose()?,
+            hash: self.hash,
+            edited: self.edited,
+        })
+    }
+}
+
+/// Write all posts in the database to `out`, one per line if `ndjson` is set.
+///
+/// Returns the number of exported posts.
+pub fn export<W: Write>(db: &Database, ndjson: bool, mut out: W)
+    -> io::Result<usize>
+{
+    let posts = db.all_posts().map_err(io::Error::other)?.into_iter()
+        .map(|(feed_url, post)| ExportedPost::new(&feed_url, &post));
+
+    let mut exported = 0;
+    if ndjson {
+        for post in posts {
+            serde_json::to_writer(&mut out, &post)?;
+            writeln!(out)?;
+            exported += 1;
+        }
+    } else {
+        let posts: Vec<ExportedPost> = posts.collect();
+        serde_json::to_writer_pretty(&mut out, &posts)?;
+        writeln!(out)?;
+        exported = posts.len();
+    }
+
+    out.flush()?;
+    Ok(exported)
+}
+
+/// Parse exported posts, either a JSON array or one JSON object per line.
+pub fn parse(input: &str) -> Result<Vec<ExportedPost>, String> {
+    if input.trim_start().starts_with('[') {
+        return serde_json::from_str(input).map_err(|err| err.to_string());
+    }
+
+    input.lines().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| serde_json::from_str(line)
+            .map_err(|err| format!("Line {}: {}", idx + 1, err)))
+        .collect()
+}
+
+/// Store the exported posts in the `input` into the database.
+///
+/// Posts that are already stored are replaced, including their read state.
+/// Nothing is stored if any of the posts is invalid. Returns the number of
+/// imported posts.
+pub fn import(db: &Database, input: &str) -> Result<usize, String> {
+    let posts = parse(input)?.into_iter()
+        .map(|post| {
+            let feed = post.feed.clone();
+            post.into_post().map(|post| (feed, post))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Save the posts of each feed at once.
+    let imported = posts.len();
+    let mut feeds: HashMap<String, Posts> = HashMap::new();
+    for (feed_url, post) in posts {
+        feeds.entry(feed_url).or_default().insert(post);
+    }
+
+    for (feed_url, posts) in feeds {
+        db.save_posts(&feed_url, posts)?;
+    }
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_post;
+
+    #[test]
+    fn parses_both_formats() {
+        let post = Post {
+            title: Arc::from("Hello"),
+            arrived: DateTime::from_timestamp(1_700_000_100, 0).unwrap(),
+            starred: true,
+            read: true,
+            comments: Some(Url::parse("https://example.com/a#c").unwrap()),
+            authors: vec!["Jane Doe".into()],
+            summary: Some("Hello there.".into()),
+            hash: Some("12ab".into()),
+            edited: true,
+            ..test_post("1", "https://example.com/a", 1_700_000_000)
+        };
+        let exported = ExportedPost::new("https://example.com/feed", &post);
+
+        let line = serde_json::to_string(&exported).unwrap();
+        let ndjson = format!("{}\n\n{}\n", line, line);
+        assert_eq!(parse(&ndjson).unwrap().len(), 2);
+
+        let json = serde_json::to_string_pretty(&[&exported]).unwrap();
+        let parsed = parse(&json).unwrap().pop().unwrap();
+        assert_eq!(parsed, exported);
+
+        let back = parsed.into_post().unwrap();
+        assert_eq!(back.arrived, post.arrived);
+        assert!(back.read);
+        assert!(back.starred);
+        assert_eq!(back.comments, post.comments);
+        assert_eq!(back.authors, post.authors);
+        assert_eq!(back.summary, post.summary);
+        assert_eq!(back.hash, post.hash);
+        assert!(back.edited);
+
+        assert!(parse("{\"feed\": 1}").is_err());
+    }
+}
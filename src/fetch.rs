@@ -0,0 +1,187 @@
+//! Headless fetching of all feeds, without the TUI.
+
+use std::sync::Arc;
+use std::collections::HashMap;
+use crate::app::FeedState;
+use crate::cli::exit_code;
+use crate::config::{FeedConfig, FeedId};
+use crate::error::{Error, Result};
+use crate::hooks::HookRunner;
+use crate::tui::text;
+use crate::settings::{NetworkSettings, Settings};
+use crate::download::*;
+use crate::database::*;
+use crate::cache::BodyCache;
+
+/// The result of fetching a single feed.
+pub struct FeedResult {
+    /// Title of the feed.
+    pub title: Arc<str>,
+
+    /// The number of new posts or the reason why the feed couldn't be fetched.
+    pub outcome: Result<usize, String>,
+}
+
+/// The results of fetching all feeds, in the order of the feed file.
+pub struct FetchSummary {
+    pub results: Vec<FeedResult>,
+
+    /// The errors of the posts that couldn't be saved in the database.
+    pub errors: Vec<Error>,
+}
+
+impl FetchSummary {
+    /// Get the number of feeds that couldn't be fetched.
+    pub fn failed(&self) -> usize {
+        self.results.iter().filter(|r| r.outcome.is_err()).count()
+    }
+
+    /// Get the exit code describing this summary.
+    pub fn exit_code(&self) -> u8 {
+        if self.failed() == 0 && self.errors.is_empty() {
+            exit_code::OK
+        } else {
+            exit_code::FEEDS_FAILED
+        }
+    }
+
+    /// Print a line for each feed followed by the totals.
+    ///
+    /// If `quiet` is set, only the feeds that failed are printed, so that cron
+    /// only sends mail when something went wrong.
+    pub fn print(&self, quiet: bool) {
+        // Wide characters take two columns.
+        let width = self.results.iter()
+            .map(|r| text::width(&r.title))
+            .max()
+            .unwrap_or(0);
+
+        for result in self.results.iter() {
+            let title = text::pad(&result.title, width);
+            match &result.outcome {
+                Ok(_) if quiet => {},
+                Ok(new) => println!("ok      {}  {} new", title, new),
+                Err(err) => println!("failed  {}  {}", title, err),
+            }
+        }
+
+        for err in self.errors.iter() {
+            println!("error   {}", err);
+        }
+
+        if quiet {
+            return;
+        }
+
+        let new: usize = self.results.iter()
+            .filter_map(|r| r.outcome.as_ref().ok())
+            .sum();
+        println!("{} feeds, {} failed, {} new posts",
+            self.results.len(), self.failed(), new);
+    }
+}
+
+/// Download all feeds and store the new posts in the database.
+///
+/// Blocks until all feeds have been downloaded and all new posts have been
+/// written to the database. Fails only if the database can't be opened.
+pub fn fetch_all(mut feeds: FeedConfig, settings: Settings)
+    -> Result<FetchSummary>
+{
+    let (database, _) = DatabaseChannel::spawn_database_thread(
+        &mut feeds, &settings)?;
+    // Scripts are better off failing than waiting for the network.
+    let network = NetworkSettings {
+        probe: String::new(),
+        ..Default::default()
+    };
+    let download = DownloadChannel::spawn_downloader_thread(
+        settings.queues.downloads, settings.titles.clone(),
+        settings.politeness, settings.timeouts,
+        Some(BodyCache::open(&settings)?), network);
+    let mut hooks = HookRunner::new(settings.hooks.clone());
+    let unread_edited = settings.unread_edited;
+    let sections = stalest_first(&feeds);
+    let mut state = FeedState::new(feeds, settings);
+
+    // Download everything.
+    let feed_ids: Vec<FeedId> = state.feed_ids().collect();
+    download.request_tx.send(DownloadRequest::All(sections))
+        .map_err(|_| Error::Download("The downloader has stopped".into()))?;
+
+    // Wait until every feed has either finished or failed.
+    let mut outcomes = HashMap::new();
+    while outcomes.len() < feed_ids.len() {
+        let Ok(response) = download.response_rx.recv() else {
+            break;
+        };
+
+        match response {
+            DownloadResponse::Started(_)
+            | DownloadResponse::Waiting(_)
+            | DownloadResponse::Moved(..)
+            | DownloadResponse::Received(..)
+            | DownloadResponse::LinkChecked(..)
+            | DownloadResponse::Discovered(..)
+            | DownloadResponse::SavedForLater(..)
+            | DownloadResponse::Image(..)
+            | DownloadResponse::Article(..)
+            | DownloadResponse::Archived(..) => {},
+            DownloadResponse::Failed(feed, reason) => {
+                outcomes.insert(feed, Err(reason.to_string()));
+            },
+            DownloadResponse::Fetched(feed, health) => {
+                if let Some(request) = state.record_fetch(&feed, health) {
+                    database.send(request);
+                }
+            },
+            DownloadResponse::Described(feed, meta) => {
+                if let Some(request) = state.record_meta(&feed, meta) {
+                    database.send(request);
+                }
+            },
+            DownloadResponse::Finished { feed, posts } => {
+                let Some(state) = state.get_feed_mut(&feed) else {
+                    continue;
+                };
+
+                // Save only the new posts in the feed and the database. The
+                // backlog of a feed downloaded for the first time isn't new
+                // to the hooks.
+                let first = state.posts.is_empty();
+                let edited = state.update_posts(&posts, unread_edited);
+                let gone = state.trim_pruned(&posts);
+                let mut posts = state.merge_posts(posts);
+                let feed_url: Arc<str> = state.url.as_str().into();
+                outcomes.insert(feed, Ok(posts.len()));
+                if !first {
+                    hooks.run(state, &posts);
+                }
+
+                // Waiting for the database makes the downloaders wait too.
+                if !gone.is_empty() {
+                    database.send(DatabaseRequest::ForgetPruned {
+                        feed_url: feed_url.clone(), post_ids: gone
+                    });
+                }
+                posts.append(edited);
+                database.send(DatabaseRequest::SavePosts { feed_url, posts });
+            },
+        }
+    }
+
+    // Make sure everything is written and handed to the hooks before we
+    // return.
+    let errors = database.close();
+    hooks.finish(None);
+
+    let results = feed_ids.into_iter().map(|feed_id| {
+        let title = state.get_feed(&feed_id).unwrap().title.clone();
+        let outcome = outcomes.remove(&feed_id).unwrap_or_else(|| {
+            Err("The downloader has closed abruptly.".into())
+        });
+        FeedResult { title, outcome }
+    }).collect();
+
+    Ok(FetchSummary { results, errors })
+}
@@ -0,0 +1,166 @@
+//! Commands run for every newly downloaded post.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+use crate::config::{Feed, Post, Posts};
+use crate::settings::Hook;
+
+/// How often the hooks are checked while waiting for them to finish.
+const POLL: Duration = Duration::from_millis(50);
+
+/// Runs the hooks of the new posts in the background, one batch of posts at a
+/// time per feed.
+pub struct HookRunner {
+    /// The hooks from the settings.
+    hooks: Arc<[Hook]>,
+
+    /// The threads running the hooks.
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl HookRunner {
+    /// Create a runner of the `hooks`.
+    pub fn new(hooks: Vec<Hook>) -> Self {
+        Self { hooks: hooks.into(), threads: Vec::new() }
+    }
+
+    /// Run the hooks that apply to the `feed` for each of its new `posts`.
+    pub fn run(&mut self, feed: &Feed, posts: &Posts) {
+        let hooks: Vec<usize> = (0..self.hooks.len())
+            .filter(|&idx| self.hooks[idx].applies_to(&feed.url))
+            .collect();
+        if hooks.is_empty() || posts.is_empty() {
+            return;
+        }
+
+        // The oldest posts first, the order they've been published in.
+        let payloads: Vec<String> = posts.as_ref().iter()
+            .rev()
+            .map(|post| payload(feed, post).to_string())
+            .collect();
+
+        self.threads.retain(|thread| !thread.is_finished());
+        let all = self.hooks.clone();
+        self.threads.push(thread::spawn(move || {
+            for payload in &payloads {
+                for &idx in &hooks {
+                    run_hook(&all[idx], payload);
+                }
+            }
+        }));
+    }
+
+    /// Wait until the hooks that have been started have finished, or until
+    /// the `deadline` passes. Without one, they're waited for indefinitely.
+    pub fn finish(self, deadline: Option<Instant>) {
+        for thread in self.threads {
+            while !thread.is_finished() {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    warn!("Quitting before the hooks have finished");
+                    return;
+                }
+                thread::sleep(POLL);
+            }
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The JSON given to the hooks for the `post` of the `feed`.
+fn payload(feed: &Feed, post: &Post) -> Value {
+    json!({
+        "feed": {
+            "title": feed.title.as_ref(),
+            "url": feed.url.as_str(),
+        },
+        "id": post.id.0.as_ref(),
+        "title": post.title.as_ref(),
+        "urls": post.urls.iter().map(|url| url.as_str()).collect::<Vec<_>>(),
+        "published": post.published.to_rfc3339(),
+        "comments": post.comments.as_ref().map(|url| url.as_str()),
+        "authors": post.authors,
+        "categories": post.categories,
+        "summary": post.summary,
+    })
+}
+
+/// Run the `hook` with a shell, giving it the `payload` on stdin.
+fn run_hook(hook: &Hook, payload: &str) {
+    debug!("Running the hook `{}`", hook.command);
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(&hook.command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            warn!("Couldn't run the hook `{}`: {}", hook.command, err);
+            return;
+        },
+    };
+
+    // A hook that doesn't read its input closes the pipe early, which is
+    // fine.
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = writeln!(stdin, "{}", payload);
+    }
+
+    match child.wait() {
+        Ok(status) if status.success() => {},
+        Ok(status) => warn!("The hook `{}` failed: {}", hook.command, status),
+        Err(err) => warn!("Couldn't run the hook `{}`: {}", hook.command, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+    use crate::config::test_post;
+
+    #[test]
+    fn pipes_new_posts_into_hooks() {
+        let dir = std::env::temp_dir()
+            .join(format!("nia-hooks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("posts.json");
+
+        let url = Url::parse("https://example.com/feed.xml").unwrap();
+        let hooks = vec![
+            Hook {
+                feed: None,
+                command: format!("cat >> {}", out.display()),
+            },
+            Hook {
+                feed: Some(Url::parse("https://other.example/").unwrap()),
+                command: format!("echo other >> {}", out.display()),
+            },
+        ];
+
+        let post = |id: &str| test_post(id, "https://example.com/post", 0);
+        let feed = Feed::new("Example".into(), url);
+
+        let mut runner = HookRunner::new(hooks);
+        runner.run(&feed, &vec![post("a"), post("b")].into());
+        runner.finish(None);
+
+        let lines: Vec<Value> = std::fs::read_to_string(&out).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["feed"]["title"], "Example");
+        assert_eq!(lines[0]["urls"][0], "https://example.com/post");
+        assert_eq!(lines[0]["comments"], Value::Null);
+    }
+}
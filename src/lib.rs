@@ -1,8 +1,61 @@
+//! nia is a terminal feed reader, and this library is what it's built on.
+//!
+//! The facade below is enough to read feeds without the terminal interface:
+//! download a feed with [`fetch_feed`], merge the posts into a [`Feed`] of a
+//! [`FeedConfig`] and keep them in a [`Database`]. None of these panic, they
+//! return an [`Error`] instead.
+//!
+//! ```no_run
+//! use nia::{Database, FeedConfig, Posts, StorageSettings};
+//!
+//! # fn main() -> nia::Result<()> {
+//! let url = url::Url::parse("https://example.com/feed.xml").unwrap();
+//! let mut cfg = FeedConfig::single_feed("Example", url.clone(), Posts::new());
+//!
+//! // Start from the posts stored by previous runs.
+//! let db = Database::open(&StorageSettings::default())?;
+//! db.load_config(&mut cfg)?;
+//!
+//! // Download the feed and store the posts that are new.
+//! let id = cfg.feed_ids().next().unwrap();
+//! let feed = cfg.get_feed_mut(&id).unwrap();
+//! let new = feed.merge_posts(nia::fetch_feed(&url)?);
+//! db.save_posts(url.as_str(), new)?;
+//!
+//! // Posts shared by several feeds are shown once.
+//! for post in cfg.merged_posts() {
+//!     println!("{} in {} feeds", post.published, post.sources.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
 pub mod config;
+pub mod error;
 pub mod tui;
 pub mod app;
 pub mod download;
+pub mod events;
 pub mod database;
+pub mod fetch;
+pub mod cli;
+pub mod settings;
+pub mod remote;
+pub mod suggest;
+pub mod read_later;
+pub mod export;
+pub mod bookmarks;
+pub mod state;
+pub mod logging;
+pub mod hooks;
+pub mod notes;
+pub mod cache;
+
+pub use config::{Feed, FeedConfig, FeedId, MergedPost, Post, PostId, Posts};
+pub use database::Database;
+pub use download::{DiscoveredFeed, discover_feed, fetch_feed, parse_feed};
+pub use error::{Error, Result};
+pub use settings::{Settings, StorageSettings};
 
 /// A function that generates a stable hash for `s`.
 pub fn hash(s: &str) -> String {
@@ -18,16 +71,3 @@ pub fn hash(s: &str) -> String {
 
     hash.to_string()
 }
-
-/// Log the string `s` to a file.
-pub fn log(s: &str) {
-    use std::fs::OpenOptions;
-    use std::io::Write;
-    if let Ok(mut file) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("/tmp/nia_log")
-    {
-        let _ = writeln!(file, "{}", s);
-    }
-}
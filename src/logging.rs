@@ -0,0 +1,64 @@
+//! Logging to a file.
+//!
+//! The TUI owns the terminal, so the log is written to `nia.log` in the state
+//! directory instead of to stderr.
+
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+/// The size after which the log is moved aside to `nia.log.old` on startup.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Start logging to the log file, with the debug messages if `verbose` is set.
+///
+/// Returns the path of the log file.
+pub fn init(verbose: bool) -> io::Result<PathBuf> {
+    let path = get_state_dir()?.join("nia.log");
+
+    // Keep only the previous log around once it grows too large.
+    if fs::metadata(&path).is_ok_and(|meta| meta.len() > MAX_LOG_SIZE) {
+        fs::rename(&path, path.with_extension("log.old"))?;
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let level = if verbose { LevelFilter::DEBUG } else { LevelFilter::INFO };
+
+    // Only the warnings of the libraries are interesting.
+    let filter = Targets::new()
+        .with_target(env!("CARGO_CRATE_NAME"), level)
+        .with_default(LevelFilter::WARN);
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(Mutex::new(file))
+        .with_ansi(false);
+
+    tracing_subscriber::registry()
+        .with(layer.with_filter(filter))
+        .try_init()
+        .map_err(io::Error::other)?;
+
+    Ok(path)
+}
+
+/// Get path to the state directory, creating it if it doesn't exist.
+fn get_state_dir() -> io::Result<PathBuf> {
+    // Get a path to the state directory.
+    let state_dir = match std::env::var("XDG_STATE_HOME") {
+        Ok(dir) => PathBuf::new().join(dir),
+        Err(_) => crate::config::home_dir()?.join(".local/state"),
+    };
+
+    // Use the compile time project name as the state dir.
+    let state_dir = state_dir.join(env!("CARGO_PKG_NAME"));
+
+    // If the directory doesn't exist, create it.
+    if !state_dir.exists() {
+        fs::DirBuilder::new().recursive(true).create(&state_dir)?;
+    }
+
+    Ok(state_dir)
+}
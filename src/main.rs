@@ -1,33 +1,577 @@
-use std::io;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use crossterm::cursor::Show;
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen,
     LeaveAlternateScreen
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
+use nia::app::{App, SessionSummary};
+use nia::config::FeedConfig;
+use nia::settings::Settings;
+use nia::cli::{self, Args, Cli, exit_code};
+use nia::database::Database;
+use nia::error::Error;
+use nia::export::ExportedPost;
 
-fn main() -> io::Result<()> {
-    // Parse the feeds
-    let feeds = nia::config::FeedConfig::parse_feed_file()
-        .expect("Couldn't parse the feed file.");
-    let Some(feeds) = feeds else {
-        println!("No feeds!");
-        return Ok(());
+fn main() -> io::Result<ExitCode> {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            return Ok(exit_code::USAGE.into());
+        },
     };
 
-    // Set up the terminal.
+    // Without the log, nia works all the same.
+    if let Err(err) = nia::logging::init(args.verbose) {
+        eprintln!("Couldn't open the log file: {}", err);
+    }
+
+    // A profile given on the command line wins over the environment.
+    let profile = |arg: &Option<String>, var| arg.clone()
+        .or_else(|| std::env::var(var).ok())
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from);
+    if let Some(dir) = profile(&args.config, "NIA_CONFIG_DIR") {
+        FeedConfig::set_config_dir(dir);
+    }
+
+    let mut settings = match Settings::load() {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("Couldn't parse the settings: {}", err);
+            return Ok(exit_code::CONFIG_ERROR.into());
+        },
+    };
+
+    settings.storage.read_only = args.read_only;
+    settings.offline = args.offline;
+    settings.storage.dir = profile(&args.data_dir, "NIA_DATA_DIR");
+
+    let quit_summary = settings.quit_summary;
+    let app = match args.command {
+        // Browse a feed document read from stdin.
+        Cli::Stdin => {
+            let mut body = String::new();
+            io::stdin().read_to_string(&mut body)?;
+
+            let Some(mut posts) = nia::download::parse_feed(&body) else {
+                eprintln!("stdin is not an Atom, RSS or JSON feed.");
+                return Ok(exit_code::DATA_ERROR.into());
+            };
+
+            // Only the rules for every feed apply.
+            let url = url::Url::parse(nia::config::STDIN_URL).unwrap();
+            nia::download::rewrite_titles(&settings.titles, &url, &mut posts);
+            let feeds = FeedConfig::single_feed("stdin", url, posts);
+            App::new_ephemeral(feeds, settings)
+        },
+
+        // Commands that don't start the TUI.
+        Cli::Fetch { quiet } => return Ok(fetch(quiet, settings)),
+        Cli::Add { url, section, title } => {
+            return Ok(add(&url, section.as_deref(), title, &settings));
+        },
+        Cli::Unread { json } => return Ok(unread(json, &settings)),
+        Cli::Sync => return Ok(sync(&settings)),
+        Cli::Clean { archive, yes } => {
+            return Ok(clean(archive.as_deref(), yes, &settings));
+        },
+        Cli::DbExport { ndjson, file } => {
+            return Ok(db_export(ndjson, file.as_deref(), &settings));
+        },
+        Cli::DbImport { file } => {
+            return Ok(db_import(file.as_deref(), &settings));
+        },
+        Cli::Search { query } => return Ok(search(&query, &settings)),
+        Cli::BookmarksExport { file } => {
+            return Ok(bookmarks_export(file.as_deref(), &settings));
+        },
+        Cli::BookmarksImport { file } => {
+            return Ok(bookmarks_import(file.as_deref(), &settings));
+        },
+        Cli::StateExport { file } => {
+            return Ok(state_export(file.as_deref(), &settings));
+        },
+        Cli::StateImport { file } => {
+            return Ok(state_import(file.as_deref(), &settings));
+        },
+
+        // Browse the configured feeds.
+        Cli::Tui => {
+            // Parse the feeds
+            let feeds = match nia::remote::load_feeds(&settings) {
+                Ok(Some(feeds)) => feeds,
+                Ok(None) => {
+                    println!("No feeds!");
+                    return Ok(ExitCode::SUCCESS);
+                },
+                Err(err) => {
+                    eprintln!("Couldn't load the feeds: {}", err);
+                    return Ok(exit_code::CONFIG_ERROR.into());
+                },
+            };
+
+            let feed_file = nia::remote::uses_feed_file(&settings);
+            match App::new(feeds, settings) {
+                Ok(mut app) => {
+                    if let Err(err) = app.quit_on_signals() {
+                        eprintln!("Couldn't handle the signals: {}", err);
+                    }
+                    if feed_file && let Err(err) = app.watch_feed_file() {
+                        eprintln!("Couldn't watch the feed file: {}", err);
+                    }
+                    app
+                },
+                Err(err) => {
+                    eprintln!("Couldn't open the database: {}", err);
+                    return Ok(exit_code::IO_ERROR.into());
+                },
+            }
+        },
+    };
+
+    // A panic would leave the terminal in raw mode, with its message lost
+    // on the alternate screen.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        hook(info);
+    }));
+
+    // Run the app! The terminal is restored before any error is printed.
+    let result = run_tui(app);
+    restore_terminal()?;
+    let summary = match result {
+        Ok(summary) => summary,
+        Err(err) => {
+            eprintln!("Couldn't use the terminal: {}", err);
+            return Ok(exit_code::IO_ERROR.into());
+        },
+    };
+
+    if quit_summary {
+        println!("{}", summary);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Set up the terminal and run the `app` in it.
+fn run_tui(app: App) -> io::Result<SessionSummary> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     crossterm::execute!(stdout, EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
+    app.run(&mut terminal)
+}
 
-    // Run the app!
-    nia::app::App::new(feeds).run(&mut terminal);
-
-    // Restore the terminal.
+/// Leave the alternate screen and raw mode, showing the cursor again.
+fn restore_terminal() -> io::Result<()> {
     disable_raw_mode()?;
-    crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    crossterm::execute!(io::stdout(), LeaveAlternateScreen, Show)
+}
+
+/// Open the database for a command that can't do anything without it.
+fn open_database(settings: &Settings) -> Result<Database, ExitCode> {
+    Database::open(&settings.storage).map_err(|err| {
+        eprintln!("Couldn't open the database: {}", err);
+        exit_code::IO_ERROR.into()
+    })
+}
+
+/// Load the feeds for a command that can't do anything without them.
+fn load_feeds(settings: &Settings) -> Result<FeedConfig, ExitCode> {
+    match nia::remote::load_feeds(settings) {
+        Ok(Some(feeds)) => Ok(feeds),
+        Ok(None) => {
+            eprintln!("No feed file found.");
+            Err(exit_code::CONFIG_ERROR.into())
+        },
+        Err(err) => {
+            eprintln!("Couldn't load the feeds: {}", err);
+            Err(exit_code::CONFIG_ERROR.into())
+        },
+    }
+}
+
+/// Download all feeds without starting the TUI.
+fn fetch(quiet: bool, settings: Settings) -> ExitCode {
+    let feeds = match load_feeds(&settings) {
+        Ok(feeds) => feeds,
+        Err(code) => return code,
+    };
+
+    let summary = match nia::fetch::fetch_all(feeds, settings) {
+        Ok(summary) => summary,
+        Err(err) => {
+            eprintln!("Couldn't fetch the feeds: {}", err);
+            return exit_code::IO_ERROR.into();
+        },
+    };
+    summary.print(quiet);
+    summary.exit_code().into()
+}
+
+/// Subscribe to the feed at (or advertised by) `url`.
+fn add(url: &str, section: Option<&str>, title: Option<String>,
+    settings: &Settings) -> ExitCode
+{
+    if let Err(err) = nia::remote::check_feed_file(settings) {
+        eprintln!("Couldn't add the feed: {}", err);
+        return exit_code::CONFIG_ERROR.into();
+    }
+
+    let url = match url::Url::parse(url) {
+        Ok(url) => url,
+        Err(err) => {
+            eprintln!("Invalid URL \"{}\": {}", url, err);
+            return exit_code::USAGE.into();
+        },
+    };
+
+    let feed = match nia::download::discover_feed(&url) {
+        Ok(feed) => feed,
+        Err(err) => {
+            eprintln!("{}", err);
+            return exit_code::FEEDS_FAILED.into();
+        },
+    };
+
+    // Prefer the title given by the user, then the feed's own title.
+    let title = title
+        .or(feed.title)
+        .or_else(|| feed.url.host_str().map(String::from))
+        .unwrap_or_else(|| feed.url.to_string());
+
+    match FeedConfig::add_feed(section, &title, &feed.url) {
+        Ok(section) => {
+            println!("Added \"{}\" ({}) to {}", title, feed.url, section);
+            ExitCode::SUCCESS
+        },
+        Err(err) => {
+            eprintln!("Couldn't add the feed: {}", err);
+            exit_code::CONFIG_ERROR.into()
+        },
+    }
+}
+
+/// A post as printed by `nia unread --json`.
+#[derive(serde::Serialize)]
+struct UnreadPost<'a> {
+    section: &'a str,
+    feed: &'a str,
+    title: &'a str,
+    url: Option<&'a str>,
+    published: String,
+}
+
+/// Print all unread posts, newest first within each feed.
+fn unread(json: bool, settings: &Settings) -> ExitCode {
+    let mut feeds = match load_feeds(settings) {
+        Ok(feeds) => feeds,
+        Err(code) => return code,
+    };
+    if let Err(err) = nia::database::load_posts(&mut feeds, &settings.storage)
+    {
+        eprintln!("Couldn't load the posts: {}", err);
+        return exit_code::IO_ERROR.into();
+    }
+
+    let posts = feeds.sections.iter().flat_map(|section| {
+        section.feeds.iter().flat_map(move |feed| {
+            feed.posts.as_ref().iter()
+                .filter(|post| !post.read)
+                .map(move |post| UnreadPost {
+                    section: &section.title,
+                    feed: &feed.title,
+                    title: &post.title,
+                    url: post.urls.first().map(|url| url.as_str()),
+                    published: post.published.to_rfc3339(),
+                })
+        })
+    });
+
+    if json {
+        let posts: Vec<UnreadPost> = posts.collect();
+        match serde_json::to_string_pretty(&posts) {
+            Ok(json) => println!("{}", json),
+            Err(err) => {
+                eprintln!("Couldn't write the posts: {}", err);
+                return exit_code::IO_ERROR.into();
+            },
+        }
+    } else {
+        // Tabs would break the columns.
+        let clean = |s: &str| s.replace(['\t', '\n'], " ");
+        for post in posts {
+            println!("{}\t{}\t{}\t{}", post.published, clean(post.feed),
+                clean(post.title), post.url.unwrap_or(""));
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Sync with all remotes configured in the settings.
+fn sync(settings: &Settings) -> ExitCode {
+    let remotes = match nia::remote::configured(settings) {
+        Ok(remotes) if remotes.is_empty() => {
+            eprintln!("No remote is configured in config.toml.");
+            return exit_code::CONFIG_ERROR.into();
+        },
+        Ok(remotes) => remotes,
+        Err(err) => {
+            eprintln!("{}", err);
+            return exit_code::CONFIG_ERROR.into();
+        },
+    };
+
+    let db = match open_database(settings) {
+        Ok(db) => db,
+        Err(code) => return code,
+    };
+    let mut code = ExitCode::SUCCESS;
+    for remote in remotes.iter() {
+        match nia::remote::sync(remote.as_ref(), &db) {
+            Ok(summary) => println!("{}: {}", remote.name(), summary),
+            Err(err) => {
+                eprintln!("{}: {}", remote.name(), err);
+                code = exit_code::REMOTE_FAILED.into();
+            },
+        }
+    }
+
+    code
+}
+
+/// Delete the stored posts of the feeds that aren't subscribed to anymore,
+/// saving them to the `archive` file first if there's one.
+fn clean(archive: Option<&str>, yes: bool, settings: &Settings) -> ExitCode {
+    let feeds = match load_feeds(settings) {
+        Ok(feeds) => feeds,
+        Err(code) => return code,
+    };
+
+    let db = match open_database(settings) {
+        Ok(db) => db,
+        Err(code) => return code,
+    };
+    let orphaned = match db.orphaned_feeds(&feeds) {
+        Ok(orphaned) => orphaned,
+        Err(err) => {
+            eprintln!("Couldn't read the database: {}", err);
+            return exit_code::IO_ERROR.into();
+        },
+    };
+    if orphaned.is_empty() {
+        println!("No posts of unsubscribed feeds are stored.");
+        return ExitCode::SUCCESS;
+    }
+
+    for (url, posts) in orphaned.iter() {
+        println!("{:>6} posts  {}", posts, url);
+    }
+
+    // Ask before anything is deleted.
+    if !yes {
+        let verb = if archive.is_some() { "Archive" } else { "Delete" };
+        print!("{} the posts of {} feeds? [y/N] ", verb, orphaned.len());
+        let _ = io::stdout().flush();
+
+        let mut answer = String::new();
+        let _ = io::stdin().read_line(&mut answer);
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            return ExitCode::SUCCESS;
+        }
+    }
+
+    // Nothing is deleted unless the archive has been written.
+    if let Some(path) = archive {
+        let mut archived: Vec<ExportedPost> = Vec::new();
+        for (url, _) in orphaned.iter() {
+            let posts = match db.load_feed(url) {
+                Ok(posts) => posts,
+                Err(err) => {
+                    eprintln!("Couldn't read the database: {}", err);
+                    return exit_code::IO_ERROR.into();
+                },
+            };
+            archived.extend(posts.as_ref().iter()
+                .map(|post| ExportedPost::new(url, post)));
+        }
+
+        let json = match serde_json::to_string_pretty(&archived) {
+            Ok(json) => json,
+            Err(err) => {
+                eprintln!("Couldn't write the archive {}: {}", path, err);
+                return exit_code::IO_ERROR.into();
+            },
+        };
+        if let Err(err) = std::fs::write(path, json) {
+            eprintln!("Couldn't write the archive {}: {}", path, err);
+            return exit_code::IO_ERROR.into();
+        }
+    }
+
+    for (url, _) in orphaned.iter() {
+        if let Err(err) = db.remove_feed(url) {
+            eprintln!("Couldn't remove the posts of {}: {}", url, err);
+            return exit_code::IO_ERROR.into();
+        }
+    }
+
+    println!("Removed the posts of {} feeds.", orphaned.len());
+    ExitCode::SUCCESS
+}
+
+/// Write what `export` makes of the database to the `file`, or to stdout,
+/// and `report` how much it has written. The `what` names it in the errors.
+fn export_to<E, R>(file: Option<&str>, settings: &Settings, what: &str,
+    export: E, report: R) -> ExitCode
+where
+    E: Fn(&Database, &mut dyn Write) -> io::Result<usize>,
+    R: Fn(usize),
+{
+    let db = match open_database(settings) {
+        Ok(db) => db,
+        Err(code) => return code,
+    };
+    let result = match file {
+        Some(path) => std::fs::File::create(path)
+            .and_then(|file| export(&db, &mut io::BufWriter::new(file))),
+        None => export(&db, &mut io::stdout().lock()),
+    };
+
+    match result {
+        Ok(exported) => {
+            report(exported);
+            ExitCode::SUCCESS
+        },
+        Err(err) => {
+            eprintln!("Couldn't export the {}: {}", what, err);
+            exit_code::IO_ERROR.into()
+        },
+    }
+}
+
+/// Store what `import` makes of the `file`, or of stdin, in the database and
+/// `report` what it has stored. The `what` names it in the errors.
+fn import_from<T, I, R>(file: Option<&str>, settings: &Settings, what: &str,
+    import: I, report: R) -> ExitCode
+where
+    I: Fn(&Database, &str) -> Result<T, String>,
+    R: Fn(T),
+{
+    let input = match file {
+        Some(path) => std::fs::read_to_string(path),
+        None => io::read_to_string(io::stdin()),
+    };
+    let input = match input {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("Couldn't read the {}: {}", what, err);
+            return exit_code::IO_ERROR.into();
+        },
+    };
+
+    let db = match open_database(settings) {
+        Ok(db) => db,
+        Err(code) => return code,
+    };
+    match import(&db, &input) {
+        Ok(imported) => {
+            report(imported);
+            ExitCode::SUCCESS
+        },
+        Err(err) => {
+            eprintln!("Couldn't import the {}: {}", what, err);
+            exit_code::IO_ERROR.into()
+        },
+    }
+}
+
+/// Write all stored posts to the `file`, or to stdout.
+fn db_export(ndjson: bool, file: Option<&str>, settings: &Settings)
+    -> ExitCode
+{
+    export_to(file, settings, "posts",
+        |db, out| nia::export::export(db, ndjson, out),
+        |exported| eprintln!("Exported {} posts.", exported))
+}
+
+/// Store the posts exported into the `file`, or read from stdin.
+fn db_import(file: Option<&str>, settings: &Settings) -> ExitCode {
+    import_from(file, settings, "posts", nia::export::import,
+        |imported| println!("Imported {} posts.", imported))
+}
+
+/// Print the stored posts whose titles match the `query`.
+fn search(query: &str, settings: &Settings) -> ExitCode {
+    let db = match open_database(settings) {
+        Ok(db) => db,
+        Err(code) => return code,
+    };
+    let posts = match db.search(query) {
+        Ok(posts) => posts,
+        Err(err @ Error::InvalidQuery(_)) => {
+            eprintln!("Couldn't search the posts: {}", err);
+            return exit_code::USAGE.into();
+        },
+        Err(err) => {
+            eprintln!("Couldn't search the posts: {}", err);
+            return exit_code::IO_ERROR.into();
+        },
+    };
+
+    // Tabs would break the columns.
+    let clean = |s: &str| s.replace(['\t', '\n'], " ");
+    for (feed_url, post) in posts.iter() {
+        println!("{}\t{}\t{}\t{}", post.published.to_rfc3339(), feed_url,
+            clean(&post.title),
+            post.urls.first().map(|url| url.as_str()).unwrap_or(""));
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Write the starred posts as bookmarks to the `file`, or to stdout.
+fn bookmarks_export(file: Option<&str>, settings: &Settings) -> ExitCode {
+    export_to(file, settings, "bookmarks",
+        |db, out| nia::bookmarks::export(db, out),
+        |exported| eprintln!("Exported {} starred posts.", exported))
+}
+
+/// Star the posts bookmarked in the `file`, or in the bookmarks read from
+/// stdin.
+fn bookmarks_import(file: Option<&str>, settings: &Settings) -> ExitCode {
+    import_from(file, settings, "bookmarks", nia::bookmarks::import,
+        |(starred, unmatched)| {
+            println!("Starred {} posts.", starred);
+            if unmatched > 0 {
+                println!("{} bookmarks aren't stored posts.", unmatched);
+            }
+        })
+}
+
+/// Write which stored posts are read or starred into the `file`, or to
+/// stdout.
+fn state_export(file: Option<&str>, settings: &Settings) -> ExitCode {
+    export_to(file, settings, "state",
+        |db, out| nia::state::export(db, out),
+        |exported| eprintln!("Exported the state of {} posts.", exported))
+}
 
-    Ok(())
+/// Mark the stored posts read or starred as the state exported into the
+/// `file`, or read from stdin, says.
+fn state_import(file: Option<&str>, settings: &Settings) -> ExitCode {
+    import_from(file, settings, "state", nia::state::import,
+        |(changed, unmatched)| {
+            println!("Updated {} posts.", changed);
+            if unmatched > 0 {
+                println!("{} posts aren't stored.", unmatched);
+            }
+        })
 }
@@ -0,0 +1,118 @@
+//! Notes written from posts as Markdown files, e.g. to be linked from a
+//! zettelkasten.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::Local;
+use crate::config::{Feed, Post};
+
+/// The number of characters of the title kept in the file name.
+const MAX_SLUG: usize = 60;
+
+/// Get the name of the note of the `post`, the day it was published on and
+/// its title, e.g. `2024-01-05-hello-world.md`.
+pub fn file_name(post: &Post) -> String {
+    let mut slug = String::new();
+    for word in post.title.split(|c: char| !c.is_alphanumeric()) {
+        if word.is_empty() {
+            continue;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.extend(word.chars().flat_map(char::to_lowercase));
+    }
+    let mut slug: String = slug.chars().take(MAX_SLUG).collect();
+    slug.truncate(slug.trim_end_matches('-').len());
+
+    // Titles without any letters are told apart by the IDs of the posts.
+    if slug.is_empty() {
+        slug = crate::hash(&post.id.0);
+    }
+
+    let day = post.published.with_timezone(&Local).format("%Y-%m-%d");
+    format!("{}-{}.md", day, slug)
+}
+
+/// Write the `post` of the `feed` as Markdown, with its metadata in the
+/// front matter.
+pub fn markdown(post: &Post, feed: &Feed) -> String {
+    // JSON strings are YAML strings too.
+    let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    let published = post.published.with_timezone(&Local);
+
+    let mut note = String::from("---\n");
+    note += &format!("title: {}\n", quote(&post.title));
+    note += &format!("feed: {}\n", quote(&feed.title));
+    note += &format!("date: {}\n", published.to_rfc3339());
+    if let Some(url) = post.urls.first() {
+        note += &format!("url: {}\n", quote(url.as_str()));
+    }
+    note += "---\n\n";
+
+    note += &format!("# {}\n\n", post.title);
+    note += &format!("Published in {} on {}.\n",
+        feed.title, published.format("%Y-%m-%d %H:%M"));
+
+    if !post.urls.is_empty() || post.comments.is_some() {
+        note += "\n## Links\n\n";
+        for url in post.urls.iter() {
+            note += &format!("- <{}>\n", url);
+        }
+        if let Some(url) = &post.comments {
+            note += &format!("- [Comments](<{}>)\n", url);
+        }
+    }
+
+    note
+}
+
+/// Write the note of the `post` of the `feed` into the `dir`, creating it if
+/// it doesn't exist. Returns the path of the note.
+///
+/// A note written before for the post is replaced.
+pub fn write(dir: &Path, post: &Post, feed: &Feed) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(file_name(post));
+    std::fs::write(&path, markdown(post, feed))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use chrono::{DateTime, TimeZone};
+    use url::Url;
+    use crate::config::test_post;
+
+    #[test]
+    fn writes_posts_as_markdown() {
+        let published = Local.with_ymd_and_hms(2024, 1, 5, 12, 30, 0)
+            .unwrap()
+            .to_utc();
+        let mut post = Post {
+            title: Arc::from("Hello, \"World\": Part 2!"),
+            ..test_post("1", "https://blog.example/hello",
+                published.timestamp())
+        };
+        let url = Url::parse("https://blog.example/feed.xml").unwrap();
+        let feed = Feed::new("Blog".into(), url);
+
+        assert_eq!(file_name(&post), "2024-01-05-hello-world-part-2.md");
+        let note = markdown(&post, &feed);
+        assert!(note.starts_with("---\n\
+            title: \"Hello, \\\"World\\\": Part 2!\"\n\
+            feed: \"Blog\"\n"));
+        assert!(note.contains("\n# Hello, \"World\": Part 2!\n\n\
+            Published in Blog on 2024-01-05 12:30.\n\n\
+            ## Links\n\n- <https://blog.example/hello>\n"));
+
+        post.title = Arc::from("???");
+        let date = DateTime::from_timestamp(0, 0).unwrap();
+        post.published = date;
+        let day = date.with_timezone(&Local).format("%Y-%m-%d");
+        assert_eq!(file_name(&post),
+            format!("{}-{}.md", day, crate::hash("1")));
+    }
+}
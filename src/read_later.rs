@@ -0,0 +1,102 @@
+//! Saving URLs to read-it-later services.
+
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::json;
+use url::Url;
+use crate::settings::{PocketSettings, Settings, WallabagSettings};
+
+/// A read-it-later service.
+#[derive(Debug, Clone)]
+pub enum Service {
+    /// A Wallabag instance (<https://doc.wallabag.org/developer/api/oauth/>).
+    Wallabag(WallabagSettings),
+
+    /// Pocket (<https://getpocket.com/developer/docs/v3/add>).
+    Pocket(PocketSettings),
+}
+
+/// Get all services configured in the settings.
+pub fn configured(settings: &Settings) -> Vec<Service> {
+    let wallabag = settings.wallabag.clone().map(Service::Wallabag);
+    let pocket = settings.pocket.clone().map(Service::Pocket);
+    wallabag.into_iter().chain(pocket).collect()
+}
+
+impl Service {
+    /// Name of the service shown to the user.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Service::Wallabag(_) => "Wallabag",
+            Service::Pocket(_) => "Pocket",
+        }
+    }
+
+    /// Save the `url` to the service.
+    pub fn save(&self, url: &Url) -> Result<(), String> {
+        match self {
+            Service::Wallabag(settings) => save_to_wallabag(settings, url),
+            Service::Pocket(settings) => save_to_pocket(settings, url),
+        }
+    }
+}
+
+/// Get an access token from Wallabag and create an entry with the `url`.
+fn save_to_wallabag(settings: &WallabagSettings, url: &Url)
+    -> Result<(), String>
+{
+    #[derive(Deserialize)]
+    struct Token {
+        access_token: String,
+    }
+
+    let base = Url::parse(&settings.url)
+        .map_err(|err| format!("Invalid Wallabag URL: {}", err))?;
+    let endpoint = |path| base.join(path).map_err(|err| err.to_string());
+    let client = Client::new();
+
+    let form = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("grant_type", "password")
+        .append_pair("client_id", &settings.client_id)
+        .append_pair("client_secret", &settings.client_secret)
+        .append_pair("username", &settings.username)
+        .append_pair("password", &settings.password)
+        .finish();
+
+    let token: Token = client.post(endpoint("oauth/v2/token")?)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(form)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .and_then(|r| r.text())
+        .map_err(|err| err.to_string())
+        .and_then(|body| serde_json::from_str(&body)
+            .map_err(|err| format!("Invalid Wallabag token: {}", err)))?;
+
+    client.post(endpoint("api/entries.json")?)
+        .bearer_auth(token.access_token)
+        .header("Content-Type", "application/json")
+        .body(json!({ "url": url.as_str() }).to_string())
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Add the `url` to the Pocket list.
+fn save_to_pocket(settings: &PocketSettings, url: &Url) -> Result<(), String> {
+    let body = json!({
+        "url": url.as_str(),
+        "consumer_key": settings.consumer_key,
+        "access_token": settings.access_token,
+    });
+
+    Client::new().post("https://getpocket.com/v3/add")
+        .header("Content-Type", "application/json; charset=UTF-8")
+        .header("X-Accept", "application/json")
+        .body(body.to_string())
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
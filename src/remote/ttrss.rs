@@ -0,0 +1,353 @@
+//! Client of the Tiny Tiny RSS JSON API
+//! (<https://tt-rss.org/ApiReference/>).
+//!
+//! Unlike Fever, the subscriptions on the instance replace the feed file.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use url::Url;
+use crate::config::{Post, PostId};
+use crate::download::{extract_urls_from_text, push_url, summarize};
+use crate::remote::{Remote, RemoteFeed, RemoteItem};
+use crate::settings::TtRssSettings;
+
+/// The number of headlines requested at once. It's also the maximum the
+/// API allows.
+const PAGE_SIZE: usize = 200;
+
+/// Virtual feed containing all articles.
+const ALL_ARTICLES: i64 = -4;
+
+/// Virtual feed containing the starred articles.
+const STARRED: i64 = -1;
+
+/// Virtual category containing all feeds except the virtual ones.
+const ALL_FEEDS: i64 = -3;
+
+/// The envelope of every API response.
+#[derive(Deserialize)]
+struct Response {
+    status: u8,
+    content: Value,
+}
+
+/// Older versions send the IDs as strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Id {
+    Number(i64),
+    Text(String),
+}
+
+impl Id {
+    /// Get the numeric value of the ID.
+    fn get(&self) -> Option<i64> {
+        match self {
+            Id::Number(id) => Some(*id),
+            Id::Text(id) => id.parse().ok(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Category {
+    id: Id,
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct Feed {
+    id: Id,
+    title: String,
+    feed_url: String,
+    cat_id: Option<Id>,
+}
+
+#[derive(Deserialize)]
+struct Headline {
+    id: Id,
+    feed_id: Id,
+
+    #[serde(default)]
+    unread: bool,
+
+    #[serde(default)]
+    title: String,
+
+    #[serde(default)]
+    link: String,
+
+    #[serde(default)]
+    author: String,
+
+    #[serde(default)]
+    content: String,
+
+    #[serde(default)]
+    updated: i64,
+}
+
+/// A Tiny Tiny RSS instance.
+pub struct TinyTinyRss {
+    /// URL of the API endpoint.
+    url: Url,
+
+    /// Name of the user.
+    user: String,
+
+    /// Password of the user.
+    password: String,
+
+    /// ID of the session, once logged in.
+    session: RefCell<Option<String>>,
+
+    /// The HTTP client.
+    client: Client,
+}
+
+impl TinyTinyRss {
+    /// Create a client of the instance in the settings.
+    pub fn new(settings: &TtRssSettings) -> Result<Self, String> {
+        let url = Url::parse(&settings.url)
+            .and_then(|url| url.join("api/"))
+            .map_err(|err| format!("Invalid Tiny Tiny RSS URL: {}", err))?;
+
+        Ok(Self {
+            url,
+            user: settings.user.clone(),
+            password: settings.password.clone(),
+            session: RefCell::new(None),
+            client: Client::new(),
+        })
+    }
+
+    /// Send the `body` to the API and return the content of the response.
+    fn send(&self, body: &Value) -> Result<Value, String> {
+        let response: Response = self.client.post(self.url.clone())
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text())
+            .map_err(|err| err.to_string())
+            .and_then(|body| serde_json::from_str(&body).map_err(|err| {
+                format!("Invalid Tiny Tiny RSS response: {}", err)
+            }))?;
+
+        if response.status != 0 {
+            let err = response.content.get("error")
+                .and_then(Value::as_str)
+                .unwrap_or("UNKNOWN_ERROR");
+            return Err(format!("Tiny Tiny RSS error: {}", err));
+        }
+
+        Ok(response.content)
+    }
+
+    /// Call the API operation `op` with the `params`, logging in first if we
+    /// haven't yet.
+    fn call<T: DeserializeOwned>(&self, op: &str, mut params: Value)
+        -> Result<T, String>
+    {
+        if self.session.borrow().is_none() {
+            let content = self.send(&json!({
+                "op": "login",
+                "user": self.user,
+                "password": self.password,
+            }))?;
+
+            let session = content.get("session_id")
+                .and_then(Value::as_str)
+                .ok_or("Tiny Tiny RSS didn't return a session")?;
+            *self.session.borrow_mut() = Some(session.to_string());
+        }
+
+        params["op"] = op.into();
+        params["sid"] = self.session.borrow().clone().into();
+
+        let content = self.send(&params)?;
+        serde_json::from_value(content)
+            .map_err(|err| format!("Invalid Tiny Tiny RSS response: {}", err))
+    }
+
+    /// Get all headlines matching the `params`, page by page.
+    fn headlines(&self, params: Value) -> Result<Vec<Headline>, String> {
+        let mut headlines = Vec::new();
+
+        loop {
+            let mut params = params.clone();
+            params["limit"] = PAGE_SIZE.into();
+            params["skip"] = headlines.len().into();
+
+            let page: Vec<Headline> = self.call("getHeadlines", params)?;
+            let last = page.len() < PAGE_SIZE;
+            headlines.extend(page);
+
+            if last {
+                return Ok(headlines);
+            }
+        }
+    }
+
+    /// Get the IDs of all headlines matching the `params`.
+    fn headline_ids(&self, params: Value) -> Result<HashSet<u64>, String> {
+        let headlines = self.headlines(params)?;
+        Ok(headlines.iter().filter_map(|h| to_u64(&h.id)).collect())
+    }
+}
+
+impl Remote for TinyTinyRss {
+    fn name(&self) -> &str {
+        "ttrss"
+    }
+
+    fn manages_subscriptions(&self) -> bool {
+        true
+    }
+
+    fn feeds(&self) -> Result<Vec<RemoteFeed>, String> {
+        let categories: Vec<Category> = self.call("getCategories", json!({}))?;
+        let titles: HashMap<i64, String> = categories.into_iter()
+            .filter_map(|c| Some((c.id.get()?, c.title)))
+            .collect();
+
+        let feeds: Vec<Feed> = self.call("getFeeds", json!({
+            "cat_id": ALL_FEEDS,
+        }))?;
+
+        let feeds = feeds.into_iter()
+            .filter_map(|feed| Some(RemoteFeed {
+                id: to_u64(&feed.id)?,
+                url: Url::parse(&feed.feed_url).ok()?,
+                group: feed.cat_id.as_ref()
+                    .and_then(Id::get)
+                    .and_then(|id| titles.get(&id).cloned()),
+                title: feed.title,
+            }))
+            .collect();
+
+        Ok(feeds)
+    }
+
+    /// All new items are returned at once, so that the paging doesn't depend
+    /// on the items being sorted by their IDs.
+    fn items(&self, since_id: u64) -> Result<Vec<RemoteItem>, String> {
+        let headlines = self.headlines(json!({
+            "feed_id": ALL_ARTICLES,
+            "since_id": since_id,
+            "view_mode": "all_articles",
+            "order_by": "date_reverse",
+            "show_content": true,
+        }))?;
+
+        Ok(headlines.into_iter().filter_map(to_remote_item).collect())
+    }
+
+    fn unread_ids(&self) -> Result<HashSet<u64>, String> {
+        self.headline_ids(json!({
+            "feed_id": ALL_ARTICLES,
+            "view_mode": "unread",
+        }))
+    }
+
+    fn saved_ids(&self) -> Result<HashSet<u64>, String> {
+        self.headline_ids(json!({
+            "feed_id": STARRED,
+            "view_mode": "all_articles",
+        }))
+    }
+
+    fn mark_read(&self, id: u64) -> Result<(), String> {
+        // Set the `unread` field (2) to false (0).
+        self.call::<Value>("updateArticle", json!({
+            "article_ids": id.to_string(),
+            "mode": 0,
+            "field": 2,
+        })).map(|_| ())
+    }
+}
+
+/// Get the ID as `u64`. Negative IDs belong to virtual feeds and labels.
+fn to_u64(id: &Id) -> Option<u64> {
+    id.get().and_then(|id| u64::try_from(id).ok())
+}
+
+/// Convert a headline to a post.
+///
+/// The post ID is made from the link, so that the post is likely to match
+/// the one downloaded from the feed itself.
+fn to_remote_item(headline: Headline) -> Option<RemoteItem> {
+    let id = to_u64(&headline.id)?;
+    let feed_id = to_u64(&headline.feed_id)?;
+
+    let post_id = Url::parse(headline.link.trim()).ok()
+        .and_then(|url| PostId::from_link(&url))
+        .unwrap_or_else(|| format!("ttrss:{}", id).into());
+
+    let title = match headline.title.trim() {
+        "" => "Untitled".into(),
+        title => title.into(),
+    };
+
+    let published = chrono::DateTime::from_timestamp(headline.updated, 0)
+        .unwrap_or_else(chrono::Utc::now);
+
+    let mut urls = Vec::new();
+    push_url(&mut urls, &headline.link);
+    extract_urls_from_text(&mut urls, &headline.content);
+
+    let authors = Some(headline.author.trim())
+        .filter(|author| !author.is_empty())
+        .map(String::from)
+        .into_iter()
+        .collect();
+
+    let read = !headline.unread;
+    let arrived = chrono::Utc::now();
+    let post = Post {
+        id: post_id, title, urls, published, read, arrived, starred: false,
+        comments: None, comment_feed: None, authors, categories: Vec::new(),
+        summary: summarize(&headline.content),
+        updated: None, hash: None, edited: false,
+    };
+    Some(RemoteItem { id, feed_id, post })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_headlines() {
+        let headlines: Vec<Headline> = serde_json::from_str(r#"[{
+            "id": 12,
+            "feed_id": "3",
+            "unread": true,
+            "marked": false,
+            "updated": 1700000000,
+            "title": "Hello",
+            "link": "https://example.com/hello",
+            "content": "See https://example.com/more"
+        }, {
+            "id": 13,
+            "feed_id": -1,
+            "title": "Label"
+        }]"#).unwrap();
+
+        let items: Vec<_> = headlines.into_iter()
+            .filter_map(to_remote_item)
+            .collect();
+        let [item] = &items[..] else { panic!("Expected one item") };
+
+        assert_eq!((item.id, item.feed_id), (12, 3));
+        // The same ID as the post downloaded from the feed gets.
+        let link = Url::parse("https://example.com/hello").unwrap();
+        assert_eq!(item.post.id, PostId::from_link(&link).unwrap());
+        assert!(!item.post.read);
+        assert_eq!(item.post.urls.len(), 2);
+    }
+}
@@ -0,0 +1,867 @@
+//! Application settings from the optional `config.toml` file.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use url::Url;
+use crate::config::{FeedConfig, Post, PostFilter, deserialize_filters};
+
+/// Settings of the application.
+///
+/// Every setting has a default, so the file and any of its keys can be
+/// missing.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Print a summary of the reading session on quit.
+    pub quit_summary: bool,
+
+    /// Move on to the next unread post after opening a post or marking it
+    /// read on a feed page.
+    pub auto_advance: bool,
+
+    /// When a post is marked read without pressing `r`.
+    pub mark_read: MarkRead,
+
+    /// Mark the read posts that have been edited since as unread again.
+    pub unread_edited: bool,
+
+    /// The shell command opening URLs in the browser, with every `%u`
+    /// replaced by the URL. The system's opener is used if it's not set.
+    pub browser: Option<String>,
+
+    /// Settings of the drawing of the TUI.
+    pub ui: UiSettings,
+
+    /// Settings of the color theme.
+    pub theme: ThemeSettings,
+
+    /// What to do when no key has been pressed for a while.
+    pub idle: IdleSettings,
+
+    /// How often the feeds without refresh intervals of their own are
+    /// downloaded while the TUI runs.
+    pub refresh: RefreshSettings,
+
+    /// How the downloads from the same host are spaced out.
+    pub politeness: PolitenessSettings,
+
+    /// When the downloads of the feeds give up.
+    pub timeouts: TimeoutSettings,
+
+    /// How it's told whether there's a network to download the feeds with.
+    pub network: NetworkSettings,
+
+    /// Read the feeds from their bodies cached when they were last
+    /// downloaded instead of downloading them, set by `--offline`.
+    #[serde(skip)]
+    pub offline: bool,
+
+    /// Sizes of the queues between the threads.
+    pub queues: QueueSettings,
+
+    /// Which posts are removed from the database.
+    pub retention: RetentionSettings,
+
+    /// When to help with catching up after a long break.
+    pub catch_up: CatchUpSettings,
+
+    /// Where the posts are stored.
+    pub storage: StorageSettings,
+
+    /// Rewrites of the titles of the posts, in the order they're applied.
+    pub titles: Vec<TitleRule>,
+
+    /// Commands run for every newly downloaded post.
+    pub hooks: Vec<Hook>,
+
+    /// The posts hidden from the feed pages of every feed.
+    pub killfile: Killfile,
+
+    /// Shell commands the URLs of the posts can be run through, by name.
+    /// Every `%u` in them is replaced with the URL.
+    pub actions: BTreeMap<String, String>,
+
+    /// Where the posts are written as Markdown notes.
+    pub notes: NotesSettings,
+
+    /// How the videos and audio linked from the posts are played.
+    pub media: MediaSettings,
+
+    /// The Fever API endpoint to sync with.
+    pub fever: Option<FeverSettings>,
+
+    /// The Tiny Tiny RSS instance to sync with.
+    pub ttrss: Option<TtRssSettings>,
+
+    /// The Wallabag instance to save posts to.
+    pub wallabag: Option<WallabagSettings>,
+
+    /// The Pocket account to save posts to.
+    pub pocket: Option<PocketSettings>,
+}
+
+/// Settings of the drawing of the TUI.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiSettings {
+    /// How many times per second the spinners are animated.
+    pub fps: u64,
+
+    /// Whether the spinners are animated at all.
+    ///
+    /// Without animations, the TUI is redrawn once a second while feeds are
+    /// downloaded or saved instead. Otherwise it's only redrawn when a key is
+    /// pressed, a download or the database answers or a timer, such as that
+    /// of the idle period or the clock, runs out.
+    pub animations: bool,
+
+    /// Send as little as possible to the terminal, e.g. over SSH: no
+    /// animations or image previews, and the downloads and the database
+    /// redraw the TUI at most every two seconds.
+    ///
+    /// When it's not set, it's enabled in SSH sessions.
+    pub low_bandwidth: Option<bool>,
+
+    /// How the images linked from the posts are previewed.
+    pub images: ImageProtocol,
+
+    /// How the dates of the posts are shown on the feed pages.
+    pub dates: DateFormat,
+
+    /// Whether the titles too long for the feed pages are wrapped onto a
+    /// second line instead of being cut.
+    pub wrap_titles: bool,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self {
+            fps: 60,
+            animations: true,
+            low_bandwidth: None,
+            images: ImageProtocol::default(),
+            dates: DateFormat::default(),
+            wrap_titles: false,
+        }
+    }
+}
+
+/// The terminal graphics protocol the images are previewed with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageProtocol {
+    /// Find out which one the terminal supports on startup.
+    #[default]
+    Auto,
+
+    /// The kitty graphics protocol, also supported by WezTerm and Ghostty.
+    Kitty,
+
+    /// Sixel graphics.
+    Sixel,
+
+    /// Don't preview images.
+    None,
+}
+
+/// How the dates of the posts are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateFormat {
+    /// How long ago the post was published, e.g. `3h` or `2d`, and the day
+    /// for the posts older than a week, e.g. `Jan 5`.
+    #[default]
+    Relative,
+
+    /// The day the post was published, e.g. `2024-01-05`.
+    Iso,
+}
+
+impl UiSettings {
+    /// Whether as little as possible is sent to the terminal.
+    pub fn is_low_bandwidth(&self) -> bool {
+        self.low_bandwidth.unwrap_or_else(|| {
+            ["SSH_CONNECTION", "SSH_TTY"].iter()
+                .any(|var| std::env::var_os(var).is_some())
+        })
+    }
+
+    /// Whether the spinners are animated.
+    pub fn is_animated(&self) -> bool {
+        self.animations && !self.is_low_bandwidth()
+    }
+
+    /// Get how the images are previewed, if at all.
+    pub fn image_protocol(&self) -> ImageProtocol {
+        if self.is_low_bandwidth() {
+            ImageProtocol::None
+        } else {
+            self.images
+        }
+    }
+
+    /// Get the time between two redraws while feeds are downloaded or saved.
+    pub fn tick_rate(&self) -> Duration {
+        if self.is_low_bandwidth() {
+            Duration::from_secs(2)
+        } else if self.animations {
+            Duration::from_millis(1000 / self.fps.clamp(1, 1000))
+        } else {
+            Duration::from_secs(1)
+        }
+    }
+}
+
+/// Sizes of the queues between the threads.
+///
+/// When a queue is full, the threads sending into it wait for it to drain.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueSettings {
+    /// The number of database writes waiting to be done.
+    pub database: usize,
+
+    /// The number of downloaded feeds waiting to be handled.
+    pub downloads: usize,
+}
+
+impl Default for QueueSettings {
+    fn default() -> Self {
+        Self { database: 64, downloads: 64 }
+    }
+}
+
+/// Which posts are removed from the database.
+///
+/// Unread and starred posts and posts saved on a remote are always kept.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionSettings {
+    /// The number of newest posts kept in each feed, 0 keeps all of them.
+    pub max_posts: usize,
+
+    /// The number of days after which a post is removed, 0 keeps it forever.
+    pub max_age_days: u32,
+}
+
+impl RetentionSettings {
+    /// Whether any posts are removed at all.
+    pub fn prunes(&self) -> bool {
+        self.max_posts > 0 || self.max_age_days > 0
+    }
+}
+
+/// When to help with catching up after a long break.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CatchUpSettings {
+    /// The number of days without refreshing after which the catch-up page is
+    /// shown once the feeds are refreshed. Zero disables it.
+    pub days: u32,
+
+    /// The number of newest unread posts kept unread by the catch-up page.
+    pub keep: usize,
+}
+
+impl Default for CatchUpSettings {
+    fn default() -> Self {
+        Self { days: 7, keep: 10 }
+    }
+}
+
+/// When a post is marked read without pressing `r`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkRead {
+    /// When its post page or its page in the browser is opened.
+    #[default]
+    Open,
+
+    /// When one of its links is copied, saved or run through an action.
+    Link,
+
+    /// Never.
+    Never,
+}
+
+/// The database the posts are stored in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// A sled database.
+    #[default]
+    Sled,
+
+    /// An SQLite database, with a full-text index of the post titles.
+    Sqlite,
+}
+
+/// Where the posts are stored.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    /// The database the posts are stored in.
+    ///
+    /// The posts aren't moved between the backends when it's changed, export
+    /// and import them for that.
+    pub backend: StorageBackend,
+
+    /// Compress the posts and the archived articles stored in sled with
+    /// zstd. The ones stored before are read either way.
+    pub compress: bool,
+
+    /// Open the database without ever writing to it, set by `--read-only`.
+    #[serde(skip)]
+    pub read_only: bool,
+
+    /// The directory the database is in instead of the data directory, set
+    /// by `--data-dir`.
+    #[serde(skip)]
+    pub dir: Option<PathBuf>,
+}
+
+/// A rewrite of the titles of the posts, applied when a feed is downloaded.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TitleRule {
+    /// The URL of the feed whose titles are rewritten. Without it, the titles
+    /// of every feed are.
+    #[serde(default, deserialize_with = "deserialize_url")]
+    pub feed: Option<Url>,
+
+    /// The regex matching the parts of the titles that are replaced.
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub pattern: Option<Regex>,
+
+    /// What the matches are replaced with. `$1` or `${name}` insert a group.
+    #[serde(default)]
+    pub replace: String,
+
+    /// The number of characters the titles are cut to.
+    pub max_length: Option<usize>,
+}
+
+impl TitleRule {
+    /// Whether the rule rewrites the titles of the feed at `feed_url`.
+    pub fn applies_to(&self, feed_url: &Url) -> bool {
+        self.feed.as_ref().is_none_or(|feed| feed == feed_url)
+    }
+
+    /// Rewrite the `title`.
+    ///
+    /// A title that would end up empty is kept as it is.
+    pub fn rewrite(&self, title: &str) -> String {
+        let mut rewritten = match &self.pattern {
+            Some(pattern) => pattern.replace_all(title, &self.replace)
+                .trim()
+                .to_string(),
+            None => title.to_string(),
+        };
+        if rewritten.is_empty() {
+            return title.to_string();
+        }
+
+        // Cut the title, marking that it has been.
+        if let Some(max) = self.max_length
+            && rewritten.chars().count() > max
+        {
+            rewritten = rewritten.chars().take(max.saturating_sub(1))
+                .collect::<String>()
+                .trim_end()
+                .to_string() + "…";
+        }
+
+        rewritten
+    }
+}
+
+/// A command run for every newly downloaded post, given the post as JSON on
+/// its stdin.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    /// The URL of the feed whose posts are given to the command. Without it,
+    /// the posts of every feed are.
+    #[serde(default, deserialize_with = "deserialize_url")]
+    pub feed: Option<Url>,
+
+    /// The command, run with `sh -c`.
+    pub command: String,
+}
+
+impl Hook {
+    /// Whether the hook is run for the posts of the feed at `feed_url`.
+    pub fn applies_to(&self, feed_url: &Url) -> bool {
+        self.feed.as_ref().is_none_or(|feed| feed == feed_url)
+    }
+}
+
+/// The patterns of the posts hidden from every feed. They're hidden only
+/// while they're shown, unlike the filters of single feeds.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Killfile {
+    /// The posts whose titles match any of these are hidden.
+    #[serde(deserialize_with = "deserialize_filters")]
+    pub titles: Vec<PostFilter>,
+
+    /// The posts with a URL matching any of these are hidden.
+    #[serde(deserialize_with = "deserialize_filters")]
+    pub urls: Vec<PostFilter>,
+
+    /// The posts by an author matching any of these are hidden.
+    #[serde(deserialize_with = "deserialize_filters")]
+    pub authors: Vec<PostFilter>,
+}
+
+impl Killfile {
+    /// Whether the `post` is hidden.
+    pub fn kills(&self, post: &Post) -> bool {
+        self.titles.iter().any(|filter| filter.is_match(&post.title))
+            || post.urls.iter().any(|url| {
+                self.urls.iter().any(|filter| filter.is_match(url.as_str()))
+            })
+            || post.authors.iter().any(|author| {
+                self.authors.iter().any(|filter| filter.is_match(author))
+            })
+    }
+}
+
+/// Where the posts are written as Markdown notes.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotesSettings {
+    /// The directory of the notes, `~/notes` if it's not set. A leading `~`
+    /// stands for the home directory.
+    pub dir: Option<String>,
+}
+
+impl NotesSettings {
+    /// Get the directory of the notes.
+    pub fn dir(&self) -> io::Result<PathBuf> {
+        let dir = self.dir.as_deref().unwrap_or("~/notes");
+        match dir.strip_prefix('~') {
+            Some(rest) => {
+                let rest = rest.trim_start_matches('/');
+                Ok(crate::config::home_dir()?.join(rest))
+            },
+            None => Ok(PathBuf::from(dir)),
+        }
+    }
+}
+
+/// How the videos and audio linked from the posts are played.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MediaSettings {
+    /// The shell command playing the media, with every `%u` replaced by its
+    /// URL.
+    pub player: String,
+}
+
+impl Default for MediaSettings {
+    fn default() -> Self {
+        Self { player: "mpv %u".into() }
+    }
+}
+
+/// Deserialize an optional URL.
+fn deserialize_url<'de, D>(deserializer: D) -> Result<Option<Url>, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let url = String::deserialize(deserializer)?;
+    Url::parse(&url).map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Deserialize an optional regex.
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Deserialize an hour of the day.
+fn deserialize_hour<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let hour = u32::deserialize(deserializer)?;
+    if hour < 24 {
+        Ok(hour)
+    } else {
+        Err(serde::de::Error::custom(format!("invalid hour {}, expected 0 to \
+            23", hour)))
+    }
+}
+
+/// What is shown when the TUI is idle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Screensaver {
+    /// Keep showing the current page.
+    #[default]
+    None,
+
+    /// Show nothing.
+    Blank,
+
+    /// Show the current time and the number of unread posts.
+    Clock,
+}
+
+/// What to do when no key has been pressed for a while.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdleSettings {
+    /// The number of minutes without a key press after which the TUI is
+    /// idle. Zero disables it.
+    pub minutes: u64,
+
+    /// Download all feeds every `minutes` while idle.
+    pub refresh: bool,
+
+    /// What to show while idle.
+    pub screensaver: Screensaver,
+}
+
+impl IdleSettings {
+    /// Get the idle period, if it's enabled.
+    pub fn period(&self) -> Option<Duration> {
+        (self.minutes > 0).then(|| Duration::from_secs(self.minutes * 60))
+    }
+}
+
+/// How often the feeds without refresh intervals of their own are downloaded
+/// while the TUI runs.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RefreshSettings {
+    /// Download the feeds about twice as often as they post, as far as the
+    /// bounds allow. Otherwise they're only downloaded on demand.
+    pub adaptive: bool,
+
+    /// The fewest minutes between the downloads of a feed.
+    pub min_minutes: u32,
+
+    /// The most minutes between the downloads of a feed.
+    pub max_minutes: u32,
+}
+
+impl Default for RefreshSettings {
+    fn default() -> Self {
+        Self { adaptive: false, min_minutes: 15, max_minutes: 24 * 60 }
+    }
+}
+
+/// How the downloads from the same host are spaced out, so that the feeds
+/// sharing a host don't get the downloads banned.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolitenessSettings {
+    /// The fewest milliseconds between the downloads from a host, 0 doesn't
+    /// space them out.
+    pub delay_ms: u64,
+
+    /// The number of downloads from a host that can start at once before
+    /// they're spaced out.
+    pub burst: u32,
+}
+
+impl Default for PolitenessSettings {
+    fn default() -> Self {
+        Self { delay_ms: 1000, burst: 1 }
+    }
+}
+
+/// When the downloads of the feeds give up, so that a hung server doesn't
+/// hold up the feeds downloaded after it.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutSettings {
+    /// The most seconds spent connecting to the server.
+    pub connect_secs: u64,
+
+    /// The most seconds spent waiting for the server to answer, and then
+    /// for the whole body of its answer.
+    pub read_secs: u64,
+
+    /// The most seconds a download of a feed takes altogether.
+    pub deadline_secs: u64,
+}
+
+impl Default for TimeoutSettings {
+    fn default() -> Self {
+        Self { connect_secs: 10, read_secs: 30, deadline_secs: 60 }
+    }
+}
+
+/// How it's told whether there's a network to download the feeds with, so
+/// that the downloads wait for it instead of failing when there's none.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkSettings {
+    /// The `host:port` connected to when a server can't be reached, to tell
+    /// whether there's a network at all. An empty one never waits for it.
+    pub probe: String,
+
+    /// The seconds between the connections while waiting for the network.
+    pub probe_secs: u64,
+
+    /// The most seconds a download waits for the network before it fails.
+    pub max_wait_secs: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self { probe: String::new(), probe_secs: 30, max_wait_secs: 600 }
+    }
+}
+
+/// Settings of a Fever API endpoint.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeverSettings {
+    /// URL of the endpoint.
+    pub url: String,
+
+    /// MD5 hash of `email:password`.
+    pub api_key: String,
+}
+
+/// Settings of a Wallabag instance.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WallabagSettings {
+    /// URL of the instance.
+    pub url: String,
+
+    /// ID of the API client created in Wallabag.
+    pub client_id: String,
+
+    /// Secret of the API client.
+    pub client_secret: String,
+
+    /// Name of the user.
+    pub username: String,
+
+    /// Password of the user.
+    pub password: String,
+}
+
+/// Settings of a Pocket account.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PocketSettings {
+    /// Key of the Pocket application.
+    pub consumer_key: String,
+
+    /// Access token of the user for the application.
+    pub access_token: String,
+}
+
+/// Settings of a Tiny Tiny RSS instance.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TtRssSettings {
+    /// URL of the instance, e.g. `https://example.com/tt-rss/`.
+    pub url: String,
+
+    /// Name of the user.
+    pub user: String,
+
+    /// Password of the user.
+    pub password: String,
+}
+
+/// How the color theme is chosen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    /// Always use the dark theme.
+    #[default]
+    Dark,
+
+    /// Always use the light theme.
+    Light,
+
+    /// Use the light theme during the day and the dark theme at night.
+    Time,
+
+    /// Ask the terminal for its background color on startup.
+    Terminal,
+}
+
+/// Settings of the color theme.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeSettings {
+    /// How the theme is chosen.
+    pub mode: ThemeMode,
+
+    /// The hour at which the day starts with `mode = "time"`.
+    #[serde(deserialize_with = "deserialize_hour")]
+    pub day_starts: u32,
+
+    /// The hour at which the night starts with `mode = "time"`, which may be
+    /// before `day_starts` for days that go on past midnight.
+    #[serde(deserialize_with = "deserialize_hour")]
+    pub night_starts: u32,
+
+    /// Whether to start in the focus mode.
+    pub focus: bool,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            mode: ThemeMode::default(),
+            day_starts: 7,
+            night_starts: 19,
+            focus: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Parse the settings from the contents of a settings file.
+    pub fn parse(s: &str) -> io::Result<Self> {
+        let settings: Self = toml::from_str(s)
+            .map_err(|err| io::Error::other(err.to_string()))?;
+
+        if settings.storage.backend == StorageBackend::Sqlite
+            && !cfg!(feature = "sqlite")
+        {
+            return Err(io::Error::other("nia was built without the \
+                \"sqlite\" feature, so the sqlite storage backend is not \
+                available"));
+        }
+
+        Ok(settings)
+    }
+
+    /// Load the settings from `config.toml` in the config directory.
+    ///
+    /// Returns the default settings if the file doesn't exist.
+    pub fn load() -> io::Result<Self> {
+        let path = FeedConfig::get_config_dir()?.join("config.toml");
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents).map_err(|err| {
+                io::Error::other(format!("{}: {}", path.display(), err))
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                Ok(Self::default())
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Get the shell command opening URLs in the browser.
+    pub fn browser(&self) -> &str {
+        let opener = if cfg!(target_os = "macos") {
+            "open %u"
+        } else {
+            "xdg-open %u"
+        };
+        self.browser.as_deref().unwrap_or(opener)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_post;
+
+    #[test]
+    fn parses_partial_settings() {
+        let settings = Settings::parse("[theme]\nmode = \"time\"\n").unwrap();
+        assert_eq!(settings.theme.mode, ThemeMode::Time);
+        assert_eq!(settings.theme.day_starts, 7);
+
+        let settings = Settings::parse("").unwrap();
+        assert_eq!(settings.theme.mode, ThemeMode::Dark);
+
+        let ui = "[ui]\nlow_bandwidth = false\n";
+        let settings = Settings::parse(ui).unwrap();
+        assert_eq!(settings.ui.tick_rate(), Duration::from_millis(16));
+
+        let ui = "[ui]\nanimations = false\nlow_bandwidth = false\n";
+        let settings = Settings::parse(ui).unwrap();
+        assert_eq!(settings.ui.tick_rate(), Duration::from_secs(1));
+
+        let settings = Settings::parse("[ui]\nlow_bandwidth = true\n").unwrap();
+        assert!(!settings.ui.is_animated());
+        assert_eq!(settings.ui.tick_rate(), Duration::from_secs(2));
+        assert_eq!(settings.ui.image_protocol(), ImageProtocol::None);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(Settings::parse("[theme]\ncolour = \"red\"\n").is_err());
+        assert!(Settings::parse("[theme]\nmode = \"purple\"\n").is_err());
+        assert!(Settings::parse("[fever]\nurl = \"https://a.b\"\n").is_err());
+        assert!(Settings::parse("[storage]\nbackend = \"csv\"\n").is_err());
+        assert!(Settings::parse("[[titles]]\npattern = \"(\"\n").is_err());
+        assert!(Settings::parse("[theme]\nnight_starts = 24\n").is_err());
+    }
+
+    #[test]
+    fn kills_matching_posts() {
+        let settings = Settings::parse(r#"
+            [killfile]
+            titles = ["[sponsor]", '/^Ad\b/']
+            urls = ["youtube.com/shorts/"]
+            authors = ["/^Sponsored by/"]
+        "#).unwrap();
+
+        let post = |title: &str, url: &str| test_post(title, url, 0);
+
+        let killfile = &settings.killfile;
+        assert!(killfile.kills(&post("[Sponsor] Jobs", "https://a.example/")));
+        assert!(killfile.kills(&post("Ad break", "https://a.example/")));
+        assert!(!killfile.kills(&post("Adverbs", "https://a.example/")));
+        assert!(killfile.kills(
+            &post("Neat", "https://www.youtube.com/shorts/abc")));
+        let sponsored = Post {
+            authors: vec!["Sponsored by Acme".into()],
+            ..post("Neat", "https://a.example/")
+        };
+        assert!(killfile.kills(&sponsored));
+        assert!(Settings::parse("[killfile]
+titles = [\"/(/\"]\n").is_err());
+    }
+
+    #[test]
+    fn rewrites_titles() {
+        let settings = Settings::parse(r#"
+            [[titles]]
+            feed = "https://example.com/feed.xml"
+            pattern = '^Example Blog: |\s*\p{Emoji_Presentation}'
+            replace = ""
+
+            [[titles]]
+            pattern = '^\[(\w+)\]'
+            replace = "$1:"
+            max_length = 12
+        "#).unwrap();
+        let [strip, cut] = &settings.titles[..] else {
+            panic!("Expected two rules");
+        };
+
+        let feed = Url::parse("https://example.com/feed.xml").unwrap();
+        let other = Url::parse("https://other.org/feed.xml").unwrap();
+        assert!(strip.applies_to(&feed) && !strip.applies_to(&other));
+        assert!(cut.applies_to(&other));
+
+        assert_eq!(strip.rewrite("Example Blog: Hello 🎉"), "Hello");
+        assert_eq!(strip.rewrite("Example Blog: "), "Example Blog: ");
+        assert_eq!(cut.rewrite("[rust] Hi"), "rust: Hi");
+        assert_eq!(cut.rewrite("[rust] Hello world"), "rust: Hello…");
+    }
+}
@@ -0,0 +1,139 @@
+//! Export and import of the read state of the stored posts, so that the
+//! read and starred posts of several machines can be kept in sync without a
+//! server, e.g. with syncthing or rsync.
+//!
+//! The state of each post is written on a line of its own as a JSON array,
+//! `[feed URL, post ID, read, starred]`. Only the read or starred posts are
+//! written, the rest have no state to share.
+
+use std::io::{self, Write};
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use crate::config::{Post, Posts};
+use crate::database::Database;
+
+/// The read state of a post of a feed.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct PostState(pub String, pub String, pub bool, pub bool);
+
+/// Write the state of the read or starred posts in the database to `out`.
+///
+/// Returns the number of exported posts.
+pub fn export<W: Write>(db: &Database, mut out: W) -> io::Result<usize> {
+    let mut exported = 0;
+    for (feed_url, post) in db.all_posts().map_err(io::Error::other)? {
+        if !post.read && !post.starred {
+            continue;
+        }
+        let state = PostState(feed_url, post.id.0.to_string(), post.read,
+            post.starred);
+        serde_json::to_writer(&mut out, &state)?;
+        writeln!(out)?;
+        exported += 1;
+    }
+
+    out.flush()?;
+    Ok(exported)
+}
+
+/// Parse the exported state, one post per line.
+pub fn parse(input: &str) -> Result<Vec<PostState>, String> {
+    input.lines().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| serde_json::from_str(line)
+            .map_err(|err| format!("Line {}: {}", idx + 1, err)))
+        .collect()
+}
+
+/// Merge the `states` into the `stored` posts, given along with the URLs of
+/// their feeds.
+///
+/// A post that's read or starred on either side stays so, nothing is ever
+/// marked unread or unstarred. Returns the posts that have changed by the
+/// URLs of their feeds, and the number of states of posts that aren't
+/// stored.
+pub fn merge(stored: Vec<(String, Post)>, states: Vec<PostState>)
+    -> (HashMap<String, Posts>, usize)
+{
+    let mut by_id: HashMap<(String, String), Post> = stored.into_iter()
+        .map(|(feed_url, post)| ((feed_url, post.id.0.to_string()), post))
+        .collect();
+
+    let mut unmatched = 0;
+    let mut changed: HashMap<String, Posts> = HashMap::new();
+    for PostState(feed_url, id, read, starred) in states {
+        let key = (feed_url, id);
+        let Some(post) = by_id.get_mut(&key) else {
+            unmatched += 1;
+            continue;
+        };
+
+        let read = post.read || read;
+        let starred = post.starred || starred;
+        if (read, starred) == (post.read, post.starred) {
+            continue;
+        }
+        post.read = read;
+        post.starred = starred;
+        changed.entry(key.0).or_default().insert(post.clone());
+    }
+
+    (changed, unmatched)
+}
+
+/// Merge the state exported into the `input` into the stored posts.
+///
+/// Returns the number of posts that have changed and the number of states
+/// of posts that aren't stored, e.g. because their feeds haven't been
+/// downloaded on this machine yet.
+pub fn import(db: &Database, input: &str) -> Result<(usize, usize), String> {
+    let states = parse(input)?;
+    let (changed, unmatched) = merge(db.all_posts()?, states);
+
+    let count = changed.values().map(|posts| posts.len()).sum();
+    for (feed_url, posts) in changed {
+        db.save_posts(&feed_url, posts)?;
+    }
+    Ok((count, unmatched))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_post;
+
+    #[test]
+    fn merges_read_and_starred_posts() {
+        let feed = "https://example.com/feed.xml";
+        let post = |id: &str, read, starred| (feed.to_string(), Post {
+            read,
+            starred,
+            ..test_post(id, "", 0)
+        });
+        let stored = vec![
+            post("unread", false, false),
+            post("read", true, false),
+            post("starred", false, true),
+        ];
+
+        let input = format!("{}\n\n{}\n{}\n{}\n",
+            serde_json::to_string(&PostState(feed.into(), "unread".into(),
+                true, false)).unwrap(),
+            r#"["https://example.com/feed.xml", "read", false, true]"#,
+            r#"["https://example.com/feed.xml", "starred", false, false]"#,
+            r#"["https://other.example/feed.xml", "read", true, true]"#);
+        let states = parse(&input).unwrap();
+        assert_eq!(states.len(), 4);
+
+        let (changed, unmatched) = merge(stored, states);
+        assert_eq!(unmatched, 1);
+        let posts = &changed[feed];
+        assert_eq!(posts.len(), 2);
+        let unread = posts.get_by_id(&"unread".to_string().into()).unwrap();
+        assert!(unread.read && !unread.starred);
+        let read = posts.get_by_id(&"read".to_string().into()).unwrap();
+        assert!(read.read && read.starred);
+
+        assert!(parse("[\"feed\", 1]").is_err());
+    }
+}
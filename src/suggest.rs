@@ -0,0 +1,99 @@
+//! Subscription suggestions based on the hosts linked to from posts.
+
+use std::collections::{HashMap, HashSet};
+use url::Url;
+use crate::config::FeedConfig;
+
+/// The number of posts that have to link to a host for it to be suggested.
+const MIN_POSTS: usize = 3;
+
+/// A host that is linked to often enough to be worth subscribing to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The host without a `www.` prefix.
+    pub host: String,
+
+    /// The number of posts linking to the host.
+    pub posts: usize,
+}
+
+impl Suggestion {
+    /// Get the URL of the host's home page, where its feed is likely to be
+    /// advertised, if the host makes a valid URL.
+    pub fn url(&self) -> Option<Url> {
+        Url::parse(&format!("https://{}/", self.host)).ok()
+    }
+}
+
+/// Get the host of the `url` without a `www.` prefix.
+fn host(url: &Url) -> Option<&str> {
+    url.host_str().map(|host| host.strip_prefix("www.").unwrap_or(host))
+}
+
+/// Find the hosts that are linked to from posts the most, excluding the hosts
+/// of the feeds we're already subscribed to.
+///
+/// The suggestions are sorted by the number of posts, most linked first.
+pub fn suggest(config: &FeedConfig) -> Vec<Suggestion> {
+    let feeds = config.sections.iter().flat_map(|s| s.feeds.iter());
+    let subscribed: HashSet<&str> = feeds.clone()
+        .filter_map(|feed| host(&feed.url))
+        .collect();
+
+    // Count every host at most once per post.
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for post in feeds.flat_map(|feed| feed.posts.as_ref().iter()) {
+        let hosts: HashSet<&str> = post.urls.iter()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .filter_map(host)
+            .filter(|host| !subscribed.contains(host))
+            .collect();
+
+        for host in hosts {
+            *counts.entry(host).or_default() += 1;
+        }
+    }
+
+    let mut suggestions: Vec<Suggestion> = counts.into_iter()
+        .filter(|&(_, posts)| posts >= MIN_POSTS)
+        .map(|(host, posts)| Suggestion { host: host.to_string(), posts })
+        .collect();
+
+    suggestions.sort_by(|a, b| b.posts.cmp(&a.posts)
+        .then_with(|| a.host.cmp(&b.host)));
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Post, Posts, test_post};
+
+    fn post(id: &str, urls: &[&str]) -> Post {
+        Post {
+            urls: urls.iter().map(|url| Url::parse(url).unwrap()).collect(),
+            ..test_post(id, "", chrono::Utc::now().timestamp())
+        }
+    }
+
+    #[test]
+    fn suggests_often_linked_hosts() {
+        let posts = Posts::from(vec![
+            post("1", &["https://www.blog.example/a",
+                "https://blog.example/b"]),
+            post("2", &["https://blog.example/c", "https://news.example/1"]),
+            post("3", &["https://blog.example/d", "https://once.example/"]),
+            post("4", &["https://news.example/2"]),
+        ]);
+
+        let url = Url::parse("https://news.example/rss").unwrap();
+        let config = FeedConfig::single_feed("News", url, posts);
+
+        // The links to the subscribed feed's host don't count and neither do
+        // multiple links in one post.
+        assert_eq!(suggest(&config), vec![Suggestion {
+            host: "blog.example".into(),
+            posts: 3,
+        }]);
+    }
+}
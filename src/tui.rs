@@ -1,6 +1,22 @@
 pub mod main;
 pub mod feed;
 pub mod post;
+pub mod command;
+pub mod theme;
+pub mod suggestions;
+pub mod catch_up;
+pub mod qr;
+pub mod health;
+pub mod actions;
+pub mod feed_form;
+pub mod timeline;
+pub mod image;
+pub mod media;
+pub mod info;
+pub mod text;
+pub mod stats;
+pub mod history;
+pub mod downloads;
 
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -11,7 +27,57 @@ use ratatui::{
 use crossterm::event::KeyCode;
 use crate::app::FeedState;
 use crate::config::{FeedId, PostId};
-use crate::database::DatabaseChannel;
+use crate::database::PageSession;
+use theme::Theme;
+
+/// Send the `query` to the terminal, followed by a request for the primary
+/// device attributes (DA1), and get the response to both.
+///
+/// Every terminal answers the latter, so if it arrives, we know that nothing
+/// else is coming and we don't have to wait for the timeout. The terminal
+/// must be in raw mode. Returns `None` if the terminal doesn't answer.
+pub fn query_terminal(query: &[u8]) -> Option<String> {
+    use std::io::{Read, Write};
+    use rustix::event::{poll, PollFd, PollFlags, Timespec};
+
+    const TIMEOUT: Duration = Duration::from_millis(200);
+
+    let mut tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+
+    tty.write_all(query).ok()?;
+    tty.write_all(b"\x1b[c").ok()?;
+    tty.flush().ok()?;
+
+    let start = Instant::now();
+    let mut response = Vec::new();
+    let mut buf = [0u8; 64];
+
+    while !is_da1_response(&response) {
+        let Some(remaining) = TIMEOUT.checked_sub(start.elapsed()) else {
+            break;
+        };
+        let timeout = Timespec::try_from(remaining).ok()?;
+        let mut fds = [PollFd::new(&tty, PollFlags::IN)];
+        if poll(&mut fds, Some(&timeout)).ok()? == 0 {
+            break;
+        }
+
+        let read = tty.read(&mut buf).ok()?;
+        response.extend_from_slice(&buf[..read]);
+    }
+
+    Some(String::from_utf8_lossy(&response).into_owned())
+}
+
+/// Check whether the response ends with the answer to DA1, `ESC [ ? ... c`.
+fn is_da1_response(response: &[u8]) -> bool {
+    response.ends_with(b"c")
+        && response.windows(3).any(|w| w == b"\x1b[?")
+}
 
 /// Trait which must be implemented for all entries in a navigable list that are
 /// selectable.
@@ -22,8 +88,8 @@ pub trait Selectable {
 
 /// Implementation of a single page in the TUI.
 pub trait Page {
-    /// Draw this page in the TUI.
-    fn draw(&mut self, f: &mut Frame, state: &FeedState);
+    /// Draw this page into the `area` of the TUI.
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState);
 
     /// Called after list navigation keys are handled.
     #[allow(unused_variables)]
@@ -34,10 +100,32 @@ pub trait Page {
     /// Access to the list for shared navigation.
     fn list(&mut self) -> &mut dyn NavigableList;
 
+    /// Whether the page takes all of the keys but Escape, because text is
+    /// typed into it.
+    fn is_editing(&self) -> bool {
+        false
+    }
+
+    /// The downloaded image that is previewed in the area of the screen, to
+    /// be drawn over the page.
+    fn image(&self) -> Option<(&url::Url, Rect)> {
+        None
+    }
+
     /// A hook that is executed by the app when the page is created and pushed
-    /// to the page stack.
+    /// to the page stack, returning what the app should do then.
+    #[allow(unused_variables)]
+    fn on_new(&mut self, state: &mut FeedState) -> PageAction {
+        PageAction::None
+    }
+
+    /// Where the page is, so that it can be opened there again on the next
+    /// launch. The pages that can't be are closed along with the pages
+    /// above them.
     #[allow(unused_variables)]
-    fn on_new(&mut self, state: &mut FeedState, database: &DatabaseChannel) {}
+    fn session(&self, state: &FeedState) -> Option<PageSession> {
+        None
+    }
 }
 
 /// Navigation controls for selectable lists.
@@ -47,6 +135,25 @@ pub trait NavigableList {
 
     /// Select the entry `amount` below the currently selected one.
     fn down(&mut self, amount: usize);
+
+    /// Select the `idx`-th selectable entry.
+    fn select(&mut self, idx: usize);
+
+    /// Get the index of the selected entry among the selectable ones.
+    fn selected(&self) -> usize;
+
+    /// Select the first entry.
+    fn first(&mut self) {
+        self.select(0);
+    }
+
+    /// Select the last entry.
+    fn last(&mut self) {
+        self.select(usize::MAX);
+    }
+
+    /// The number of rows visible when the list was last drawn.
+    fn page_height(&self) -> usize;
 }
 
 /// Strings in lists are always selectable.
@@ -67,17 +174,98 @@ pub enum PageAction {
     /// Download a feed.
     DownloadFeed(FeedId),
 
+    /// Download the feeds of the section with the index.
+    DownloadSection(usize),
+
     /// Download all feeds.
     DownloadAllFeeds,
 
+    /// Stop the downloads waiting for the network, so that they fail.
+    StopWaiting,
+
     /// Mark all posts within the feed as read.
     MarkFeedRead(FeedId),
 
+    /// Ask how old the posts within the feed have to be to be marked as
+    /// read.
+    MarkFeedReadOlder(FeedId),
+
     /// Toggle the read status for the post.
     TogglePostRead(FeedId, PostId),
 
+    /// Star or unstar the post.
+    TogglePostStarred(FeedId, PostId),
+
+    /// Mark the posts within the feed as read, or unread with `false`.
+    MarkPostsRead(FeedId, Vec<PostId>, bool),
+
+    /// Star the posts within the feed, or take their stars away with
+    /// `false`.
+    StarPosts(FeedId, Vec<PostId>, bool),
+
+    /// Change how the posts of the feed are shown.
+    SetFeedView(FeedId, crate::config::FeedView),
+
+    /// Mark all but the given number of newest unread posts within the feed
+    /// as read.
+    KeepLatestUnread(FeedId, usize),
+
     /// Copy something into clipboard.
     CopyToClipboard(Arc<str>),
+
+    /// Check whether the URLs are still reachable.
+    CheckLinks(Vec<url::Url>),
+
+    /// Subscribe to the feed at or advertised by the URL.
+    Subscribe(url::Url),
+
+    /// Save the URL to the read-it-later services.
+    ReadLater(url::Url),
+
+    /// Add a feed with the section, title and URL to the feed file, or
+    /// change the given feed to them.
+    SaveFeed(Option<FeedId>, Arc<str>, Arc<str>, url::Url),
+
+    /// Ask whether to remove the feed from the feed file.
+    RemoveFeed(FeedId),
+
+    /// Swap two feeds of the same section in the feed file.
+    SwapFeeds(FeedId, FeedId),
+
+    /// Move the feed to the end of the section with the index in the feed
+    /// file.
+    MoveFeed(FeedId, usize),
+
+    /// Download the image at the URL to preview it.
+    PreviewImage(url::Url),
+
+    /// Run the URL through the shell command of an external action.
+    RunAction(String, url::Url),
+
+    /// Show the text in the pager.
+    ShowInPager(String),
+
+    /// Download the article at the URL, linked from a post of the feed, and
+    /// show its text in the pager.
+    ReadArticle(FeedId, url::Url),
+
+    /// Write the post within the feed as a Markdown note.
+    WriteNote(FeedId, PostId),
+
+    /// Download the article of the post within the feed and archive its
+    /// text in the database.
+    ArchiveArticle(FeedId, PostId),
+
+    /// Play the media at the URL linked from the feed with the player of
+    /// the settings.
+    PlayMedia(FeedId, url::Url),
+
+    /// Open the URL in the browser of the settings.
+    OpenInBrowser(url::Url),
+
+    /// Do the action with the link of the post, marking the post read if
+    /// the settings say so and remembering that the link has been opened.
+    OpenLink(FeedId, PostId, url::Url, Box<PageAction>),
 }
 
 /// A page that lists out selectable `T` elements.
@@ -93,6 +281,9 @@ pub struct ListPage<T> {
 
     /// The state of the ratatui list.
     state: ListState,
+
+    /// The number of rows visible when the list was last drawn.
+    height: usize,
 }
 
 impl<T: Selectable> ListPage<T> {
@@ -103,9 +294,9 @@ impl<T: Selectable> ListPage<T> {
             .collect();
 
         let mut state = ListState::default();
-        state.select(selectable.get(0).copied());
+        state.select(selectable.first().copied());
 
-        Self { items, state, selectable, selected: 0 }
+        Self { items, state, selectable, selected: 0, height: 0 }
     }
 
     /// Get a reference to the currently selected item.
@@ -113,10 +304,45 @@ impl<T: Selectable> ListPage<T> {
         self.selectable.get(self.selected).and_then(|&idx| self.items.get(idx))
     }
 
+    /// Select the next item after the selected one that `matches`, wrapping
+    /// around the end of the list. Returns whether there was one.
+    pub fn select_next(&mut self, matches: impl Fn(&T) -> bool) -> bool {
+        let len = self.selectable.len();
+        let next = (1..=len)
+            .map(|offset| (self.selected + offset) % len)
+            .find(|&position| matches(&self.items[self.selectable[position]]));
+        if let Some(position) = next {
+            self.selected = position;
+            self.update_state();
+        }
+        next.is_some()
+    }
+
     /// Map `selected` into `state`.
     pub fn update_state(&mut self) {
         self.state.select(self.selectable.get(self.selected).copied())
     }
+
+    /// Get the index of the first row shown.
+    pub fn offset(&self) -> usize {
+        self.state.offset()
+    }
+
+    /// Scroll the list down to the row at `offset`, as far as the selected
+    /// row stays shown.
+    pub fn set_offset(&mut self, offset: usize) {
+        *self.state.offset_mut() = offset;
+    }
+
+    /// Render the `list` built from this page's items into `area`.
+    pub fn render(&mut self, f: &mut Frame, area: Rect, list: List,
+        theme: &Theme)
+    {
+        // Don't count the borders.
+        let borders = if theme.focus { 0 } else { 2 };
+        self.height = area.height.saturating_sub(borders) as usize;
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
 }
 
 impl<T: Selectable> NavigableList for ListPage<T> {
@@ -131,6 +357,20 @@ impl<T: Selectable> NavigableList for ListPage<T> {
         self.selected = max.min(self.selected.saturating_add(amount));
         self.update_state();
     }
+
+    fn select(&mut self, idx: usize) {
+        let max = self.selectable.len().saturating_sub(1);
+        self.selected = max.min(idx);
+        self.update_state();
+    }
+
+    fn selected(&self) -> usize {
+        self.selected
+    }
+
+    fn page_height(&self) -> usize {
+        self.height
+    }
 }
 
 /// Animated spinner that can be used to show that something is being loaded.
@@ -142,6 +382,12 @@ pub struct Spinner {
     last_tick: Instant,
 }
 
+impl Default for Spinner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Spinner {
     /// Frames of the spinner which will be shown on the screen when a feed is
     /// being actively downloaded.
@@ -180,22 +426,48 @@ impl Spinner {
     }
 }
 
+/// Get the number of columns left for the rows of a list drawn into the
+/// `area`, inside the borders and after the highlight symbol.
+fn row_width(area: Rect, theme: &Theme) -> usize {
+    let borders = if theme.focus { 0 } else { 2 };
+    area.width.saturating_sub(borders + 1) as usize
+}
+
 /// Helper function to build the page list.
-fn build_list<'a, T>(title: &'a str, items: T) -> List<'a>
+fn build_list<'a, T>(title: &'a str, items: T, theme: &Theme) -> List<'a>
 where
     T: IntoIterator,
     <T as IntoIterator>::Item: Into<ListItem<'a>>
 {
+    // The focus mode doesn't draw the borders and so neither the title.
+    let block = if theme.focus {
+        Block::default()
+    } else {
+        Block::default().borders(Borders::ALL).title(title)
+    };
+
     List::new(items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(title),
-        )
-        .highlight_style(
-            Style::default()
-                .fg(Color::Blue)
-        )
+        .block(block)
+        .highlight_style(theme.highlight)
         .highlight_symbol(" ")
         .scroll_padding(4)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_next_match_wrapping_around() {
+        let items = ["a", "b", "c", "a", "b"].map(String::from).to_vec();
+        let mut list = ListPage::new(items);
+        list.select(3);
+
+        assert!(list.select_next(|item| item == "a"));
+        assert_eq!(list.selected_item().unwrap(), "a");
+        assert_eq!(list.selected, 0);
+
+        assert!(!list.select_next(|item| item == "d"));
+        assert_eq!(list.selected, 0);
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+use ratatui::{
+    prelude::*,
+    widgets::{ListItem, Paragraph},
+};
+use crossterm::event::KeyCode;
+use crate::tui::{Page, NavigableList, ListPage, PageAction};
+use crate::app::FeedState;
+use crate::config::FeedId;
+
+/// The page that lists the external actions a URL can be run through.
+pub struct ActionsPage {
+    /// The URL the actions are run with.
+    url: url::Url,
+
+    /// The shell commands of the actions, by name.
+    actions: BTreeMap<String, String>,
+
+    /// The names of the actions.
+    list: ListPage<String>,
+}
+
+impl ActionsPage {
+    /// Create a new page with the actions for `url`, a link of a post of the
+    /// feed with `feed_id`.
+    ///
+    /// The actions of the feed take precedence over those of the settings.
+    pub fn new(url: url::Url, feed_id: &FeedId, state: &FeedState) -> Self {
+        let mut actions = state.settings.actions.clone();
+        if let Some(feed) = state.get_feed(feed_id) {
+            actions.extend(feed.options.actions.clone());
+        }
+        let names = actions.keys().cloned().collect();
+        Self { url, actions, list: ListPage::new(names) }
+    }
+}
+
+/// Build the shell command of an action from its `template`, with every `%u`
+/// replaced by the quoted `url`.
+pub fn command_line(template: &str, url: &url::Url) -> String {
+    let quoted = format!("'{}'", url.as_str().replace('\'', r"'\''"));
+    template.replace("%u", &quoted)
+}
+
+impl Page for ActionsPage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        if self.list.items.is_empty() {
+            let message = "No actions are configured, add them to the \
+                [actions] table of config.toml or to the feed in feeds.toml";
+            f.render_widget(Paragraph::new(message), area);
+            return;
+        }
+
+        let items = self.list.items.iter().map(|name| {
+            let template = &self.actions[name];
+            let line = if state.theme.focus {
+                Line::raw(name.clone())
+            } else {
+                Line::from(vec![
+                    Span::raw(format!("{:<12}", name)),
+                    Span::raw("  │  "),
+                    Span::raw(template.as_str()),
+                ])
+            };
+            ListItem::new(line)
+        });
+
+        let title = format!(" Run {} with ", self.url);
+        let list = crate::tui::build_list(&title, items, &state.theme);
+        self.list.render(f, area, list, &state.theme);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+
+    fn on_key(&mut self, key: KeyCode, _state: &FeedState) -> PageAction {
+        let Some(name) = self.list.selected_item() else {
+            return PageAction::None;
+        };
+
+        match key {
+            KeyCode::Enter | KeyCode::Char('l') => {
+                let template = self.actions[name].clone();
+                PageAction::RunAction(template, self.url.clone())
+            },
+            _ => PageAction::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_urls_in_commands() {
+        let url = url::Url::parse("https://example.com/it's?a=1&b=2").unwrap();
+        assert_eq!(command_line("mpv %u", &url),
+            r"mpv 'https://example.com/it'\''s?a=1&b=2'");
+        assert_eq!(command_line("true", &url), "true");
+    }
+}
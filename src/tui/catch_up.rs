@@ -0,0 +1,123 @@
+use ratatui::{
+    prelude::*,
+    widgets::ListItem,
+};
+use crossterm::event::KeyCode;
+use chrono::{DateTime, Utc};
+use crate::tui::{Page, NavigableList, ListPage, PageAction, feed::FeedPage};
+use crate::app::FeedState;
+use crate::config::FeedId;
+
+/// Rows in the catch-up page.
+struct CatchUpRow {
+    /// The feed with unread posts.
+    feed_id: FeedId,
+
+    /// The number of posts that have arrived since the break.
+    new: usize,
+}
+
+/// All feeds are selectable.
+impl crate::tui::Selectable for CatchUpRow {
+    fn selectable(&self) -> bool {
+        true
+    }
+}
+
+/// The page that summarizes the posts accumulated during a long break, so that
+/// they can be dealt with feed by feed.
+pub struct CatchUpPage {
+    /// List of the feeds with unread posts.
+    list: ListPage<CatchUpRow>,
+
+    /// When the last post before the break has arrived.
+    since: DateTime<Utc>,
+}
+
+impl CatchUpPage {
+    /// Create a new catch-up page for the break that started `since`.
+    ///
+    /// Returns `None` if nothing is unread.
+    pub fn new(since: DateTime<Utc>, state: &FeedState) -> Option<Self> {
+        let rows: Vec<CatchUpRow> = state.feed_ids().filter_map(|feed_id| {
+            let feed = state.get_feed(&feed_id)?;
+            if feed.posts.unread() == 0 {
+                return None;
+            }
+
+            let new = feed.posts.as_ref().iter()
+                .filter(|post| post.arrived > since)
+                .count();
+            Some(CatchUpRow { feed_id, new })
+        }).collect();
+
+        (!rows.is_empty()).then(|| Self { list: ListPage::new(rows), since })
+    }
+}
+
+impl Page for CatchUpPage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        let items = self.list.items.iter().map(|row| {
+            let Some(feed) = state.get_feed(&row.feed_id) else {
+                return ListItem::new("");
+            };
+            let unread = feed.posts.unread();
+
+            let counts = if state.theme.focus {
+                String::new()
+            } else {
+                format!("{:>5} new  {:>5} unread  │  ", row.new, unread)
+            };
+
+            let line = Line::from(vec![
+                Span::raw(counts),
+                Span::raw(feed.title.as_ref()),
+            ]);
+
+            // Feeds that still have unread posts stand out.
+            let line = if unread != 0 {
+                line.style(state.theme.unread)
+            } else {
+                line
+            };
+
+            ListItem::new(line)
+        });
+
+        let days = (Utc::now() - self.since).num_days();
+        let title = format!(" Catch up | {} days without new posts ", days);
+        let list = crate::tui::build_list(&title, items, &state.theme);
+
+        self.list.render(f, area, list, &state.theme);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+
+    fn on_key(&mut self, key: KeyCode, state: &FeedState) -> PageAction {
+        let Some(row) = self.list.selected_item() else {
+            return PageAction::None;
+        };
+        let feed_id = row.feed_id.clone();
+
+        match key {
+            // Mark all posts in the feed as read.
+            KeyCode::Char('r') => PageAction::MarkFeedRead(feed_id),
+
+            // Keep only the newest unread posts.
+            KeyCode::Char('n') => {
+                let keep = state.settings.catch_up.keep;
+                PageAction::KeepLatestUnread(feed_id, keep)
+            },
+
+            // Check the posts listing for the selected feed.
+            KeyCode::Enter | KeyCode::Char('l') => {
+                let page = Box::new(FeedPage::new(feed_id));
+                PageAction::NewPage(page)
+            },
+
+            _ => PageAction::None,
+        }
+    }
+}
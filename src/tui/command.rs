@@ -0,0 +1,306 @@
+//! The `:` command line.
+
+use chrono::{NaiveDate, TimeDelta};
+use ratatui::{prelude::*, widgets::Paragraph};
+use crate::app::FeedState;
+use crate::tui::text;
+
+/// Names of all commands understood by the command line.
+///
+/// New commands should be added here so that they can be completed.
+const COMMANDS: &[&str] = &[
+    "refresh", "mark-read", "open", "goto", "as-of", "suggest",
+    "watch-clipboard", "killfile", "health", "stats", "history",
+    "downloads", "quit"
+];
+
+/// A parsed command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Download all feeds.
+    RefreshAll,
+
+    /// Download a single feed given its title.
+    Refresh(String),
+
+    /// Mark the posts older than the given age as read, only in the feed
+    /// with the given title if there is one.
+    MarkReadOlder(TimeDelta, Option<String>),
+
+    /// Open the `n`-th entry in the current list.
+    Open(usize),
+
+    /// Go to the feed with the given title.
+    Goto(String),
+
+    /// Go to the feed with the given title as it was at the end of the day.
+    AsOf(NaiveDate, String),
+
+    /// Show the subscription suggestions.
+    Suggest,
+
+    /// Start or stop offering to subscribe to the feed URLs copied into the
+    /// clipboard.
+    WatchClipboard,
+
+    /// Stop hiding the posts matching the killfile, or start again.
+    Killfile,
+
+    /// Show how the last downloads of the feeds went.
+    Health,
+
+    /// Show how much has been read and how much is waiting.
+    Stats,
+
+    /// Show the posts that have been read, the latest first.
+    History,
+
+    /// Show how the downloads queued since the downloader was last idle are
+    /// going.
+    Downloads,
+
+    /// Quit the application.
+    Quit,
+}
+
+impl Command {
+    /// Parse a command from the string the user has typed in.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let (name, args) = s.split_once(char::is_whitespace)
+            .map(|(name, args)| (name, args.trim()))
+            .unwrap_or((s, ""));
+
+        match name {
+            "refresh" => match args {
+                "" | "all" => Ok(Command::RefreshAll),
+                feed => Ok(Command::Refresh(feed.to_string())),
+            },
+
+            "mark-read" => {
+                let args = args.strip_prefix("older")
+                    .map(str::trim)
+                    .ok_or("Usage: mark-read older <age> [<feed>]")?;
+                let (age, feed) = args.split_once(char::is_whitespace)
+                    .map(|(age, feed)| (age, Some(feed.trim().to_string())))
+                    .unwrap_or((args, None));
+                parse_age(age)
+                    .map(|age| Command::MarkReadOlder(age, feed))
+                    .ok_or_else(|| format!("Invalid age: {}", age))
+            },
+
+            "open" => args.parse()
+                .map(Command::Open)
+                .map_err(|_| "Usage: open <index>".to_string()),
+
+            "goto" if !args.is_empty() => Ok(Command::Goto(args.to_string())),
+            "goto" => Err("Usage: goto <feed>".to_string()),
+
+            "as-of" => {
+                let usage = "Usage: as-of <YYYY-MM-DD> <feed>";
+                let (day, feed) = args.split_once(char::is_whitespace)
+                    .ok_or(usage)?;
+                let day = NaiveDate::parse_from_str(day, "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid date: {}", day))?;
+                Ok(Command::AsOf(day, feed.trim().to_string()))
+            },
+
+            "suggest" => Ok(Command::Suggest),
+
+            "watch-clipboard" => Ok(Command::WatchClipboard),
+
+            "killfile" => Ok(Command::Killfile),
+
+            "health" => Ok(Command::Health),
+
+            "stats" => Ok(Command::Stats),
+
+            "history" => Ok(Command::History),
+
+            "downloads" => Ok(Command::Downloads),
+
+            "quit" | "q" => Ok(Command::Quit),
+
+            _ => Err(format!("Unknown command: {}", name)),
+        }
+    }
+}
+
+/// Parse an age such as `12h`, `7d` or `2w`.
+pub fn parse_age(s: &str) -> Option<TimeDelta> {
+    let unit_idx = s.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = s.split_at(unit_idx);
+    let amount: i64 = amount.parse().ok()?;
+
+    match unit {
+        "h" => TimeDelta::try_hours(amount),
+        "d" => TimeDelta::try_days(amount),
+        "w" => TimeDelta::try_weeks(amount),
+        _ => None,
+    }
+}
+
+/// State of the command line input.
+#[derive(Default)]
+pub struct CommandLine {
+    /// The text typed in by the user.
+    input: String,
+
+    /// Completion candidates for the word being completed and the index of the
+    /// one that is currently filled in.
+    completion: Option<(Vec<String>, usize)>,
+
+    /// Length of the input before the word being completed.
+    completion_start: usize,
+}
+
+impl CommandLine {
+    /// Create a new empty command line.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the text typed in so far.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Type a character into the command line.
+    pub fn push(&mut self, c: char) {
+        self.completion = None;
+        self.input.push(c);
+    }
+
+    /// Delete the last grapheme. Returns `false` if there was nothing to
+    /// delete.
+    pub fn pop(&mut self) -> bool {
+        self.completion = None;
+        text::pop_grapheme(&mut self.input)
+    }
+
+    /// Complete the word under the cursor, cycling through the candidates on
+    /// repeated calls.
+    pub fn complete(&mut self, state: &FeedState) {
+        // Cycle through the candidates we already have.
+        if let Some((candidates, idx)) = &mut self.completion {
+            *idx = (*idx + 1) % candidates.len();
+            self.input.truncate(self.completion_start);
+            self.input.push_str(&candidates[*idx]);
+            return;
+        }
+
+        // Split the input into the command and the argument being completed.
+        let (start, prefix, candidates) = match self.input.split_once(' ') {
+            None => {
+                let commands = COMMANDS.iter().map(|c| c.to_string());
+                (0, self.input.as_str(), commands.collect())
+            },
+            Some((command, arg)) => {
+                let mut start = command.len() + 1;
+                let mut arg = arg;
+                let candidates = match command {
+                    "refresh" => std::iter::once("all".to_string())
+                        .chain(state.feed_titles())
+                        .collect(),
+                    "goto" => state.feed_titles().collect(),
+                    "mark-read" => match arg.split_once(' ') {
+                        // The feed comes after the age.
+                        Some((older, rest)) => match rest.split_once(' ') {
+                            Some((age, feed)) => {
+                                start += older.len() + age.len() + 2;
+                                arg = feed;
+                                state.feed_titles().collect()
+                            },
+                            None => Vec::new(),
+                        },
+                        None => vec!["older".to_string()],
+                    },
+
+                    // The feed comes after the date.
+                    "as-of" => match arg.split_once(' ') {
+                        Some((day, feed)) => {
+                            start += day.len() + 1;
+                            arg = feed;
+                            state.feed_titles().collect()
+                        },
+                        None => Vec::new(),
+                    },
+                    _ => Vec::new(),
+                };
+                (start, arg, candidates)
+            },
+        };
+
+        // Only keep the candidates that match what's been typed in.
+        let prefix = prefix.to_lowercase();
+        let candidates: Vec<String> = candidates.into_iter()
+            .filter(|c| c.to_lowercase().starts_with(&prefix))
+            .collect();
+
+        if candidates.is_empty() {
+            return;
+        }
+
+        self.completion_start = start;
+        self.input.truncate(start);
+        self.input.push_str(&candidates[0]);
+        self.completion = Some((candidates, 0));
+    }
+
+    /// Draw the command line into `area`.
+    pub fn draw(&self, f: &mut Frame, area: Rect) {
+        let line = Line::from(vec![Span::raw(":"), Span::raw(&self.input)]);
+        f.render_widget(Paragraph::new(line), area);
+        let column = 1 + text::width(&self.input) as u16;
+        f.set_cursor_position((area.x + column, area.y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(Command::parse("refresh"), Ok(Command::RefreshAll));
+        assert_eq!(Command::parse("refresh all"), Ok(Command::RefreshAll));
+        assert_eq!(Command::parse("refresh Rust Blog"),
+            Ok(Command::Refresh("Rust Blog".to_string())));
+        assert_eq!(Command::parse("open 3"), Ok(Command::Open(3)));
+        assert_eq!(Command::parse(" goto  xkcd "),
+            Ok(Command::Goto("xkcd".to_string())));
+        assert_eq!(Command::parse("mark-read older 7d"),
+            Ok(Command::MarkReadOlder(TimeDelta::days(7), None)));
+        assert_eq!(Command::parse("mark-read older 2w  Rust Blog"),
+            Ok(Command::MarkReadOlder(TimeDelta::weeks(2),
+                Some("Rust Blog".to_string()))));
+        assert_eq!(Command::parse("as-of 2024-02-29 Rust Blog"),
+            Ok(Command::AsOf(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
+                "Rust Blog".to_string())));
+        assert_eq!(Command::parse("watch-clipboard"),
+            Ok(Command::WatchClipboard));
+        assert_eq!(Command::parse("health"), Ok(Command::Health));
+        assert_eq!(Command::parse("stats"), Ok(Command::Stats));
+        assert_eq!(Command::parse("history"), Ok(Command::History));
+        assert_eq!(Command::parse("downloads"), Ok(Command::Downloads));
+        assert_eq!(Command::parse("killfile"), Ok(Command::Killfile));
+    }
+
+    #[test]
+    fn rejects_invalid_commands() {
+        assert!(Command::parse("frobnicate").is_err());
+        assert!(Command::parse("open three").is_err());
+        assert!(Command::parse("goto").is_err());
+        assert!(Command::parse("mark-read older 7y").is_err());
+        assert!(Command::parse("as-of 2024-01-01").is_err());
+        assert!(Command::parse("as-of yesterday xkcd").is_err());
+    }
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!(parse_age("12h"), Some(TimeDelta::hours(12)));
+        assert_eq!(parse_age("2w"), Some(TimeDelta::weeks(2)));
+        assert_eq!(parse_age("d"), None);
+        assert_eq!(parse_age("5"), None);
+    }
+}
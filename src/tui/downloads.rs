@@ -0,0 +1,216 @@
+use std::time::Instant;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Gauge, ListItem},
+};
+use crossterm::event::KeyCode;
+use crate::tui::{Page, NavigableList, ListPage, PageAction, info::FeedInfoPage};
+use crate::app::{Download, DownloadStatus, FeedState};
+use crate::download::DownloadError;
+
+/// Rows in the downloads page.
+struct DownloadRow {
+    /// The index of the download among the downloads of the feed state.
+    idx: usize,
+}
+
+/// All downloads are selectable.
+impl crate::tui::Selectable for DownloadRow {
+    fn selectable(&self) -> bool {
+        true
+    }
+}
+
+/// The page that shows how the downloads queued since the downloader was
+/// last idle are going, with the progress of all of them on top.
+pub struct DownloadsPage {
+    /// List of the downloads, in the order they were queued.
+    list: ListPage<DownloadRow>,
+}
+
+impl Default for DownloadsPage {
+    /// Create a new downloads page.
+    fn default() -> Self {
+        Self { list: ListPage::new(Vec::new()) }
+    }
+}
+
+/// Describe a number of `bytes` in the biggest unit that fits it.
+fn size(bytes: usize) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+    }
+}
+
+/// Describe the `download` as of `now` in fixed-width columns.
+fn columns(download: &Download, now: Instant) -> String {
+    let status = match download.status {
+        DownloadStatus::Queued => "queued",
+        DownloadStatus::Downloading => "downloading",
+        DownloadStatus::Waiting => "no network",
+        DownloadStatus::Done => "done",
+        DownloadStatus::Failed(DownloadError::TimedOut(_)) => "timed out",
+        DownloadStatus::Failed(DownloadError::Unreachable(_)) => "unreachable",
+        DownloadStatus::Failed(DownloadError::Failed(_)) => "failed",
+    };
+    let bytes = download.bytes.map(size).unwrap_or_default();
+    let elapsed = download.elapsed(now)
+        .map(|elapsed| format!("{:.1}s", elapsed.as_secs_f64()))
+        .unwrap_or_default();
+    format!("{:<11}  {:>10}  {:>6}", status, bytes, elapsed)
+}
+
+/// Count the `downloads` that are over and those of them that have failed.
+fn progress(downloads: &[Download]) -> (usize, usize) {
+    let over = downloads.iter().filter(|download| download.is_over()).count();
+    let failed = downloads.iter()
+        .filter(|download| {
+            matches!(download.status, DownloadStatus::Failed(_))
+        })
+        .count();
+    (over, failed)
+}
+
+/// Sum up how far the `downloads` have got, e.g. `12/87 feeds refreshed, 3
+/// failed`.
+pub fn summary(downloads: &[Download]) -> String {
+    let (over, failed) = progress(downloads);
+    let mut summary = format!("{}/{} feeds refreshed", over, downloads.len());
+    if failed > 0 {
+        summary += &format!(", {} failed", failed);
+    }
+    summary
+}
+
+impl Page for DownloadsPage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        let downloads = state.downloads();
+        if self.list.items.len() != downloads.len() {
+            let rows = (0..downloads.len())
+                .map(|idx| DownloadRow { idx })
+                .collect();
+            self.list = ListPage::new(rows);
+        }
+
+        // The progress of all downloads goes on top of the list.
+        let (over, _) = progress(downloads);
+        let ratio = over as f64 / downloads.len().max(1) as f64;
+        let (gauge_height, block) = if state.theme.focus {
+            (1, Block::default())
+        } else {
+            (3, Block::default().borders(Borders::ALL).title(" Downloads "))
+        };
+        let gauge = Gauge::default()
+            .block(block)
+            .gauge_style(state.theme.section)
+            .ratio(ratio)
+            .label(summary(downloads));
+        let [gauge_area, list_area] = Layout::vertical([
+            Constraint::Length(gauge_height),
+            Constraint::Min(0),
+        ]).areas(area);
+        f.render_widget(gauge, gauge_area);
+
+        let now = Instant::now();
+        let items = self.list.items.iter().filter_map(|row| {
+            let download = downloads.get(row.idx)?;
+            let feed = state.get_feed(&download.feed_id)?;
+
+            let mut spans = Vec::new();
+            if !state.theme.focus {
+                spans.push(Span::raw(columns(download, now)));
+                spans.push(Span::raw("  │  "));
+            }
+            spans.push(Span::raw(feed.title.as_ref()));
+
+            // Say why the download has failed.
+            let line = match &download.status {
+                DownloadStatus::Failed(reason) => {
+                    spans.push(Span::raw(format!(" — {}", reason)));
+                    Line::from(spans).style(state.theme.error)
+                },
+                DownloadStatus::Queued | DownloadStatus::Waiting => {
+                    Line::from(spans).style(state.theme.dim)
+                },
+                _ => Line::from(spans),
+            };
+            Some(ListItem::new(line))
+        });
+
+        let title = format!(" Queue | {} feeds ", downloads.len());
+        let list = crate::tui::build_list(&title, items, &state.theme);
+        self.list.render(f, list_area, list, &state.theme);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+
+    fn on_key(&mut self, key: KeyCode, state: &FeedState) -> PageAction {
+        let Some(row) = self.list.selected_item() else {
+            return PageAction::None;
+        };
+        let Some(download) = state.downloads().get(row.idx) else {
+            return PageAction::None;
+        };
+        let feed_id = download.feed_id.clone();
+
+        match key {
+            // Try downloading the feed again.
+            KeyCode::Char('d') => PageAction::DownloadFeed(feed_id),
+
+            // Give up waiting for the network.
+            KeyCode::Char('x') => PageAction::StopWaiting,
+
+            // Check how the downloads of the feed have gone.
+            KeyCode::Enter | KeyCode::Char('l') => {
+                let page = Box::new(FeedInfoPage::new(feed_id));
+                PageAction::NewPage(page)
+            },
+
+            _ => PageAction::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::config::FeedId;
+
+    #[test]
+    fn describes_downloads() {
+        let now = Instant::now();
+        let download = |status, started: Option<u64>, bytes| Download {
+            feed_id: FeedId { section_idx: 0, feed_idx: 0 },
+            status,
+            started: started.map(|secs| now - Duration::from_secs(secs)),
+            ended: None,
+            bytes,
+        };
+        let queued = download(DownloadStatus::Queued, None, None);
+        let downloading = download(DownloadStatus::Downloading, Some(2), None);
+        let mut done = download(DownloadStatus::Done, Some(5), Some(2560));
+        done.ended = Some(now - Duration::from_secs(4));
+        let failed = download(DownloadStatus::Failed(
+            DownloadError::Failed("Not Found".into())), Some(1), Some(12));
+        let timed_out = download(DownloadStatus::Failed(
+            DownloadError::TimedOut("operation timed out".into())), Some(60),
+            None);
+
+        assert_eq!(columns(&queued, now), "queued                         ");
+        assert_eq!(columns(&downloading, now),
+            "downloading                2.0s");
+        assert_eq!(columns(&done, now), "done            2.5 KiB    1.0s");
+        assert_eq!(columns(&failed, now), "failed             12 B    1.0s");
+        assert_eq!(columns(&timed_out, now),
+            "timed out                 60.0s");
+        let downloads = [queued, downloading, done, failed, timed_out];
+        assert_eq!(progress(&downloads), (3, 2));
+        assert_eq!(summary(&downloads), "3/5 feeds refreshed, 2 failed");
+        assert_eq!(summary(&downloads[..2]), "0/2 feeds refreshed");
+    }
+}
@@ -1,15 +1,71 @@
+use std::collections::HashSet;
 use ratatui::{
     prelude::*,
     widgets::ListItem,
 };
 use crossterm::event::KeyCode;
-use crate::tui::{PageAction, Page, NavigableList, ListPage, post::PostPage};
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeDelta, Utc};
+use crate::tui::{
+    PageAction, Page, NavigableList, ListPage, post::PostPage, text,
+};
 use crate::app::FeedState;
-use crate::config::FeedId;
+use crate::database::PageSession;
+use crate::config::{Feed, FeedId, FeedView, Grouping, Post, PostId, SortOrder};
+use crate::settings::{DateFormat, MarkRead};
+
+/// The most columns of the name of an author shown in the author column.
+const MAX_AUTHOR: usize = 20;
+
+/// Rows in the feed page.
+enum FeedRow {
+    /// Header of the posts published on the day.
+    Day(NaiveDate),
+
+    /// Index of a post in the feed.
+    Post(usize),
+}
+
+/// Format the day the post was `published` on in the `format`, as of `now`.
+fn date(published: DateTime<Utc>, now: DateTime<Utc>, format: DateFormat)
+    -> String
+{
+    let local = published.with_timezone(&Local);
+    if format == DateFormat::Iso {
+        return local.format("%Y-%m-%d").to_string();
+    }
+
+    // The posts from the future have just been published.
+    let age = (now - published).max(TimeDelta::zero());
+    if age < TimeDelta::minutes(1) {
+        "now".into()
+    } else if age < TimeDelta::hours(1) {
+        format!("{}m", age.num_minutes())
+    } else if age < TimeDelta::days(1) {
+        format!("{}h", age.num_hours())
+    } else if age < TimeDelta::weeks(1) {
+        format!("{}d", age.num_days())
+    } else if local.year() == now.with_timezone(&Local).year() {
+        local.format("%b %-d").to_string()
+    } else {
+        local.format("%b %Y").to_string()
+    }
+}
+
+/// Name the `day` of a header of posts, as of `today`.
+fn day_label(day: NaiveDate, today: NaiveDate) -> String {
+    if day == today {
+        "Today".into()
+    } else if today.pred_opt() == Some(day) {
+        "Yesterday".into()
+    } else {
+        day.format("%Y-%m-%d").to_string()
+    }
+}
 
-impl crate::tui::Selectable for usize {
+/// Only posts are selectable.
+impl crate::tui::Selectable for FeedRow {
     fn selectable(&self) -> bool {
-        true
+        matches!(self, FeedRow::Post(_))
     }
 }
 
@@ -19,49 +75,357 @@ pub struct FeedPage {
     feed_id: FeedId,
 
     /// List of rows on the feed page.
-    ///
-    /// In this case, each row is a post index.
-    list: ListPage<usize>,
+    list: ListPage<FeedRow>,
+
+    /// The number of posts in the feed, the view and whether the killfile
+    /// hid posts when the list was last built.
+    built: Option<(usize, FeedView, bool)>,
+
+    /// The number of posts hidden by the killfile.
+    hidden: usize,
+
+    /// The day the feed is shown as of and the end of that day.
+    as_of: Option<(NaiveDate, DateTime<Utc>)>,
+
+    /// The posts selected for a batch operation.
+    selection: HashSet<PostId>,
+
+    /// Where the range being selected starts, as an index into the
+    /// selectable rows. The range ends at the selected row.
+    range_start: Option<usize>,
+
+    /// The posts that had arrived since the feed was last opened.
+    fresh: HashSet<PostId>,
+
+    /// Only the posts by this author are listed, if it's set.
+    author: Option<String>,
+
+    /// Whether the summaries of the posts are shown under their titles.
+    detailed: bool,
+
+    /// The post to select and the offset to scroll to once the rows are
+    /// built, when the page is opened again where it was.
+    restored: Option<(Option<PostId>, usize)>,
 }
 
 impl FeedPage {
     pub fn new(feed_id: FeedId) -> Self {
-        Self { feed_id, list: ListPage::new(Vec::new()), }
+        Self { feed_id, list: ListPage::new(Vec::new()), built: None,
+            hidden: 0, as_of: None, selection: HashSet::new(),
+            range_start: None, fresh: HashSet::new(), author: None,
+            detailed: false, restored: None }
+    }
+
+    /// Open the page where it was, with the `post` selected and the list
+    /// scrolled down to the row at `offset`.
+    pub fn restored(feed_id: FeedId, post: Option<PostId>, offset: usize)
+        -> Self
+    {
+        Self { restored: Some((post, offset)), ..Self::new(feed_id) }
+    }
+
+    /// Show the feed as it was at the end of the `day`, with only the posts
+    /// that had arrived by then and without their read state.
+    pub fn as_of(feed_id: FeedId, day: NaiveDate) -> Self {
+        // Posts arriving before the local midnight ending the day count.
+        let end = day.succ_opt()
+            .and_then(|next| next.and_hms_opt(0, 0, 0))
+            .and_then(|midnight| midnight
+                .and_local_timezone(chrono::Local)
+                .earliest())
+            .map(|midnight| midnight.with_timezone(&Utc))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+
+        Self { as_of: Some((day, end)), ..Self::new(feed_id) }
+    }
+
+    /// Build the rows of the `feed` as its view says, counting the posts
+    /// hidden by the killfile.
+    fn rows(&mut self, feed: &Feed, state: &FeedState) -> Vec<FeedRow> {
+        let posts = feed.posts.as_ref();
+        let view = feed.view;
+
+        // Nothing had been read as of a day, so everything is unread then.
+        let mut indices: Vec<usize> = (0..posts.len())
+            .filter(|&idx| self.as_of
+                .is_none_or(|(_, end)| posts[idx].arrived < end))
+            .filter(|&idx| !view.unread_only || self.as_of.is_some()
+                || !posts[idx].read)
+            .filter(|&idx| self.author.as_ref()
+                .is_none_or(|author| posts[idx].authors.contains(author)))
+            .collect();
+
+        let shown = indices.len();
+        indices.retain(|&idx| !state.is_killed(&posts[idx]));
+        self.hidden = shown - indices.len();
+
+        // The posts are sorted newest first already.
+        match view.sort {
+            SortOrder::Newest => {},
+            SortOrder::Oldest => indices.reverse(),
+            SortOrder::Title => {
+                indices.sort_by_cached_key(|&i| posts[i].title.to_lowercase())
+            },
+        }
+
+        // Posts sorted by title would get a header each.
+        let grouped = view.grouping == Grouping::Day
+            && view.sort != SortOrder::Title;
+
+        let mut rows = Vec::with_capacity(indices.len());
+        let mut day = None;
+        for idx in indices {
+            let published = posts[idx].published
+                .with_timezone(&Local)
+                .date_naive();
+            if grouped && day != Some(published) {
+                rows.push(FeedRow::Day(published));
+                day = Some(published);
+            }
+            rows.push(FeedRow::Post(idx));
+        }
+
+        rows
+    }
+
+    /// Get the indices of the posts selected for a batch operation, in the
+    /// order they're listed in, including the range being selected.
+    fn marked(&self, feed: &Feed) -> Vec<usize> {
+        let range = self.range_start.map(|start| {
+            let end = self.list.selected;
+            start.min(end)..=start.max(end)
+        });
+
+        self.list.selectable.iter()
+            .enumerate()
+            .filter_map(|(position, &row)| match self.list.items[row] {
+                FeedRow::Post(idx) => Some((position, idx)),
+                FeedRow::Day(_) => None,
+            })
+            .filter(|&(position, idx)| {
+                range.as_ref().is_some_and(|range| range.contains(&position))
+                    || self.selection.contains(&feed.posts.as_ref()[idx].id)
+            })
+            .map(|(_, idx)| idx)
+            .collect()
+    }
+
+    /// Run the batch operation of the `key` on the `marked` posts of the
+    /// `feed`, clearing the selection.
+    fn batch(&mut self, key: KeyCode, feed: &Feed, marked: &[usize])
+        -> PageAction
+    {
+        let posts: Vec<&Post> = marked.iter()
+            .map(|&idx| &feed.posts.as_ref()[idx])
+            .collect();
+        let ids = posts.iter().map(|post| post.id.clone()).collect();
+        let feed_id = self.feed_id.clone();
+
+        let action = match key {
+            // Mark them read, or unread if they're all read already.
+            KeyCode::Char('r') if self.as_of.is_none() => {
+                let read = posts.iter().any(|post| !post.read);
+                PageAction::MarkPostsRead(feed_id, ids, read)
+            },
+
+            // Star them, or take the stars away if they all have one.
+            KeyCode::Char('*') => {
+                let starred = posts.iter().any(|post| !post.starred);
+                PageAction::StarPosts(feed_id, ids, starred)
+            },
+
+            // Copy their links, one per line.
+            KeyCode::Enter | KeyCode::Char('l') => {
+                let links: Vec<&str> = posts.iter()
+                    .filter_map(|post| post.urls.first())
+                    .map(|url| url.as_str())
+                    .collect();
+                PageAction::CopyToClipboard(links.join("\n").into())
+            },
+
+            _ => return PageAction::None,
+        };
+
+        self.selection.clear();
+        self.range_start = None;
+        action
     }
 }
 
 impl Page for FeedPage {
-    fn draw(&mut self, f: &mut Frame, state: &FeedState) {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
         // Get this feed state.
-        let feed = state.get_feed(&self.feed_id).unwrap();
+        let Some(feed) = state.get_feed(&self.feed_id) else {
+            return;
+        };
 
-        // Rebuild index list if lengths differ.
-        if self.list.items.len() != feed.posts.len() {
-            self.list = ListPage::new((0..feed.posts.len()).collect());
+        // Rebuild the rows if the posts or the view have changed.
+        let built = Some((feed.posts.len(), feed.view, state.killfile));
+        if self.built != built {
+            let rows = self.rows(feed, state);
+            self.list = ListPage::new(rows);
+            self.built = built;
+            self.range_start = None;
         }
+        if let Some((post_id, offset)) = self.restored.take() {
+            let posts = feed.posts.as_ref();
+            if let Some(post_id) = post_id {
+                self.list.select_next(|row| matches!(row,
+                    FeedRow::Post(idx) if posts[*idx].id == post_id));
+            }
+            self.list.set_offset(offset);
+        }
+        let marked: HashSet<usize> = self.marked(feed).into_iter().collect();
+
+        // The dates are aligned to the right of the widest one.
+        let now = Utc::now();
+        let format = state.settings.ui.dates;
+        let date = |idx: usize| {
+            date(feed.posts.as_ref()[idx].published, now, format)
+        };
+        let width = self.list.items.iter()
+            .filter_map(|row| match row {
+                FeedRow::Post(idx) => Some(date(*idx).chars().count()),
+                FeedRow::Day(_) => None,
+            })
+            .max()
+            .unwrap_or(0);
 
-        let items = feed.posts.as_ref().iter().enumerate().map(|(idx, post)| {
-            let line = Line::from(vec![
-                Span::raw(format!("{:>5}", idx.to_string())),
-                Span::raw(post.published
-                    .format("  ┊  %Y-%m-%d  │  ").to_string()),
-                Span::raw(post.title.as_ref()),
-            ]);
+        // The author column is as wide as the longest name, if any post has
+        // an author.
+        let author = |idx: usize| -> String {
+            feed.posts.as_ref()[idx].authors.first()
+                .map(|author| text::truncate(author, MAX_AUTHOR).into())
+                .unwrap_or_default()
+        };
+        let author_width = self.list.items.iter()
+            .filter_map(|row| match row {
+                FeedRow::Post(idx) => Some(text::width(&author(*idx))),
+                FeedRow::Day(_) => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        // Titles too long for the rows are cut, or wrapped if wanted and
+        // there's no summary under them.
+        let columns = crate::tui::row_width(area, &state.theme);
+        let wrap = state.settings.ui.wrap_titles && !self.detailed;
+        let lines = if wrap { 2 } else { 1 };
+
+        // Posts are numbered the way `:open` and `gg` count them.
+        let today = Local::now().date_naive();
+        let mut number = 0;
+        let items = self.list.items.iter().map(|row| {
+            let idx = match row {
+                FeedRow::Day(day) => {
+                    return ListItem::new(Line::styled(
+                        format!("──┤ {} ├──", day_label(*day, today)),
+                        state.theme.section,
+                    ));
+                },
+                FeedRow::Post(idx) => *idx,
+            };
 
-            let line = if !post.read {
-                line.style(Style::default().add_modifier(Modifier::BOLD))
+            let post = &feed.posts.as_ref()[idx];
+            let star = Span::raw(if post.starred { "★ " } else { "" });
+            let mark = Span::raw(if marked.contains(&idx) { "✓ " } else { "" });
+            let new = if self.fresh.contains(&post.id) {
+                Span::styled("NEW ", state.theme.section)
+            } else if post.edited {
+                Span::styled("EDITED ", state.theme.dim)
             } else {
-                line
+                Span::raw("")
             };
+            let mut prefix = if state.theme.focus {
+                vec![mark, new, star]
+            } else {
+                let author = if author_width > 0 {
+                    format!("{}  │  ", text::pad(&author(idx), author_width))
+                } else {
+                    String::new()
+                };
+                vec![
+                    Span::raw(format!("{:>5}", number.to_string())),
+                    Span::raw(format!("  ┊  {:>width$}  │  ", date(idx))),
+                    Span::raw(author),
+                    mark,
+                    new,
+                    star,
+                ]
+            };
+            number += 1;
 
-            ListItem::new(line)
+            // The wrapped lines of the title line up under its start.
+            let indent = prefix.iter().map(Span::width).sum::<usize>();
+            let room = columns.saturating_sub(indent);
+            let mut title = text::wrap(&post.title, room, lines);
+            let last = title.pop().unwrap_or_default();
+
+            // The categories fill what's left of the last line.
+            let focus = state.theme.focus;
+            let categories = if post.categories.is_empty() || focus {
+                String::new()
+            } else {
+                let categories =
+                    format!("  [{}]", post.categories.join(", "));
+                let room = room.saturating_sub(text::width(&last));
+                text::truncate(&categories, room).into()
+            };
+
+            let mut rows = Vec::new();
+            for line in title {
+                prefix.push(Span::raw(line));
+                rows.push(Line::from(std::mem::take(&mut prefix)));
+                prefix.push(Span::raw(" ".repeat(indent)));
+            }
+            prefix.push(Span::raw(last));
+            prefix.push(Span::styled(categories, state.theme.section));
+            rows.push(Line::from(prefix));
+
+            // Nothing had been read as of then.
+            let mut rows: Vec<Line> = if !post.read || self.as_of.is_some() {
+                rows.into_iter()
+                    .map(|row| row.style(state.theme.unread))
+                    .collect()
+            } else {
+                rows
+            };
+
+            if self.detailed {
+                let summary = post.summary.as_deref().unwrap_or_default();
+                rows.push(Line::styled(format!("{}{}", " ".repeat(indent),
+                    text::truncate(summary, room)), state.theme.dim));
+            }
+
+            ListItem::new(rows)
         });
 
-        let section = state.get_section(self.feed_id.section_idx).unwrap();
-        let title = format!(" {} | {} ", section.title, feed.title);
-        let list = crate::tui::build_list(&title, items);
+        let section = state.get_section(self.feed_id.section_idx)
+            .map_or("", |section| &section.title);
+        let mut title = format!(" {} | {} ", section, feed.title);
+        if let Some((day, _)) = self.as_of {
+            title += &format!("| as of {} ", day.format("%Y-%m-%d"));
+        }
+        match feed.view.sort {
+            SortOrder::Newest => {},
+            SortOrder::Oldest => title += "| oldest first ",
+            SortOrder::Title => title += "| by title ",
+        }
+        if feed.view.unread_only {
+            title += "| unread ";
+        }
+        if let Some(author) = &self.author {
+            title += &format!("| by {} ", author);
+        }
+        if self.hidden > 0 {
+            title += &format!("| {} hidden ", self.hidden);
+        }
+        if !marked.is_empty() {
+            title += &format!("| {} selected ", marked.len());
+        }
+        let list = crate::tui::build_list(&title, items, &state.theme);
 
-        f.render_stateful_widget(list, f.area(), &mut self.list.state);
+        self.list.render(f, area, list, &state.theme);
     }
 
     fn list(&mut self) -> &mut dyn NavigableList {
@@ -69,31 +433,224 @@ impl Page for FeedPage {
     }
 
     fn on_key(&mut self, key: KeyCode, state: &FeedState) -> PageAction {
-        let Some(&selected) = self.list.selected_item() else {
+        let Some(feed) = state.get_feed(&self.feed_id) else {
+            return PageAction::None;
+        };
+
+        // The view can be changed even if it hides all of the posts.
+        let view = feed.view;
+        let view = match key {
+            // Cycle the sort order.
+            KeyCode::Char('O') => {
+                Some(FeedView { sort: view.sort.next(), ..view })
+            },
+
+            // Toggle listing only the unread posts.
+            KeyCode::Char('u') => {
+                Some(FeedView { unread_only: !view.unread_only, ..view })
+            },
+
+            // Toggle grouping the posts by day.
+            KeyCode::Char('d') => {
+                Some(FeedView { grouping: view.grouping.next(), ..view })
+            },
+            _ => None,
+        };
+        if let Some(view) = view {
+            return PageAction::SetFeedView(self.feed_id.clone(), view);
+        }
+
+        // Toggle showing the summaries of the posts.
+        if key == KeyCode::Char('s') {
+            self.detailed = !self.detailed;
+            return PageAction::None;
+        }
+
+        // List all of the posts again after listing those by an author.
+        if key == KeyCode::Char('A') && self.author.is_some() {
+            self.author = None;
+            self.built = None;
+            return PageAction::None;
+        }
+
+        let Some(&FeedRow::Post(selected)) = self.list.selected_item() else {
             return PageAction::None;
         };
+        let post = &feed.posts.as_ref()[selected];
 
         match key {
-            // Toggle the read status on the post.
-            KeyCode::Char('r') => {
-                let feed = state.get_feed(&self.feed_id).unwrap();
-                let post = &feed.posts.as_ref()[selected];
-                let post_id = post.id.clone();
+            // Select the post or unselect it, moving on to the next one.
+            KeyCode::Char(' ') => {
+                if !self.selection.remove(&post.id) {
+                    self.selection.insert(post.id.clone());
+                }
+                self.list.down(1);
+                return PageAction::None;
+            },
+
+            // Jump to the next unread post.
+            KeyCode::Char('n') => {
+                let as_of = self.as_of.is_some();
+                self.list.select_next(|row| matches!(row, FeedRow::Post(idx)
+                    if as_of || !feed.posts.as_ref()[*idx].read));
+                return PageAction::None;
+            },
+
+            // List only the posts by the author of the post.
+            KeyCode::Char('A') => {
+                self.author = post.authors.first().cloned();
+                self.built = None;
+                return PageAction::None;
+            },
+
+            // Start selecting a range of posts, or add the range to the
+            // selection.
+            KeyCode::Char('V') => {
+                match self.range_start {
+                    Some(_) => {
+                        let ids: Vec<_> = self.marked(feed).into_iter()
+                            .map(|idx| feed.posts.as_ref()[idx].id.clone())
+                            .collect();
+                        self.selection.extend(ids);
+                        self.range_start = None;
+                    },
+                    None => self.range_start = Some(self.list.selected),
+                }
+                return PageAction::None;
+            },
+            _ => {},
+        }
+
+        // Mark the old posts as read, unless they're all unread as of a day.
+        if key == KeyCode::Char('R') && self.as_of.is_none() {
+            return PageAction::MarkFeedReadOlder(self.feed_id.clone());
+        }
+
+        // With posts selected, the keys act on all of them.
+        let marked = self.marked(feed);
+        if !marked.is_empty() {
+            return self.batch(key, feed, &marked);
+        }
+
+        // Move on to the next unread post once this one is read.
+        let advance = state.settings.auto_advance && self.as_of.is_none()
+            && !state.settings.storage.read_only
+            && !post.read
+            && match key {
+                KeyCode::Char('r') => true,
+                KeyCode::Char('l') | KeyCode::Enter => {
+                    state.settings.mark_read == MarkRead::Open
+                },
+                KeyCode::Char('o') => {
+                    state.settings.mark_read != MarkRead::Never
+                        && post.primary_url().is_some()
+                },
+                _ => false,
+            };
+        let post_id = post.id.clone();
+        if advance {
+            self.list.select_next(|row| matches!(row, FeedRow::Post(idx)
+                if !feed.posts.as_ref()[*idx].read
+                    && feed.posts.as_ref()[*idx].id != post_id));
+        }
+
+        match key {
+            // Toggle the read status, unless it is ignored as of a day.
+            KeyCode::Char('r') if self.as_of.is_none() => {
                 PageAction::TogglePostRead(self.feed_id.clone(), post_id)
             }
 
-            // Check the post page of the selected post.
-            KeyCode::Enter | KeyCode::Char('l') => {
-                let feed = state.get_feed(&self.feed_id).unwrap();
-                let post = &feed.posts.as_ref()[selected];
+            // Star or unstar the selected post.
+            KeyCode::Char('*') => {
+                PageAction::TogglePostStarred(self.feed_id.clone(), post_id)
+            }
 
+            // Check the post page of the selected post, as of the day too.
+            KeyCode::Enter | KeyCode::Char('l') => {
                 let feed_id = self.feed_id.clone();
-                let post_id = post.id.clone();
 
-                let page = Box::new(PostPage::new(feed_id, post_id));
-                PageAction::NewPage(page)
+                let page = match self.as_of {
+                    Some(_) => PostPage::historical(feed_id, post_id),
+                    None => PostPage::new(feed_id, post_id),
+                };
+                PageAction::NewPage(Box::new(page))
+            }
+
+            // Open the page of the selected post in the browser, which
+            // reads it, unless nothing is read as of a day.
+            KeyCode::Char('o') => {
+                let Some(url) = post.primary_url() else {
+                    return PageAction::None;
+                };
+                let action = PageAction::OpenInBrowser(url.clone());
+                if self.as_of.is_some() {
+                    return action;
+                }
+                let feed_id = self.feed_id.clone();
+                PageAction::OpenLink(feed_id, post_id, url.clone(),
+                    Box::new(action))
             }
             _ => PageAction::None,
         }
     }
+
+    fn session(&self, state: &FeedState) -> Option<PageSession> {
+        // The feed as of a day is opened as it is now.
+        if self.as_of.is_some() {
+            return None;
+        }
+
+        let feed = state.get_feed(&self.feed_id)?;
+        let post = match self.list.selected_item() {
+            Some(FeedRow::Post(idx)) => {
+                Some(feed.posts.as_ref()[*idx].id.clone())
+            },
+            _ => None,
+        };
+        Some(PageSession::Feed {
+            feed: feed.url.to_string(),
+            post,
+            offset: self.list.offset(),
+        })
+    }
+
+    fn on_new(&mut self, state: &mut FeedState) -> PageAction {
+        // The posts stay new while the page is open, but not the next time.
+        if self.as_of.is_none() {
+            self.fresh = state.take_fresh_posts(&self.feed_id);
+        }
+        PageAction::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_relative_dates() {
+        let now = Utc::now();
+        let ago = |delta| date(now - delta, now, DateFormat::Relative);
+        assert_eq!(ago(TimeDelta::seconds(-30)), "now");
+        assert_eq!(ago(TimeDelta::minutes(5)), "5m");
+        assert_eq!(ago(TimeDelta::minutes(200)), "3h");
+        assert_eq!(ago(TimeDelta::days(2)), "2d");
+
+        let old = now - TimeDelta::days(800);
+        let local = old.with_timezone(&Local);
+        assert_eq!(date(old, now, DateFormat::Relative),
+            local.format("%b %Y").to_string());
+        assert_eq!(date(old, now, DateFormat::Iso),
+            local.format("%Y-%m-%d").to_string());
+    }
+
+    #[test]
+    fn labels_days() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+        assert_eq!(day_label(day(3), today), "Today");
+        assert_eq!(day_label(day(2), today), "Yesterday");
+        assert_eq!(day_label(day(1), today), "2024-05-01");
+        assert_eq!(day_label(day(4), today), "2024-05-04");
+    }
 }
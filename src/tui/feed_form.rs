@@ -0,0 +1,268 @@
+use std::sync::Arc;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+use crossterm::event::KeyCode;
+use crate::tui::{Page, NavigableList, ListPage, PageAction, text};
+use crate::app::FeedState;
+use crate::config::FeedId;
+
+/// The fields of the feed form, in the order they're shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Title,
+    Url,
+    Section,
+}
+
+impl Field {
+    /// Get the field below this one, or the first one below the last one.
+    fn next(self) -> Self {
+        match self {
+            Self::Title => Self::Url,
+            Self::Url => Self::Section,
+            Self::Section => Self::Title,
+        }
+    }
+
+    /// Get the field above this one, or the last one above the first one.
+    fn prev(self) -> Self {
+        self.next().next()
+    }
+}
+
+/// The page with the form that adds a feed to the feed file or changes one.
+pub struct FeedFormPage {
+    /// The feed being changed, or `None` when adding a new one.
+    feed_id: Option<FeedId>,
+
+    /// The title typed in.
+    title: String,
+
+    /// The URL typed in.
+    url: String,
+
+    /// The titles of the sections the feed can be put into.
+    sections: Vec<Arc<str>>,
+
+    /// Index into `sections`.
+    section: usize,
+
+    /// The field the keys are typed into.
+    field: Field,
+
+    /// Why the form couldn't be saved.
+    error: Option<String>,
+
+    /// There's nothing to select on this page.
+    list: ListPage<String>,
+}
+
+impl FeedFormPage {
+    /// Create an empty form adding a feed to the section at `section_idx`.
+    pub fn add(section_idx: usize, state: &FeedState) -> Self {
+        let mut sections: Vec<Arc<str>> = (0..)
+            .map_while(|idx| state.get_section(idx))
+            .map(|section| section.title.clone())
+            .collect();
+
+        // The feed file is created with a section.
+        if sections.is_empty() {
+            sections.push("Feeds".into());
+        }
+
+        Self {
+            feed_id: None,
+            title: String::new(),
+            url: String::new(),
+            section: section_idx.min(sections.len() - 1),
+            sections,
+            field: Field::Title,
+            error: None,
+            list: ListPage::new(Vec::new()),
+        }
+    }
+
+    /// Create a form changing the feed, filled in with what it is now.
+    ///
+    /// Returns `None` if the feed isn't there anymore.
+    pub fn edit(feed_id: FeedId, state: &FeedState) -> Option<Self> {
+        let feed = state.get_feed(&feed_id)?;
+        Some(Self {
+            title: feed.title.to_string(),
+            url: feed.url.to_string(),
+            feed_id: Some(feed_id.clone()),
+            ..Self::add(feed_id.section_idx, state)
+        })
+    }
+
+    /// Get the text of the `field`, unless it's chosen instead of typed.
+    fn text(&mut self, field: Field) -> Option<&mut String> {
+        match field {
+            Field::Title => Some(&mut self.title),
+            Field::Url => Some(&mut self.url),
+            Field::Section => None,
+        }
+    }
+
+    /// Check the form, returning the action saving it.
+    fn save(&self) -> Result<PageAction, String> {
+        let title = self.title.trim();
+        if title.is_empty() || title.contains('|') {
+            return Err("The title can't be empty or contain a |".into());
+        }
+
+        let url = url::Url::parse(self.url.trim())
+            .map_err(|err| format!("Invalid URL: {}", err))?;
+        let section = self.sections[self.section].clone();
+        Ok(PageAction::SaveFeed(
+            self.feed_id.clone(), section, title.into(), url))
+    }
+}
+
+impl Page for FeedFormPage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        let title = match &self.feed_id {
+            Some(_) => " Edit the feed ",
+            None => " Add a feed ",
+        };
+        let block = if state.theme.focus {
+            Block::default()
+        } else {
+            Block::default().borders(Borders::ALL).title(title)
+        };
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let section = format!("◂ {} ▸", self.sections[self.section]);
+        let fields = [
+            (Field::Title, "Title    ", self.title.as_str()),
+            (Field::Url, "URL      ", self.url.as_str()),
+            (Field::Section, "Section  ", section.as_str()),
+        ];
+
+        let mut lines: Vec<Line> = fields.iter()
+            .map(|&(field, label, value)| {
+                let style = if field == self.field {
+                    state.theme.highlight
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(label, state.theme.section),
+                    Span::styled(value, style),
+                ])
+            })
+            .collect();
+        lines.push(Line::raw(""));
+        lines.push(Line::raw("Tab moves to the next field, ←/→ choose the \
+            section, Enter saves and Esc cancels"));
+        if let Some(error) = &self.error {
+            lines.push(Line::styled(error.as_str(), state.theme.error));
+        }
+
+        // Put the cursor at the end of the text being typed in.
+        let row = fields.iter().position(|&(field, ..)| field == self.field)
+            .unwrap_or(0) as u16;
+        let column = text::width(fields[row as usize].1)
+            + text::width(fields[row as usize].2);
+        if self.field != Field::Section && row < inner.height {
+            let x = (inner.x + column as u16).min(inner.right() - 1);
+            f.set_cursor_position((x, inner.y + row));
+        }
+
+        f.render_widget(Paragraph::new(lines), inner);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+
+    fn is_editing(&self) -> bool {
+        true
+    }
+
+    fn on_key(&mut self, key: KeyCode, _state: &FeedState) -> PageAction {
+        self.error = None;
+        let sections = self.sections.len();
+
+        match key {
+            KeyCode::Tab | KeyCode::Down => self.field = self.field.next(),
+            KeyCode::BackTab | KeyCode::Up => self.field = self.field.prev(),
+
+            // Choose the section.
+            KeyCode::Left if self.field == Field::Section => {
+                self.section = (self.section + sections - 1) % sections;
+            },
+            KeyCode::Right if self.field == Field::Section => {
+                self.section = (self.section + 1) % sections;
+            },
+
+            KeyCode::Char(c) => {
+                if let Some(text) = self.text(self.field) {
+                    text.push(c);
+                }
+            },
+            KeyCode::Backspace => {
+                if let Some(typed) = self.text(self.field) {
+                    text::pop_grapheme(typed);
+                }
+            },
+
+            KeyCode::Enter => match self.save() {
+                Ok(action) => return action,
+                Err(err) => self.error = Some(err),
+            },
+            _ => {},
+        }
+
+        PageAction::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FeedConfig;
+    use crate::settings::Settings;
+
+    /// Type the `keys` into the `page`, returning the last action.
+    fn type_in(page: &mut FeedFormPage, keys: &str, state: &FeedState)
+        -> PageAction
+    {
+        let mut action = PageAction::None;
+        for c in keys.chars() {
+            let key = match c {
+                '\t' => KeyCode::Tab,
+                '\n' => KeyCode::Enter,
+                '\x08' => KeyCode::Backspace,
+                c => KeyCode::Char(c),
+            };
+            action = page.on_key(key, state);
+        }
+        action
+    }
+
+    #[test]
+    fn saves_typed_in_feeds() {
+        let url = url::Url::parse("https://a.example/feed.xml").unwrap();
+        let config = FeedConfig::single_feed("A", url, Vec::new().into());
+        let state = FeedState::new(config, Settings::default());
+        let mut page = FeedFormPage::add(0, &state);
+
+        let action = type_in(&mut page, "Blog\tnot a url\n", &state);
+        assert!(matches!(action, PageAction::None));
+        assert!(page.error.is_some());
+
+        let erase = "\x08".repeat("not a url".len());
+        let keys = erase + "https://b.example/\n";
+        let PageAction::SaveFeed(None, section, title, url) =
+            type_in(&mut page, &keys, &state)
+        else {
+            panic!("The form wasn't saved");
+        };
+        assert_eq!((section.as_ref(), title.as_ref(), url.as_str()),
+            ("A", "Blog", "https://b.example/"));
+    }
+}
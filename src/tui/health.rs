@@ -0,0 +1,161 @@
+use ratatui::{
+    prelude::*,
+    widgets::ListItem,
+};
+use crossterm::event::KeyCode;
+use crate::tui::{Page, NavigableList, ListPage, PageAction, feed::FeedPage};
+use crate::app::FeedState;
+use crate::config::{FeedHealth, FeedId};
+
+/// Rows in the feed health page.
+struct HealthRow {
+    /// The feed whose downloads are shown.
+    feed_id: FeedId,
+}
+
+/// All feeds are selectable.
+impl crate::tui::Selectable for HealthRow {
+    fn selectable(&self) -> bool {
+        true
+    }
+}
+
+/// The page that shows how the last download of every feed went, so that the
+/// dead ones can be spotted and fixed.
+pub struct HealthPage {
+    /// List of all feeds, the failing ones first.
+    list: ListPage<HealthRow>,
+}
+
+impl HealthPage {
+    /// Create a new feed health page.
+    pub fn new(state: &FeedState) -> Self {
+        let mut rows: Vec<HealthRow> = state.feed_ids()
+            .map(|feed_id| HealthRow { feed_id })
+            .collect();
+
+        // The feeds that have failed the longest come first, the rest stay
+        // in the order of the feed file.
+        rows.sort_by_key(|row| {
+            let health = state.get_feed(&row.feed_id)
+                .and_then(|feed| feed.health.as_ref());
+            std::cmp::Reverse(health.map_or(0, |health| health.failures))
+        });
+
+        Self { list: ListPage::new(rows) }
+    }
+}
+
+/// Describe the `health` of a feed in fixed-width columns.
+fn columns(health: Option<&FeedHealth>) -> String {
+    let Some(health) = health else {
+        return format!("{:>3}  {:>3}  {:>6}  {:<16}", "", "", "", "never");
+    };
+
+    let status = health.status.map_or("---".to_string(), |s| s.to_string());
+    let duration = format!("{:.1}s", health.duration_ms as f64 / 1000.0);
+    let fetched = health.fetched
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M");
+    let failures = match health.failures {
+        0 => String::new(),
+        n => format!("{}✗", n),
+    };
+    format!("{:>3}  {}  {:>6}  {}", failures, status, duration, fetched)
+}
+
+impl Page for HealthPage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        let items = self.list.items.iter().map(|row| {
+            let Some(feed) = state.get_feed(&row.feed_id) else {
+                return ListItem::new("");
+            };
+            let health = feed.health.as_ref();
+
+            let mut spans = Vec::new();
+            if !state.theme.focus {
+                spans.push(Span::raw(columns(health)));
+                spans.push(Span::raw("  │  "));
+            }
+            spans.push(Span::raw(feed.title.as_ref()));
+
+            // Say why the feed is failing.
+            let error = health.and_then(|health| health.error.as_deref());
+            if let Some(error) = error {
+                spans.push(Span::raw(format!(" — {}", error)));
+            }
+
+            let line = Line::from(spans);
+            let line = if error.is_some() {
+                line.style(state.theme.error)
+            } else {
+                line
+            };
+
+            ListItem::new(line)
+        });
+
+        let failing = state.feed_ids()
+            .filter_map(|feed_id| state.get_feed(&feed_id))
+            .filter(|feed| feed.health.as_ref()
+                .is_some_and(FeedHealth::is_failing))
+            .count();
+        let title = format!(" Feed health | {} failing ", failing);
+        let list = crate::tui::build_list(&title, items, &state.theme);
+
+        self.list.render(f, area, list, &state.theme);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+
+    fn on_key(&mut self, key: KeyCode, state: &FeedState) -> PageAction {
+        let Some(row) = self.list.selected_item() else {
+            return PageAction::None;
+        };
+        let feed_id = row.feed_id.clone();
+
+        match key {
+            // Try downloading the feed again.
+            KeyCode::Char('d') => PageAction::DownloadFeed(feed_id),
+
+            // Copy the URL of the feed to fix it in the feed file.
+            KeyCode::Char('c') => match state.get_feed(&feed_id) {
+                Some(feed) => {
+                    PageAction::CopyToClipboard(feed.url.as_str().into())
+                },
+                None => PageAction::None,
+            },
+
+            // Check the posts listing for the selected feed.
+            KeyCode::Enter | KeyCode::Char('l') => {
+                let page = Box::new(FeedPage::new(feed_id));
+                PageAction::NewPage(page)
+            },
+
+            _ => PageAction::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_health_columns() {
+        let health = FeedHealth {
+            fetched: chrono::Utc::now(),
+            duration_ms: 1300,
+            status: Some(404),
+            error: Some("Not Found".into()),
+            failures: 12,
+            succeeded: None,
+        };
+        let failing = columns(Some(&health));
+        let never = columns(None);
+        assert_eq!(failing.chars().count(), never.chars().count());
+        assert!(failing.starts_with("12✗  404    1.3s"));
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+use ratatui::{
+    prelude::*,
+    widgets::ListItem,
+};
+use crossterm::event::KeyCode;
+use chrono::{DateTime, Local, Utc};
+use crate::tui::{
+    Page, NavigableList, ListPage, PageAction, post::PostPage, text,
+};
+use crate::app::FeedState;
+use crate::config::{FeedId, PostId};
+
+/// The widest the column with the titles of the feeds gets.
+const MAX_FEED_WIDTH: usize = 20;
+
+/// A post that has been read.
+#[derive(Debug, PartialEq)]
+struct Entry {
+    /// The feed of the post.
+    feed_id: FeedId,
+
+    /// The post that has been read.
+    post_id: PostId,
+
+    /// When it was last marked read.
+    at: DateTime<Utc>,
+}
+
+/// All posts are selectable.
+impl crate::tui::Selectable for Entry {
+    fn selectable(&self) -> bool {
+        true
+    }
+}
+
+/// The page that lists the posts that have been read, the latest first, so
+/// that a post marked read by accident can be found again.
+pub struct HistoryPage {
+    /// List of the read posts.
+    list: ListPage<Entry>,
+
+    /// The number of reads of all feeds when the list was last built.
+    built: Option<usize>,
+
+    /// The width of the column with the titles of the feeds.
+    feed_width: usize,
+}
+
+impl Default for HistoryPage {
+    /// Create a new history page.
+    fn default() -> Self {
+        Self { list: ListPage::new(Vec::new()), built: None, feed_width: 0 }
+    }
+}
+
+/// List the posts of all feeds that have been marked read, the latest
+/// first. A post read several times is listed once, when it was last read,
+/// and the posts that are no longer stored are left out.
+fn history(state: &FeedState) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = state.feed_ids()
+        .filter_map(|feed_id| state.get_feed(&feed_id)
+            .map(|feed| (feed_id, feed)))
+        .flat_map(|(feed_id, feed)| feed.reads.iter()
+            .filter(|read| feed.posts.get_by_id(&read.post_id).is_some())
+            .map(move |read| Entry {
+                feed_id: feed_id.clone(),
+                post_id: read.post_id.clone(),
+                at: read.at,
+            }))
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.at));
+
+    let mut seen = HashSet::new();
+    entries.retain(|entry| {
+        seen.insert((entry.feed_id.clone(), entry.post_id.clone()))
+    });
+    entries
+}
+
+impl Page for HistoryPage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        // Rebuild the list if posts have been read since.
+        let reads = state.feed_ids()
+            .filter_map(|feed_id| state.get_feed(&feed_id))
+            .map(|feed| feed.reads.len())
+            .sum();
+        if self.built != Some(reads) {
+            let entries = history(state);
+            self.feed_width = entries.iter()
+                .filter_map(|entry| state.get_feed(&entry.feed_id))
+                .map(|feed| text::width(&feed.title))
+                .max()
+                .unwrap_or(0)
+                .min(MAX_FEED_WIDTH);
+            self.list = ListPage::new(entries);
+            self.built = Some(reads);
+        }
+
+        let columns = crate::tui::row_width(area, &state.theme);
+        let items = self.list.items.iter().filter_map(|entry| {
+            let feed = state.get_feed(&entry.feed_id)?;
+            let post = feed.posts.get_by_id(&entry.post_id)?;
+            let mut line = if state.theme.focus {
+                Line::default()
+            } else {
+                Line::from(vec![
+                    Span::raw(entry.at.with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M  │  ").to_string()),
+                    Span::styled(text::pad(&feed.title, self.feed_width),
+                        state.theme.section),
+                    Span::raw("  │  "),
+                ])
+            };
+            let room = columns.saturating_sub(line.width());
+            line.push_span(text::truncate(&post.title, room));
+
+            // The posts marked unread again stand out.
+            let line = if post.read {
+                line
+            } else {
+                line.style(state.theme.unread)
+            };
+            Some(ListItem::new(line))
+        });
+
+        let title = format!(" History | {} posts ", self.list.items.len());
+        let list = crate::tui::build_list(&title, items, &state.theme);
+        self.list.render(f, area, list, &state.theme);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+
+    fn on_key(&mut self, key: KeyCode, _state: &FeedState) -> PageAction {
+        let Some(entry) = self.list.selected_item() else {
+            return PageAction::None;
+        };
+        let feed_id = entry.feed_id.clone();
+        let post_id = entry.post_id.clone();
+
+        match key {
+            // Mark the post unread again, or read.
+            KeyCode::Char('r') => PageAction::TogglePostRead(feed_id, post_id),
+
+            // Star or unstar the post.
+            KeyCode::Char('*') => {
+                PageAction::TogglePostStarred(feed_id, post_id)
+            },
+
+            // Check the post page of the selected post.
+            KeyCode::Enter | KeyCode::Char('l') => {
+                let page = Box::new(PostPage::new(feed_id, post_id));
+                PageAction::NewPage(page)
+            },
+
+            _ => PageAction::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use url::Url;
+    use crate::config::{FeedConfig, Post, Posts, test_post};
+    use crate::settings::Settings;
+
+    #[test]
+    fn lists_the_latest_reads_first() {
+        let at = |secs| DateTime::from_timestamp(secs, 0).unwrap();
+        let id = |id: &str| PostId(Arc::from(id));
+        let post = |name: &str| Post { read: true, ..test_post(name, "", 0) };
+        let url = Url::parse("https://blog.example/feed.xml").unwrap();
+        let posts = Posts::from(vec![post("a"), post("b")]);
+        let config = FeedConfig::single_feed("Blog", url, posts);
+        let mut state = FeedState::new(config, Settings::default());
+
+        let feed_id = state.feed_ids().next().unwrap();
+        let feed = state.get_feed_mut(&feed_id).unwrap();
+        feed.log_reads(&[id("a"), id("b")], at(100));
+        feed.log_reads(&[id("a")], at(200));
+        // The pruned posts are gone from the history.
+        feed.log_reads(&[id("pruned")], at(300));
+
+        let entries: Vec<_> = history(&state).into_iter()
+            .map(|entry| (entry.post_id, entry.at))
+            .collect();
+        assert_eq!(entries, [(id("a"), at(200)), (id("b"), at(100))]);
+    }
+}
@@ -0,0 +1,265 @@
+//! Inline previews of images with the kitty graphics or sixel protocol.
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use ratatui::layout::Rect;
+use crate::settings::ImageProtocol;
+
+/// The file extensions of the links that are previewed as images.
+const EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+/// The signature at the start of every PNG file.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The size of a cell in pixels if the terminal doesn't tell.
+const CELL_PIXELS: (u32, u32) = (10, 20);
+
+/// The most base64 characters sent in one kitty escape sequence.
+const CHUNK: usize = 4096;
+
+/// Deletes all kitty images from the screen.
+pub const KITTY_DELETE: &[u8] = b"\x1b_Ga=d,d=a,q=2\x1b\\";
+
+/// A terminal graphics protocol the images can be drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+}
+
+/// Find out which protocol to draw the images with, given the `setting`.
+///
+/// With the automatic setting, the terminal is asked whether it supports
+/// sixel graphics, so it must be in raw mode. Returns `None` if the images
+/// can't or shouldn't be drawn.
+pub fn detect(setting: ImageProtocol) -> Option<Protocol> {
+    match setting {
+        ImageProtocol::Kitty => return Some(Protocol::Kitty),
+        ImageProtocol::Sixel => return Some(Protocol::Sixel),
+        ImageProtocol::None => return None,
+        ImageProtocol::Auto => {},
+    }
+
+    // The terminals speaking the kitty protocol don't advertise it in DA1.
+    let var = |name| std::env::var(name).unwrap_or_default();
+    if std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || var("TERM").contains("kitty")
+        || ["WezTerm", "ghostty"].contains(&var("TERM_PROGRAM").as_str())
+    {
+        return Some(Protocol::Kitty);
+    }
+
+    crate::tui::query_terminal(b"")
+        .filter(|response| supports_sixel(response))
+        .map(|_| Protocol::Sixel)
+}
+
+/// Check whether the answer to DA1 lists sixel graphics, attribute 4.
+fn supports_sixel(response: &str) -> bool {
+    let Some(start) = response.rfind("\x1b[?") else {
+        return false;
+    };
+    response[start + 3..].trim_end_matches('c')
+        .split(';')
+        .any(|attribute| attribute == "4")
+}
+
+/// Whether the `url` links to an image, judging by its extension.
+pub fn is_image(url: &url::Url) -> bool {
+    url.path().rsplit_once('.').is_some_and(|(_, extension)| {
+        EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+    })
+}
+
+/// Encode the image file `bytes` into the escape sequences drawing it with
+/// the `protocol`, fit into the `area` of the screen.
+///
+/// Other images than PNGs are converted with ImageMagick, as is everything
+/// drawn as sixels unless `img2sixel` is installed.
+pub fn encode(protocol: Protocol, bytes: &[u8], area: Rect)
+    -> Result<Vec<u8>, String>
+{
+    let cell = cell_pixels();
+    let (width, height) = (area.width as u32 * cell.0,
+        area.height as u32 * cell.1);
+
+    match protocol {
+        Protocol::Kitty => {
+            let png = if bytes.starts_with(PNG_SIGNATURE) {
+                bytes.to_vec()
+            } else {
+                magick(&["-", "png:-"], bytes)?
+            };
+            let size = png_size(&png).ok_or("Not a PNG image")?;
+            let (columns, rows) = fit(size, cell, area);
+            Ok(kitty(&png, columns, rows))
+        },
+        Protocol::Sixel => {
+            let size = format!("{}x{}>", width, height);
+            convert("img2sixel", &["-w", &width.to_string(), "-h",
+                &height.to_string()], bytes)
+                .or_else(|_| magick(&["-", "-resize", &size, "sixel:-"],
+                    bytes))
+        },
+    }
+}
+
+/// Get the size of a cell of the terminal in pixels.
+fn cell_pixels() -> (u32, u32) {
+    crossterm::terminal::window_size().ok()
+        .filter(|size| size.width > 0 && size.height > 0
+            && size.columns > 0 && size.rows > 0)
+        .map(|size| ((size.width / size.columns) as u32,
+            (size.height / size.rows) as u32))
+        .unwrap_or(CELL_PIXELS)
+}
+
+/// Get the width and height of the `png` in pixels from its header.
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    if !png.starts_with(PNG_SIGNATURE) || png.len() < 24 {
+        return None;
+    }
+    let read = |at: usize| u32::from_be_bytes(png[at..at + 4].try_into()
+        .unwrap());
+    Some((read(16), read(20)))
+}
+
+/// Get the most columns and rows an image of `size` pixels can cover in the
+/// `area` without being stretched, given the size of a `cell` in pixels.
+fn fit(size: (u32, u32), cell: (u32, u32), area: Rect) -> (u16, u16) {
+    let (width, height) = (size.0.max(1) as f64, size.1.max(1) as f64);
+    let available = (area.width as f64 * cell.0 as f64,
+        area.height as f64 * cell.1 as f64);
+
+    // Images are never scaled up.
+    let scale = (available.0 / width).min(available.1 / height).min(1.0);
+    let columns = (width * scale / cell.0 as f64).ceil() as u16;
+    let rows = (height * scale / cell.1 as f64).ceil() as u16;
+    (columns.clamp(1, area.width.max(1)), rows.clamp(1, area.height.max(1)))
+}
+
+/// Build the kitty escape sequences drawing the `png` over the `columns`
+/// and `rows` from the cursor, without moving it.
+fn kitty(png: &[u8], columns: u16, rows: u16) -> Vec<u8> {
+    let data = base64(png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(CHUNK).collect();
+
+    let mut out = Vec::new();
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let more = (idx + 1 < chunks.len()) as u8;
+        if idx == 0 {
+            let _ = write!(out, "\x1b_Ga=T,f=100,c={},r={},q=2,C=1,m={};",
+                columns, rows, more);
+        } else {
+            let _ = write!(out, "\x1b_Gm={};", more);
+        }
+        out.extend_from_slice(chunk);
+        out.extend_from_slice(b"\x1b\\");
+    }
+    out
+}
+
+/// Encode the `bytes` in base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate()
+            .fold(0u32, |n, (idx, &b)| n | (b as u32) << (16 - 8 * idx));
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * idx) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Run ImageMagick with the `args`, whichever of its versions is installed.
+fn magick(args: &[&str], input: &[u8]) -> Result<Vec<u8>, String> {
+    convert("magick", args, input)
+        .or_else(|_| convert("convert", args, input))
+        .map_err(|_| "Install ImageMagick to preview this image".to_string())
+}
+
+/// Run the `program` with the `args`, piping the `input` through it.
+fn convert(program: &str, args: &[&str], input: &[u8])
+    -> Result<Vec<u8>, String>
+{
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| format!("Couldn't run {}: {}", program, err))?;
+
+    // Write the input while the output is read, so that neither pipe fills.
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || {
+        let _ = stdin.write_all(&input);
+    });
+
+    let mut output = Vec::new();
+    let read = child.stdout.take().unwrap().read_to_end(&mut output);
+    let _ = writer.join();
+    let status = child.wait().map_err(|err| err.to_string())?;
+
+    match read {
+        Ok(_) if status.success() && !output.is_empty() => Ok(output),
+        Ok(_) => Err(format!("{} failed: {}", program, status)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn recognizes_images() {
+        let url = |url| url::Url::parse(url).unwrap();
+        assert!(is_image(&url("https://example.com/a/cat.PNG?size=2")));
+        assert!(is_image(&url("https://example.com/cat.jpeg")));
+        assert!(!is_image(&url("https://example.com/cat.html")));
+        assert!(!is_image(&url("https://example.com/")));
+    }
+
+    #[test]
+    fn detects_sixel_support() {
+        assert!(supports_sixel("\x1b[?62;4;6;22c"));
+        assert!(!supports_sixel("\x1b[?62;22c"));
+        assert!(!supports_sixel(""));
+    }
+
+    #[test]
+    fn fits_images_without_stretching() {
+        let area = Rect::new(0, 0, 40, 10);
+        assert_eq!(fit((800, 100), (10, 20), area), (40, 3));
+        assert_eq!(fit((100, 100), (10, 20), area), (10, 5));
+        assert_eq!(fit((100, 1000), (10, 20), area), (2, 10));
+    }
+
+    #[test]
+    fn chunks_kitty_images() {
+        let png = vec![0; CHUNK];
+        let out = String::from_utf8(kitty(&png, 4, 2)).unwrap();
+        assert!(out.starts_with("\x1b_Ga=T,f=100,c=4,r=2,q=2,C=1,m=1;AAAA"));
+        assert_eq!(out.matches("\x1b_G").count(), 2);
+        assert!(out.contains("\x1b_Gm=0;"));
+        assert!(out.ends_with("\x1b\\"));
+    }
+}
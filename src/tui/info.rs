@@ -0,0 +1,153 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+use crate::tui::{Page, NavigableList, ListPage};
+use crate::app::FeedState;
+use crate::config::{Feed, FeedId};
+
+/// The width of the names of the fields, so that the values line up.
+const NAME_WIDTH: usize = 12;
+
+/// The page that shows what a feed says about itself and how its downloads
+/// have gone.
+pub struct FeedInfoPage {
+    /// The feed whose information is shown.
+    feed_id: FeedId,
+
+    /// There's nothing to select on this page.
+    list: ListPage<String>,
+}
+
+impl FeedInfoPage {
+    /// Create a new page with the information of the feed.
+    pub fn new(feed_id: FeedId) -> Self {
+        Self { feed_id, list: ListPage::new(Vec::new()) }
+    }
+}
+
+/// Describe the `feed` as pairs of names and values. Fields the feed doesn't
+/// have are left out.
+fn fields(feed: &Feed) -> Vec<(&'static str, String)> {
+    let local = |date: chrono::DateTime<chrono::Utc>| date
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string();
+
+    let mut fields = vec![("URL", feed.url.to_string())];
+    if let Some(moved) = &feed.moved {
+        fields.push(("Moved to", moved.to_string()));
+    }
+    if let Some(meta) = &feed.meta {
+        if let Some(description) = &meta.description {
+            fields.push(("Description", description.clone()));
+        }
+        if let Some(site) = &meta.site {
+            fields.push(("Site", site.to_string()));
+        }
+        if let Some(updated) = meta.updated {
+            fields.push(("Updated", local(updated)));
+        }
+        if let Some(icon) = &meta.icon {
+            fields.push(("Icon", icon.to_string()));
+        }
+    }
+
+    let posts = feed.posts.as_ref();
+    let starred = posts.iter().filter(|post| post.starred).count();
+    fields.push(("Posts", format!("{} ({} unread, {} starred)",
+        posts.len(), feed.posts.unread(), starred)));
+
+    let Some(health) = &feed.health else {
+        fields.push(("Fetched", "never".into()));
+        return fields;
+    };
+    fields.push(("Fetched", format!("{} in {:.1}s", local(health.fetched),
+        health.duration_ms as f64 / 1000.0)));
+    if let Some(status) = health.status {
+        fields.push(("Status", status.to_string()));
+    }
+    if health.failures > 0 {
+        fields.push(("Failures", format!("{} in a row", health.failures)));
+    }
+    if let Some(error) = &health.error {
+        fields.push(("Error", error.clone()));
+    }
+
+    fields
+}
+
+impl Page for FeedInfoPage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        let Some(feed) = state.get_feed(&self.feed_id) else {
+            return;
+        };
+
+        let title = format!(" {} | info ", feed.title);
+        let block = if state.theme.focus {
+            Block::default()
+        } else {
+            Block::default().borders(Borders::ALL).title(title)
+        };
+
+        let lines: Vec<Line> = fields(feed).into_iter()
+            .map(|(name, value)| {
+                let style = if name == "Error" {
+                    state.theme.error
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(format!("{:<NAME_WIDTH$}", name),
+                        state.theme.section),
+                    Span::styled(value, style),
+                ])
+            })
+            .collect();
+
+        let text = Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false });
+        f.render_widget(text, area);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use url::Url;
+    use crate::config::{FeedHealth, FeedMeta};
+
+    #[test]
+    fn describes_feeds() {
+        let url = Url::parse("https://blog.example/feed.xml").unwrap();
+        let mut feed = Feed::new("Blog".into(), url);
+        let names = |feed: &Feed| fields(feed).into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names(&feed), ["URL", "Posts", "Fetched"]);
+
+        feed.meta = Some(Arc::new(FeedMeta {
+            description: Some("Posts about things".into()),
+            ..Default::default()
+        }));
+        feed.health = Some(FeedHealth {
+            fetched: chrono::Utc::now(),
+            duration_ms: 1300,
+            status: Some(404),
+            error: Some("Not Found".into()),
+            failures: 2,
+            succeeded: None,
+        });
+        assert_eq!(names(&feed), [
+            "URL", "Description", "Posts", "Fetched", "Status", "Failures",
+            "Error",
+        ]);
+        assert_eq!(fields(&feed)[2].1, "0 (0 unread, 0 starred)");
+    }
+}
@@ -5,21 +5,26 @@ use ratatui::{
 };
 use crossterm::event::KeyCode;
 use crate::tui::{
-    PageAction, Page, NavigableList, ListPage, feed::FeedPage, Selectable};
+    PageAction, Page, NavigableList, ListPage, feed::FeedPage,
+    feed_form::FeedFormPage, info::FeedInfoPage,
+    timeline::{Timeline, TimelinePage}, Selectable};
 use crate::config::{FeedConfig, FeedId};
+use crate::database::PageSession;
 use crate::app::FeedState;
 
 /// Rows in the main page.
 enum MainRow {
+    /// The posts of all feeds in one timeline.
+    Timeline(Timeline),
     SectionHeader(Arc<str>),
     Feed(FeedId),
     Spacer,
 }
 
-/// Only feeds are selectable.
+/// Only feeds and the timeline are selectable.
 impl Selectable for MainRow {
     fn selectable(&self) -> bool {
-        matches!(self, MainRow::Feed { .. })
+        matches!(self, MainRow::Feed { .. } | MainRow::Timeline(_))
     }
 }
 
@@ -31,8 +36,13 @@ pub struct MainPage {
 impl MainPage {
     /// Create a new main page.
     pub fn new(config: &FeedConfig) -> Self {
-        // Build the rows for the main page.
+        // Build the rows for the main page. The timelines come first, once
+        // there are feeds to merge.
         let mut rows = Vec::new();
+        if config.sections.iter().any(|section| !section.feeds.is_empty()) {
+            rows.extend(Timeline::ALL.map(MainRow::Timeline));
+            rows.push(MainRow::Spacer);
+        }
 
         // Go through each section.
         for (section_idx, section) in config.sections.iter().enumerate() {
@@ -48,47 +58,96 @@ impl MainPage {
             rows.push(MainRow::Spacer);
         }
 
-        Self {
-            list: ListPage::new(rows),
-        }
+        // The first feed is selected to begin with.
+        let mut list = ListPage::new(rows);
+        list.select_next(|row| matches!(row, MainRow::Feed(_)));
+        Self { list }
+    }
+
+    /// Select the row of the feed.
+    pub fn select_feed(&mut self, feed_id: &FeedId) {
+        self.list.select_next(|row| {
+            matches!(row, MainRow::Feed(id) if id == feed_id)
+        });
+    }
+
+    /// Scroll the list down to the row at `offset`.
+    pub fn set_offset(&mut self, offset: usize) {
+        self.list.set_offset(offset);
     }
 }
 
 impl Page for MainPage {
-    fn draw(&mut self, f: &mut Frame, state: &FeedState) {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
         // Build the list items.
         let items = self.list.items.iter().map(|row| match row {
             MainRow::Spacer => {
                 ListItem::new("")
             }
 
+            MainRow::Timeline(timeline) => {
+                let unread = timeline.unread(state);
+                let count = match timeline {
+                    Timeline::Unread => unread.to_string(),
+                    _ => format!("{} unread", unread),
+                };
+                let line = Line::raw(format!("   ☰  {} ({})",
+                    timeline.title(), count));
+
+                // If there are unread posts in the timeline, make it more
+                // visible.
+                let line = if unread != 0 {
+                    line.style(state.theme.unread)
+                } else {
+                    line
+                };
+                ListItem::new(line)
+            }
+
             MainRow::SectionHeader(title) => {
                 ListItem::new(Line::styled(
                     format!("────┤ {} ├────", title),
-                    Style::default()
-                        .add_modifier(Modifier::BOLD)
-                        .fg(Color::Magenta),
+                    state.theme.section,
                 ))
             }
 
             MainRow::Feed(feed_id) => {
-                // If the feed is being downloaded, prepend it with a spinner.
-                let spinner = if state.is_downloading(&feed_id) {
+                // If the feed is being downloaded or its posts are being
+                // loaded, prepend it with a spinner.
+                let loading = state.is_loading(feed_id);
+                let spinner = if state.is_downloading(feed_id) || loading {
                     state.spinner.frame()
                 } else {
                     ' '
                 };
 
                 // Build the feed line.
-                let feed = state.get_feed(&feed_id).unwrap();
-                let line = Line::from(vec![
+                let Some(feed) = state.get_feed(feed_id) else {
+                    return ListItem::new("");
+                };
+                let mut spans = vec![
                     Span::raw(format!("   {}  ", spinner)),
                     Span::raw(feed.title.as_ref()),
-                ]);
+                ];
+                if !state.theme.focus {
+                    for tag in feed.options.tags.iter() {
+                        spans.push(Span::styled(format!("  #{}", tag),
+                            state.theme.section));
+                    }
+                }
+                if loading {
+                    spans.push(Span::styled("  loading…", state.theme.dim));
+                }
+                let fresh = state.fresh_posts(feed_id);
+                if fresh != 0 {
+                    spans.push(Span::styled(format!("  {} new", fresh),
+                        state.theme.section));
+                }
+                let line = Line::from(spans);
 
                 // If there are unread posts in this feed, make it more visible.
                 let line = if feed.posts.unread() != 0 {
-                    line.style(Style::default().add_modifier(Modifier::BOLD))
+                    line.style(state.theme.unread)
                 } else {
                     line
                 };
@@ -97,8 +156,15 @@ impl Page for MainPage {
             }
         });
 
-        let list = crate::tui::build_list(" Feeds ", items);
-        f.render_stateful_widget(list, f.area(), &mut self.list.state);
+        // Tell how far the downloads have got while they're going.
+        let downloads = state.downloads();
+        let title = if downloads.iter().any(|download| !download.is_over()) {
+            format!(" Feeds | {} ", crate::tui::downloads::summary(downloads))
+        } else {
+            " Feeds ".to_string()
+        };
+        let list = crate::tui::build_list(&title, items, &state.theme);
+        self.list.render(f, area, list, &state.theme);
     }
 
     fn list(&mut self) -> &mut dyn NavigableList {
@@ -106,7 +172,28 @@ impl Page for MainPage {
     }
 
     fn on_key(&mut self, key: KeyCode, state: &FeedState) -> PageAction {
-        let Some(MainRow::Feed(feed_id)) = self.list.selected_item() else {
+        let selected = match self.list.selected_item() {
+            Some(MainRow::Feed(feed_id)) => Some(feed_id),
+            _ => None,
+        };
+
+        // Add a feed to the section of the selected one, even if there are no
+        // feeds yet.
+        if key == KeyCode::Char('a') {
+            let section_idx = selected.map_or(0, |feed_id| feed_id.section_idx);
+            let page = FeedFormPage::add(section_idx, state);
+            return PageAction::NewPage(Box::new(page));
+        }
+
+        // Read the posts of all feeds in one timeline.
+        if let Some(&MainRow::Timeline(timeline)) = self.list.selected_item()
+            && matches!(key, KeyCode::Enter | KeyCode::Char('l'))
+        {
+            let page = TimelinePage::new(timeline);
+            return PageAction::NewPage(Box::new(page));
+        }
+
+        let Some(feed_id) = selected else {
             return PageAction::None;
         };
 
@@ -120,6 +207,11 @@ impl Page for MainPage {
                 PageAction::DownloadFeed(feed_id.clone())
             },
 
+            // Download the feeds of the section of the selected feed.
+            KeyCode::Char('S') => {
+                PageAction::DownloadSection(feed_id.section_idx)
+            },
+
             // Download all feeds.
             KeyCode::Char('H') => {
                 PageAction::DownloadAllFeeds
@@ -130,11 +222,75 @@ impl Page for MainPage {
                 PageAction::MarkFeedRead(feed_id.clone())
             },
 
+            // Mark the old posts in the feed as read.
+            KeyCode::Char('R') => {
+                PageAction::MarkFeedReadOlder(feed_id.clone())
+            },
+
+            // Jump to the next feed with unread posts.
+            KeyCode::Tab => {
+                self.list.select_next(|row| matches!(row, MainRow::Feed(id)
+                    if state.get_feed(id)
+                        .is_some_and(|feed| feed.posts.unread() != 0)));
+                PageAction::None
+            },
+
+            // Change the title, the URL or the section of the feed.
+            KeyCode::Char('e') => {
+                match FeedFormPage::edit(feed_id.clone(), state) {
+                    Some(page) => PageAction::NewPage(Box::new(page)),
+                    None => PageAction::None,
+                }
+            },
+
+            // Remove the feed from the feed file.
+            KeyCode::Char('D') => PageAction::RemoveFeed(feed_id.clone()),
+
+            // Show what the feed says about itself and how it's downloaded.
+            KeyCode::Char('i') => {
+                let page = FeedInfoPage::new(feed_id.clone());
+                PageAction::NewPage(Box::new(page))
+            },
+
+            // Move the feed up or down within its section.
+            KeyCode::Char(c @ ('[' | ']')) => {
+                let Some(section) = state.get_section(feed_id.section_idx)
+                else {
+                    return PageAction::None;
+                };
+                let feeds = &section.feeds;
+                let feed_idx = match c {
+                    '[' => feed_id.feed_idx.checked_sub(1),
+                    _ => Some(feed_id.feed_idx + 1)
+                        .filter(|&idx| idx < feeds.len()),
+                };
+                match feed_idx {
+                    Some(feed_idx) => {
+                        let other = FeedId { feed_idx, ..feed_id.clone() };
+                        PageAction::SwapFeeds(feed_id.clone(), other)
+                    },
+                    None => PageAction::None,
+                }
+            },
+
+            // Move the feed to the end of the previous or the next section.
+            KeyCode::Char(c @ ('{' | '}')) => {
+                let section_idx = match c {
+                    '{' => feed_id.section_idx.checked_sub(1),
+                    _ => Some(feed_id.section_idx + 1)
+                        .filter(|&idx| state.get_section(idx).is_some()),
+                };
+                match section_idx {
+                    Some(idx) => PageAction::MoveFeed(feed_id.clone(), idx),
+                    None => PageAction::None,
+                }
+            },
+
             // Check the posts listing for the selected feed.
             KeyCode::Enter | KeyCode::Char('l') => {
                 // Don't do anything if the feed is empty.
-                let feed = state.get_feed(feed_id).unwrap();
-                if feed.posts.len() == 0 {
+                let feed = state.get_feed(feed_id);
+                if feed.is_none_or(|feed| feed.posts.is_empty()) {
                     PageAction::None
                 } else {
                     PageAction::NewPage(
@@ -145,4 +301,13 @@ impl Page for MainPage {
             _ => PageAction::None,
         }
     }
+
+    fn session(&self, state: &FeedState) -> Option<PageSession> {
+        let feed = match self.list.selected_item() {
+            Some(MainRow::Feed(feed_id)) => state.get_feed(feed_id)
+                .map(|feed| feed.url.to_string()),
+            _ => None,
+        };
+        Some(PageSession::Main { feed, offset: self.list.offset() })
+    }
 }
@@ -0,0 +1,54 @@
+//! Recognition of the links to videos and audio that can be played.
+
+/// The file extensions of the links played as media, e.g. podcast episodes.
+const EXTENSIONS: [&str; 12] = [
+    "mp3", "m4a", "aac", "ogg", "oga", "opus", "flac", "wav",
+    "mp4", "m4v", "webm", "mkv",
+];
+
+/// The hosts of YouTube, whose videos are played.
+const YOUTUBE_HOSTS: [&str; 4] = [
+    "youtube.com", "www.youtube.com", "m.youtube.com", "youtu.be",
+];
+
+/// Whether the `url` links to a video or audio a player can play: a file
+/// with a media extension, a YouTube video or a PeerTube video.
+pub fn is_media(url: &url::Url) -> bool {
+    let path = url.path();
+    let extension = path.rsplit_once('.').is_some_and(|(_, extension)| {
+        EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+    });
+    if extension {
+        return true;
+    }
+
+    let host = url.host_str().unwrap_or_default();
+    if YOUTUBE_HOSTS.contains(&host) {
+        return host == "youtu.be"
+            || path == "/watch"
+            || path.starts_with("/shorts/")
+            || path.starts_with("/live/");
+    }
+
+    // PeerTube instances can be hosted anywhere, but they share the paths
+    // of the videos.
+    path.starts_with("/videos/watch/") || path.starts_with("/w/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_media() {
+        let url = |url| url::Url::parse(url).unwrap();
+        assert!(is_media(&url("https://cdn.example/episode-1.MP3?x=1")));
+        assert!(is_media(&url("https://www.youtube.com/watch?v=abc")));
+        assert!(is_media(&url("https://youtu.be/abc")));
+        assert!(is_media(&url("https://tube.example/w/9c9de5e8")));
+        assert!(is_media(&url("https://tube.example/videos/watch/9c9de5e8")));
+        assert!(!is_media(&url("https://www.youtube.com/@channel")));
+        assert!(!is_media(&url("https://example.com/cat.png")));
+        assert!(!is_media(&url("https://example.com/")));
+    }
+}
@@ -1,12 +1,17 @@
 use crossterm::event::KeyCode;
 use ratatui::{
     prelude::*,
-    widgets::ListItem,
+    widgets::{Block, Borders, ListItem, Paragraph, Wrap},
 };
-use crate::tui::{Page, NavigableList, ListPage, PageAction};
-use crate::app::FeedState;
-use crate::config::{FeedId, PostId, Posts};
-use crate::database::{DatabaseChannel, DatabaseRequest};
+use crate::tui::{
+    Page, NavigableList, ListPage, PageAction, actions::ActionsPage, image,
+    media, qr::QrPage
+};
+use crate::app::{FeedState, Subscription};
+use crate::config::{Feed, FeedId, Post, PostId};
+use crate::database::PageSession;
+use crate::download::{ImagePreview, LinkHealth};
+use crate::settings::MarkRead;
 
 impl crate::tui::Selectable for url::Url {
     fn selectable(&self) -> bool {
@@ -24,71 +29,364 @@ pub struct PostPage {
 
     /// List of rows on the post page.
     ///
-    /// In this case, each row is a URL in this post.
+    /// In this case, each row is a URL in this post, and the links to its
+    /// comments come first.
     list: ListPage<url::Url>,
+
+    /// The image link previewed next to the list.
+    preview: Option<url::Url>,
+
+    /// Where the image was drawn to when the page was last drawn, once it's
+    /// downloaded.
+    image_area: Option<Rect>,
+
+    /// Whether the post is looked at as of a day, which leaves its read
+    /// state as it is.
+    historical: bool,
 }
 
 impl PostPage {
     pub fn new(feed_id: FeedId, post_id: PostId) -> Self {
-        Self { feed_id, post_id, list: ListPage::new(Vec::new()) }
+        Self {
+            feed_id,
+            post_id,
+            list: ListPage::new(Vec::new()),
+            preview: None,
+            image_area: None,
+            historical: false,
+        }
+    }
+
+    /// Show the post from the feed as of a day, without reading it.
+    pub fn historical(feed_id: FeedId, post_id: PostId) -> Self {
+        Self { historical: true, ..Self::new(feed_id, post_id) }
+    }
+
+    /// Draw the pane previewing the image at `url` into the `area`.
+    fn draw_preview(&mut self, f: &mut Frame, area: Rect, url: &url::Url,
+        state: &FeedState)
+    {
+        let block = if state.theme.focus {
+            Block::default()
+        } else {
+            Block::default().borders(Borders::ALL).title(" Preview ")
+        };
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        // The image itself is drawn over the page by the app.
+        let text = match state.image(url) {
+            None => Line::raw("This terminal can't show images"),
+            Some(ImagePreview::Loaded(_)) => {
+                self.image_area = Some(inner);
+                return;
+            },
+            Some(ImagePreview::Loading) => {
+                Line::raw(format!("{} downloading…", state.spinner.frame()))
+            },
+            Some(ImagePreview::Failed(reason)) => {
+                Line::styled(format!("Couldn't download the image: {}",
+                    reason), state.theme.error)
+            },
+        };
+        let text = Paragraph::new(text).wrap(Wrap { trim: true });
+        f.render_widget(text, inner);
+    }
+
+    /// Get the `post` of the `feed` as text, along with its archived
+    /// article, to be read in the pager.
+    fn text(post: &Post, feed: &Feed) -> String {
+        let published = post.published.with_timezone(&chrono::Local);
+        let mut text = format!("{}\n{} | {}\n", post.title, feed.title,
+            published.format("%Y-%m-%d %H:%M"));
+        if !post.authors.is_empty() {
+            text += &format!("By {}\n", post.authors.join(", "));
+        }
+        if !post.categories.is_empty() {
+            text += &format!("Categories: {}\n", post.categories.join(", "));
+        }
+        for url in Self::links(post) {
+            text += &format!("\n{}", url);
+        }
+        text += "\n";
+
+        // The archived article follows, so it can be read offline.
+        if let Some(article) = feed.articles.get(&post.id) {
+            text += &format!("\n{}\n", article);
+        }
+        text
+    }
+
+    /// Get the URLs listed for the `post`.
+    fn links(post: &Post) -> Vec<url::Url> {
+        let mut links: Vec<_> = [&post.comments, &post.comment_feed]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        for url in post.urls.iter() {
+            if !links.contains(url) {
+                links.push(url.clone());
+            }
+        }
+        links
     }
 }
 
 impl Page for PostPage {
-    fn draw(&mut self, f: &mut Frame, state: &FeedState) {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
         // Get this post state.
-        let feed = state.get_feed(&self.feed_id).unwrap();
-        let post = feed.posts.get_by_id(&self.post_id).unwrap();
+        let Some(feed) = state.get_feed(&self.feed_id) else {
+            return;
+        };
+        let Some(post) = feed.posts.get_by_id(&self.post_id) else {
+            return;
+        };
 
-        // Rebuild the URL list if the lengths differ.
-        if self.list.items.len() != post.urls.len() {
-            self.list = ListPage::new(post.urls.clone());
+        // Rebuild the URL list if the links differ.
+        let links = Self::links(post);
+        if self.list.items != links {
+            self.list = ListPage::new(links);
         }
 
-        let items = post.urls.iter().enumerate().map(|(idx, url)| {
+        // The links opened before are dimmed, so that what's left stands out.
+        let opened = feed.opened.get(&post.id);
+        let is_opened = |url| opened.is_some_and(|opened| opened.contains(url));
+
+        let items = self.list.items.iter().enumerate().map(|(idx, url)| {
+            let plain = if is_opened(url) {
+                state.theme.dim
+            } else {
+                Style::default()
+            };
+
+            // Show the reachability of the URL if it has been checked.
+            let (marker, style) = match state.link_health(url) {
+                None => (String::new(), plain),
+                Some(LinkHealth::Checking) => {
+                    (format!("  {}", state.spinner.frame()), plain)
+                },
+                Some(LinkHealth::Alive) => ("  ✓".to_string(), plain),
+                Some(LinkHealth::Dead(reason)) => {
+                    let style = state.theme.error
+                        .add_modifier(Modifier::CROSSED_OUT);
+                    (format!("  ✗ {}", reason), style)
+                },
+            };
+
+            // Label the links to the comments, along with the progress of
+            // subscribing to the comment feed.
+            let (label, subscription) = if post.comments.as_ref() == Some(url) {
+                ("comments  ", None)
+            } else if post.comment_feed.as_ref() == Some(url) {
+                ("comment feed  ", state.subscription(url))
+            } else if feed.played.contains(url) {
+                ("played  ", None)
+            } else if is_opened(url) {
+                ("opened  ", None)
+            } else if media::is_media(url) {
+                ("media  ", None)
+            } else {
+                ("", None)
+            };
+            let subscription = match subscription {
+                None => String::new(),
+                Some(Subscription::Discovering) => {
+                    format!("  {}", state.spinner.frame())
+                },
+                Some(Subscription::Subscribed(title)) => {
+                    format!("  subscribed as {}", title)
+                },
+                Some(Subscription::Failed(reason)) => {
+                    format!("  couldn't subscribe: {}", reason)
+                },
+            };
+
+            let index = if state.theme.focus {
+                String::new()
+            } else {
+                format!("{:>3}  │  ", idx)
+            };
+
             ListItem::new(Line::from(vec![
-                Span::raw(format!("{:>3}  │  ", idx)),
-                Span::raw(url.to_string()),
+                Span::raw(index),
+                Span::styled(label, state.theme.section),
+                Span::styled(url.to_string(), style),
+                Span::raw(marker),
+                Span::raw(subscription),
             ]))
         });
 
-        let section = &state.get_section(self.feed_id.section_idx)
-            .unwrap().title;
-        let title = format!(" {} | {} | {} ", section, feed.title, &post.title);
-        let list = crate::tui::build_list(&title, items);
+        let section = state.get_section(self.feed_id.section_idx)
+            .map_or("", |section| &section.title);
+        let mut title = format!(" {} | {} | {} ", section, feed.title,
+            &post.title);
+        if !post.authors.is_empty() {
+            title += &format!("| by {} ", post.authors.join(", "));
+        }
+        if !post.categories.is_empty() {
+            title += &format!("| {} ", post.categories.join(", "));
+        }
+        if feed.articles.contains_key(&post.id) {
+            title += "| archived ";
+        }
+        let count = self.list.items.iter().filter(|url| is_opened(url)).count();
+        if count > 0 {
+            title += &format!("| {} of {} opened ", count,
+                self.list.items.len());
+        }
+        let list = crate::tui::build_list(&title, items, &state.theme);
+
+        // Split the page with the preview of the image.
+        self.image_area = None;
+        let area = match self.preview.clone() {
+            Some(url) => {
+                let [list, preview] = Layout::horizontal([
+                    Constraint::Percentage(50),
+                    Constraint::Percentage(50),
+                ]).areas(area);
+                self.draw_preview(f, preview, &url, state);
+                list
+            },
+            None => area,
+        };
 
-        f.render_stateful_widget(list, f.area(), &mut self.list.state);
+        self.list.render(f, area, list, &state.theme);
     }
 
     fn list(&mut self) -> &mut dyn NavigableList {
         &mut self.list
     }
 
-    fn on_key(&mut self, key: KeyCode, _state: &FeedState) -> PageAction {
+    fn image(&self) -> Option<(&url::Url, Rect)> {
+        self.preview.as_ref().zip(self.image_area)
+    }
+
+    fn on_key(&mut self, key: KeyCode, state: &FeedState) -> PageAction {
+        let Some(feed) = state.get_feed(&self.feed_id) else {
+            return PageAction::None;
+        };
+        let Some(post) = feed.posts.get_by_id(&self.post_id) else {
+            return PageAction::None;
+        };
+
+        // Read the post in the pager.
+        if key == KeyCode::Char('p') {
+            return PageAction::ShowInPager(Self::text(post, feed));
+        }
+
+        // Archive the article of the post to read it offline.
+        if key == KeyCode::Char('a') {
+            if post.urls.is_empty() {
+                return PageAction::None;
+            }
+            let post_id = self.post_id.clone();
+            return PageAction::ArchiveArticle(self.feed_id.clone(), post_id);
+        }
+
+        // Keep the post as a Markdown note.
+        if key == KeyCode::Char('m') {
+            let post_id = self.post_id.clone();
+            return PageAction::WriteNote(self.feed_id.clone(), post_id);
+        }
+
+        // Subscribe to the comment feed of the post, unless we already have.
+        if key == KeyCode::Char('f') {
+            return match &post.comment_feed {
+                Some(url) if matches!(state.subscription(url),
+                    None | Some(Subscription::Failed(_))) => {
+                    PageAction::Subscribe(url.clone())
+                },
+                _ => PageAction::None,
+            };
+        }
+
         let Some(selected) = self.list.selected_item() else {
             return PageAction::None;
         };
 
-        match key {
-            KeyCode::Char('l') => {
+        let action = match key {
+            // Check whether the links in this post are still alive.
+            KeyCode::Char('c') => {
+                return PageAction::CheckLinks(self.list.items.clone());
+            }
+
+            // Show the URL as a QR code to open it on another device.
+            KeyCode::Char('Q') => {
+                let page = QrPage::new(selected.clone());
+                return PageAction::NewPage(Box::new(page));
+            }
+
+            // Preview the image behind the URL, or stop previewing it.
+            KeyCode::Char('i') if image::is_image(selected) => {
+                if self.preview.as_ref() == Some(selected) {
+                    self.preview = None;
+                    return PageAction::None;
+                }
+                self.preview = Some(selected.clone());
+                return PageAction::PreviewImage(selected.clone());
+            }
+
+            // Save the URL to read it later.
+            KeyCode::Char('s') => PageAction::ReadLater(selected.clone()),
+
+            // Read the text of the article in the pager.
+            KeyCode::Char('P') => {
+                PageAction::ReadArticle(self.feed_id.clone(), selected.clone())
+            }
+
+            // Play the video or audio behind the URL.
+            KeyCode::Char('v') if media::is_media(selected) => {
+                PageAction::PlayMedia(self.feed_id.clone(), selected.clone())
+            }
+
+            // Run the URL through an external command.
+            KeyCode::Char('x') => {
+                let page = ActionsPage::new(selected.clone(), &self.feed_id,
+                    state);
+                PageAction::NewPage(Box::new(page))
+            }
+
+            KeyCode::Enter | KeyCode::Char('l') => {
                 PageAction::CopyToClipboard(selected.as_str().into())
             }
-            _ => PageAction::None,
+            _ => return PageAction::None,
+        };
+
+        // The other keys open the link one way or another, which reads the
+        // post unless it's looked at as of a day.
+        if self.historical {
+            return action;
         }
+        let post_id = self.post_id.clone();
+        PageAction::OpenLink(self.feed_id.clone(), post_id, selected.clone(),
+            Box::new(action))
     }
 
-    fn on_new(&mut self, state: &mut FeedState, database: &DatabaseChannel) {
-        // Mark the post as read.
-        let feed = state.get_feed_mut(&self.feed_id).unwrap();
-        feed.posts.mark_read(&self.post_id, true);
+    fn session(&self, state: &FeedState) -> Option<PageSession> {
+        let feed = state.get_feed(&self.feed_id)?;
+        Some(PageSession::Post {
+            feed: feed.url.to_string(),
+            post: self.post_id.clone(),
+        })
+    }
 
-        // Save the post in the database.
-        let feed_url = feed.url.as_str().into();
-        let post = feed.posts.get_by_id(&self.post_id).unwrap();
-        let posts = Posts::from(post.clone());
+    fn on_new(&mut self, state: &mut FeedState) -> PageAction {
+        // The read state is left as it is in read-only mode, as of a day, or
+        // until a link is opened.
+        if state.settings.storage.read_only || self.historical
+            || state.settings.mark_read != MarkRead::Open
+        {
+            return PageAction::None;
+        }
 
-        database.request_tx.send(DatabaseRequest::SavePosts {
-            feed_url, posts
-        }).expect("The database channel closed abruptly");
+        // Mark the post as read, unless it's read already.
+        let feed = state.get_feed(&self.feed_id);
+        let post = feed.and_then(|feed| feed.posts.get_by_id(&self.post_id));
+        if post.is_none_or(|post| post.read) {
+            return PageAction::None;
+        }
+        let post_ids = vec![self.post_id.clone()];
+        PageAction::MarkPostsRead(self.feed_id.clone(), post_ids, true)
     }
 }
@@ -0,0 +1,102 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+use qrcode::QrCode;
+use qrcode::render::unicode::Dense1x2;
+use crate::tui::{Page, NavigableList, ListPage};
+use crate::app::FeedState;
+
+/// The page that shows a URL as a QR code, so that it can be opened on a
+/// phone.
+pub struct QrPage {
+    /// The URL in the QR code.
+    url: url::Url,
+
+    /// The rows of the QR code, or the reason why it couldn't be made.
+    rows: Result<Vec<String>, String>,
+
+    /// There's nothing to select on this page.
+    list: ListPage<String>,
+}
+
+impl QrPage {
+    /// Create a new page with the QR code of `url`.
+    pub fn new(url: url::Url) -> Self {
+        let rows = render(url.as_str());
+        Self { url, rows, list: ListPage::new(Vec::new()) }
+    }
+}
+
+/// Render `data` as a QR code, two modules per character cell.
+fn render(data: &str) -> Result<Vec<String>, String> {
+    let code = QrCode::new(data).map_err(|err| err.to_string())?;
+    let rows = code.render::<Dense1x2>()
+        .build()
+        .lines()
+        .map(String::from)
+        .collect();
+    Ok(rows)
+}
+
+impl Page for QrPage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        let title = format!(" {} ", self.url);
+        let block = if state.theme.focus {
+            Block::default()
+        } else {
+            Block::default().borders(Borders::ALL).title(title)
+        };
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let rows = match &self.rows {
+            Ok(rows) => rows,
+            Err(err) => {
+                let message = format!("Couldn't make the QR code: {}", err);
+                let text = Paragraph::new(message).style(state.theme.error);
+                f.render_widget(text, inner);
+                return;
+            },
+        };
+
+        // A QR code that is cut off can't be scanned.
+        let width = rows.first().map_or(0, |row| row.chars().count()) as u16;
+        let height = rows.len() as u16;
+        if width > inner.width || height > inner.height {
+            let message = format!("The terminal is too small for the QR \
+                code, it needs {}x{} cells", width, height);
+            f.render_widget(Paragraph::new(message), inner);
+            return;
+        }
+
+        // Dark modules on a light background scan with any theme.
+        let style = Style::default().fg(Color::Black).bg(Color::White);
+        let lines: Vec<Line> = rows.iter()
+            .map(|row| Line::styled(row.as_str(), style))
+            .collect();
+        let area = inner.centered(
+            Constraint::Length(width), Constraint::Length(height));
+        f.render_widget(Paragraph::new(lines), area);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_two_modules_per_row() {
+        let rows = render("https://example.com/post").unwrap();
+        let width = rows[0].chars().count();
+        assert!(rows.iter().all(|row| row.chars().count() == width));
+
+        // The code is square, with a quiet zone of four modules around it.
+        assert_eq!(rows.len(), width.div_ceil(2));
+        assert!(width > 21 + 8);
+    }
+}
@@ -0,0 +1,237 @@
+use chrono::{DateTime, Local, TimeDelta};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+use crate::tui::{Page, NavigableList, ListPage};
+use crate::app::FeedState;
+use crate::config::Feed;
+
+/// The width of the names of the fields, so that the values line up.
+const NAME_WIDTH: usize = 16;
+
+/// The number of columns of the longest bar of the reads.
+const BAR_WIDTH: usize = 30;
+
+/// The number of busiest feeds shown.
+const BUSIEST: usize = 5;
+
+/// The number of days the busiest feeds are counted over.
+const BUSY_DAYS: i64 = 30;
+
+/// A line of the statistics.
+#[derive(Debug, PartialEq)]
+enum Row {
+    /// The heading of a group of fields.
+    Section(&'static str),
+
+    /// A name and its value.
+    Field(String, String),
+}
+
+/// The page that shows how much has been read and how much is waiting.
+pub struct StatsPage {
+    /// There's nothing to select on this page.
+    list: ListPage<String>,
+}
+
+impl Default for StatsPage {
+    /// Create a new statistics page.
+    fn default() -> Self {
+        Self { list: ListPage::new(Vec::new()) }
+    }
+}
+
+/// Draw a bar of `count` out of `max`.
+fn bar(count: usize, max: usize) -> String {
+    let width = (count * BAR_WIDTH).div_ceil(max.max(1));
+    format!("{:>4} {}", count, "█".repeat(width))
+}
+
+/// Describe a `duration` in the biggest unit that fits it.
+fn duration(duration: TimeDelta) -> String {
+    if duration.num_days() > 0 {
+        format!("{} days", duration.num_days())
+    } else if duration.num_hours() > 0 {
+        format!("{} hours", duration.num_hours())
+    } else {
+        format!("{} minutes", duration.num_minutes())
+    }
+}
+
+/// Count the reads of the posts of the `feeds` and the posts waiting to be
+/// read, as of `now`.
+fn stats(feeds: &[&Feed], now: DateTime<Local>) -> Vec<Row> {
+    let today = now.date_naive();
+    let reads: Vec<_> = feeds.iter()
+        .flat_map(|feed| feed.reads.iter())
+        .map(|read| read.at.with_timezone(&Local).date_naive())
+        .collect();
+    let days_ago = |day: chrono::NaiveDate| (today - day).num_days();
+
+    let mut rows = vec![Row::Section("Read per day")];
+    let per_day: Vec<usize> = (0..7)
+        .map(|ago| reads.iter().filter(|&&day| days_ago(day) == ago).count())
+        .collect();
+    let max = per_day.iter().copied().max().unwrap_or(0);
+    for (ago, count) in per_day.into_iter().enumerate() {
+        let day = today - TimeDelta::days(ago as i64);
+        rows.push(Row::Field(day.format("%a %m-%d").to_string(),
+            bar(count, max)));
+    }
+
+    rows.push(Row::Section("Read per week"));
+    let per_week: Vec<usize> = (0..4)
+        .map(|ago| reads.iter()
+            .filter(|&&day| days_ago(day) / 7 == ago)
+            .count())
+        .collect();
+    let max = per_week.iter().copied().max().unwrap_or(0);
+    for (ago, count) in per_week.into_iter().enumerate() {
+        let name = match ago {
+            0 => "Last 7 days".to_string(),
+            1 => "1 week before".to_string(),
+            n => format!("{} weeks before", n),
+        };
+        rows.push(Row::Field(name, bar(count, max)));
+    }
+
+    // The feeds that have brought the most posts lately.
+    rows.push(Row::Section("Busiest feeds"));
+    let since = now.to_utc() - TimeDelta::days(BUSY_DAYS);
+    let mut busiest: Vec<(&Feed, usize)> = feeds.iter()
+        .map(|&feed| (feed, feed.posts.as_ref().iter()
+            .filter(|post| post.arrived >= since)
+            .count()))
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    busiest.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    for (feed, count) in busiest.into_iter().take(BUSIEST) {
+        rows.push(Row::Field(feed.title.to_string(),
+            format!("{} posts in {} days", count, BUSY_DAYS)));
+    }
+
+    rows.push(Row::Section("Backlog"));
+    let unread: usize = feeds.iter().map(|feed| feed.posts.unread()).sum();
+    let average = unread as f64 / feeds.len().max(1) as f64;
+    rows.push(Row::Field("Unread".into(),
+        format!("{} posts, {:.1} per feed", unread, average)));
+
+    // How long the posts have waited before they've been read.
+    let waits: Vec<TimeDelta> = feeds.iter()
+        .flat_map(|feed| feed.reads.iter()
+            .filter_map(|read| feed.posts.get_by_id(&read.post_id)
+                .map(|post| read.at - post.arrived)))
+        .filter(|wait| *wait >= TimeDelta::zero())
+        .collect();
+    if !waits.is_empty() {
+        let total: TimeDelta = waits.iter().sum();
+        rows.push(Row::Field("Read after".into(),
+            format!("{} on average", duration(total / waits.len() as i32))));
+    }
+
+    let oldest = feeds.iter()
+        .flat_map(|feed| feed.posts.as_ref().iter()
+            .filter(|post| !post.read)
+            .map(move |post| (feed, post)))
+        .min_by_key(|(_, post)| post.published);
+    if let Some((feed, post)) = oldest {
+        let published = post.published.with_timezone(&Local);
+        rows.push(Row::Field("Oldest unread".into(),
+            format!("{} ({}, {})", post.title, feed.title,
+                published.format("%Y-%m-%d"))));
+    }
+
+    rows
+}
+
+impl Page for StatsPage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        let block = if state.theme.focus {
+            Block::default()
+        } else {
+            Block::default().borders(Borders::ALL).title(" Statistics ")
+        };
+
+        let feeds: Vec<&Feed> = state.feed_ids()
+            .filter_map(|feed_id| state.get_feed(&feed_id))
+            .collect();
+        let mut lines = Vec::new();
+        for row in stats(&feeds, Local::now()) {
+            match row {
+                Row::Section(name) => {
+                    if !lines.is_empty() {
+                        lines.push(Line::default());
+                    }
+                    lines.push(Line::styled(name, state.theme.section));
+                },
+                Row::Field(name, value) => lines.push(Line::from(vec![
+                    Span::raw(format!("  {:<NAME_WIDTH$}", name)),
+                    Span::raw(value),
+                ])),
+            }
+        }
+
+        let text = Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false });
+        f.render_widget(text, area);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use chrono::TimeZone;
+    use url::Url;
+    use crate::config::{Post, PostId, Posts, Read, test_post};
+
+    #[test]
+    fn counts_reads_and_backlog() {
+        let now = Local.with_ymd_and_hms(2024, 1, 10, 12, 0, 0).unwrap();
+        let ago = |days| now.to_utc() - TimeDelta::days(days);
+        let post = |id: &str, days, read| Post {
+            read,
+            ..test_post(id, "", ago(days).timestamp())
+        };
+        let mut posts = Posts::new();
+        posts.insert(post("new", 1, true));
+        posts.insert(post("old", 10, true));
+        posts.insert(post("unread", 40, false));
+        let url = Url::parse("https://blog.example/feed.xml").unwrap();
+        let feed = Feed {
+            posts,
+            reads: vec![
+                Read { post_id: PostId(Arc::from("old")), at: ago(9) },
+                Read { post_id: PostId(Arc::from("new")), at: ago(0) },
+            ],
+            ..Feed::new("Blog".into(), url)
+        };
+        let url = Url::parse("https://quiet.example/feed.xml").unwrap();
+        let quiet = Feed::new("Quiet".into(), url);
+
+        let rows = stats(&[&feed, &quiet], now);
+        let field = |name: &str| rows.iter()
+            .find_map(|row| match row {
+                Row::Field(n, value) if n == name => Some(value.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(field("Wed 01-10"), format!("   1 {}", "█".repeat(30)));
+        assert_eq!(field("Tue 01-09"), "   0 ");
+        assert_eq!(field("Last 7 days"), format!("   1 {}", "█".repeat(30)));
+        assert_eq!(field("1 week before"),
+            format!("   1 {}", "█".repeat(30)));
+        assert_eq!(field("Blog"), "2 posts in 30 days");
+        assert!(!rows.contains(&Row::Field("Quiet".into(),
+            "0 posts in 30 days".into())));
+        assert_eq!(field("Unread"), "1 posts, 0.5 per feed");
+        assert_eq!(field("Read after"), "1 days on average");
+        assert!(field("Oldest unread").starts_with("unread (Blog, "));
+    }
+}
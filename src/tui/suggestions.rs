@@ -0,0 +1,95 @@
+use ratatui::{
+    prelude::*,
+    widgets::ListItem,
+};
+use crossterm::event::KeyCode;
+use crate::tui::{Page, NavigableList, ListPage, PageAction, Selectable};
+use crate::app::{FeedState, Subscription};
+use crate::config::FeedConfig;
+use crate::suggest::{suggest, Suggestion};
+
+/// All suggestions are selectable.
+impl Selectable for Suggestion {
+    fn selectable(&self) -> bool {
+        true
+    }
+}
+
+/// The page that suggests hosts to subscribe to.
+pub struct SuggestionsPage {
+    /// List of rows on the suggestions page.
+    list: ListPage<Suggestion>,
+}
+
+impl SuggestionsPage {
+    /// Create a new suggestions page from the posts in `config`.
+    pub fn new(config: &FeedConfig) -> Self {
+        Self { list: ListPage::new(suggest(config)) }
+    }
+}
+
+impl Page for SuggestionsPage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        let items = self.list.items.iter().map(|suggestion| {
+            // Show the progress of the subscription.
+            let subscription = suggestion.url()
+                .and_then(|url| state.subscription(&url));
+            let (marker, style) = match subscription {
+                None => (String::new(), Style::default()),
+                Some(Subscription::Discovering) => {
+                    (format!("  {}", state.spinner.frame()), Style::default())
+                },
+                Some(Subscription::Subscribed(title)) => {
+                    (format!("  ✓ {}", title), Style::default())
+                },
+                Some(Subscription::Failed(reason)) => {
+                    (format!("  ✗ {}", reason), state.theme.error)
+                },
+            };
+
+            let posts = if state.theme.focus {
+                String::new()
+            } else {
+                format!("{:>5}  │  ", suggestion.posts)
+            };
+
+            ListItem::new(Line::from(vec![
+                Span::raw(posts),
+                Span::raw(suggestion.host.clone()),
+                Span::styled(marker, style),
+            ]))
+        });
+
+        let title = " Suggestions ";
+        let list = crate::tui::build_list(title, items, &state.theme);
+
+        self.list.render(f, area, list, &state.theme);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+
+    fn on_key(&mut self, key: KeyCode, state: &FeedState) -> PageAction {
+        let Some(selected) = self.list.selected_item() else {
+            return PageAction::None;
+        };
+
+        match key {
+            // Subscribe to the feed of the selected host, unless we already
+            // have.
+            KeyCode::Enter | KeyCode::Char('l') | KeyCode::Char('s') => {
+                let Some(url) = selected.url() else {
+                    return PageAction::None;
+                };
+                match state.subscription(&url) {
+                    None | Some(Subscription::Failed(_)) => {
+                        PageAction::Subscribe(url)
+                    },
+                    _ => PageAction::None,
+                }
+            }
+            _ => PageAction::None,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Fitting text into the columns of the terminal.
+//!
+//! Characters aren't columns: CJK characters and most emoji take two of
+//! them, combining marks none, and a character can be a part of a bigger
+//! grapheme, e.g. a flag. Text is only ever cut between graphemes.
+
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// The mark of text that has been cut short.
+const ELLIPSIS: char = '…';
+
+/// Get the number of columns the `s` takes in the terminal.
+pub fn width(s: &str) -> usize {
+    s.width()
+}
+
+/// Cut the `s` to at most `width` columns, ending it with an ellipsis if
+/// anything has been cut.
+pub fn truncate(s: &str, width: usize) -> Cow<'_, str> {
+    if s.width() <= width {
+        return Cow::Borrowed(s);
+    }
+    if width == 0 {
+        return Cow::Borrowed("");
+    }
+
+    // Leave a column for the ellipsis.
+    let mut cut = String::new();
+    let mut used = 0;
+    for grapheme in s.graphemes(true) {
+        used += grapheme.width();
+        if used >= width {
+            break;
+        }
+        cut.push_str(grapheme);
+    }
+    cut.truncate(cut.trim_end().len());
+    cut.push(ELLIPSIS);
+    Cow::Owned(cut)
+}
+
+/// Delete the last grapheme of the `s`, e.g. a letter with its combining
+/// marks. Returns `false` if there was nothing to delete.
+pub fn pop_grapheme(s: &mut String) -> bool {
+    match s.grapheme_indices(true).next_back() {
+        Some((idx, _)) => {
+            s.truncate(idx);
+            true
+        },
+        None => false,
+    }
+}
+
+/// Pad the `s` with spaces to `width` columns, cutting it if it's wider.
+pub fn pad(s: &str, width: usize) -> String {
+    let s = truncate(s, width);
+    let padding = width.saturating_sub(s.width());
+    format!("{}{}", s, " ".repeat(padding))
+}
+
+/// Wrap the `s` into at most `lines` lines of at most `width` columns,
+/// breaking them at whitespace where there's any. The last line ends with
+/// an ellipsis if the text doesn't fit.
+pub fn wrap(s: &str, width: usize, lines: usize) -> Vec<Cow<'_, str>> {
+    let mut wrapped = Vec::new();
+    let mut rest = s.trim();
+    while !rest.is_empty() && wrapped.len() + 1 < lines {
+        let (line, tail) = split_line(rest, width);
+        wrapped.push(Cow::Borrowed(line));
+        rest = tail.trim_start();
+    }
+    if !rest.is_empty() && lines > 0 {
+        wrapped.push(truncate(rest, width));
+    }
+    wrapped
+}
+
+/// Split off the start of the `s` that fits into `width` columns, at the
+/// last whitespace in it if there's any. At least one grapheme is split off
+/// even if it doesn't fit.
+fn split_line(s: &str, width: usize) -> (&str, &str) {
+    let mut used = 0;
+    let mut space = None;
+    for (idx, grapheme) in s.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > width {
+            let end = match space {
+                Some(space) => space,
+                None if idx == 0 => grapheme.len(),
+                None => idx,
+            };
+            return (s[..end].trim_end(), &s[end..]);
+        }
+        if grapheme.trim().is_empty() {
+            space = Some(idx);
+        }
+    }
+    (s, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_text_into_columns() {
+        assert_eq!(truncate("Hello", 5), "Hello");
+        assert_eq!(truncate("Hello, World", 7), "Hello,…");
+        assert_eq!(truncate("Hello", 0), "");
+        // Wide characters take two columns and aren't cut in half.
+        assert_eq!(width("日本語"), 6);
+        assert_eq!(truncate("日本語のニュース", 6), "日本…");
+        assert_eq!(truncate("日本語のニュース", 7), "日本語…");
+        // Combining marks stay with their letters.
+        assert_eq!(truncate("Cafe\u{301} Noir", 5), "Cafe\u{301}…");
+        assert_eq!(pad("日本", 6), "日本  ");
+        assert_eq!(pad("Rust Blog", 5), "Rust…");
+
+        assert_eq!(wrap("A title that is long", 10, 2),
+            ["A title", "that is l…"]);
+        assert_eq!(wrap("Short", 10, 2), ["Short"]);
+        assert_eq!(wrap("Unbreakabletitle", 5, 2), ["Unbre", "akab…"]);
+        assert_eq!(wrap("Title", 10, 0), Vec::<&str>::new());
+        assert_eq!(wrap("日本語 ニュース", 8, 2), ["日本語", "ニュース"]);
+
+        let mut typed = String::from("Cafe\u{301}");
+        assert!(pop_grapheme(&mut typed));
+        assert_eq!(typed, "Caf");
+        typed.clear();
+        assert!(!pop_grapheme(&mut typed));
+    }
+}
@@ -0,0 +1,175 @@
+//! Color themes.
+
+use chrono::Timelike;
+use ratatui::style::{Color, Modifier, Style};
+use crate::settings::ThemeSettings;
+
+/// The kind of theme, matching the background of the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeKind {
+    Dark,
+    Light,
+}
+
+impl ThemeKind {
+    /// Get the other kind.
+    pub fn toggled(self) -> Self {
+        match self {
+            ThemeKind::Dark => ThemeKind::Light,
+            ThemeKind::Light => ThemeKind::Dark,
+        }
+    }
+
+    /// Get the kind for the current time of day.
+    pub fn for_time_of_day(settings: &ThemeSettings) -> Self {
+        Self::for_hour(settings, chrono::Local::now().hour())
+    }
+
+    /// Get the kind for the `hour` of the day.
+    fn for_hour(settings: &ThemeSettings, hour: u32) -> Self {
+        let (day, night) = (settings.day_starts, settings.night_starts);
+
+        // The day may go on past midnight, e.g. from 18 to 6.
+        let is_day = if day <= night {
+            (day..night).contains(&hour)
+        } else {
+            !(night..day).contains(&hour)
+        };
+        if is_day {
+            ThemeKind::Light
+        } else {
+            ThemeKind::Dark
+        }
+    }
+}
+
+/// Styles used to draw the TUI.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// The kind of this theme.
+    pub kind: ThemeKind,
+
+    /// The selected entry in a list.
+    pub highlight: Style,
+
+    /// Section headers on the main page.
+    pub section: Style,
+
+    /// Feeds and posts that have unread posts.
+    pub unread: Style,
+
+    /// Things that went wrong, e.g. dead links.
+    pub error: Style,
+
+    /// Text less important than what it's next to, e.g. summaries of posts.
+    pub dim: Style,
+
+    /// Whether to hide borders, indices and dates and show only the titles.
+    pub focus: bool,
+}
+
+impl Theme {
+    /// Create a theme of the given kind.
+    pub fn new(kind: ThemeKind) -> Self {
+        match kind {
+            ThemeKind::Dark => Self {
+                kind,
+                highlight: Style::default().fg(Color::Blue),
+                section: Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(Color::Magenta),
+                unread: Style::default().add_modifier(Modifier::BOLD),
+                error: Style::default().fg(Color::Red),
+                dim: Style::default().add_modifier(Modifier::DIM),
+                focus: false,
+            },
+
+            // The basic colors are often too bright on light backgrounds, so
+            // we use darker shades from the 256 color palette.
+            ThemeKind::Light => Self {
+                kind,
+                highlight: Style::default().fg(Color::Indexed(25)),
+                section: Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(Color::Indexed(90)),
+                unread: Style::default().add_modifier(Modifier::BOLD),
+                error: Style::default().fg(Color::Indexed(124)),
+                dim: Style::default().fg(Color::Indexed(244)),
+                focus: false,
+            },
+        }
+    }
+
+    /// Switch the styles to the given kind, keeping the layout.
+    pub fn set_kind(&mut self, kind: ThemeKind) {
+        *self = Self { focus: self.focus, ..Self::new(kind) };
+    }
+}
+
+/// Ask the terminal for its background color and guess the theme kind from
+/// its luminance.
+///
+/// The terminal must be in raw mode. Returns `None` if the terminal doesn't
+/// answer.
+pub fn query_terminal_background() -> Option<ThemeKind> {
+    let response = crate::tui::query_terminal(b"\x1b]11;?\x1b\\")?;
+    parse_background_response(&response)
+}
+
+/// Parse the `rgb:RRRR/GGGG/BBBB` answer to OSC 11 from the `response`.
+fn parse_background_response(response: &str) -> Option<ThemeKind> {
+    let start = response.find("rgb:")? + "rgb:".len();
+    let rgb = &response[start..];
+    let end = rgb.find(|c: char| !c.is_ascii_hexdigit() && c != '/')
+        .unwrap_or(rgb.len());
+
+    // Every component can have 1 to 4 hex digits.
+    let components = rgb[..end].split('/')
+        .map(|c| {
+            let value = u32::from_str_radix(c, 16).ok()?;
+            let max = 16u32.checked_pow(c.len() as u32)? - 1;
+            Some(value as f32 / max as f32)
+        })
+        .collect::<Option<Vec<f32>>>()?;
+
+    let [r, g, b] = components[..] else {
+        return None;
+    };
+
+    let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    if luminance > 0.5 {
+        Some(ThemeKind::Light)
+    } else {
+        Some(ThemeKind::Dark)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_background_responses() {
+        let light = "\x1b]11;rgb:ffff/ffff/eeee\x1b\\\x1b[?62;22c";
+        assert_eq!(parse_background_response(light), Some(ThemeKind::Light));
+
+        let dark = "\x1b]11;rgb:1c/1c/1c\x07\x1b[?1;2c";
+        assert_eq!(parse_background_response(dark), Some(ThemeKind::Dark));
+
+        assert_eq!(parse_background_response("\x1b[?1;2c"), None);
+    }
+
+    #[test]
+    fn picks_kinds_by_the_hour() {
+        let mut settings = ThemeSettings::default();
+        assert_eq!(ThemeKind::for_hour(&settings, 7), ThemeKind::Light);
+        assert_eq!(ThemeKind::for_hour(&settings, 19), ThemeKind::Dark);
+        assert_eq!(ThemeKind::for_hour(&settings, 3), ThemeKind::Dark);
+
+        settings.day_starts = 18;
+        settings.night_starts = 6;
+        assert_eq!(ThemeKind::for_hour(&settings, 23), ThemeKind::Light);
+        assert_eq!(ThemeKind::for_hour(&settings, 3), ThemeKind::Light);
+        assert_eq!(ThemeKind::for_hour(&settings, 12), ThemeKind::Dark);
+    }
+}
@@ -0,0 +1,266 @@
+use ratatui::{
+    prelude::*,
+    widgets::ListItem,
+};
+use crossterm::event::KeyCode;
+use chrono::{DateTime, Datelike, Days, Local, Utc};
+use crate::tui::{
+    Page, NavigableList, ListPage, PageAction, post::PostPage, text,
+};
+use crate::app::FeedState;
+use crate::config::{MergedPost, Post};
+
+/// The widest the column with the titles of the feeds gets.
+const MAX_FEED_WIDTH: usize = 20;
+
+/// Which posts of all feeds a timeline lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeline {
+    /// All posts.
+    All,
+
+    /// The unread posts.
+    Unread,
+
+    /// The posts published since the local midnight.
+    Today,
+
+    /// The posts published since the local midnight starting Monday.
+    Week,
+}
+
+impl Timeline {
+    /// All timelines, in the order they're listed on the main page.
+    pub const ALL: [Self; 4] = [Self::All, Self::Unread, Self::Today,
+        Self::Week];
+
+    /// Get the title of the timeline.
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::All => "All posts",
+            Self::Unread => "Unread",
+            Self::Today => "Today",
+            Self::Week => "This week",
+        }
+    }
+
+    /// Get the time the posts have to be published after as of `now`, if
+    /// the timeline is limited to one.
+    fn since(self, now: DateTime<Local>) -> Option<DateTime<Utc>> {
+        let days = match self {
+            Self::All | Self::Unread => return None,
+            Self::Today => 0,
+            Self::Week => now.weekday().num_days_from_monday(),
+        };
+        let day = now.date_naive() - Days::new(days.into());
+        day.and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+            .map(|midnight| midnight.with_timezone(&Utc))
+    }
+
+    /// Whether the timeline lists the `post`, given the time returned by
+    /// `since`.
+    fn includes(self, post: &Post, since: Option<DateTime<Utc>>) -> bool {
+        (self != Self::Unread || !post.read)
+            && since.is_none_or(|since| post.published >= since)
+    }
+
+    /// Count the unread posts of all feeds the timeline lists, including the
+    /// copies of the same post in several feeds.
+    pub fn unread(self, state: &FeedState) -> usize {
+        let since = self.since(Local::now());
+        state.feed_ids()
+            .filter_map(|feed_id| state.get_feed(&feed_id))
+            .flat_map(|feed| feed.posts.as_ref().iter())
+            .filter(|post| !post.read && self.includes(post, since))
+            .count()
+    }
+}
+
+/// All posts are selectable.
+impl crate::tui::Selectable for MergedPost {
+    fn selectable(&self) -> bool {
+        true
+    }
+}
+
+/// The page that lists the posts of all feeds in one timeline, newest first.
+pub struct TimelinePage {
+    /// Which posts are listed.
+    timeline: Timeline,
+
+    /// List of the posts, each shown once even if it's in more feeds.
+    list: ListPage<MergedPost>,
+
+    /// The number of posts in all feeds, whether the killfile hid posts and
+    /// the time the posts had to be published after when the list was last
+    /// built.
+    built: Option<(usize, bool, Option<DateTime<Utc>>)>,
+
+    /// The number of posts hidden by the killfile.
+    hidden: usize,
+
+    /// The width of the column with the titles of the feeds.
+    feed_width: usize,
+}
+
+impl TimelinePage {
+    /// Create a new page for the `timeline`.
+    pub fn new(timeline: Timeline) -> Self {
+        Self {
+            timeline,
+            list: ListPage::new(Vec::new()),
+            built: None,
+            hidden: 0,
+            feed_width: 0,
+        }
+    }
+
+    /// Get the newest copy of the `merged` post, unless its feed is gone.
+    fn post<'a>(merged: &MergedPost, state: &'a FeedState)
+        -> Option<&'a Post>
+    {
+        let (feed_id, post_id) = &merged.sources[0];
+        state.get_feed(feed_id)
+            .and_then(|feed| feed.posts.get_by_id(post_id))
+    }
+
+    /// Build the list of the posts of the timeline published after `since`,
+    /// counting the posts hidden by the killfile.
+    fn build(&mut self, since: Option<DateTime<Utc>>, state: &FeedState) {
+        let mut posts = state.merged_posts();
+        posts.retain(|merged| Self::post(merged, state)
+            .is_some_and(|post| self.timeline.includes(post, since)));
+        let listed = posts.len();
+        posts.retain(|merged| Self::post(merged, state)
+            .is_some_and(|post| !state.is_killed(post)));
+        self.hidden = listed - posts.len();
+
+        self.feed_width = posts.iter()
+            .filter_map(|merged| state.get_feed(&merged.sources[0].0))
+            .map(|feed| text::width(&feed.title))
+            .max()
+            .unwrap_or(0)
+            .min(MAX_FEED_WIDTH);
+        self.list = ListPage::new(posts);
+    }
+}
+
+impl Page for TimelinePage {
+    fn draw(&mut self, f: &mut Frame, area: Rect, state: &FeedState) {
+        // Rebuild the list if posts have arrived or the killfile has changed.
+        let posts = state.feed_ids()
+            .filter_map(|feed_id| state.get_feed(&feed_id))
+            .map(|feed| feed.posts.len())
+            .sum();
+        let since = self.timeline.since(Local::now());
+        let built = Some((posts, state.killfile, since));
+        if self.built != built {
+            self.build(since, state);
+            self.built = built;
+        }
+
+        let columns = crate::tui::row_width(area, &state.theme);
+        let items = self.list.items.iter().map(|merged| {
+            let feed = state.get_feed(&merged.sources[0].0);
+            let (Some(post), Some(feed)) = (Self::post(merged, state), feed)
+            else {
+                // The feed has been removed since the list was built.
+                return ListItem::new("");
+            };
+            let star = Span::raw(if post.starred { "★ " } else { "" });
+            let mut line = if state.theme.focus {
+                Line::from(vec![star])
+            } else {
+                Line::from(vec![
+                    Span::raw(post.published
+                        .format("%Y-%m-%d %H:%M  │  ").to_string()),
+                    Span::styled(text::pad(&feed.title, self.feed_width),
+                        state.theme.section),
+                    Span::raw("  │  "),
+                    star,
+                ])
+            };
+            let room = columns.saturating_sub(line.width());
+            line.push_span(text::truncate(&post.title, room));
+
+            let line = if post.read {
+                line
+            } else {
+                line.style(state.theme.unread)
+            };
+            ListItem::new(line)
+        });
+
+        let mut title = format!(" {} ", self.timeline.title());
+        if self.hidden > 0 {
+            title += &format!("| {} hidden ", self.hidden);
+        }
+        let list = crate::tui::build_list(&title, items, &state.theme);
+        self.list.render(f, area, list, &state.theme);
+    }
+
+    fn list(&mut self) -> &mut dyn NavigableList {
+        &mut self.list
+    }
+
+    fn on_key(&mut self, key: KeyCode, state: &FeedState) -> PageAction {
+        let Some(merged) = self.list.selected_item() else {
+            return PageAction::None;
+        };
+        let (feed_id, post_id) = merged.sources[0].clone();
+
+        match key {
+            // Jump to the next unread post.
+            KeyCode::Char('n') => {
+                self.list.select_next(|merged| {
+                    Self::post(merged, state).is_some_and(|post| !post.read)
+                });
+                PageAction::None
+            },
+
+            // Toggle the read status of the newest copy.
+            KeyCode::Char('r') => PageAction::TogglePostRead(feed_id, post_id),
+
+            // Star or unstar the newest copy.
+            KeyCode::Char('*') => {
+                PageAction::TogglePostStarred(feed_id, post_id)
+            },
+
+            // Check the post page of the selected post.
+            KeyCode::Enter | KeyCode::Char('l') => {
+                let page = Box::new(PostPage::new(feed_id, post_id));
+                PageAction::NewPage(page)
+            },
+
+            _ => PageAction::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn limits_timelines_to_days() {
+        let local = |month, day, hour| {
+            Local.with_ymd_and_hms(2024, month, day, hour, 0, 0).unwrap()
+        };
+        let midnight = |month, day| local(month, day, 0).with_timezone(&Utc);
+
+        // The 2nd of May 2024 was a Thursday.
+        let now = local(5, 2, 15);
+        assert_eq!(Timeline::All.since(now), None);
+        assert_eq!(Timeline::Today.since(now), Some(midnight(5, 2)));
+        assert_eq!(Timeline::Week.since(now), Some(midnight(4, 29)));
+    }
+
+    #[test]
+    fn cuts_feed_titles_to_the_column() {
+        assert_eq!(text::pad("Rust", 6), "Rust  ");
+        assert_eq!(text::pad("This Week in Rust", 8), "This We…");
+        assert_eq!(text::pad("Blog", 4), "Blog");
+    }
+}